@@ -0,0 +1,33 @@
+// Places each ball of a batch from its entry in the instance buffer, then hands over to
+// the standard material's lighting. See src/gui/planetarium/batching.rs.
+
+#import bevy_pbr::{
+    mesh_functions,
+    forward_io::VertexOutput,
+    view_transformations::position_world_to_clip,
+}
+
+// Position in xyz, radius in w
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<storage, read> instances: array<vec4<f32>>;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(8) ball: u32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let instance = instances[vertex.ball];
+    let local_position = vertex.position * instance.w + instance.xyz;
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(local_position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+    return out;
+}
@@ -0,0 +1,36 @@
+//! Runs the same eccentric orbit for one period under each integrator and prints how far
+//! each one drifted. A development tool for choosing `UniversePhysics::integrator`.
+//!
+//! Usage: compare-integrators [steps per orbit] [eccentricity]
+
+use bevy::math::DVec3;
+use exotic_matters::body::motive::integrator;
+
+/// Earth's gravitational parameter, m³/s².
+const MU: f64 = 3.986004418e14;
+/// Semi-major axis of the test orbit, in meters.
+const SEMI_MAJOR_AXIS: f64 = 1e7;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let steps: usize = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(1000);
+    let eccentricity: f64 = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(0.5);
+    if !(0.0..1.0).contains(&eccentricity) {
+        println!("Eccentricity must be at least 0 and less than 1.");
+        return;
+    }
+
+    // Start at periapsis
+    let periapsis = SEMI_MAJOR_AXIS * (1.0 - eccentricity);
+    let speed = (MU * (1.0 + eccentricity) / periapsis).sqrt();
+    let Some(reports) = integrator::compare_integrators(MU, DVec3::X * periapsis, DVec3::Y * speed, steps) else {
+        println!("Need at least one step.");
+        return;
+    };
+
+    println!("=== One orbit, e = {}, {} steps ===\n", eccentricity, steps);
+    println!("{:<20} {:>18} {:>14}", "Integrator", "Position error (m)", "Energy drift");
+    for report in reports {
+        println!("{:<20} {:>18.6e} {:>14.3e}", report.kind.label(), report.position_error, report.energy_drift);
+    }
+}
@@ -4,6 +4,7 @@ use bevy::color::LinearRgba;
 use bevy::math::DVec3;
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_egui::egui;
 use serde::{Deserialize, Serialize};
 
 #[derive(Resource, Default)]
@@ -12,7 +13,39 @@ pub struct AssetCache {
     pub materials: HashMap<String, Handle<StandardMaterial>>,
 }
 
-#[derive(Serialize, Deserialize, Default, Component, Clone)]
+impl AssetCache {
+    /// The radius 1 icosphere with this many subdivisions, made the first time it's asked for.
+    pub fn sphere(&mut self, meshes: &mut Assets<Mesh>, subdivisions: u32) -> Handle<Mesh> {
+        self.meshes.entry(format!("icosphere_ico{}", subdivisions))
+            .or_insert_with(|| meshes.add(sphere_mesh(subdivisions)))
+            .clone()
+    }
+}
+
+/// Subdivisions of the icosphere balls are drawn with up close, and exported with.
+pub const SPHERE_SUBDIVISIONS: u32 = 5;
+
+/// Radius 1 icosphere, scaled per body.
+pub fn unit_sphere_mesh() -> Mesh {
+    sphere_mesh(SPHERE_SUBDIVISIONS)
+}
+
+fn sphere_mesh(subdivisions: u32) -> Mesh {
+    Sphere::new(1.0f32).mesh().ico(subdivisions).unwrap()
+}
+
+/// How finely to draw a ball that covers `pixel_radius` pixels on screen.
+pub fn lod_subdivisions(pixel_radius: f32) -> u32 {
+    if pixel_radius < 8.0 {
+        2
+    } else if pixel_radius < 64.0 {
+        3
+    } else {
+        SPHERE_SUBDIVISIONS
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Component, Clone, PartialEq)]
 pub enum Appearance {
     #[default]
     Empty,
@@ -20,13 +53,43 @@ pub enum Appearance {
     Star(StarBall),
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct AppearanceColor {
     pub r: u16,
     pub g: u16,
     pub b: u16,
 }
 
+impl AppearanceColor {
+    /// Channels past 255 are clamped.
+    pub fn to_egui(&self) -> egui::Color32 {
+        let channel = |value: u16| value.min(255) as u8;
+        egui::Color32::from_rgb(channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    pub fn from_egui(color: egui::Color32) -> Self {
+        Self { r: color.r() as u16, g: color.g() as u16, b: color.b() as u16 }
+    }
+
+    /// Lights can be brighter than 255. Splits this into the color scaled down to fit,
+    /// and how many times brighter than that it really is.
+    pub fn split_intensity(&self) -> (egui::Color32, f32) {
+        let brightest = self.r.max(self.g).max(self.b);
+        if brightest <= 255 {
+            return (self.to_egui(), 1.0);
+        }
+        let intensity = brightest as f32 / 255.0;
+        let channel = |value: u16| (value as f32 / intensity).round() as u8;
+        (egui::Color32::from_rgb(channel(self.r), channel(self.g), channel(self.b)), intensity)
+    }
+
+    /// Undoes `split_intensity`, saturating at `u16::MAX`.
+    pub fn with_intensity(color: egui::Color32, intensity: f32) -> Self {
+        let channel = |value: u8| (value as f32 * intensity.max(0.0)).round().min(u16::MAX as f32) as u16;
+        Self { r: channel(color.r()), g: channel(color.g()), b: channel(color.b()) }
+    }
+}
+
 impl Appearance {
     pub fn radius(&self) -> f64 {
         match self {
@@ -37,7 +100,7 @@ impl Appearance {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct DebugBall {
     pub radius: f64,
     pub color: AppearanceColor,
@@ -51,12 +114,9 @@ impl DebugBall {
                       mut images: &mut ResMut<Assets<Image>>,
     ) -> (Mesh3d, MeshMaterial3d<StandardMaterial>) {
         let color = Color::srgb(self.color.r as f32 / 255.0, self.color.g as f32 / 255.0, self.color.b as f32 / 255.0);
-        let mesh_key = format!("icosphere_{}", self.radius);
         let material_key = format!("color_{:02x}{:02x}{:02x}", self.color.r, self.color.g, self.color.b);
 
-        let mesh_handle = cache.meshes.entry(mesh_key.clone()).or_insert_with(|| {
-            meshes.add(Sphere::new(1.0f32).mesh().ico(5).unwrap())
-        }).clone();
+        let mesh_handle = cache.sphere(meshes, SPHERE_SUBDIVISIONS);
 
         let material_handle = cache.materials.entry(material_key.clone()).or_insert_with(|| {
             materials.add(StandardMaterial {
@@ -102,7 +162,7 @@ fn uv_debug_texture() -> Image {
     )
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct StarBall {
     pub radius: f64,
     pub color: AppearanceColor,
@@ -137,12 +197,9 @@ impl StarBall {
                       mut images: &mut ResMut<Assets<Image>>,
     ) -> (Mesh3d, MeshMaterial3d<StandardMaterial>, PointLight) {
         let color = Color::srgb(self.color.r as f32 / 255.0, self.color.g as f32 / 255.0, self.color.b as f32 / 255.0);
-        let mesh_key = format!("icosphere_{}", self.radius);
         let material_key = format!("color_{:02x}{:02x}{:02x}_{:03x}:{:03x}:{:03x}", self.color.r, self.color.g, self.color.b, self.light.r, self.light.g, self.light.b);
 
-        let mesh_handle = cache.meshes.entry(mesh_key.clone()).or_insert_with(|| {
-            meshes.add(Sphere::new(1.0f32).mesh().ico(5).unwrap())
-        }).clone();
+        let mesh_handle = cache.sphere(meshes, SPHERE_SUBDIVISIONS);
 
         let material_handle = cache.materials.entry(material_key.clone()).or_insert_with(|| {
             let e = self.emissive_luminance();
@@ -174,4 +231,49 @@ impl StarBall {
             light
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lod_by_pixel_radius() {
+        assert_eq!(lod_subdivisions(0.5), 2);
+        assert_eq!(lod_subdivisions(7.9), 2);
+        assert_eq!(lod_subdivisions(8.0), 3);
+        assert_eq!(lod_subdivisions(63.0), 3);
+        assert_eq!(lod_subdivisions(64.0), SPHERE_SUBDIVISIONS);
+        // Filling the screen
+        assert_eq!(lod_subdivisions(2000.0), SPHERE_SUBDIVISIONS);
+    }
+
+    #[test]
+    fn test_egui_color() {
+        assert_eq!(AppearanceColor { r: 255, g: 128, b: 0 }.to_egui(), egui::Color32::from_rgb(255, 128, 0));
+        assert_eq!(AppearanceColor::default().to_egui(), egui::Color32::BLACK);
+        // Out of range channels saturate rather than wrap
+        assert_eq!(AppearanceColor { r: 256, g: 1000, b: 300 }.to_egui(), egui::Color32::WHITE);
+    }
+
+    #[test]
+    fn test_color_round_trip() {
+        for color in [egui::Color32::BLACK, egui::Color32::WHITE, egui::Color32::from_rgb(255, 0, 1)] {
+            assert_eq!(AppearanceColor::from_egui(color).to_egui(), color);
+            assert_eq!(AppearanceColor::from_egui(color).split_intensity(), (color, 1.0));
+        }
+        assert_eq!(AppearanceColor::from_egui(egui::Color32::WHITE), AppearanceColor { r: 255, g: 255, b: 255 });
+
+        // The template sun's light is fourteen times white
+        let sunlight = AppearanceColor { r: 255 * 14, g: 255 * 14, b: 255 * 14 };
+        let (color, intensity) = sunlight.split_intensity();
+        assert_eq!((color, intensity), (egui::Color32::WHITE, 14.0));
+        assert_eq!(AppearanceColor::with_intensity(color, intensity), sunlight);
+
+        // Only the brightest channel sets the intensity, so the hue survives
+        let (color, intensity) = AppearanceColor { r: 510, g: 255, b: 0 }.split_intensity();
+        assert_eq!((color, intensity), (egui::Color32::from_rgb(255, 128, 0), 2.0));
+        assert_eq!(AppearanceColor::with_intensity(egui::Color32::WHITE, 1e6).r, u16::MAX);
+        assert_eq!(AppearanceColor::with_intensity(egui::Color32::WHITE, -1.0), AppearanceColor::default());
+    }
+}
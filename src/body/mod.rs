@@ -4,21 +4,10 @@ use crate::gui::common::despawn_entities_with;
 pub mod motive;
 pub mod universe;
 pub mod appearance;
+pub mod rotation;
+pub mod simulation;
 
 
-#[derive(Resource, Debug, PartialEq, /*Eq,*/ Clone, Copy)]
-pub struct SimulationSettings {
-    pub gravity_constant: f64,
-}
-
-impl Default for SimulationSettings {
-    fn default() -> Self {
-        Self {
-            gravity_constant: scilib::constant::G,
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct SimulationObject;
 
@@ -6,12 +6,17 @@
 //! 2. Calculating Fixed and Keplerian positions in correct order (parents first)
 //! 3. Calculating Newtonian positions affected by gravity from Major bodies
 //!
+//! With `UniversePhysics::mutual_gravity`, Major bodies that would be Fixed or Keplerian are
+//! instead integrated under each other's gravity, starting from where their motives put them.
+//! Everything orbiting them stays on its motive, relative to where they've been integrated to.
+//!
 //! Physics calculations are decoupled from frame rate - multiple time steps can be
 //! processed per frame, with a configurable time budget to prevent frame drops.
 //!
 //! ## Performance Optimizations
 //! - Uses Entity (u64, Copy) instead of String IDs to avoid allocations
 //! - Caches PhysicsGraph as a Resource, only rebuilds when motives change
+//! - Mass edits are patched into the graph in place
 //! - Reuses PositionCache across frames
 //! - Uses enum iterator to avoid Box<dyn Iterator> heap allocation
 
@@ -19,8 +24,10 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Instant as StdInstant;
 use bevy::math::DVec3;
 use bevy::prelude::*;
+use rayon::prelude::*;
 
 use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::integrator::{integrate, Integrated, IntegratorKind};
 use crate::body::motive::{Motive, MotiveSelection};
 use crate::body::universe::Major;
 use crate::body::universe::save::UniversePhysics;
@@ -99,6 +106,8 @@ pub struct PhysicsGraph {
     pub sorted_entities: Vec<Entity>,
     /// List of Newtonian body entities
     pub newtonian_entities: Vec<Entity>,
+    /// Major bodies integrated under mutual gravity instead of following their motives
+    pub mutual_entities: HashSet<Entity>,
     /// Map from String ID to Entity (for primary_id lookups)
     pub id_to_entity: HashMap<String, Entity>,
     /// The last simulation time the graph was built for
@@ -118,6 +127,7 @@ impl PhysicsGraph {
         self.cached_motives.clear();
         self.sorted_entities.clear();
         self.newtonian_entities.clear();
+        self.mutual_entities.clear();
         self.id_to_entity.clear();
     }
     
@@ -148,6 +158,26 @@ impl PhysicsGraph {
         }
         false
     }
+
+    /// Picks up new masses without re-sorting, since they never change which body orbits which.
+    /// Keplerian children of a changed body get their `mu` recomputed.
+    pub fn update_masses(&mut self, masses: impl Iterator<Item = (Entity, f64)>, gravitational_constant: f64) {
+        for (entity, mass) in masses {
+            let Some(data) = self.body_data.get_mut(&entity) else { continue };
+            if data.mass == mass {
+                continue;
+            }
+            data.mass = mass;
+            for cached in self.cached_motives.values_mut() {
+                if cached.parent_entity != Some(entity) {
+                    continue;
+                }
+                if let CachedMotiveSelection::Keplerian { mu } = &mut cached.selection {
+                    *mu = gravitational_constant * mass;
+                }
+            }
+        }
+    }
 }
 
 /// Cached positions during a physics step.
@@ -156,6 +186,8 @@ impl PhysicsGraph {
 pub struct PositionCache {
     /// Calculated global positions keyed by Entity
     pub positions: HashMap<Entity, DVec3>,
+    /// Global velocities, only worked out while a mutual gravity body needs starting
+    pub velocities: HashMap<Entity, DVec3>,
     /// Major body data for Newtonian gravity calculations: (entity, mass, position)
     pub major_bodies: Vec<(Entity, f64, DVec3)>,
     /// Cached counts for pre-allocation
@@ -167,6 +199,7 @@ impl PositionCache {
     /// Clear for next step but keep capacity
     pub fn clear(&mut self) {
         self.positions.clear();
+        self.velocities.clear();
         // Don't clear major_bodies here - it's rebuilt separately and clearing twice is wasteful
     }
     
@@ -250,6 +283,8 @@ pub fn calculate_body_positions(
     mut cache: ResMut<PositionCache>,
     mut metrics: ResMut<SimulationPerformanceMetrics>,
     mut bodies: Query<(Entity, &BodyInfo, &Motive, &mut BodyState, Option<&Major>)>,
+    changed_info: Query<(Entity, &BodyInfo), Changed<BodyInfo>>,
+    mut missing_primaries: Local<HashSet<String>>,
 ) {
     // Start frame timing
     sim_time.begin_frame();
@@ -264,7 +299,7 @@ pub fn calculate_body_positions(
     
     if needs_rebuild {
         let rebuild_start = StdInstant::now();
-        rebuild_physics_graph(&mut graph, &bodies, current_time, physics.gravitational_constant);
+        rebuild_physics_graph(&mut graph, &bodies, current_time, physics.gravitational_constant, physics.mutual_gravity, &mut missing_primaries);
         graph.needs_rebuild = false;
         graph.last_build_time = current_time;
         
@@ -273,6 +308,8 @@ pub fn calculate_body_positions(
         
         // Update cache capacity based on new counts from graph rebuild
         cache.reserve(graph.last_body_count, graph.last_major_count);
+    } else {
+        graph.update_masses(changed_info.iter().map(|(entity, info)| (entity, info.mass)), physics.gravitational_constant);
     }
     
     // Track how many steps we process and the last processed time
@@ -309,8 +346,11 @@ pub fn calculate_body_positions(
         // Clear major bodies separately (only once, not in both clear() and update_major_body_cache())
         cache.clear_major_bodies();
         
-        // Phase 1: Calculate Fixed and Keplerian positions
+        // Phase 1: Calculate Fixed and Keplerian positions, after moving mutual gravity bodies on
         let t0 = StdInstant::now();
+        if !graph.mutual_entities.is_empty() && sim_time.playing {
+            step_mutual_bodies(&mut bodies, &graph, sim_time.signed_step(), &physics);
+        }
         calculate_hierarchical_positions(
             &mut bodies,
             &graph,
@@ -331,9 +371,9 @@ pub fn calculate_body_positions(
             &graph,
             &cache,
             step_time,
-            sim_time.step,
+            sim_time.signed_step(),
             sim_time.playing,
-            physics.gravitational_constant,
+            &physics,
         );
         total_newtonian_ns += t2.elapsed().as_nanos();
         
@@ -389,6 +429,8 @@ fn rebuild_physics_graph(
     bodies: &Query<(Entity, &BodyInfo, &Motive, &mut BodyState, Option<&Major>)>,
     time: Instant,
     gravitational_constant: f64,
+    mutual_gravity: bool,
+    missing_primaries: &mut HashSet<String>,
 ) {
     // Count bodies for pre-allocation
     let body_count = bodies.iter().len();
@@ -421,7 +463,7 @@ fn rebuild_physics_graph(
     
     // Second pass: build cached motives and dependencies
     // This is the ONLY place we call motive_at() - results are cached
-    for (entity, _info, motive, _, _) in bodies.iter() {
+    for (entity, info, motive, _, _) in bodies.iter() {
         let (event, selection) = motive.motive_at(time);
         
         match selection {
@@ -432,6 +474,9 @@ fn rebuild_physics_graph(
                 
                 dependencies.insert(entity, parent_entity);
                 hierarchical_bodies.insert(entity);
+                if mutual_gravity && graph.body_data[&entity].is_major {
+                    graph.mutual_entities.insert(entity);
+                }
                 
                 graph.cached_motives.insert(entity, CachedMotive {
                     parent_entity,
@@ -440,6 +485,10 @@ fn rebuild_physics_graph(
             }
             MotiveSelection::Keplerian(kepler) => {
                 let parent_entity = graph.id_to_entity.get(&kepler.primary_id).copied();
+                // Warned about once per primary, since the graph is rebuilt often
+                if parent_entity.is_none() && missing_primaries.insert(kepler.primary_id.clone()) {
+                    warn!("{} orbits {}, which doesn't exist", info.id, kepler.primary_id);
+                }
                 let parent_mass = parent_entity
                     .and_then(|pe| graph.body_data.get(&pe))
                     .map(|d| d.mass)
@@ -447,6 +496,9 @@ fn rebuild_physics_graph(
 
                 dependencies.insert(entity, parent_entity);
                 hierarchical_bodies.insert(entity);
+                if mutual_gravity && graph.body_data[&entity].is_major {
+                    graph.mutual_entities.insert(entity);
+                }
 
                 graph.cached_motives.insert(entity, CachedMotive {
                     parent_entity,
@@ -493,12 +545,18 @@ fn rebuild_physics_graph(
 
 /// Calculate positions for Fixed and Keplerian bodies in dependency order.
 /// Uses cached parent/mu data but calls motive_at() fresh for each body.
+/// Mutual gravity bodies already integrated to `time` are left where they are, and the rest
+/// are started off from their motives.
 fn calculate_hierarchical_positions(
     bodies: &mut Query<(Entity, &BodyInfo, &Motive, &mut BodyState, Option<&Major>)>,
     graph: &PhysicsGraph,
     cache: &mut PositionCache,
     time: Instant,
 ) {
+    // Starting a body off needs its velocity, which is relative to its parent's
+    let track_velocities = graph.mutual_entities.iter()
+        .any(|entity| bodies.get(*entity).is_ok_and(|(_, _, _, state, _)| state.newtonian_init_time.is_none()));
+
     // Calculate positions in topological order
     for &entity in &graph.sorted_entities {
         // Get cached motive data for parent info and pre-computed mu
@@ -513,20 +571,44 @@ fn calculate_hierarchical_positions(
             .copied()
             .unwrap_or(DVec3::ZERO);
         
+        let parent_velocity = cached_motive.parent_entity
+            .and_then(|pe| cache.velocities.get(&pe))
+            .copied()
+            .unwrap_or(DVec3::ZERO);
+        let mutual = graph.mutual_entities.contains(&entity);
+
+        if mutual && state.newtonian_init_time.is_some() {
+            // Integrated by step_mutual_bodies
+            let global_position = state.current_position;
+            state.current_local_position = Some(global_position - parent_position);
+            state.current_primary_position = cached_motive.parent_entity.map(|_| parent_position);
+            cache.positions.insert(entity, global_position);
+            if let Some(velocity) = state.current_velocity {
+                cache.velocities.insert(entity, velocity);
+            }
+            continue;
+        }
+        
         // Get fresh motive selection at current time
         let (_, selection) = motive.motive_at(time);
         
         // Calculate local position based on motive selection
-        let local_position = match selection {
+        let (local_position, local_velocity) = match selection {
             MotiveSelection::Fixed { position, .. } => {
-                *position
+                (*position, DVec3::ZERO)
+            }
+            // Stays where it was until its primary turns up
+            MotiveSelection::Keplerian(_) if cached_motive.parent_entity.is_none() => {
+                cache.positions.insert(entity, state.current_position);
+                continue;
             }
             MotiveSelection::Keplerian(kepler) => {
                 let mu = match &cached_motive.selection {
                     CachedMotiveSelection::Keplerian { mu } => *mu,
                     _ => 0.0,
                 };
-                kepler.displacement(time, mu).unwrap_or(DVec3::ZERO)
+                let velocity = if track_velocities { kepler.velocity(time, mu) } else { DVec3::ZERO };
+                (kepler.displacement(time, mu).unwrap_or(DVec3::ZERO), velocity)
             }
             MotiveSelection::Newtonian { .. } => {
                 continue;
@@ -534,6 +616,17 @@ fn calculate_hierarchical_positions(
         };
         
         let global_position = parent_position + local_position;
+        if track_velocities {
+            cache.velocities.insert(entity, parent_velocity + local_velocity);
+        }
+        if mutual {
+            state.current_velocity = Some(parent_velocity + local_velocity);
+            state.newtonian_init_time = Some(time);
+        } else {
+            // So turning mutual gravity back on starts over from here
+            state.current_velocity = None;
+            state.newtonian_init_time = None;
+        }
         
         // Update body state
         state.current_position = global_position;
@@ -566,18 +659,88 @@ fn update_major_body_cache(
     }
 }
 
+// ============================================================================
+// Mutual Gravity
+// ============================================================================
+
+/// Moves the mutual gravity bodies that have been started on by `delta` seconds.
+fn step_mutual_bodies(
+    bodies: &mut Query<(Entity, &BodyInfo, &Motive, &mut BodyState, Option<&Major>)>,
+    graph: &PhysicsGraph,
+    delta: f64,
+    physics: &UniversePhysics,
+) {
+    if delta.abs() <= f64::EPSILON { return; }
+    let mut entities = Vec::with_capacity(graph.mutual_entities.len());
+    let mut starts = Vec::with_capacity(graph.mutual_entities.len());
+    for &entity in &graph.mutual_entities {
+        let Ok((_, _, _, state, _)) = bodies.get(entity) else { continue };
+        let (Some(_), Some(velocity)) = (state.newtonian_init_time, state.current_velocity) else { continue };
+        let mass = graph.body_data.get(&entity).map(|data| data.mass).unwrap_or(0.0);
+        entities.push(entity);
+        starts.push((mass, state.current_position, velocity));
+    }
+
+    let stepped = step_mutual_gravity(&starts, physics.gravitational_constant, delta, physics.integrator, physics.max_substeps);
+    for (entity, (position, velocity)) in entities.into_iter().zip(stepped) {
+        let Ok((_, _, _, mut state, _)) = bodies.get_mut(entity) else { continue };
+        state.current_position = position;
+        state.current_velocity = Some(velocity);
+    }
+}
+
+/// Acceleration of each body from the pull of all the others.
+fn mutual_accelerations(masses: &[f64], positions: &[DVec3], gravitational_constant: f64) -> Vec<DVec3> {
+    let mut accelerations = vec![DVec3::ZERO; positions.len()];
+    for a in 0..positions.len() {
+        for b in a + 1..positions.len() {
+            let (on_a, on_b) = gravity::pair_acceleration(
+                gravitational_constant * masses[a],
+                gravitational_constant * masses[b],
+                positions[b] - positions[a],
+            );
+            accelerations[a] += on_a;
+            accelerations[b] += on_b;
+        }
+    }
+    accelerations
+}
+
+/// New position and velocity of each `(mass, position, velocity)` after `delta` seconds of
+/// them all pulling on each other, stepped together by `integrator`.
+pub fn step_mutual_gravity(
+    bodies: &[(f64, DVec3, DVec3)],
+    gravitational_constant: f64,
+    delta: f64,
+    integrator: IntegratorKind,
+    max_substeps: usize,
+) -> Vec<(DVec3, DVec3)> {
+    let masses: Vec<f64> = bodies.iter().map(|(mass, _, _)| *mass).collect();
+    let positions: Vec<DVec3> = bodies.iter().map(|(_, position, _)| *position).collect();
+    let velocities: Vec<DVec3> = bodies.iter().map(|(_, _, velocity)| *velocity).collect();
+    let accelerations = |positions: Vec<DVec3>| mutual_accelerations(&masses, &positions, gravitational_constant);
+
+    let stepped = integrate(integrator, positions, velocities, delta, max_substeps, accelerations);
+    stepped.position.into_iter().zip(stepped.velocity).collect()
+}
+
 // ============================================================================
 // Newtonian Position Calculation
 // ============================================================================
 
+/// Below this many Newtonian bodies, stepping them on other threads costs more than it saves.
+pub const PARALLEL_NEWTONIAN_THRESHOLD: usize = 256;
+
 /// Calculate positions for Newtonian bodies using gravity from Major bodies.
 /// 
 /// This function handles:
-/// - Standard Newtonian integration using velocity stored in BodyState
+/// - Standard Newtonian integration using velocity stored in BodyState, by `UniversePhysics::integrator`
 /// - Initialization of Newtonian state when first entering a Newtonian motive
 /// - Release transitions from Fixed to Newtonian (computing position and transforming velocity)
 /// 
 /// Uses cached motive data to avoid repeated motive_at() calls.
+/// Major bodies only move in the hierarchical phase, so the bodies are integrated in parallel
+/// once there are enough of them, then written back serially.
 fn calculate_newtonian_positions(
     bodies: &mut Query<(Entity, &BodyInfo, &Motive, &mut BodyState, Option<&Major>)>,
     graph: &PhysicsGraph,
@@ -585,11 +748,12 @@ fn calculate_newtonian_positions(
     time: Instant,
     delta_time: f64,
     playing: bool,
-    gravitational_constant: f64,
+    physics: &UniversePhysics,
 ) {
     let effective_delta = if playing { delta_time } else { 0.0 };
     
-    // Process each Newtonian body
+    // Gather where each Newtonian body starts this step
+    let mut starts = Vec::with_capacity(graph.newtonian_entities.len());
     for &entity in &graph.newtonian_entities {
         // Get cached motive data
         let Some(cached_motive) = graph.cached_motives.get(&entity) else { continue };
@@ -605,7 +769,7 @@ fn calculate_newtonian_positions(
                 || state.newtonian_init_time.is_none()
                 || is_release;
             
-            let (mut current_pos, mut current_vel) = if needs_init {
+            let (current_pos, current_vel) = if needs_init {
                 // Handle Release transition: compute position from cached previous Fixed motive
                 if let Some((prev_parent_entity, fixed_pos)) = release_from_fixed {
                     // Compute the global position from the cached Fixed motive data
@@ -632,30 +796,55 @@ fn calculate_newtonian_positions(
                 // Use the current state from previous integration step
                 (state.current_position, state.current_velocity.unwrap_or(*velocity))
             };
-            
-            if effective_delta.abs() > f64::EPSILON {
-                // Calculate gravitational acceleration from all Major bodies
-                let acceleration: DVec3 = cache.major_bodies.iter()
-                    .filter(|(e, _, _)| *e != entity) // Don't apply self-gravity
-                    .map(|(_, mass, pos)| {
-                        let a_to_b = current_pos - *pos;
-                        gravity::one_body_acceleration(gravitational_constant * mass, a_to_b)
-                    })
-                    .sum();
-                
-                // Update position and velocity using simple Euler integration
-                // TODO: Consider using Verlet or RK4 for better accuracy
-                current_pos += current_vel * effective_delta;
-                current_vel += acceleration * effective_delta;
-            }
-            
-            state.current_position = current_pos;
-            state.current_velocity = Some(current_vel);
-            state.last_step_position = *position;
-            state.current_local_position = None;
-            state.current_primary_position = None;
+            starts.push((entity, current_pos, current_vel, *position));
         }
     }
+    
+    let parallel = starts.len() >= PARALLEL_NEWTONIAN_THRESHOLD;
+    let stepped = step_newtonian(&starts, &cache.major_bodies, physics.gravitational_constant, effective_delta, physics.integrator, physics.max_substeps, parallel);
+    
+    for ((entity, _, _, motive_position), step) in starts.into_iter().zip(stepped) {
+        let Ok((_, _, _, mut state, _)) = bodies.get_mut(entity) else { continue };
+        state.current_position = step.position;
+        state.current_velocity = Some(step.velocity);
+        state.substeps = step.substeps;
+        state.inaccurate = step.clamped;
+        state.last_step_position = motive_position;
+        state.current_local_position = None;
+        state.current_primary_position = None;
+    }
+}
+
+/// New position and velocity of each `(entity, position, velocity, _)` after `delta` seconds
+/// in the gravity of the major bodies, in the same order.
+fn step_newtonian(
+    starts: &[(Entity, DVec3, DVec3, DVec3)],
+    major_bodies: &[(Entity, f64, DVec3)],
+    gravitational_constant: f64,
+    delta: f64,
+    integrator: IntegratorKind,
+    max_substeps: usize,
+    parallel: bool,
+) -> Vec<Integrated> {
+    let step = |&(entity, current_pos, current_vel, _): &(Entity, DVec3, DVec3, DVec3)| {
+        if delta.abs() <= f64::EPSILON {
+            return Integrated { position: current_pos, velocity: current_vel, substeps: 0, clamped: false };
+        }
+        // Gravitational acceleration from all Major bodies
+        let acceleration = |at: DVec3| -> DVec3 {
+            major_bodies.iter()
+                .filter(|(e, _, _)| *e != entity) // Don't apply self-gravity
+                .map(|(_, mass, pos)| gravity::one_body_acceleration(gravitational_constant * mass, at - *pos))
+                .sum()
+        };
+        integrate(integrator, current_pos, current_vel, delta, max_substeps, acceleration)
+    };
+    
+    if parallel {
+        starts.par_iter().map(step).collect()
+    } else {
+        starts.iter().map(step).collect()
+    }
 }
 
 // ============================================================================
@@ -732,3 +921,133 @@ fn topological_sort_optimized(
     
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::kepler_motive::KeplerMotive;
+    use crate::gui::planetarium::time::PreviousTimes;
+    use crate::util::circular;
+
+    #[test]
+    fn test_parallel_newtonian_matches_serial() {
+        let mut world = World::new();
+        let sun = world.spawn_empty().id();
+        let planet = world.spawn_empty().id();
+        let majors = [(sun, 2e30, DVec3::ZERO), (planet, 6e24, DVec3::new(1.5e11, 0.0, 0.0))];
+        let starts: Vec<_> = (0..1000u32).map(|i| {
+            let angle = i as f64 * 0.01;
+            let position = DVec3::new(angle.cos(), angle.sin(), 0.01 * angle) * (1e11 + i as f64 * 1e8);
+            let velocity = DVec3::new(-angle.sin(), angle.cos(), 0.0) * 3e4;
+            (world.spawn_empty().id(), position, velocity, position)
+        }).collect();
+        // A major body stepped as a Newtonian one doesn't pull on itself
+        let starts = [vec![(planet, majors[1].2, DVec3::Y * 3e4, majors[1].2)], starts].concat();
+
+        let serial = step_newtonian(&starts, &majors, 6.674e-11, 3600.0, IntegratorKind::Rk45, 1000, false);
+        let parallel = step_newtonian(&starts, &majors, 6.674e-11, 3600.0, IntegratorKind::Rk45, 1000, true);
+        assert_eq!(serial, parallel);
+        assert!(serial[0].velocity.is_finite());
+    }
+
+    #[test]
+    fn test_mutual_gravity_uses_integrator() {
+        // Two suns circling each other, a third of an AU apart, stepped one day
+        let (mass, separation) = (2e30, 5e10);
+        let g = UniversePhysics::default().gravitational_constant;
+        let speed = (g * mass / (2.0 * separation)).sqrt();
+        let bodies = [
+            (mass, DVec3::X * separation / 2.0, DVec3::Y * speed),
+            (mass, DVec3::NEG_X * separation / 2.0, DVec3::NEG_Y * speed),
+        ];
+        let stepped: Vec<_> = IntegratorKind::ALL.iter()
+            .map(|&kind| step_mutual_gravity(&bodies, g, 86400.0, kind, 1000))
+            .collect();
+        for (i, one) in stepped.iter().enumerate() {
+            for other in &stepped[i + 1..] {
+                assert_ne!(one, other);
+            }
+        }
+
+        let drift = |kind: IntegratorKind| {
+            let index = IntegratorKind::ALL.iter().position(|&k| k == kind).unwrap();
+            (stepped[index][0].0.distance(stepped[index][1].0) - separation).abs()
+        };
+        assert!(drift(IntegratorKind::Euler) > 1e6, "{}", drift(IntegratorKind::Euler));
+        assert!(drift(IntegratorKind::Rk45) < 1e3, "{}", drift(IntegratorKind::Rk45));
+    }
+
+    /// A sun with two major planets, one an AU out and one five, run `steps` hours.
+    fn two_planets(mutual_gravity: bool, steps: usize) -> (App, Vec<(Entity, f64, Option<KeplerMotive>)>) {
+        let mut app = App::new();
+        app
+            .insert_resource(SimTime {
+                playing: true,
+                step: 3600.0,
+                max_frame_time: 60.0,
+                previous_times: PreviousTimes::with_values(0.0, steps, 3600.0),
+                ..default()
+            })
+            .insert_resource(UniversePhysics { mutual_gravity, integrator: IntegratorKind::Rk4, ..default() })
+            .init_resource::<PhysicsGraph>()
+            .init_resource::<PositionCache>()
+            .init_resource::<SimulationPerformanceMetrics>()
+            .add_systems(Update, calculate_body_positions);
+
+        let mut spawn = |id: &str, mass: f64, motive: Motive| {
+            let info = BodyInfo { id: id.into(), mass, major: true, ..default() };
+            app.world_mut().spawn((info, motive, BodyState::default(), Major)).id()
+        };
+        let mut bodies = vec![(spawn("sun", 2e30, Motive::fixed(DVec3::ZERO)), 2e30, None)];
+        for (id, mass, radius) in [("inner", 6e24, 1.5e11), ("outer", 2e27, 7.8e11)] {
+            let orbit = circular::circular_orbit(String::from("sun"), radius, 1.5);
+            let motive = Motive::keplerian(orbit.primary_id.clone(), orbit.shape.clone(), orbit.rotation.clone(), orbit.epoch.clone());
+            bodies.push((spawn(id, mass, motive), mass, Some(orbit)));
+        }
+        app.update();
+        (app, bodies)
+    }
+
+    #[test]
+    fn test_without_mutual_gravity_matches_kepler() {
+        let (app, bodies) = two_planets(false, 240);
+        let time = app.world().resource::<SimTime>().time;
+        assert_eq!(time.to_j2000_seconds(), 239.0 * 3600.0);
+
+        let mu = UniversePhysics::default().gravitational_constant * 2e30;
+        for (entity, _, orbit) in bodies {
+            let expected = orbit.map(|orbit| orbit.displacement(time, mu).unwrap()).unwrap_or(DVec3::ZERO);
+            assert_eq!(app.world().get::<BodyState>(entity).unwrap().current_position, expected);
+        }
+    }
+
+    #[test]
+    fn test_mutual_gravity_conserves_energy() {
+        let energy = |app: &App, bodies: &[(Entity, f64, Option<KeplerMotive>)]| {
+            let g = UniversePhysics::default().gravitational_constant;
+            let states: Vec<_> = bodies.iter()
+                .map(|(entity, mass, _)| (*mass, app.world().get::<BodyState>(*entity).unwrap()))
+                .collect();
+            let mut total = 0.0;
+            for (i, (mass, state)) in states.iter().enumerate() {
+                total += 0.5 * mass * state.current_velocity.unwrap().length_squared();
+                for (other_mass, other) in &states[i + 1..] {
+                    total += gravity::potential_energy(g, *mass, *other_mass, state.current_position.distance(other.current_position));
+                }
+            }
+            total
+        };
+
+        let (start, bodies) = two_planets(true, 1);
+        let (app, _) = two_planets(true, 2400);
+        let (start_energy, end_energy) = (energy(&start, &bodies), energy(&app, &bodies));
+        assert!(((end_energy - start_energy) / start_energy).abs() < 1e-8, "{} {}", start_energy, end_energy);
+
+        // The outer planet's pull has taken the inner one off its Kepler orbit
+        let time = app.world().resource::<SimTime>().time;
+        let (inner, _, orbit) = &bodies[1];
+        let analytic = orbit.as_ref().unwrap().displacement(time, UniversePhysics::default().gravitational_constant * 2e30).unwrap();
+        let position = app.world().get::<BodyState>(*inner).unwrap().current_position;
+        assert!(position.distance(analytic) > 1.0, "{} {}", position, analytic);
+    }
+}
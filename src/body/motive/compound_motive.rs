@@ -9,11 +9,36 @@ use crate::util;
 use crate::util::time_map::SortedTimes;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
+#[serde(from = "StoredMotive")]
 pub struct Motive {
     times: SortedTimes,
     motives: HashMap<u64, (TransitionEvent, MotiveSelection)>
 }
 
+/// Motive as written to disk. Older files keyed events by the raw bits of their time,
+/// before bitfutz keys were order-preserving, so keys are re-derived from `times` on load.
+#[derive(Deserialize)]
+struct StoredMotive {
+    times: SortedTimes,
+    motives: HashMap<u64, (TransitionEvent, MotiveSelection)>
+}
+
+impl From<StoredMotive> for Motive {
+    fn from(stored: StoredMotive) -> Self {
+        let motives = stored.motives.into_iter()
+            .map(|(key, entry)| {
+                let time = util::bitfutz::u64::to_f64(key);
+                let time = if !time.is_nan() && stored.times.has(time) { time } else { f64::from_bits(key) };
+                (util::bitfutz::f64::to_u64(time), entry)
+            })
+            .collect();
+        Self {
+            times: stored.times,
+            motives,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum TransitionEvent {
     Epoch,
@@ -59,6 +84,14 @@ impl MotiveSelection {
             MotiveSelection::Newtonian { .. } => None,
         }
     }
+
+    pub fn primary_id_mut(&mut self) -> Option<&mut String> {
+        match self {
+            MotiveSelection::Fixed { primary_id, .. } => primary_id.as_mut(),
+            MotiveSelection::Keplerian(k) => Some(&mut k.primary_id),
+            MotiveSelection::Newtonian { .. } => None,
+        }
+    }
 }
 
 impl Motive {
@@ -81,11 +114,13 @@ impl Motive {
 
     /// Check if any event occurred in the time range (start, end] using binary search.
     /// Returns true if there's at least one event with time > start AND time <= end.
-    /// This is O(log n) instead of O(n).
+    /// This is O(log n) instead of O(n). The bounds may be given in either order.
     pub fn has_event_in_range(&self, start: Instant, end: Instant) -> bool {
         if self.times.is_empty() {
             return false;
         }
+        // Playing in reverse
+        let (start, end) = if end < start { (end, start) } else { (start, end) };
         
         // Find the first event after start
         let index_after_start = self.times.get_index_after(start);
@@ -107,6 +142,11 @@ impl Motive {
         })
     }
 
+    /// Every event's motive, in no particular order.
+    pub fn selections_mut(&mut self) -> impl Iterator<Item = &mut MotiveSelection> {
+        self.motives.values_mut().map(|(_, selection)| selection)
+    }
+
     /// Create a fixed motive at the origin (no parent)
     pub fn fixed(position: DVec3) -> Self {
         Self::fixed_with_parent(None, position)
@@ -121,9 +161,13 @@ impl Motive {
     }
 
     pub fn newtonian(position: DVec3, velocity: DVec3) -> Self {
+        Self::newtonian_at(Instant::from_seconds_since_j2000(0.0), position, velocity)
+    }
+
+    /// A Newtonian motive whose state is given at `epoch`. There is no motive before it.
+    pub fn newtonian_at(epoch: Instant, position: DVec3, velocity: DVec3) -> Self {
         let mut new = Self::new();
-        let zero = Instant::from_seconds_since_j2000(0.0);
-        new.insert_event(zero, TransitionEvent::Epoch, MotiveSelection::Newtonian { position, velocity });
+        new.insert_event(epoch, TransitionEvent::Epoch, MotiveSelection::Newtonian { position, velocity });
         new
     }
 
@@ -170,6 +214,20 @@ impl Motive {
         self.motives.get(&key).expect(format!("Invariant violated: CompoundMotive.times gave the time {}, but CompoundMotive.time motives has no such key {}.", time, key).as_ref())
     }
 
+    pub fn motive_at_mut(&mut self, time: Instant) -> &mut (TransitionEvent, MotiveSelection) {
+        let time_f64 = time.to_j2000_seconds();
+        let time = self.times.get_at_or_before(time_f64).expect("Invariant violated: CompoundMotive must have at least one motive.");
+        let key = util::bitfutz::f64::to_u64(time);
+        self.motives.get_mut(&key).expect(format!("Invariant violated: CompoundMotive.times gave the time {}, but CompoundMotive.time motives has no such key {}.", time, key).as_ref())
+    }
+
+    /// Time of the event whose motive is active at `time`.
+    pub fn event_time_at(&self, time: Instant) -> Instant {
+        let time_f64 = time.to_j2000_seconds();
+        let time = self.times.get_at_or_before(time_f64).expect("Invariant violated: CompoundMotive must have at least one motive.");
+        Instant::from_seconds_since_j2000(time)
+    }
+
     /// Get the motive that was active just before the motive at the given time.
     /// Returns None if there is no previous motive (i.e., the motive at `time` is the first one).
     pub fn motive_before(&self, time: Instant) -> Option<&(TransitionEvent, MotiveSelection)> {
@@ -215,3 +273,30 @@ lazy_static! {
         }),
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loads_legacy_raw_bit_keys() {
+        let mut times = SortedTimes::new();
+        let mut motives = HashMap::new();
+        for (time, x) in [(0.0, 1.0), (-3600.0, 2.0), (86400.0, 3.0)] {
+            times.insert(time);
+            motives.insert(f64::to_bits(time), (TransitionEvent::Epoch, MotiveSelection::Fixed { primary_id: None, position: DVec3::new(x, 0.0, 0.0) }));
+        }
+        let motive = Motive::from(StoredMotive { times, motives });
+
+        for (time, x) in [(-3600.0, 2.0), (0.0, 1.0), (86400.0, 3.0)] {
+            match motive.motive_at(Instant::from_seconds_since_j2000(time)) {
+                (_, MotiveSelection::Fixed { position, .. }) => assert_eq!(position.x, x),
+                _ => panic!("Expected a fixed motive at {}", time),
+            }
+        }
+
+        // Already current keys are left alone
+        let reloaded = Motive::from(StoredMotive { times: motive.times.clone(), motives: motive.motives.clone() });
+        assert_eq!(reloaded.iter_events().count(), 3);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use bevy::math::DVec3;
 use serde::{Deserialize, Serialize};
 use bevy::prelude::*;
@@ -16,6 +17,16 @@ pub struct BodyInfo {
     pub tags: Vec<String>,
 }
 
+/// Free-form notes about a body, like its spectral type or where its numbers came from.
+#[derive(Serialize, Deserialize, Component, Clone, Default, Debug, PartialEq)]
+pub struct BodyProperties(pub BTreeMap<String, String>);
+
+impl BodyProperties {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 #[derive(Component)]
 pub struct BodyState {
     pub current_position: DVec3,
@@ -25,9 +36,17 @@ pub struct BodyState {
     pub current_local_position: Option<DVec3>,
     pub current_primary_position: Option<DVec3>,
     pub trajectory: Option<TimeMap<DVec3>>,
+    /// The body `trajectory` is relative to, when it isn't this body's primary.
+    pub trajectory_origin: Option<Entity>,
+    /// Bumped whenever `trajectory` is recalculated, so its line knows to rebake.
+    pub trajectory_revision: u32,
     /// Time at which the current Newtonian state was last initialized/updated
     /// Used to detect motive transitions that require reinitialization
     pub newtonian_init_time: Option<Instant>,
+    /// Pieces the last Newtonian step was split into.
+    pub substeps: usize,
+    /// The last Newtonian step hit `UniversePhysics::max_substeps`, so the body may be off course.
+    pub inaccurate: bool,
 }
 
 impl Default for BodyState {
@@ -39,7 +58,11 @@ impl Default for BodyState {
             current_local_position: None,
             current_primary_position: None,
             trajectory: None,
+            trajectory_origin: None,
+            trajectory_revision: 0,
             newtonian_init_time: None,
+            substeps: 0,
+            inaccurate: false,
         }
     }
 }
@@ -0,0 +1,278 @@
+//! Ways of stepping a Newtonian body through its primaries' gravity, or a system of bodies
+//! through each other's, and a harness for seeing how far each one drifts off a known orbit.
+
+use bevy::math::DVec3;
+use serde::{Deserialize, Serialize};
+use crate::foundations::gravity;
+use crate::util::circular;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    /// Position from the old velocity, velocity from the old acceleration. Cheapest, and
+    /// spirals outward on any orbit.
+    #[default]
+    Euler,
+    /// Velocity first, then position from the new velocity. Keeps energy bounded.
+    SemiImplicitEuler,
+    /// Velocity Verlet. Second order, and keeps energy bounded.
+    Verlet,
+    /// Classic fourth-order Runge-Kutta.
+    Rk4,
+    /// Dormand-Prince, splitting each step as finely as `RK45_TOLERANCE` needs.
+    Rk45,
+}
+
+impl IntegratorKind {
+    pub const ALL: [IntegratorKind; 5] = [
+        IntegratorKind::Euler,
+        IntegratorKind::SemiImplicitEuler,
+        IntegratorKind::Verlet,
+        IntegratorKind::Rk4,
+        IntegratorKind::Rk45,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IntegratorKind::Euler => "Euler",
+            IntegratorKind::SemiImplicitEuler => "Semi-implicit Euler",
+            IntegratorKind::Verlet => "Verlet",
+            IntegratorKind::Rk4 => "RK4",
+            IntegratorKind::Rk45 => "RK45 (adaptive)",
+        }
+    }
+}
+
+/// Largest error allowed in one RK45 substep, relative to the size of the position and velocity.
+pub const RK45_TOLERANCE: f64 = 1e-10;
+/// Default for `UniversePhysics::max_substeps`.
+pub const DEFAULT_MAX_SUBSTEPS: usize = 1000;
+
+/// What the integrators step: one body's position or velocity, or every body's in a system at once.
+pub trait Phase: Clone {
+    /// `self + by * scale`.
+    fn nudged(&self, by: &Self, scale: f64) -> Self;
+    fn scaled(&self, scale: f64) -> Self;
+    /// Size to judge an RK45 step's error against.
+    fn magnitude(&self) -> f64;
+}
+
+impl Phase for DVec3 {
+    fn nudged(&self, by: &Self, scale: f64) -> Self { *self + *by * scale }
+    fn scaled(&self, scale: f64) -> Self { *self * scale }
+    fn magnitude(&self) -> f64 { self.length() }
+}
+
+impl Phase for Vec<DVec3> {
+    fn nudged(&self, by: &Self, scale: f64) -> Self {
+        self.iter().zip(by).map(|(base, by)| *base + *by * scale).collect()
+    }
+    fn scaled(&self, scale: f64) -> Self { self.iter().map(|vector| *vector * scale).collect() }
+    /// The largest of them, so one body's error isn't hidden by the size of another's orbit.
+    fn magnitude(&self) -> f64 { self.iter().map(|vector| vector.length()).fold(0.0, f64::max) }
+}
+
+/// Where one step left a body, or a system of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integrated<P = DVec3> {
+    pub position: P,
+    pub velocity: P,
+    /// Pieces the step was split into. Always 1 for the fixed-step integrators.
+    pub substeps: usize,
+    /// The step wanted more than the most substeps allowed, so it's less accurate than asked for.
+    pub clamped: bool,
+}
+
+impl<P> Integrated<P> {
+    fn whole((position, velocity): (P, P)) -> Self {
+        Self { position, velocity, substeps: 1, clamped: false }
+    }
+}
+
+/// Position and velocity after `delta` seconds under `acceleration`, which gives the
+/// acceleration at a position. Adaptive integrators split the step into at most `max_substeps`.
+pub fn integrate<P: Phase>(kind: IntegratorKind, position: P, velocity: P, delta: f64, max_substeps: usize, acceleration: impl Fn(P) -> P) -> Integrated<P> {
+    match kind {
+        IntegratorKind::Euler => {
+            let velocity_after = velocity.nudged(&acceleration(position.clone()), delta);
+            Integrated::whole((position.nudged(&velocity, delta), velocity_after))
+        }
+        IntegratorKind::SemiImplicitEuler => {
+            let velocity = velocity.nudged(&acceleration(position.clone()), delta);
+            Integrated::whole((position.nudged(&velocity, delta), velocity))
+        }
+        IntegratorKind::Verlet => {
+            let before = acceleration(position.clone());
+            let position = position.nudged(&velocity, delta).nudged(&before, delta * delta / 2.0);
+            let after = acceleration(position.clone());
+            Integrated::whole((position, velocity.nudged(&before, delta / 2.0).nudged(&after, delta / 2.0)))
+        }
+        IntegratorKind::Rk4 => Integrated::whole(rk4(position, velocity, delta, &acceleration)),
+        IntegratorKind::Rk45 => rk45(position, velocity, delta, max_substeps.max(1), &acceleration),
+    }
+}
+
+fn rk4<P: Phase>(position: P, velocity: P, delta: f64, acceleration: &impl Fn(P) -> P) -> (P, P) {
+    let k1_v = acceleration(position.clone());
+    let k2_x = velocity.nudged(&k1_v, delta / 2.0);
+    let k2_v = acceleration(position.nudged(&velocity, delta / 2.0));
+    let k3_x = velocity.nudged(&k2_v, delta / 2.0);
+    let k3_v = acceleration(position.nudged(&k2_x, delta / 2.0));
+    let k4_x = velocity.nudged(&k3_v, delta);
+    let k4_v = acceleration(position.nudged(&k3_x, delta));
+    (
+        position.nudged(&velocity, delta / 6.0).nudged(&k2_x, delta / 3.0).nudged(&k3_x, delta / 3.0).nudged(&k4_x, delta / 6.0),
+        velocity.nudged(&k1_v, delta / 6.0).nudged(&k2_v, delta / 3.0).nudged(&k3_v, delta / 3.0).nudged(&k4_v, delta / 6.0),
+    )
+}
+
+// Dormand-Prince tableau. The fifth-order weights are the last row of `DP_A`.
+const DP_A: [[f64; 6]; 6] = [
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+/// Fifth-order weights less the embedded fourth-order ones.
+const DP_ERROR: [f64; 7] = [
+    35.0 / 384.0 - 5179.0 / 57600.0,
+    0.0,
+    500.0 / 1113.0 - 7571.0 / 16695.0,
+    125.0 / 192.0 - 393.0 / 640.0,
+    -2187.0 / 6784.0 + 92097.0 / 339200.0,
+    11.0 / 84.0 - 187.0 / 2100.0,
+    -1.0 / 40.0,
+];
+
+/// One Dormand-Prince step, with its error relative to `RK45_TOLERANCE`.
+fn dormand_prince<P: Phase>(position: &P, velocity: &P, delta: f64, acceleration: &impl Fn(P) -> P) -> (P, P, f64) {
+    let mut k = Vec::with_capacity(7);
+    k.push((velocity.clone(), acceleration(position.clone())));
+    for stage in 1..7 {
+        let (mut x, mut v) = (position.clone(), velocity.clone());
+        for (weight, (k_x, k_v)) in DP_A[stage - 1].iter().zip(&k) {
+            x = x.nudged(k_x, delta * weight);
+            v = v.nudged(k_v, delta * weight);
+        }
+        let a = acceleration(x);
+        k.push((v, a));
+    }
+    // The last stage is taken at the fifth-order result
+    let (mut x, mut v) = (position.clone(), velocity.clone());
+    for (weight, (k_x, k_v)) in DP_A[5].iter().zip(&k) {
+        x = x.nudged(k_x, delta * weight);
+        v = v.nudged(k_v, delta * weight);
+    }
+    let (mut error_x, mut error_v) = (k[0].0.scaled(delta * DP_ERROR[0]), k[0].1.scaled(delta * DP_ERROR[0]));
+    for (weight, (k_x, k_v)) in DP_ERROR.iter().zip(&k).skip(1) {
+        error_x = error_x.nudged(k_x, delta * weight);
+        error_v = error_v.nudged(k_v, delta * weight);
+    }
+    let error = (error_x.magnitude() / (RK45_TOLERANCE * position.magnitude().max(1.0)))
+        .max(error_v.magnitude() / (RK45_TOLERANCE * velocity.magnitude().max(1e-3)));
+    (x, v, error)
+}
+
+fn rk45<P: Phase>(mut position: P, mut velocity: P, delta: f64, max_substeps: usize, acceleration: &impl Fn(P) -> P) -> Integrated<P> {
+    let total = delta.abs();
+    let smallest = total / max_substeps as f64;
+    let (mut done, mut substep, mut substeps, mut clamped) = (0.0, total, 0, false);
+    while done < total {
+        // The last substep allowed takes whatever's left
+        let last = substeps + 1 >= max_substeps;
+        substep = if last { total - done } else { substep.min(total - done) };
+        let (x, v, error) = dormand_prince(&position, &velocity, substep * delta.signum(), acceleration);
+        let forced = last || substep <= smallest;
+        if error <= 1.0 || forced {
+            clamped |= error > 1.0;
+            (position, velocity) = (x, v);
+            done += substep;
+            substeps += 1;
+        }
+        let factor = if error == 0.0 { 5.0 } else { (0.9 * error.powf(-0.2)).clamp(0.2, 5.0) };
+        substep = (substep * factor).max(smallest);
+    }
+    Integrated { position, velocity, substeps, clamped }
+}
+
+/// How far one integrator ended up from where it started, after what should have been one orbit.
+#[derive(Debug, Clone, Copy)]
+pub struct IntegratorReport {
+    pub kind: IntegratorKind,
+    /// Meters between where the body started and where it ended.
+    pub position_error: f64,
+    /// Change in specific orbital energy, relative to where it started.
+    pub energy_drift: f64,
+}
+
+/// Runs a body starting at `position` and `velocity` around a point mass with gravitational
+/// parameter `mu` for one period, in `steps` steps, under each integrator.
+/// None if the orbit isn't bound or `steps` is zero.
+pub fn compare_integrators(mu: f64, position: DVec3, velocity: DVec3, steps: usize) -> Option<Vec<IntegratorReport>> {
+    let energy = |position: DVec3, velocity: DVec3| velocity.length_squared() / 2.0 - mu / position.length();
+    let start_energy = energy(position, velocity);
+    if start_energy >= 0.0 || steps == 0 { return None; }
+    let period = circular::period(mu, -mu / (2.0 * start_energy));
+    let delta = period / steps as f64;
+    let acceleration = |at: DVec3| gravity::one_body_acceleration(mu, at);
+
+    Some(IntegratorKind::ALL.iter().map(|&kind| {
+        let (mut x, mut v) = (position, velocity);
+        for _ in 0..steps {
+            let step = integrate(kind, x, v, delta, DEFAULT_MAX_SUBSTEPS, acceleration);
+            (x, v) = (step.position, step.velocity);
+        }
+        IntegratorReport {
+            kind,
+            position_error: x.distance(position),
+            energy_drift: ((energy(x, v) - start_energy) / start_energy).abs(),
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EARTH_MU: f64 = 3.986004418e14;
+
+    #[test]
+    fn test_higher_order_beats_euler() {
+        // Periapsis of an orbit with e = 0.5 and a = 10,000 km
+        let periapsis = 5e6;
+        let speed = (EARTH_MU * 1.5 / periapsis).sqrt();
+        let reports = compare_integrators(EARTH_MU, DVec3::X * periapsis, DVec3::Y * speed, 1000).unwrap();
+        let error = |kind: IntegratorKind| reports.iter().find(|report| report.kind == kind).unwrap().position_error;
+
+        let euler = error(IntegratorKind::Euler);
+        for kind in &IntegratorKind::ALL[1..] {
+            assert!(error(*kind) < euler, "{:?}", reports);
+        }
+        assert!(error(IntegratorKind::Rk4) < error(IntegratorKind::Verlet), "{:?}", reports);
+        assert!(error(IntegratorKind::Rk45) < 1.0, "{:?}", reports);
+        let verlet = reports.iter().find(|report| report.kind == IntegratorKind::Verlet).unwrap();
+        assert!(verlet.energy_drift < 1e-6, "{:?}", reports);
+
+        assert!(compare_integrators(EARTH_MU, DVec3::X * periapsis, DVec3::Y * speed * 2.0, 1000).is_none());
+    }
+
+    #[test]
+    fn test_substeps_clamped() {
+        // Falling almost straight in, to pass 100 km from the center within the hour
+        let position = DVec3::X * 1e7;
+        let velocity = DVec3::Y * 900.0;
+        let acceleration = |at: DVec3| gravity::one_body_acceleration(EARTH_MU, at);
+
+        let free = integrate(IntegratorKind::Rk45, position, velocity, 3600.0, 100_000, acceleration);
+        assert!(free.substeps > 16 && !free.clamped, "{:?}", free);
+        let clamped = integrate(IntegratorKind::Rk45, position, velocity, 3600.0, 16, acceleration);
+        assert!(clamped.substeps <= 16 && clamped.clamped, "{:?}", clamped);
+
+        // A gentle step needs no splitting
+        let circular = DVec3::Y * circular::velocity(EARTH_MU, 1e7);
+        let gentle = integrate(IntegratorKind::Rk45, position, circular, 1.0, 16, acceleration);
+        assert!(gentle.substeps == 1 && !gentle.clamped, "{:?}", gentle);
+        assert_eq!(integrate(IntegratorKind::Rk4, position, velocity, 3600.0, 16, acceleration).substeps, 1);
+    }
+}
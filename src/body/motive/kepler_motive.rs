@@ -1,12 +1,13 @@
+use std::collections::{HashMap, HashSet};
 use bevy::math::{DMat3, DVec3};
 use serde::{Deserialize, Serialize};
 use bevy::prelude::*;
 use bevy_egui::egui::Ui;
 use crate::body::motive::info::{BodyInfo, BodyState};
-use crate::body::SimulationObject;
 use crate::body::universe::save::{UniversePhysics, ViewSettings};
 use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
 use crate::gui::planetarium::time::SimTime;
+use crate::foundations::reference_frame::plane::ReferencePlane;
 use crate::foundations::kepler::{angular_motion, apoapsis, eccentric_anomaly, eccentricity, local, mean_anomaly, periapsis, period, semi_latus_rectum, semi_major_axis, semi_minor_axis, semi_parameter, true_anomaly};
 use crate::foundations::time::{Includes, Instant, TimeDelta, TimeLength};
 use crate::util::{mappings};
@@ -45,7 +46,7 @@ impl KeplerMotive {
 
     pub fn time_at_periapsis_passage(&self, gravitational_parameter: f64) -> Instant {
         let period = self.period(gravitational_parameter);
-        self.epoch.time_at_periapsis_passage(period)
+        self.epoch.time_at_periapsis_passage(period, self.eccentricity())
     }
 
     pub fn semi_latus_rectum(&self) -> f64 {
@@ -127,19 +128,19 @@ impl KeplerMotive {
     }
 
     pub fn mean_anomaly(&self, time: Instant, gravitational_parameter: f64) -> f64 {
-        let mean_anomaly_at_epoch = self.epoch.mean_anomaly_at_epoch();
+        let mean_anomaly_at_epoch = self.epoch.mean_anomaly_at_epoch(self.eccentricity());
         let sma = self.shape.semi_major_axis();
         let epoch_time = self.epoch.epoch();
         mean_anomaly::definition(mean_anomaly_at_epoch, gravitational_parameter, sma, epoch_time.to_j2000_seconds(), time.to_j2000_seconds())
     }
 
     pub fn true_anomaly(&self, time: Instant, gravitational_parameter: f64) -> f64 {
-        true_anomaly::fourier_expansion(self.mean_anomaly(time, gravitational_parameter), self.shape.eccentricity(), EXPANSION_ITERATIONS)
+        true_anomaly::at_mean_anomaly(self.mean_anomaly(time, gravitational_parameter), self.shape.eccentricity(), EXPANSION_ITERATIONS)
     }
 
     pub fn radius_from_primary_at_time(&self, time: Instant, gravitational_parameter: f64) -> Option<f64> {
         let ecc = self.shape.eccentricity();
-        let ta = true_anomaly::fourier_expansion(self.mean_anomaly(time, gravitational_parameter), ecc, EXPANSION_ITERATIONS);
+        let ta = true_anomaly::at_mean_anomaly(self.mean_anomaly(time, gravitational_parameter), ecc, EXPANSION_ITERATIONS);
         local::radius::from_elements2(self.shape.semi_major_axis(), ecc, ta)
     }
 
@@ -149,17 +150,89 @@ impl KeplerMotive {
     }
 
     pub fn eccentric_anomaly(&self, time: Instant, gravitational_parameter: f64) -> f64 {
-        let ta = true_anomaly::fourier_expansion(self.mean_anomaly(time, gravitational_parameter), self.shape.eccentricity(), EXPANSION_ITERATIONS);
+        let ta = true_anomaly::at_mean_anomaly(self.mean_anomaly(time, gravitational_parameter), self.shape.eccentricity(), EXPANSION_ITERATIONS);
         eccentric_anomaly::from_true_anomaly(self.shape.eccentricity(), ta)
     }
 
+    /// Sim seconds from `time` until the mean anomaly next reaches `target` (radians).
+    /// Wraps to the next period if the body is already past it. None for open orbits.
+    pub fn time_until_mean_anomaly(&self, target: f64, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        if self.is_open() {
+            return None;
+        }
+        let remaining = (target - self.mean_anomaly(time, gravitational_parameter)).rem_euclid(std::f64::consts::TAU);
+        Some(remaining / self.mean_angular_motion(gravitational_parameter))
+    }
+
+    pub fn time_until_true_anomaly(&self, target: f64, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        let target = mean_anomaly_from_true_anomaly(target, self.eccentricity());
+        self.time_until_mean_anomaly(target, time, gravitational_parameter)
+    }
+
+    /// This orbit's epoch given as `kind` instead, so the body is where it was at every time.
+    /// None if that takes a period and the orbit is open.
+    pub fn epoch_as(&self, kind: KeplerEpochKind, gravitational_parameter: f64) -> Option<KeplerEpoch> {
+        let epoch = self.epoch.epoch();
+        let wrap = |anomaly: f64| mappings::bound_circle(anomaly, std::f64::consts::TAU);
+        Some(match kind {
+            KeplerEpochKind::MeanAnomaly => KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch {
+                epoch,
+                mean_anomaly: wrap(self.epoch.mean_anomaly_at_epoch(self.eccentricity())),
+            }),
+            KeplerEpochKind::TrueAnomaly => KeplerEpoch::TrueAnomaly(TrueAnomalyAtEpoch {
+                epoch,
+                true_anomaly: wrap(self.true_anomaly(epoch, gravitational_parameter)),
+            }),
+            _ if self.is_open() => return None,
+            KeplerEpochKind::J2000 => KeplerEpoch::J2000(MeanAnomalyAtJ2000 {
+                mean_anomaly: wrap(self.mean_anomaly(Instant::J2000, gravitational_parameter)),
+            }),
+            KeplerEpochKind::TimeAtPeriapsisPassage => KeplerEpoch::TimeAtPeriapsisPassage(self.time_at_periapsis_passage(gravitational_parameter)),
+        })
+    }
+
+    pub fn time_until_periapsis(&self, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        self.time_until_mean_anomaly(0.0, time, gravitational_parameter)
+    }
+
+    pub fn time_until_apoapsis(&self, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        self.time_until_mean_anomaly(std::f64::consts::PI, time, gravitational_parameter)
+    }
+
+    /// Until the body next crosses the reference plane heading +Z.
+    /// None for orbits in the reference plane, which have no nodes.
+    pub fn time_until_ascending_node(&self, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        let argument_of_latitude = self.ascending_node_argument_of_latitude()?;
+        let target = argument_of_latitude - self.argument_of_periapsis(time).to_radians();
+        self.time_until_true_anomaly(target, time, gravitational_parameter)
+    }
+
+    pub fn time_until_descending_node(&self, time: Instant, gravitational_parameter: f64) -> Option<f64> {
+        let argument_of_latitude = self.ascending_node_argument_of_latitude()? + std::f64::consts::PI;
+        let target = argument_of_latitude - self.argument_of_periapsis(time).to_radians();
+        self.time_until_true_anomaly(target, time, gravitational_parameter)
+    }
+
+    /// Height above the reference plane goes as sin(ω + ν)·sin(i), so the node heading +Z is at
+    /// ω + ν = 0 for any inclination in (0°, 180°), retrograde included.
+    fn ascending_node_argument_of_latitude(&self) -> Option<f64> {
+        let sin_inclination = self.inclination().to_radians().sin();
+        if sin_inclination.abs() < 1e-9 {
+            None
+        } else if sin_inclination > 0.0 {
+            Some(0.0)
+        } else {
+            Some(std::f64::consts::PI)
+        }
+    }
+
     /// Perifocal Frame
     /// +P (+x) points to periapsis
     /// +Q (+y) points toward motion at periapsis, normal to P
     /// +W (+z) normal to the other 2 according to RHR
     pub fn displacement_pqw(&self, time: Instant, gravitational_parameter: f64) -> Option<DVec3> {
         let ecc = self.shape.eccentricity();
-        let ta = true_anomaly::fourier_expansion(self.mean_anomaly(time, gravitational_parameter), ecc, EXPANSION_ITERATIONS);
+        let ta = true_anomaly::at_mean_anomaly(self.mean_anomaly(time, gravitational_parameter), ecc, EXPANSION_ITERATIONS);
         let rad = local::radius::from_elements2(self.shape.semi_major_axis(), ecc, ta)?;
 
         Some(DVec3::new(rad * ta.cos(), rad * ta.sin(), 0.0))
@@ -172,6 +245,72 @@ impl KeplerMotive {
         Some(rotated)
     }
 
+    /// Velocity relative to the primary, in the perifocal frame.
+    pub fn velocity_pqw(&self, time: Instant, gravitational_parameter: f64) -> DVec3 {
+        let ecc = self.shape.eccentricity();
+        let ta = self.true_anomaly(time, gravitational_parameter);
+        let slr = semi_latus_rectum::conic_definition(self.shape.semi_major_axis(), ecc);
+        let speed = f64::sqrt(gravitational_parameter / slr);
+
+        DVec3::new(-speed * ta.sin(), speed * (ecc + ta.cos()), 0.0)
+    }
+
+    pub fn velocity(&self, time: Instant, gravitational_parameter: f64) -> DVec3 {
+        let perifocal_velocity = self.velocity_pqw(time, gravitational_parameter);
+        self.perifocal_to_reference(perifocal_velocity, time)
+    }
+
+    /// Fits an orbit around `primary_id` to a position and velocity relative to that primary.
+    /// The fit is anchored with a mean anomaly at `time`, so `displacement(time, ...)` lands back on
+    /// `local_position`.
+    /// Returns None for anything that isn't a closed orbit.
+    pub fn from_state_vectors(primary_id: String, local_position: DVec3, local_velocity: DVec3, gravitational_parameter: f64, time: Instant) -> Option<Self> {
+        let radius = local_position.length();
+        let angular_momentum = local::angular_momentum::specific(local_position, local_velocity);
+        let h = angular_momentum.length();
+        if radius < f64::EPSILON || h < f64::EPSILON || gravitational_parameter <= 0.0 {
+            return None;
+        }
+        let normal = angular_momentum / h;
+
+        let eccentricity_vector = eccentricity::vector::definition(local_position, local_velocity, gravitational_parameter);
+        let ecc = eccentricity_vector.length();
+        if ecc >= 1.0 {
+            return None;
+        }
+        // Vis-viva
+        let sma = 1.0 / (2.0 / radius - local_velocity.length_squared() / gravitational_parameter);
+
+        let inclination = normal.z.clamp(-1.0, 1.0).acos();
+
+        // Equatorial orbits have no line of nodes; measure from +x instead.
+        let node = DVec3::Z.cross(angular_momentum);
+        let node = if node.length() / h > 1e-12 { node.normalize() } else { DVec3::X };
+        let longitude_of_ascending_node = node.y.atan2(node.x);
+
+        // Circular orbits have no periapsis; put it on the ascending node.
+        let periapsis = if ecc > 1e-12 { eccentricity_vector / ecc } else { node };
+        let argument_of_periapsis = signed_angle(node, periapsis, normal);
+        let true_anomaly = mappings::bound_circle(signed_angle(periapsis, local_position, normal), std::f64::consts::TAU);
+
+        Some(Self {
+            primary_id,
+            shape: KeplerShape::EccentricitySMA(EccentricitySMA {
+                eccentricity: ecc,
+                semi_major_axis: sma,
+            }),
+            rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+                inclination: inclination.to_degrees(),
+                longitude_of_ascending_node: mappings::bound_degrees(longitude_of_ascending_node.to_degrees()),
+                argument_of_periapsis: mappings::bound_degrees(argument_of_periapsis.to_degrees()),
+            }),
+            epoch: KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch {
+                epoch: time,
+                mean_anomaly: mean_anomaly_from_true_anomaly(true_anomaly, ecc),
+            }),
+        })
+    }
+
     fn perifocal_to_reference(&self, perifocal_displacement: DVec3, time: Instant) -> DVec3 {
         let rot_arg_peri = DMat3::from_rotation_z(self.argument_of_periapsis(time).to_radians());
         let rot_inc = DMat3::from_rotation_x(self.inclination().to_radians());
@@ -180,6 +319,31 @@ impl KeplerMotive {
         rot_long_asc_node * rot_inc * rot_arg_peri * perifocal_displacement
     }
 
+    /// Like the rotation `displacement` and `velocity` use, but into `plane`'s frame.
+    pub fn perifocal_to_plane(&self, perifocal: DVec3, time: Instant, plane: ReferencePlane) -> DVec3 {
+        plane.from_ecliptic() * self.perifocal_to_reference(perifocal, time)
+    }
+
+    /// Inclination, longitude of the ascending node and argument of periapsis in degrees,
+    /// measured against `plane` rather than the ecliptic the elements are stored against.
+    pub fn angles_in(&self, plane: ReferencePlane, time: Instant) -> (f64, f64, f64) {
+        let normal = self.perifocal_to_plane(DVec3::Z, time, plane);
+        let periapsis = self.perifocal_to_plane(DVec3::X, time, plane);
+        let inclination = normal.z.clamp(-1.0, 1.0).acos();
+
+        // Orbits in the plane have no line of nodes; measure from +x instead.
+        let node = DVec3::Z.cross(normal);
+        let node = if node.length() > 1e-12 { node.normalize() } else { DVec3::X };
+        let longitude_of_ascending_node = node.y.atan2(node.x);
+        let argument_of_periapsis = signed_angle(node, periapsis, normal);
+
+        (
+            inclination.to_degrees(),
+            mappings::bound_degrees(longitude_of_ascending_node.to_degrees()),
+            mappings::bound_degrees(argument_of_periapsis.to_degrees()),
+        )
+    }
+
     pub fn display(&self, ui: &mut Ui) {
         ui.label("Shape");
 
@@ -189,6 +353,27 @@ impl KeplerMotive {
     }
 }
 
+/// Angle from `from` to `to`, positive counterclockwise about `axis`.
+fn signed_angle(from: DVec3, to: DVec3, axis: DVec3) -> f64 {
+    from.cross(to).dot(axis).atan2(from.dot(to))
+}
+
+/// Inverts the same `true_anomaly::at_mean_anomaly` that `KeplerMotive::true_anomaly` uses,
+/// so a fitted orbit reproduces the position it was fitted to.
+fn mean_anomaly_from_true_anomaly(target: f64, eccentricity: f64) -> f64 {
+    const STEP: f64 = 1e-7;
+    let forward = |mean_anomaly: f64| true_anomaly::at_mean_anomaly(mean_anomaly, eccentricity, EXPANSION_ITERATIONS);
+
+    let mut mean_anomaly = target;
+    for _ in 0..50 {
+        let error = forward(mean_anomaly) - target;
+        if error.abs() < 1e-13 { break; }
+        let slope = (forward(mean_anomaly + STEP) - forward(mean_anomaly - STEP)) / (2.0 * STEP);
+        mean_anomaly -= error / slope;
+    }
+    mean_anomaly
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub enum KeplerShape {
     EccentricitySMA(EccentricitySMA),
@@ -196,6 +381,17 @@ pub enum KeplerShape {
 }
 
 impl KeplerShape {
+    /// The same orbit as an eccentricity and semi-major axis.
+    pub fn to_eccentricity_sma(&self) -> KeplerShape {
+        KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: self.eccentricity(), semi_major_axis: self.semi_major_axis() })
+    }
+
+    /// The same orbit by its apsides. None if it's open and has no apoapsis.
+    pub fn to_apsides(&self) -> Option<KeplerShape> {
+        let apoapsis = self.apoapsis()?;
+        Some(KeplerShape::Apsides(Apsides { periapsis: self.periapsis(), apoapsis }))
+    }
+
     fn semi_major_axis(&self) -> f64 {
         match self {
             KeplerShape::EccentricitySMA(esma) => {
@@ -361,17 +557,33 @@ pub struct KeplerPrecessingEulerAngles {
     pub argument_of_periapsis: f64,
     pub apsidal_precession_period: TimeLength, // Julian Days
     pub nodal_precession_period: TimeLength, // Julian Days
+    /// Periapsis advances, turning the same way as the orbit. False if it regresses.
+    #[serde(default = "default_prograde")]
+    pub apsidal_prograde: bool,
+    /// The nodes advance. False if they regress, like the Moon's.
+    #[serde(default = "default_prograde")]
+    pub nodal_prograde: bool,
 }
 
+fn default_prograde() -> bool { true }
+
 impl KeplerPrecessingEulerAngles {
+    /// How far periapsis has turned since the epoch, a full turn per apsidal period.
+    /// Negative when it regresses.
     pub fn apsidal_precession_deg(&self, time_since_epoch: TimeDelta) -> f64 {
-        let bound_times = mappings::bound_circle(time_since_epoch.to_seconds(), self.apsidal_precession_period.to_seconds());
-        (bound_times / self.apsidal_precession_period.to_seconds()) / 360.0
+        Self::precession_deg(time_since_epoch, self.apsidal_precession_period, self.apsidal_prograde)
     }
 
+    /// How far the ascending node has turned since the epoch, a full turn per nodal period.
+    /// Negative when the nodes regress.
     pub fn nodal_precession_deg(&self, time_since_epoch: TimeDelta) -> f64 {
-         let bound_times = mappings::bound_circle(time_since_epoch.to_seconds(), self.apsidal_precession_period.to_seconds());
-        (bound_times / self.nodal_precession_period.to_seconds()) / 360.0
+        Self::precession_deg(time_since_epoch, self.nodal_precession_period, self.nodal_prograde)
+    }
+
+    fn precession_deg(time_since_epoch: TimeDelta, period: TimeLength, prograde: bool) -> f64 {
+        let bound_times = mappings::bound_circle(time_since_epoch.to_seconds(), period.to_seconds());
+        let sign = if prograde { 1.0 } else { -1.0 };
+        sign * 360.0 * (bound_times / period.to_seconds())
     }
 }
 
@@ -398,25 +610,37 @@ impl KeplerEpoch {
         }
     }
 
+    pub fn kind(&self) -> KeplerEpochKind {
+        match self {
+            KeplerEpoch::MeanAnomaly(_) => KeplerEpochKind::MeanAnomaly,
+            KeplerEpoch::TimeAtPeriapsisPassage(_) => KeplerEpochKind::TimeAtPeriapsisPassage,
+            KeplerEpoch::TrueAnomaly(_) => KeplerEpochKind::TrueAnomaly,
+            KeplerEpoch::J2000(_) => KeplerEpochKind::J2000,
+        }
+    }
+
     /// This refers to the internal epoch of this particular orbit description.
     /// Most orbits should share the same epoch, but they might not.
-    pub fn mean_anomaly_at_epoch(&self) -> f64 {
+    /// `eccentricity` is only needed to turn a true anomaly into a mean one.
+    pub fn mean_anomaly_at_epoch(&self, eccentricity: f64) -> f64 {
         match self {
             KeplerEpoch::MeanAnomaly(mean_anomaly) => mean_anomaly.mean_anomaly,
             KeplerEpoch::TimeAtPeriapsisPassage(_) => 0.0,
-            KeplerEpoch::TrueAnomaly(_) => { todo!() }
+            KeplerEpoch::TrueAnomaly(taae) => mean_anomaly_from_true_anomaly(taae.true_anomaly, eccentricity),
             KeplerEpoch::J2000(j2000) => j2000.mean_anomaly,
         }
     }
 
-    pub fn time_at_periapsis_passage(&self, period: TimeLength) -> Instant {
+    pub fn time_at_periapsis_passage(&self, period: TimeLength, eccentricity: f64) -> Instant {
         let period_seconds = period.to_seconds();
         let raw_time = match self {
             KeplerEpoch::MeanAnomaly(mean_anomaly) => {
                mean_anomaly.epoch.to_j2000_seconds() - period_seconds * (mean_anomaly.mean_anomaly / std::f64::consts::TAU)
             }
             KeplerEpoch::TimeAtPeriapsisPassage(tapp) => tapp.to_j2000_seconds(),
-            KeplerEpoch::TrueAnomaly(_) => { todo!() }
+            KeplerEpoch::TrueAnomaly(taae) => {
+                taae.epoch.to_j2000_seconds() - period_seconds * (self.mean_anomaly_at_epoch(eccentricity) / std::f64::consts::TAU)
+            }
             KeplerEpoch::J2000(j2000) => {
                 -period_seconds * (j2000.mean_anomaly / (std::f64::consts::TAU))
             }
@@ -433,6 +657,33 @@ impl KeplerEpoch {
     }
 }
 
+/// Which of the ways of giving a `KeplerEpoch` is used, without its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeplerEpochKind {
+    MeanAnomaly,
+    TimeAtPeriapsisPassage,
+    TrueAnomaly,
+    J2000,
+}
+
+impl KeplerEpochKind {
+    pub const ALL: [KeplerEpochKind; 4] = [
+        KeplerEpochKind::MeanAnomaly,
+        KeplerEpochKind::TimeAtPeriapsisPassage,
+        KeplerEpochKind::TrueAnomaly,
+        KeplerEpochKind::J2000,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeplerEpochKind::MeanAnomaly => "Mean anomaly at epoch",
+            KeplerEpochKind::TimeAtPeriapsisPassage => "Time of periapsis passage",
+            KeplerEpochKind::TrueAnomaly => "True anomaly at epoch",
+            KeplerEpochKind::J2000 => "Mean anomaly at J2000",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MeanAnomalyAtEpoch {
     pub epoch: Instant,
@@ -450,62 +701,102 @@ pub struct MeanAnomalyAtJ2000 {
     pub mean_anomaly: f64,
 }
 
-pub fn calculate(
-    mut sim_time: ResMut<SimTime>,
-    mut kepler_bodies: Query<(&mut KeplerMotive, &BodyInfo, &mut BodyState)>,
-    fixed_bodies: Query<(&SimulationObject, &BodyInfo, &BodyState), Without<KeplerMotive>>,
-    physics: Res<UniversePhysics>,
+/// Bodies whose trajectories are waiting for edits to settle before being recomputed.
+/// Dragging an element would otherwise rebuild the whole polyline every frame.
+#[derive(Resource, Default)]
+pub struct TrajectoryDebounce {
+    /// Body id -> real time, in seconds, of the last edit.
+    dirty: HashMap<String, f64>,
+}
+
+impl TrajectoryDebounce {
+    /// How long a body has to go unedited before its trajectory is recomputed.
+    pub const DELAY: f64 = 0.15;
+
+    pub fn mark_dirty<T: AsRef<str>>(&mut self, id: T, now: f64) {
+        self.dirty.insert(id.as_ref().to_string(), now);
+    }
+
+    pub fn is_dirty<T: AsRef<str>>(&self, id: T) -> bool {
+        self.dirty.contains_key(id.as_ref())
+    }
+
+    /// Removes and returns the bodies that have been left alone for at least `DELAY`.
+    pub fn take_ready(&mut self, now: f64) -> Vec<String> {
+        let ready: Vec<String> = self.dirty.iter()
+            .filter(|(_, last_edit)| now - **last_edit >= Self::DELAY)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &ready {
+            self.dirty.remove(id);
+        }
+        ready
+    }
+}
+
+pub fn flush_trajectory_debounce(
+    mut debounce: ResMut<TrajectoryDebounce>,
+    mut calcs: MessageWriter<CalculateTrajectory>,
+    time: Res<Time<Real>>,
 ) {
-    // First collect all body IDs and masses into a HashMap to avoid borrow conflicts
-    let mut bodies_prev_frame: std::collections::HashMap<String, (f64, DVec3)> = std::collections::HashMap::new();
-    for (_, info, state) in fixed_bodies.iter() {
-        bodies_prev_frame.insert(info.id.clone(), (info.mass, state.current_position));
-    }
-    for (_, info, state) in kepler_bodies.iter() {
-        bodies_prev_frame.insert(info.id.clone(), (info.mass, state.current_position));
-    }
-
-    let time = sim_time.time;
-    for (mut motive, _, mut state) in kepler_bodies.iter_mut() {
-        let (primary_mass, primary_position) = bodies_prev_frame.get(&motive.primary_id)
-            .copied()
-            .expect("Missing body info");
-
-        let mu = physics.gravitational_constant * primary_mass;
-        let position = motive.displacement(time, mu);
-        if let Some(position) = position {
-            state.current_position = primary_position + position;
-            state.current_local_position = Some(position);
-            state.current_primary_position = Some(primary_position);
+    let ready = debounce.take_ready(time.elapsed_secs_f64());
+    if !ready.is_empty() {
+        calcs.write(CalculateTrajectory { selection: BodySelection::IDs(ready) });
+    }
+}
+
+/// One period of `kepler`'s displacement from its primary, starting at periapsis and keyed by
+/// seconds since then, in `resolution` segments.
+pub fn sample_trajectory(kepler: &KeplerMotive, mu: f64, resolution: usize) -> TimeMap<DVec3> {
+    let mut map = TimeMap::new();
+    let period = kepler.period(mu);
+    let periapsis_time = kepler.time_at_periapsis_passage(mu);
+
+    if !kepler.is_open() {
+        map.set_periodicity(periapsis_time, period);
+    }
+
+    for i in 0..=resolution {
+        let relative_time = (i as f64 / resolution as f64) * period.to_seconds();
+        let absolute_time = Instant::from_seconds_since_j2000(periapsis_time.to_j2000_seconds() + relative_time);
+        let displacement = kepler.displacement(absolute_time, mu);
+        if let Some(displacement) = displacement {
+            map.insert(relative_time, displacement); // Store using relative time as key
         }
     }
+    map
 }
 
 pub fn calculate_trajectory(
     mut calcs: MessageReader<CalculateTrajectory>,
-    mut bodies: Query<(&mut BodyState, &BodyInfo, &crate::body::motive::Motive)>,
+    mut bodies: Query<(Entity, &mut BodyState, &BodyInfo, &crate::body::motive::Motive)>,
     physics: Res<UniversePhysics>,
     view_settings: Res<ViewSettings>,
     sim_time: Res<SimTime>,
+    mut missing_primaries: Local<HashSet<String>>,
 ) {
     if calcs.is_empty() { return; }
 
     // First collect all body masses into a HashMap
     let mut body_masses: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
-    for (_, info, _) in bodies.iter() {
+    let mut entities: HashMap<String, Entity> = HashMap::new();
+    for (entity, _, info, _) in bodies.iter() {
         body_masses.insert(info.id.clone(), info.mass);
+        entities.insert(info.id.clone(), entity);
     }
 
     let current_time = sim_time.time;
 
     for calc in calcs.read() {
-        for (mut state, info, motive) in bodies.iter_mut() {
-            let do_this = match &calc.selection {
-                BodySelection::All => true,
-                BodySelection::Tag(tag) => info.tags.contains(tag),
-                BodySelection::IDs(ids) => ids.contains(&info.id),
-            };
-            if !do_this { continue; }
+        let selected = |info: &BodyInfo| match &calc.selection {
+            BodySelection::All => true,
+            BodySelection::Tag(tag) => info.tags.contains(tag),
+            BodySelection::IDs(ids) => ids.contains(&info.id),
+        };
+        let mut recalculated = HashSet::new();
+
+        for (_, mut state, info, motive) in bodies.iter_mut() {
+            if !selected(info) { continue; }
 
             // Get the current motive selection
             let (_, selection) = motive.motive_at(current_time);
@@ -516,29 +807,388 @@ pub fn calculate_trajectory(
                 _ => continue,
             };
 
-            let primary_mass = body_masses.get(&kepler_motive.primary_id)
-                .copied()
-                .expect("Missing primary body mass");
+            // Keeps whatever trajectory it had until the primary turns up
+            let Some(primary_mass) = body_masses.get(&kepler_motive.primary_id).copied() else {
+                if missing_primaries.insert(kepler_motive.primary_id.clone()) {
+                    warn!("{} orbits {}, which doesn't exist", info.id, kepler_motive.primary_id);
+                }
+                continue;
+            };
             let mu = physics.gravitational_constant * primary_mass;
 
-            state.trajectory = Some(TimeMap::new());
-            let map = state.trajectory.as_mut().unwrap();
-            let period = kepler_motive.period(mu);
+            trace!("Caching trajectory for {}", info.id);
+            state.trajectory = Some(sample_trajectory(kepler_motive, mu, view_settings.trajectory_resolution));
+            state.trajectory_origin = None;
+            state.trajectory_revision = state.trajectory_revision.wrapping_add(1);
+            recalculated.insert(info.id.clone());
+        }
 
-            let periapsis_time = kepler_motive.time_at_periapsis_passage(mu);
+        // Bodies fixed to a moving primary follow its trajectory, so they're redone with it
+        let fixed: Vec<(Entity, String, DVec3)> = bodies.iter()
+            .filter_map(|(entity, _, info, motive)| match motive.motive_at(current_time).1 {
+                crate::body::motive::MotiveSelection::Fixed { primary_id: Some(primary_id), position }
+                    if selected(info) || recalculated.contains(primary_id) => Some((entity, primary_id.clone(), *position)),
+                _ => None,
+            })
+            .collect();
+        for (entity, primary_id, position) in fixed {
+            let Some(&primary) = entities.get(&primary_id) else { continue };
+            let Ok((_, primary_state, _, primary_motive)) = bodies.get(primary) else { continue };
+            let Some(primary_trajectory) = &primary_state.trajectory else { continue };
+            let trajectory = primary_trajectory.map_values(|displacement| *displacement + position);
+            // Relative to whatever the primary's trajectory is relative to
+            let origin = primary_state.trajectory_origin.or_else(|| {
+                primary_motive.motive_at(current_time).1.primary_id().and_then(|id| entities.get(id).copied())
+            });
+
+            let Ok((_, mut state, info, _)) = bodies.get_mut(entity) else { continue };
+            trace!("Caching trajectory for {} from {}", info.id, primary_id);
+            state.trajectory = Some(trajectory);
+            state.trajectory_origin = origin;
+            state.trajectory_revision = state.trajectory_revision.wrapping_add(1);
+        }
+    }
+}
 
-            if !kepler_motive.is_open() {
-                map.set_periodicity(periapsis_time, period);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foundations::time::JD_SECONDS_PER_JULIAN_DAY;
+
+    fn moon(inclination: f64) -> KeplerMotive {
+        KeplerMotive {
+            primary_id: String::from("earth"),
+            shape: KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.0549, semi_major_axis: 3.844e8 }),
+            rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+                inclination,
+                longitude_of_ascending_node: 125.08,
+                argument_of_periapsis: 318.15,
+            }),
+            epoch: KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch { epoch: Instant::from_seconds_since_j2000(0.0), mean_anomaly: 2.0 }),
+        }
+    }
 
-            for i in 0..=view_settings.trajectory_resolution {
-                let relative_time = (i as f64 / view_settings.trajectory_resolution as f64) * period.to_seconds();
-                let absolute_time = Instant::from_seconds_since_j2000(periapsis_time.to_j2000_seconds() + relative_time);
-                let displacement = kepler_motive.displacement(absolute_time, mu);
-                if let Some(displacement) = displacement {
-                    map.insert(relative_time, displacement); // Store using relative time as key
-                }
+    const EARTH_MU: f64 = 3.986e14;
+
+    #[test]
+    fn test_equatorial_angles() {
+        let epoch = Instant::from_seconds_since_j2000(0.0);
+        let assert_close = |(a, b, c): (f64, f64, f64), (x, y, z): (f64, f64, f64)| {
+            assert!((a - x).abs() < 1e-9 && (b - y).abs() < 1e-9 && (c - z).abs() < 1e-9, "{:?} != {:?}", (a, b, c), (x, y, z));
+        };
+
+        // In the ecliptic, its node is the vernal equinox where the two planes cross
+        let mut in_ecliptic = moon(0.0);
+        in_ecliptic.rotation = KeplerRotation::EulerAngles(KeplerEulerAngles {
+            inclination: 0.0,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 102.9,
+        });
+        assert_close(in_ecliptic.angles_in(ReferencePlane::Ecliptic, epoch), (0.0, 0.0, 102.9));
+        assert_close(in_ecliptic.angles_in(ReferencePlane::Equatorial, epoch), (23.4392911, 0.0, 102.9));
+
+        // Into equatorial coordinates and back leaves the orbit where it was
+        let moon = moon(5.145);
+        assert_close(moon.angles_in(ReferencePlane::Ecliptic, epoch), (5.145, 125.08, 318.15));
+        let position = moon.displacement(epoch, EARTH_MU).unwrap();
+        let equatorial = moon.perifocal_to_plane(moon.displacement_pqw(epoch, EARTH_MU).unwrap(), epoch, ReferencePlane::Equatorial);
+        assert!((equatorial.length() - position.length()).abs() < 1e-6);
+        assert!((ReferencePlane::Equatorial.to_ecliptic() * equatorial - position).length() < 1e-6);
+        let (inclination, _, _) = moon.angles_in(ReferencePlane::Equatorial, epoch);
+        // Tilted from the equator by somewhere between the obliquity less and plus its own inclination
+        assert!(inclination > 23.4392911 - 5.145 && inclination < 23.4392911 + 5.145, "{}", inclination);
+    }
+
+    #[test]
+    fn test_time_until_periapsis() {
+        let moon = moon(5.145);
+        let epoch = Instant::from_seconds_since_j2000(0.0);
+        let n = moon.mean_angular_motion(EARTH_MU);
+        let period = moon.period(EARTH_MU).to_seconds();
+
+        // M = 2 at epoch, so periapsis is 2π - 2 radians of mean motion away
+        let until = moon.time_until_periapsis(epoch, EARTH_MU).unwrap();
+        assert!((until - (std::f64::consts::TAU - 2.0) / n).abs() < 1e-6);
+        let until = moon.time_until_apoapsis(epoch, EARTH_MU).unwrap();
+        assert!((until - (std::f64::consts::PI - 2.0) / n).abs() < 1e-6);
+
+        // Just past periapsis wraps to the next period
+        let just_past = Instant::from_seconds_since_j2000((std::f64::consts::TAU - 2.0) / n + 60.0);
+        let until = moon.time_until_periapsis(just_past, EARTH_MU).unwrap();
+        assert!((until - (period - 60.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_precession_full_turn_per_period() {
+        // The Moon's node goes round in about 18.6 years and its periapsis in about 8.85
+        let rotation = KeplerPrecessingEulerAngles {
+            inclination: 5.145,
+            longitude_of_ascending_node: 125.08,
+            argument_of_periapsis: 318.15,
+            apsidal_precession_period: TimeLength::period_from_julian_day(3231.50),
+            nodal_precession_period: TimeLength::period_from_julian_day(6798.38),
+            apsidal_prograde: true,
+            nodal_prograde: true,
+        };
+        let days = |days: f64| TimeDelta::from_seconds(days * JD_SECONDS_PER_JULIAN_DAY);
+        assert!((rotation.nodal_precession_deg(days(6798.38 / 4.0)) - 90.0).abs() < 1e-9);
+        assert!((rotation.apsidal_precession_deg(days(3231.50 / 2.0)) - 180.0).abs() < 1e-9);
+        // Each angle goes by its own period
+        assert!((rotation.nodal_precession_deg(days(3231.50)) - 360.0 * 3231.50 / 6798.38).abs() < 1e-9);
+
+        let rotation = KeplerRotation::PrecessingEulerAngles(rotation);
+        let node = |days_since_epoch: f64| rotation.longitude_of_ascending_node_infallible(days(days_since_epoch));
+        // A day short of a full turn it's almost back round, and after one it's where it started
+        let daily = 360.0 / 6798.38;
+        assert!((node(6798.38 - 1.0) - (125.08 - daily)).abs() < 1e-6, "{}", node(6798.38 - 1.0));
+        assert!((node(6798.38) - 125.08).abs() < 1e-6, "{}", node(6798.38));
+    }
+
+    #[test]
+    fn test_nodes_regress() {
+        // The Moon's nodes regress while its periapsis advances
+        let moon = |nodal_prograde: bool| KeplerRotation::PrecessingEulerAngles(KeplerPrecessingEulerAngles {
+            inclination: 5.145,
+            longitude_of_ascending_node: 125.08,
+            argument_of_periapsis: 318.15,
+            apsidal_precession_period: TimeLength::period_from_julian_day(3231.50),
+            nodal_precession_period: TimeLength::period_from_julian_day(6798.38),
+            apsidal_prograde: true,
+            nodal_prograde,
+        });
+        let days = |days: f64| TimeDelta::from_seconds(days * JD_SECONDS_PER_JULIAN_DAY);
+        let (advancing, regressing) = (moon(true), moon(false));
+        let quarter = days(6798.38 / 4.0);
+        assert!((advancing.longitude_of_ascending_node_infallible(quarter) - (125.08 + 90.0)).abs() < 1e-6);
+        assert!((regressing.longitude_of_ascending_node_infallible(quarter) - (125.08 - 90.0)).abs() < 1e-6);
+        // Back where it started after a nodal period, whichever way it went
+        assert!((regressing.longitude_of_ascending_node_infallible(days(6798.38)) - 125.08).abs() < 1e-6);
+        // Only the nodes were turned round
+        let apsides = days(3231.50 / 4.0);
+        assert_eq!(advancing.argument_of_periapsis(apsides), regressing.argument_of_periapsis(apsides));
+        assert!((regressing.argument_of_periapsis(apsides) - (318.15 + 90.0 - 360.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_orbit_plane_orientation() {
+        // Tilted 30° about a line of nodes along +Y, so the normal leans over towards +X
+        let mut orbit = moon(30.0);
+        orbit.rotation = KeplerRotation::EulerAngles(KeplerEulerAngles {
+            inclination: 30.0,
+            longitude_of_ascending_node: 90.0,
+            argument_of_periapsis: 40.0,
+        });
+        let quarter = orbit.period(EARTH_MU).to_seconds() / 4.0;
+        let at = |seconds: f64| orbit.displacement(Instant::from_seconds_since_j2000(seconds), EARTH_MU).unwrap();
+        let normal = at(0.0).cross(at(quarter)).normalize();
+
+        let (inclination, node) = (30f64.to_radians(), 90f64.to_radians());
+        let expected = DVec3::new(inclination.sin() * node.sin(), -inclination.sin() * node.cos(), inclination.cos());
+        assert!((normal - expected).length() < 1e-9, "{} vs {}", normal, expected);
+    }
+
+    #[test]
+    fn test_time_until_nodes() {
+        for inclination in [5.145, 150.0] {
+            let moon = moon(inclination);
+            let time = Instant::from_seconds_since_j2000(0.0);
+            let ascending = moon.time_until_ascending_node(time, EARTH_MU).unwrap();
+            let descending = moon.time_until_descending_node(time, EARTH_MU).unwrap();
+
+            let height = |seconds: f64| moon.displacement(Instant::from_seconds_since_j2000(seconds), EARTH_MU).unwrap().z;
+            assert!(height(ascending).abs() < 1e3, "{}: {}", inclination, height(ascending));
+            assert!(height(ascending - 60.0) < 0.0 && height(ascending + 60.0) > 0.0);
+            assert!(height(descending).abs() < 1e3, "{}: {}", inclination, height(descending));
+            assert!(height(descending - 60.0) > 0.0 && height(descending + 60.0) < 0.0);
+        }
+        assert!(moon(0.0).time_until_ascending_node(Instant::from_seconds_since_j2000(0.0), EARTH_MU).is_none());
+    }
+
+    #[test]
+    fn test_reverse_step_returns_to_start() {
+        use std::time::Duration;
+        use crate::body::motive::Motive;
+        use crate::body::motive::calculate_body_positions::{calculate_body_positions, PhysicsGraph, PositionCache, SimulationPerformanceMetrics};
+
+        let mut app = App::new();
+        app.insert_resource(SimTime { playing: true, step: 60.0, gui_speed: 600.0, owed_speed: 600.0, ..Default::default() })
+            .init_resource::<UniversePhysics>()
+            .init_resource::<PhysicsGraph>()
+            .init_resource::<PositionCache>()
+            .init_resource::<SimulationPerformanceMetrics>()
+            .add_systems(Update, calculate_body_positions);
+        let body = |id: &str, mass: f64| BodyInfo { id: String::from(id), mass, ..Default::default() };
+        app.world_mut().spawn((body("earth", 5.972e24), Motive::fixed(DVec3::ZERO), BodyState::default()));
+        let orbit = moon(5.145);
+        let moon = app.world_mut().spawn((
+            body("moon", 7.342e22),
+            Motive::keplerian(orbit.primary_id, orbit.shape, orbit.rotation, orbit.epoch),
+            BodyState::default(),
+        )).id();
+        app.update();
+        let start = app.world().get::<BodyState>(moon).unwrap().current_position;
+
+        // A second of real time a frame, ten steps each way
+        let play = |app: &mut App, frames: usize| {
+            for _ in 0..frames {
+                app.world_mut().resource_mut::<SimTime>().advance(Duration::from_secs(1));
+                app.update();
             }
+            (app.world().resource::<SimTime>().time.to_j2000_seconds(), app.world().get::<BodyState>(moon).unwrap().current_position)
+        };
+        let (time, moved) = play(&mut app, 5);
+        assert_eq!(time, 3000.0);
+        assert!(moved.distance(start) > 1.0);
+
+        app.world_mut().resource_mut::<SimTime>().gui_speed = -600.0;
+        let (time, returned) = play(&mut app, 5);
+        assert_eq!(time, 0.0);
+        assert!(returned.distance(start) < 1e-6, "{} vs {}", returned, start);
+    }
+
+    #[test]
+    fn test_missing_primary_keeps_position() {
+        use crate::body::motive::Motive;
+        use crate::body::motive::calculate_body_positions::{calculate_body_positions, PhysicsGraph, PositionCache, SimulationPerformanceMetrics};
+
+        let mut app = App::new();
+        app.init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<ViewSettings>()
+            .init_resource::<PhysicsGraph>()
+            .init_resource::<PositionCache>()
+            .init_resource::<SimulationPerformanceMetrics>()
+            .add_message::<CalculateTrajectory>()
+            .add_systems(Update, (calculate_body_positions, calculate_trajectory).chain());
+        let orbit = moon(5.145);
+        let start = DVec3::new(1.0, 2.0, 3.0);
+        let entity = app.world_mut().spawn((
+            BodyInfo { id: String::from("orphan"), ..Default::default() },
+            Motive::keplerian(String::from("nowhere"), orbit.shape, orbit.rotation, orbit.epoch),
+            BodyState { current_position: start, ..Default::default() },
+        )).id();
+
+        app.world_mut().write_message(CalculateTrajectory { selection: BodySelection::All });
+        app.update();
+        app.world_mut().resource_mut::<SimTime>().time = Instant::from_seconds_since_j2000(3600.0);
+        app.update();
+        let state = app.world().get::<BodyState>(entity).unwrap();
+        assert_eq!(state.current_position, start);
+        assert!(state.trajectory.is_none());
+    }
+
+    #[test]
+    fn test_fixed_offset_follows_primary() {
+        use crate::body::motive::Motive;
+
+        let mut app = App::new();
+        app.init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<ViewSettings>()
+            .add_message::<CalculateTrajectory>()
+            .add_systems(Update, calculate_trajectory);
+        let body = |id: &str, mass: f64| BodyInfo { id: String::from(id), mass, ..Default::default() };
+        let earth = app.world_mut().spawn((body("earth", 5.972e24), Motive::fixed(DVec3::ZERO), BodyState::default())).id();
+        let radius = 4.2e7;
+        let satellite = app.world_mut().spawn((
+            body("satellite", 1000.0),
+            Motive::keplerian(
+                String::from("earth"),
+                KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.0, semi_major_axis: radius }),
+                KeplerRotation::EulerAngles(KeplerEulerAngles { inclination: 10.0, longitude_of_ascending_node: 30.0, argument_of_periapsis: 0.0 }),
+                KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch { epoch: Instant::from_seconds_since_j2000(0.0), mean_anomaly: 0.0 }),
+            ),
+            BodyState::default(),
+        )).id();
+        let offset = DVec3::new(1e5, -2e5, 3e5);
+        let station = app.world_mut().spawn((
+            body("station", 100.0),
+            Motive::fixed_with_parent(Some(String::from("satellite")), offset),
+            BodyState::default(),
+        )).id();
+
+        app.world_mut().write_message(CalculateTrajectory { selection: BodySelection::All });
+        app.update();
+
+        let world = app.world();
+        let satellite = world.get::<BodyState>(satellite).unwrap();
+        let station = world.get::<BodyState>(station).unwrap();
+        assert_eq!(station.trajectory_origin, Some(earth));
+        let (orbit, path) = (satellite.trajectory.as_ref().unwrap(), station.trajectory.as_ref().unwrap());
+        assert_eq!(path.len(), orbit.len());
+        assert_eq!(path.periodicity().map(|p| p.interval_size), orbit.periodicity().map(|p| p.interval_size));
+        // The same circle, moved over by the offset
+        for ((time, on_orbit), (path_time, on_path)) in orbit.iter().zip(path.iter()) {
+            assert_eq!(time, path_time);
+            assert!((*on_path - *on_orbit - offset).length() < 1e-6);
+            assert!(((*on_path - offset).length() - radius).abs() < 1e-3, "{}", (*on_path - offset).length());
+        }
+    }
+
+    #[test]
+    fn test_debounce_waits_for_edits_to_settle() {
+        let mut debounce = TrajectoryDebounce::default();
+        debounce.mark_dirty("moon", 0.0);
+        assert!(debounce.take_ready(0.125).is_empty());
+
+        // Still being dragged
+        debounce.mark_dirty("moon", 0.125);
+        assert!(debounce.take_ready(0.25).is_empty());
+        assert!(debounce.is_dirty("moon"));
+
+        assert_eq!(debounce.take_ready(0.5), vec![String::from("moon")]);
+        assert!(!debounce.is_dirty("moon"));
+        assert!(debounce.take_ready(10.0).is_empty());
+    }
+
+    #[test]
+    fn test_debounce_tracks_bodies_separately() {
+        let mut debounce = TrajectoryDebounce::default();
+        debounce.mark_dirty("moon", 0.0);
+        debounce.mark_dirty("phobos", 1.0);
+
+        assert_eq!(debounce.take_ready(1.0), vec![String::from("moon")]);
+        assert_eq!(debounce.take_ready(1.25), vec![String::from("phobos")]);
+    }
+
+    #[test]
+    fn test_shape_round_trips_through_apsides() {
+        let shape = KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.0549, semi_major_axis: 3.844e8 });
+        let Some(KeplerShape::Apsides(apsides)) = shape.to_apsides() else { panic!("Expected apsides") };
+        assert!((apsides.periapsis - 3.844e8 * (1.0 - 0.0549)).abs() < 1e-3);
+        assert!((apsides.apoapsis - 3.844e8 * (1.0 + 0.0549)).abs() < 1e-3);
+
+        let KeplerShape::EccentricitySMA(back) = KeplerShape::Apsides(apsides).to_eccentricity_sma() else { panic!("Expected e/a") };
+        assert!((back.eccentricity - 0.0549).abs() < 1e-12);
+        assert!((back.semi_major_axis - 3.844e8).abs() < 1e-6);
+
+        // Escaping orbits don't have an apoapsis to keep
+        assert!(KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 1.2, semi_major_axis: -1e9 }).to_apsides().is_none());
+    }
+
+    #[test]
+    fn test_true_anomaly_epoch_to_mean() {
+        let epoch = |true_anomaly: f64| KeplerEpoch::TrueAnomaly(TrueAnomalyAtEpoch { epoch: Instant::J2000, true_anomaly });
+        // The apsides are the same in either
+        assert!(epoch(0.0).mean_anomaly_at_epoch(0.3).abs() < 1e-9);
+        assert!((epoch(std::f64::consts::PI).mean_anomaly_at_epoch(0.3) - std::f64::consts::PI).abs() < 1e-9);
+        // By Kepler's equation, 1 rad past periapsis at e = 0.3 is a mean anomaly of 0.5553
+        let mean_anomaly = epoch(1.0).mean_anomaly_at_epoch(0.3);
+        assert!((mean_anomaly - 0.5552988988308167).abs() < 1e-3, "{}", mean_anomaly);
+        // And a circle's anomalies are the same everywhere
+        assert!((epoch(1.0).mean_anomaly_at_epoch(0.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_epoch_as_keeps_position() {
+        let moon = moon(5.145);
+        let later = Instant::from_seconds_since_j2000(3.0e6);
+        let expected = moon.displacement(later, EARTH_MU).unwrap();
+        for kind in KeplerEpochKind::ALL {
+            let converted = KeplerMotive { epoch: moon.epoch_as(kind, EARTH_MU).unwrap(), ..moon.clone() };
+            assert_eq!(converted.epoch.kind(), kind);
+            let position = converted.displacement(later, EARTH_MU).unwrap();
+            assert!((position - expected).length() < 1.0, "{:?} put it at {} instead of {}", kind, position, expected);
         }
     }
 }
@@ -3,7 +3,9 @@ pub mod fixed_motive;
 pub mod newton_motive;
 pub mod mass;
 pub mod kepler_motive;
+pub mod orbit_determination;
 pub mod compound_motive;
+pub mod integrator;
 pub mod calculate_body_positions;
 
 pub use compound_motive::{Motive, MotiveSelection, TransitionEvent};
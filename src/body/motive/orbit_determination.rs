@@ -0,0 +1,119 @@
+//! Orbits worked out from where a body has been seen.
+
+use bevy::math::DVec3;
+use crate::body::motive::kepler_motive::KeplerMotive;
+use crate::foundations::time::Instant;
+
+/// Below this angle between samples, in radians, Gibbs's method loses precision and
+/// Herrick-Gibbs is used instead.
+const HERRICK_GIBBS_MAX_SEPARATION: f64 = 5.0 * std::f64::consts::PI / 180.0;
+/// How far, in radians, the first sample may sit out of the plane of the other two.
+const COPLANAR_TOLERANCE: f64 = 0.5 * std::f64::consts::PI / 180.0;
+
+/// Velocity at `r2` from three coplanar positions, by Gibbs's method. Times don't come into it.
+fn gibbs(r1: DVec3, r2: DVec3, r3: DVec3, mu: f64) -> Option<DVec3> {
+    let (l1, l2, l3) = (r1.length(), r2.length(), r3.length());
+    let n = l1 * r2.cross(r3) + l2 * r3.cross(r1) + l3 * r1.cross(r2);
+    let d = r1.cross(r2) + r2.cross(r3) + r3.cross(r1);
+    let s = r1 * (l2 - l3) + r2 * (l3 - l1) + r3 * (l1 - l2);
+    let (n, d_length) = (n.length(), d.length());
+    // Colinear samples, or ones going the wrong way round
+    if n * d_length <= 0.0 || n.dot(d) <= 0.0 {
+        return None;
+    }
+    Some((mu / (n * d_length)).sqrt() * (d.cross(r2) / l2 + s))
+}
+
+/// Velocity at `r2` from three closely spaced positions, by the Herrick-Gibbs Taylor series.
+fn herrick_gibbs(r1: DVec3, t1: f64, r2: DVec3, t2: f64, r3: DVec3, t3: f64, mu: f64) -> Option<DVec3> {
+    let (dt21, dt31, dt32) = (t2 - t1, t3 - t1, t3 - t2);
+    if dt21 <= 0.0 || dt32 <= 0.0 {
+        return None;
+    }
+    let term = |dt_a: f64, dt_b: f64, r: DVec3| 1.0 / (dt_a * dt_b) + mu / (12.0 * r.length().powi(3));
+    Some(-dt32 * term(dt21, dt31, r1) * r1
+        + (dt32 - dt21) * term(dt21, dt32, r2) * r2
+        + dt21 * term(dt32, dt31, r3) * r3)
+}
+
+/// The orbit through three positions relative to the primary, seen one after another at `t1`, `t2`
+/// and `t3`. Widely spread samples use Gibbs's method and close ones Herrick-Gibbs, then the
+/// elements come from the state at the middle sample.
+/// `primary_id` is left empty for the caller to fill in.
+/// None for samples that are colinear, out of one plane, out of order, or not on a closed orbit.
+pub fn fit_orbit_from_three(r1: DVec3, t1: Instant, r2: DVec3, t2: Instant, r3: DVec3, t3: Instant, mu: f64) -> Option<KeplerMotive> {
+    if mu <= 0.0 || [r1, r2, r3].iter().any(|r| r.length() <= 0.0 || !r.is_finite()) {
+        return None;
+    }
+    let normal = r2.cross(r3);
+    if normal.length() <= 0.0 {
+        return None;
+    }
+    if r1.normalize().dot(normal.normalize()).abs() > COPLANAR_TOLERANCE.sin() {
+        return None;
+    }
+
+    let close = r1.angle_between(r2) < HERRICK_GIBBS_MAX_SEPARATION && r2.angle_between(r3) < HERRICK_GIBBS_MAX_SEPARATION;
+    let v2 = if close {
+        herrick_gibbs(r1, t1.to_j2000_seconds(), r2, t2.to_j2000_seconds(), r3, t3.to_j2000_seconds(), mu)?
+    } else {
+        gibbs(r1, r2, r3, mu)?
+    };
+    KeplerMotive::from_state_vectors(String::new(), r2, v2, mu, t2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerRotation, KeplerShape, MeanAnomalyAtJ2000};
+
+    const EARTH_MU: f64 = 3.986004418e14;
+
+    fn inclined() -> KeplerMotive {
+        KeplerMotive {
+            primary_id: String::from("earth"),
+            shape: KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.3, semi_major_axis: 2.0e7 }),
+            rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+                inclination: 63.4,
+                longitude_of_ascending_node: 40.0,
+                argument_of_periapsis: 270.0,
+            }),
+            epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 { mean_anomaly: 0.3 }),
+        }
+    }
+
+    fn assert_recovers(orbit: &KeplerMotive, seconds: [f64; 3]) {
+        let times = seconds.map(Instant::from_seconds_since_j2000);
+        let [r1, r2, r3] = times.map(|t| orbit.displacement(t, EARTH_MU).unwrap());
+        let fit = fit_orbit_from_three(r1, times[0], r2, times[1], r3, times[2], EARTH_MU).unwrap();
+
+        assert!((fit.semi_major_axis() / orbit.semi_major_axis() - 1.0).abs() < 1e-4, "{}", fit.semi_major_axis());
+        assert!((fit.eccentricity() - orbit.eccentricity()).abs() < 1e-4, "{}", fit.eccentricity());
+        assert!((fit.inclination() - orbit.inclination()).abs() < 1e-3, "{}", fit.inclination());
+        // And it goes on to the same places
+        let later = Instant::from_seconds_since_j2000(seconds[2] + 20_000.0);
+        let miss = fit.displacement(later, EARTH_MU).unwrap().distance(orbit.displacement(later, EARTH_MU).unwrap());
+        assert!(miss < 1e-3 * orbit.semi_major_axis(), "{}", miss);
+    }
+
+    #[test]
+    fn test_three_samples_recover_orbit() {
+        let orbit = inclined();
+        // Spread out, for Gibbs
+        assert_recovers(&orbit, [0.0, 3000.0, 6000.0]);
+        // A minute apart, for Herrick-Gibbs
+        assert_recovers(&orbit, [20_000.0, 20_060.0, 20_120.0]);
+    }
+
+    #[test]
+    fn test_degenerate_samples() {
+        let time = |seconds| Instant::from_seconds_since_j2000(seconds);
+        let r = DVec3::new(7e6, 0.0, 0.0);
+        assert!(fit_orbit_from_three(r, time(0.0), r * 2.0, time(60.0), r * 3.0, time(120.0), EARTH_MU).is_none());
+        // Out of the plane of the other two
+        let r1 = DVec3::new(0.0, 0.0, 7e6);
+        let r2 = DVec3::new(7e6, 0.0, 0.0);
+        let r3 = DVec3::new(0.0, 7e6, 0.0);
+        assert!(fit_orbit_from_three(r1, time(0.0), r2, time(600.0), r3, time(1200.0), EARTH_MU).is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::math::{DMat3, DVec3};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::foundations::time::Instant;
+use crate::util::mappings;
+
+/// Spin of a body about its pole. Angles are in degrees, like the Kepler elements.
+///
+/// The body-fixed frame is reached the same way `KeplerMotive` reaches its perifocal frame:
+/// turn by the equator's node, tilt, then turn by the prime meridian.
+/// Body-fixed +X points out through the prime meridian on the equator, +Z out the north pole.
+#[derive(Serialize, Deserialize, Component, Clone, Debug, PartialEq)]
+pub struct BodyRotation {
+    /// Sidereal rotation period in seconds. Negative for retrograde rotation.
+    pub period: f64,
+    /// Angle between the pole and reference +Z.
+    pub axial_tilt: f64,
+    /// Longitude, on the reference plane, of where the equator crosses it heading north.
+    pub node_longitude: f64,
+    /// Angle from the node to the prime meridian at `epoch`, along the equator.
+    pub prime_meridian_at_epoch: f64,
+    pub epoch: Instant,
+}
+
+impl BodyRotation {
+    /// Angle from the node to the prime meridian at `time`, in [0, 360).
+    pub fn prime_meridian_angle(&self, time: Instant) -> f64 {
+        let rotations = (time - self.epoch).to_seconds() / self.period;
+        mappings::bound_circle(self.prime_meridian_at_epoch + 360.0 * rotations, 360.0)
+    }
+
+    /// Rotates body-fixed vectors into the reference frame.
+    pub fn body_to_reference(&self, time: Instant) -> DMat3 {
+        DMat3::from_rotation_z(self.node_longitude.to_radians())
+            * DMat3::from_rotation_x(self.axial_tilt.to_radians())
+            * DMat3::from_rotation_z(self.prime_meridian_angle(time).to_radians())
+    }
+
+    pub fn pole(&self) -> DVec3 {
+        DMat3::from_rotation_z(self.node_longitude.to_radians())
+            * DMat3::from_rotation_x(self.axial_tilt.to_radians())
+            * DVec3::Z
+    }
+}
+
+/// How close, as a fraction, a rotation period has to be to the orbital period to count as tidally locked.
+pub const TIDAL_LOCK_TOLERANCE: f64 = 0.01;
+
+/// Whether a body turning once every `sidereal_day` seconds keeps one face to its primary.
+pub fn tidally_locked(sidereal_day: f64, orbital_period: f64) -> bool {
+    (sidereal_day / orbital_period - 1.0).abs() < TIDAL_LOCK_TOLERANCE
+}
+
+/// Seconds from noon to noon on a body turning once every `sidereal_day` seconds and going
+/// round its star every `orbital_period`. Negative, like the sidereal day, for retrograde rotation.
+/// None when tidally locked, as the sun never moves.
+pub fn solar_day(sidereal_day: f64, orbital_period: f64) -> Option<f64> {
+    if tidally_locked(sidereal_day, orbital_period) { return None; }
+    Some(sidereal_day / (1.0 - sidereal_day / orbital_period))
+}
+
+/// Latitude and longitude, in degrees, of the point on the primary directly beneath `sat_pos`.
+/// Longitude is east of the prime meridian, in (-180, 180].
+pub fn subpoint(sat_pos: DVec3, primary: &BodyRotation, primary_pos: DVec3, t: Instant) -> (f64, f64) {
+    let body_fixed = primary.body_to_reference(t).transpose() * (sat_pos - primary_pos);
+    let latitude = (body_fixed.z / body_fixed.length()).asin().to_degrees();
+    let longitude = body_fixed.y.atan2(body_fixed.x).to_degrees();
+    (latitude, longitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDEREAL_DAY: f64 = 86_164.0905;
+
+    fn earth() -> BodyRotation {
+        BodyRotation {
+            period: SIDEREAL_DAY,
+            axial_tilt: 23.44,
+            node_longitude: 0.0,
+            prime_meridian_at_epoch: 280.46,
+            epoch: Instant::from_seconds_since_j2000(0.0),
+        }
+    }
+
+    fn assert_subpoint(actual: (f64, f64), expected: (f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_subpoint_over_prime_meridian_at_epoch() {
+        let earth = earth();
+        let epoch = earth.epoch;
+        let earth_pos = DVec3::new(-2.6e10, 1.45e11, 0.0);
+        let over_prime_meridian = earth.body_to_reference(epoch) * DVec3::new(4.2164e7, 0.0, 0.0);
+
+        assert_subpoint(subpoint(earth_pos + over_prime_meridian, &earth, earth_pos, epoch), (0.0, 0.0));
+
+        // A quarter turn later the ground has moved east underneath it
+        let later = Instant::from_seconds_since_j2000(SIDEREAL_DAY / 4.0);
+        assert_subpoint(subpoint(earth_pos + over_prime_meridian, &earth, earth_pos, later), (0.0, -90.0));
+    }
+
+    #[test]
+    fn test_solar_day() {
+        let year = 365.256363 * 86_400.0;
+        let earth = solar_day(SIDEREAL_DAY, year).unwrap();
+        assert!((earth - 86_400.0).abs() < 1.0, "{}", earth);
+
+        // Venus turns backwards slower than it goes round, so its days are shorter than its rotation
+        let venus = solar_day(-243.0226 * 86_400.0, 224.701 * 86_400.0).unwrap() / 86_400.0;
+        assert!((venus + 116.75).abs() < 0.01, "{}", venus);
+
+        assert!(tidally_locked(27.32 * 86_400.0, 27.3217 * 86_400.0));
+        assert!(solar_day(27.32 * 86_400.0, 27.3217 * 86_400.0).is_none());
+    }
+
+    #[test]
+    fn test_prime_meridian_returns_after_one_period() {
+        let earth = earth();
+        let start = earth.body_to_reference(earth.epoch);
+        let one_day = Instant::from_seconds_since_j2000(SIDEREAL_DAY);
+        assert!((earth.prime_meridian_angle(one_day) - earth.prime_meridian_at_epoch).abs() < 1e-9);
+        assert!(earth.body_to_reference(one_day).abs_diff_eq(start, 1e-12));
+
+        // Retrograde turns the other way, and still comes back
+        let venus = BodyRotation { period: -SIDEREAL_DAY, ..earth.clone() };
+        let quarter = Instant::from_seconds_since_j2000(SIDEREAL_DAY / 4.0);
+        assert!((earth.prime_meridian_angle(quarter) - 10.46).abs() < 1e-9);
+        assert!((venus.prime_meridian_angle(quarter) - 190.46).abs() < 1e-9);
+        assert!((venus.prime_meridian_angle(one_day) - venus.prime_meridian_at_epoch).abs() < 1e-9);
+        assert!(venus.body_to_reference(one_day).abs_diff_eq(start, 1e-12));
+    }
+
+    #[test]
+    fn test_subpoint_latitude() {
+        let earth = earth();
+        let epoch = earth.epoch;
+        let (latitude, _) = subpoint(earth.pole() * 1e7, &earth, DVec3::ZERO, epoch);
+        assert!((latitude - 90.0).abs() < 1e-6, "{}", latitude);
+
+        let northeast = earth.body_to_reference(epoch) * DVec3::new(1.0, 1.0, 2.0f64.sqrt());
+        assert_subpoint(subpoint(northeast, &earth, DVec3::ZERO, epoch), (45.0, 45.0));
+    }
+}
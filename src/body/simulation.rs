@@ -0,0 +1,321 @@
+use std::path::PathBuf;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use crate::body::motive::calculate_body_positions::{self, PhysicsGraph, PositionCache, SimulationPerformanceMetrics};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::SimulationObject;
+use crate::body::universe::{Major, Minor, Universe};
+use crate::body::universe::save::{UniverseFile, UniversePhysics};
+use crate::body::universe::scenario::{self, Scenario};
+use crate::foundations::time::Instant;
+use crate::gui::planetarium::time::{PreviousTimes, SimTime};
+
+/// Runs the physics without a window, renderer or any of the GUI.
+/// Time only moves when told to.
+pub struct Simulation {
+    app: App,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app
+            .init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<PhysicsGraph>()
+            .init_resource::<PositionCache>()
+            .init_resource::<SimulationPerformanceMetrics>()
+            .init_resource::<Scenario>()
+            .add_systems(Update, (
+                calculate_body_positions::calculate_body_positions,
+                scenario::run_scenario.after(calculate_body_positions::calculate_body_positions),
+            ));
+        Self { app }
+    }
+
+    /// Loads a save in either format.
+    pub fn load_em(path: &PathBuf) -> Option<Self> {
+        UniverseFile::load_from_path(path).map(Self::from_file)
+    }
+
+    pub fn from_file(file: UniverseFile) -> Self {
+        let mut simulation = Self::new();
+        let (_, sim_time) = Universe::from_file(&file);
+        let world = simulation.app.world_mut();
+        world.insert_resource(SimTime {
+            playing: true,
+            // Every step is taken, however long it takes
+            max_frame_time: f64::INFINITY,
+            ..sim_time
+        });
+        let scenario = Scenario::new(file.contents.scenario, sim_time.time, file.contents.physics.gravitational_constant);
+        world.insert_resource(UniversePhysics { gravitational_constant: scenario.gravitational_constant(), ..file.contents.physics });
+        world.insert_resource(scenario);
+
+        for body in file.contents.bodies {
+            let (info, appearance, motive, rotation, properties) = body.into_parts();
+            let mut entity = world.spawn((SimulationObject, BodyState::default(), motive, appearance));
+            if info.major {
+                entity.insert(Major);
+            } else {
+                entity.insert(Minor);
+            }
+            entity.insert(info);
+            if let Some(rotation) = rotation {
+                entity.insert(rotation);
+            }
+            entity.insert(properties);
+        }
+
+        // Positions at the starting time
+        simulation.app.update();
+        simulation
+    }
+
+    /// Physics step in seconds. Keplerian bodies are exact at any step; Newtonian ones aren't.
+    pub fn set_step(&mut self, step: f64) {
+        self.app.world_mut().resource_mut::<SimTime>().step = step;
+    }
+
+    pub fn time(&self) -> Instant {
+        self.app.world().resource::<SimTime>().time
+    }
+
+    /// Advances by `dt` seconds in whole physics steps, plus one short step for any remainder.
+    /// Negative `dt` runs backwards. Scenario events crossed along the way take effect at the end.
+    pub fn step(&mut self, dt: f64) {
+        let step = self.app.world().resource::<SimTime>().step;
+        let full_steps = (dt.abs() / step).floor();
+        let remainder = dt.abs() - full_steps * step;
+
+        self.run_steps(full_steps as usize, step * dt.signum());
+        if remainder > f64::EPSILON * dt.abs().max(1.0) {
+            self.run_steps(1, remainder * dt.signum());
+        }
+    }
+
+    pub fn advance_to(&mut self, time: Instant) {
+        self.step((time - self.time()).to_seconds());
+    }
+
+    /// Position of the body with this id, relative to the origin.
+    pub fn body_position<T: AsRef<str>>(&self, id: T) -> Option<DVec3> {
+        let world = self.app.world();
+        let mut bodies = world.try_query::<(&BodyInfo, &BodyState)>()?;
+        bodies.iter(world)
+            .find(|(info, _)| info.id == id.as_ref())
+            .map(|(_, state)| state.current_position)
+    }
+
+    fn run_steps(&mut self, count: usize, signed_step: f64) {
+        if count == 0 {
+            return;
+        }
+        let step = {
+            let mut sim_time = self.app.world_mut().resource_mut::<SimTime>();
+            let step = sim_time.step;
+            let start = sim_time.time.to_j2000_seconds();
+            sim_time.step = signed_step.abs();
+            sim_time.gui_speed = sim_time.gui_speed.abs() * signed_step.signum();
+            sim_time.previous_times = PreviousTimes::with_values(start + signed_step, count, signed_step);
+            step
+        };
+
+        while !self.app.world().resource::<SimTime>().previous_times.is_empty() {
+            self.app.update();
+        }
+        self.app.world_mut().resource_mut::<SimTime>().step = step;
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::{Motive, TransitionEvent};
+    use crate::body::motive::calculate_body_positions::CachedMotiveSelection;
+    use crate::body::appearance::Appearance;
+    use crate::body::motive::info::BodyProperties;
+    use crate::body::rotation::BodyRotation;
+    use crate::body::universe::save::{CompoundMotiveEntry, SaveFormat, SomeBody, UniverseFileContents, UniverseFileTime, ViewSettings};
+    use crate::body::universe::scenario::{ScenarioEvent, ScheduledEvent};
+    use crate::body::universe::solar_system::solar_system;
+    use crate::foundations::time::J2000_JD;
+
+    const AU: f64 = 1.495978707e11;
+
+    #[test]
+    fn test_headless_solar_system() {
+        let path = std::env::temp_dir().join(format!("headless_solar_system_{}.em", std::process::id()));
+        let mut file = solar_system();
+        file.save_as(path.clone(), SaveFormat::Sqlite).unwrap();
+        let mut simulation = Simulation::load_em(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        simulation.set_step(3600.0);
+
+        let earth_distance = |simulation: &Simulation| {
+            (simulation.body_position("earth").unwrap() - simulation.body_position("sol").unwrap()).length() / AU
+        };
+        assert!((earth_distance(&simulation) - 1.0).abs() < 0.03, "{}", earth_distance(&simulation));
+
+        let start = simulation.time();
+        let half_year = Instant::from_seconds_since_j2000(start.to_j2000_seconds() + 182.6 * 86_400.0);
+        let before = simulation.body_position("earth").unwrap();
+        simulation.advance_to(half_year);
+        assert!((simulation.time().to_j2000_seconds() - half_year.to_j2000_seconds()).abs() < 1e-3);
+        assert!((earth_distance(&simulation) - 1.0).abs() < 0.03, "{}", earth_distance(&simulation));
+        // Other side of the Sun
+        assert!(simulation.body_position("earth").unwrap().dot(before) < 0.0);
+        assert!(simulation.body_position("nowhere").is_none());
+    }
+
+    /// A probe coasting along x at 1 m/s, with a 1 m/s kick scheduled 50.5 s in.
+    fn probe_with_impulse() -> UniverseFile {
+        let probe = CompoundMotiveEntry {
+            info: BodyInfo { id: "probe".into(), mass: 1.0, ..Default::default() },
+            motive: Motive::newtonian(DVec3::ZERO, DVec3::X),
+            appearance: Default::default(),
+            rotation: None,
+            properties: Default::default(),
+        };
+        UniverseFile {
+            file: None,
+            contents: UniverseFileContents {
+                version: "0.0".into(),
+                time: UniverseFileTime { time_julian_days: J2000_JD, step: 1.0, gui_speed: 1.0, max_frame_time: 0.016 },
+                view: ViewSettings::default(),
+                physics: UniversePhysics::default(),
+                bodies: vec![SomeBody::CompoundMotiveEntry(probe)],
+                scenario: vec![ScheduledEvent {
+                    time: Instant::from_seconds_since_j2000(50.5),
+                    event: ScenarioEvent::Impulse { body_id: "probe".into(), delta_v: DVec3::X },
+                    fired_at: None,
+                }],
+                camera_bookmarks: Vec::new(),
+                includes: Vec::new(),
+            },
+        }
+    }
+
+    fn impulses(simulation: &mut Simulation) -> usize {
+        let world = simulation.app.world_mut();
+        let mut motives = world.query::<&Motive>();
+        motives.single(world).unwrap().iter_events()
+            .filter(|(_, event, _)| matches!(event, TransitionEvent::Impulse))
+            .count()
+    }
+
+    fn snapshot(
+        bodies: Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>,
+        sim_time: Res<SimTime>,
+        physics: Res<UniversePhysics>,
+        scenario: Res<Scenario>,
+    ) -> UniverseFileContents {
+        UniverseFileContents::snapshot(&bodies, &sim_time, &ViewSettings::default(), &physics, &scenario, &[])
+    }
+
+    #[test]
+    fn test_scenario_impulse_fires_once() {
+        let mut simulation = Simulation::from_file(probe_with_impulse());
+        for _ in 0..100 {
+            simulation.step(1.0);
+        }
+
+        // Coasts at 1 m/s until the step after the impulse, then at 2 m/s
+        let position = simulation.body_position("probe").unwrap();
+        assert!((position.x - (51.0 + 49.0 * 2.0)).abs() < 1e-9, "{}", position);
+        assert_eq!(impulses(&mut simulation), 1);
+    }
+
+    #[test]
+    fn test_scenario_survives_reload() {
+        let path = std::env::temp_dir().join(format!("scenario_reload_{}.em", std::process::id()));
+        let mut simulation = Simulation::from_file(probe_with_impulse());
+        simulation.step(100.0);
+        let contents = simulation.app.world_mut().run_system_cached(snapshot).unwrap();
+        UniverseFile { file: None, contents }.save_as(path.clone(), SaveFormat::Sqlite).unwrap();
+
+        let mut reloaded = Simulation::load_em(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(impulses(&mut reloaded), 1);
+
+        // Rewinding past the impulse takes it back out, so it isn't applied twice on the way forward again
+        reloaded.step(-100.0);
+        assert_eq!(impulses(&mut reloaded), 0);
+        reloaded.step(100.0);
+        assert_eq!(impulses(&mut reloaded), 1);
+    }
+
+    #[test]
+    fn test_spawned_body_survives_reload() {
+        let path = std::env::temp_dir().join(format!("spawn_reload_{}.em", std::process::id()));
+        let beacon = CompoundMotiveEntry {
+            info: BodyInfo { id: "beacon".into(), mass: 1.0, ..Default::default() },
+            motive: Motive::fixed(DVec3::new(5.0, 0.0, 0.0)),
+            appearance: Default::default(),
+            rotation: None,
+            properties: Default::default(),
+        };
+        let mut file = probe_with_impulse();
+        file.contents.scenario.push(ScheduledEvent {
+            time: Instant::from_seconds_since_j2000(10.5),
+            event: ScenarioEvent::SpawnBody(Box::new(beacon)),
+            fired_at: None,
+        });
+        let at_beacon = |simulation: &Simulation| simulation.body_position("beacon")
+            .is_some_and(|position| position.distance(DVec3::new(5.0, 0.0, 0.0)) < 1e-9);
+
+        let mut simulation = Simulation::from_file(file);
+        simulation.step(20.0);
+        assert!(at_beacon(&simulation));
+        let contents = simulation.app.world_mut().run_system_cached(snapshot).unwrap();
+        UniverseFile { file: None, contents }.save_as(path.clone(), SaveFormat::Sqlite).unwrap();
+
+        let mut reloaded = Simulation::load_em(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(at_beacon(&reloaded));
+
+        // Still goes and comes back with its event
+        reloaded.step(-20.0);
+        assert!(reloaded.body_position("beacon").is_none());
+        reloaded.step(20.0);
+        assert!(at_beacon(&reloaded));
+    }
+
+    #[test]
+    fn test_mass_edit_keeps_graph() {
+        let mut simulation = Simulation::from_file(solar_system());
+        let sorted = simulation.app.world().resource::<PhysicsGraph>().sorted_entities.clone();
+
+        let world = simulation.app.world_mut();
+        let mut bodies = world.query::<(Entity, &mut BodyInfo)>();
+        let mut sol = None;
+        let mut earth = None;
+        for (entity, mut info) in bodies.iter_mut(world) {
+            match info.id.as_str() {
+                "sol" => {
+                    info.mass *= 2.0;
+                    sol = Some((entity, info.mass));
+                }
+                "earth" => earth = Some(entity),
+                _ => {}
+            }
+        }
+        let (sol, sol_mass) = sol.unwrap();
+        simulation.step(60.0);
+
+        let graph = simulation.app.world().resource::<PhysicsGraph>();
+        assert_eq!(graph.sorted_entities, sorted);
+        assert_eq!(graph.body_data[&sol].mass, sol_mass);
+        let gravitational_constant = simulation.app.world().resource::<UniversePhysics>().gravitational_constant;
+        match graph.cached_motives[&earth.unwrap()].selection {
+            CachedMotiveSelection::Keplerian { mu } => assert_eq!(mu, gravitational_constant * sol_mass),
+            _ => panic!("earth should be Keplerian"),
+        }
+    }
+}
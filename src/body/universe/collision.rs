@@ -0,0 +1,258 @@
+//! Bodies touching each other.
+//!
+//! A contact is two bodies closer than the sum of their appearance radii, at least one of
+//! them Newtonian. Bodies on motives go where their motives say, so two of them overlapping
+//! is the scenario's business. `UniversePhysics::collision_behavior` decides what a contact does,
+//! once, when it starts.
+
+use std::collections::HashSet;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::body::appearance::Appearance;
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::{Motive, MotiveSelection, TransitionEvent};
+use crate::body::universe::deletion::{self, DeleteBody, OrphanPolicy, WorldState};
+use crate::body::universe::save::UniversePhysics;
+use crate::foundations::time::Instant;
+use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
+use crate::gui::planetarium::time::SimTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CollisionBehavior {
+    /// Log the contact and carry on.
+    #[default]
+    Ignore,
+    /// The lighter body is absorbed into the heavier one. A Newtonian survivor moves off from their
+    /// center of mass with their combined momentum; one on a Keplerian or fixed motive stays on it.
+    Merge,
+    /// Bodies push apart, keeping `UniversePhysics::restitution` of their approach speed.
+    Bounce,
+}
+
+impl CollisionBehavior {
+    pub const ALL: [CollisionBehavior; 3] = [CollisionBehavior::Ignore, CollisionBehavior::Merge, CollisionBehavior::Bounce];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CollisionBehavior::Ignore => "Ignore",
+            CollisionBehavior::Merge => "Merge",
+            CollisionBehavior::Bounce => "Bounce",
+        }
+    }
+}
+
+/// One body with all of `a` and `b`'s mass and momentum, at their center of mass.
+/// Perfectly inelastic.
+pub fn merge((mass_a, a): (f64, WorldState), (mass_b, b): (f64, WorldState)) -> (f64, WorldState) {
+    let mass = mass_a + mass_b;
+    if mass <= 0.0 {
+        return (mass, WorldState {
+            position: (a.position + b.position) / 2.0,
+            velocity: (a.velocity + b.velocity) / 2.0,
+        });
+    }
+    (mass, WorldState {
+        position: (a.position * mass_a + b.position * mass_b) / mass,
+        velocity: (a.velocity * mass_a + b.velocity * mass_b) / mass,
+    })
+}
+
+/// New velocities of `a` and `b` after bouncing off each other. The part of their relative
+/// velocity along the line between them is reflected and scaled by `restitution`, 1 being
+/// perfectly elastic. Bodies already moving apart are left alone.
+pub fn bounce((mass_a, a): (f64, WorldState), (mass_b, b): (f64, WorldState), restitution: f64) -> (DVec3, DVec3) {
+    let normal = (b.position - a.position).normalize_or_zero();
+    let approach = (b.velocity - a.velocity).dot(normal);
+    let inverse_masses = 1.0 / mass_a + 1.0 / mass_b;
+    if approach >= 0.0 || !inverse_masses.is_finite() || inverse_masses <= 0.0 {
+        return (a.velocity, b.velocity);
+    }
+    let impulse = -(1.0 + restitution) * approach / inverse_masses;
+    (a.velocity - normal * (impulse / mass_a), b.velocity + normal * (impulse / mass_b))
+}
+
+/// Pairs of bodies in contact, each with its lower entity first.
+fn contacts(
+    bodies: &Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>,
+    appearances: &Query<&Appearance>,
+    time: Instant,
+) -> HashSet<(Entity, Entity)> {
+    let all: Vec<_> = bodies.iter()
+        .map(|(entity, _, motive, state)| {
+            let newtonian = matches!(motive.motive_at(time).1, MotiveSelection::Newtonian { .. });
+            let radius = appearances.get(entity).map(|appearance| appearance.radius()).unwrap_or(0.0);
+            (entity, newtonian, state.current_position, radius)
+        })
+        .collect();
+
+    let mut found = HashSet::new();
+    for &(entity, newtonian, position, radius) in &all {
+        if !newtonian { continue; }
+        for &(other, other_newtonian, other_position, other_radius) in &all {
+            // Pairs of Newtonian bodies come up from both sides
+            if other == entity || (other_newtonian && other < entity) { continue; }
+            if position.distance(other_position) < radius + other_radius {
+                found.insert((entity.min(other), entity.max(other)));
+            }
+        }
+    }
+    found
+}
+
+/// Switches a body to Newtonian motion from `state`.
+fn set_newtonian(bodies: &mut Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>, entity: Entity, time: Instant, state: WorldState) {
+    let Ok((_, _, mut motive, mut body_state)) = bodies.get_mut(entity) else { return };
+    motive.insert_event(time, TransitionEvent::Impulse, MotiveSelection::Newtonian {
+        position: state.position,
+        velocity: state.velocity,
+    });
+    body_state.newtonian_init_time = None;
+}
+
+pub fn resolve_collisions(
+    mut touching: Local<HashSet<(Entity, Entity)>>,
+    mut commands: Commands,
+    mut deletions: MessageWriter<DeleteBody>,
+    mut calcs: MessageWriter<CalculateTrajectory>,
+    physics: Res<UniversePhysics>,
+    sim_time: Res<SimTime>,
+    mut bodies: Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>,
+    appearances: Query<&Appearance>,
+) {
+    let time = sim_time.time;
+    let g = physics.gravitational_constant;
+    let found = contacts(&bodies, &appearances, time);
+    let started: Vec<_> = found.iter().filter(|pair| !touching.contains(pair)).copied().collect();
+    *touching = found;
+
+    // A body merged away this frame is still in the query until the deletion goes through
+    let mut merged: Vec<Entity> = Vec::new();
+    for (first, second) in started {
+        if merged.contains(&first) || merged.contains(&second) { continue; }
+        let (Ok((_, first_info, _, _)), Ok((_, second_info, _, _))) = (bodies.get(first), bodies.get(second)) else { continue };
+        let (first_info, second_info) = (first_info.clone(), second_info.clone());
+        let (Some(first_state), Some(second_state)) = (
+            deletion::world_state(&first_info.id, &bodies, time, g),
+            deletion::world_state(&second_info.id, &bodies, time, g),
+        ) else { continue };
+
+        match physics.collision_behavior {
+            CollisionBehavior::Ignore => {
+                info!("{} and {} collided", first_info.display_name(), second_info.display_name());
+            }
+            CollisionBehavior::Merge => {
+                let (mass, state) = merge((first_info.mass, first_state), (second_info.mass, second_state));
+                // The heavier body carries on under its own id
+                let ((survivor, survivor_info), (absorbed, absorbed_info)) = if first_info.mass >= second_info.mass {
+                    ((first, first_info), (second, second_info))
+                } else {
+                    ((second, second_info), (first, first_info))
+                };
+                info!("{} merged into {}", absorbed_info.display_name(), survivor_info.display_name());
+                // A survivor on any other motive keeps it, only heavier
+                if bodies.get(survivor).is_ok_and(|(_, _, motive, _)| motive.is_newtonian(time)) {
+                    set_newtonian(&mut bodies, survivor, time, state);
+                }
+                commands.entity(survivor).insert(BodyInfo { mass, ..survivor_info.clone() });
+                merged.push(absorbed);
+                // Deleting also takes it out of the universe's maps and its tags
+                deletions.write(DeleteBody { id: absorbed_info.id, policy: OrphanPolicy::Reparent });
+                calcs.write(CalculateTrajectory { selection: BodySelection::IDs(vec![survivor_info.id]) });
+            }
+            CollisionBehavior::Bounce => {
+                let (first_velocity, second_velocity) = bounce(
+                    (first_info.mass, first_state),
+                    (second_info.mass, second_state),
+                    physics.restitution,
+                );
+                set_newtonian(&mut bodies, first, time, WorldState { velocity: first_velocity, ..first_state });
+                set_newtonian(&mut bodies, second, time, WorldState { velocity: second_velocity, ..second_state });
+                calcs.write(CalculateTrajectory { selection: BodySelection::IDs(vec![first_info.id, second_info.id]) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::appearance::DebugBall;
+    use crate::util::circular;
+
+    fn body(position: DVec3, velocity: DVec3) -> WorldState {
+        WorldState { position, velocity }
+    }
+
+    #[test]
+    fn test_merge_conserves_momentum() {
+        let a = (7.3e22, body(DVec3::new(1.0e6, 0.0, 0.0), DVec3::new(0.0, 1.0e3, 20.0)));
+        let b = (2.1e20, body(DVec3::new(1.0e6, 3.0e6, 0.0), DVec3::new(-4.0e3, 0.0, 0.0)));
+        let (mass, merged) = merge(a, b);
+        assert_eq!(mass, a.0 + b.0);
+
+        let momentum = a.1.velocity * a.0 + b.1.velocity * b.0;
+        assert!((merged.velocity * mass - momentum).length() < momentum.length() * 1e-12);
+        // At the center of mass, which is much nearer the heavier body
+        let center = (a.1.position * a.0 + b.1.position * b.0) / mass;
+        assert!(merged.position.distance(center) < 1e-6);
+        assert!(merged.position.distance(a.1.position) < merged.position.distance(b.1.position));
+    }
+
+    #[test]
+    fn test_bounce() {
+        let a = (2.0, body(DVec3::ZERO, DVec3::X));
+        let b = (1.0, body(DVec3::X, -DVec3::X));
+        let momentum = |(va, vb): (DVec3, DVec3)| va * a.0 + vb * b.0;
+        let energy = |(va, vb): (DVec3, DVec3)| 0.5 * a.0 * va.length_squared() + 0.5 * b.0 * vb.length_squared();
+
+        let elastic = bounce(a, b, 1.0);
+        assert!((momentum(elastic) - momentum((a.1.velocity, b.1.velocity))).length() < 1e-12);
+        assert!((energy(elastic) - energy((a.1.velocity, b.1.velocity))).abs() < 1e-12);
+        assert!(elastic.1.x > elastic.0.x, "{:?}", elastic);
+
+        // With no restitution they move off together
+        let (va, vb) = bounce(a, b, 0.0);
+        assert!((va - vb).length() < 1e-12);
+        // Moving apart already, nothing happens
+        let leaving = (1.0, body(DVec3::X, DVec3::X * 3.0));
+        assert_eq!(bounce(a, leaving, 1.0), (a.1.velocity, leaving.1.velocity));
+    }
+
+    #[test]
+    fn test_merge_keeps_keplerian_survivor() {
+        let mut app = App::new();
+        app
+            .insert_resource(UniversePhysics { collision_behavior: CollisionBehavior::Merge, ..default() })
+            .init_resource::<SimTime>()
+            .add_message::<DeleteBody>()
+            .add_message::<CalculateTrajectory>()
+            .add_systems(Update, resolve_collisions);
+        let g = app.world().resource::<UniversePhysics>().gravitational_constant;
+        let time = app.world().resource::<SimTime>().time;
+        let ball = |radius: f64| Appearance::DebugBall(DebugBall { radius, color: default() });
+
+        let sun = BodyInfo { id: "sol".into(), mass: 2e30, major: true, ..default() };
+        app.world_mut().spawn((sun, Motive::fixed(DVec3::ZERO), BodyState::default()));
+        let orbit = circular::circular_orbit("sol".into(), 1.5e11, 0.0);
+        let position = orbit.displacement(time, g * 2e30).unwrap();
+        let earth = app.world_mut().spawn((
+            BodyInfo { id: "earth".into(), mass: 6e24, major: true, ..default() },
+            Motive::keplerian(orbit.primary_id, orbit.shape, orbit.rotation, orbit.epoch),
+            BodyState { current_position: position, ..default() },
+            ball(6.4e6),
+        )).id();
+        app.world_mut().spawn((
+            BodyInfo { id: "pebble".into(), mass: 1.0, ..default() },
+            Motive::newtonian(position, DVec3::X * 1e3),
+            BodyState { current_position: position, current_velocity: Some(DVec3::X * 1e3), ..default() },
+            ball(1.0),
+        ));
+        app.update();
+
+        let motive = app.world().get::<Motive>(earth).unwrap();
+        assert!(motive.is_keplerian(time));
+        assert_eq!(motive.iter_events().count(), 1);
+        assert_eq!(app.world().get::<BodyInfo>(earth).unwrap().mass, 6e24 + 1.0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Removing bodies from a running universe.
+//!
+//! A deleted body can leave satellites behind: anything whose current motive names it as
+//! a primary. `OrphanPolicy` decides what becomes of them. Either way the satellite keeps its
+//! world position at the instant of deletion.
+
+use std::collections::HashMap;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::kepler_motive::KeplerMotive;
+use crate::body::motive::{Motive, MotiveSelection, PhysicsGraph, TransitionEvent};
+use crate::body::universe::save::{UniversePhysics, ViewSettings};
+use crate::body::universe::Universe;
+use crate::foundations::time::Instant;
+use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
+use crate::gui::planetarium::time::SimTime;
+
+#[derive(Message)]
+pub struct DeleteBody {
+    pub id: String,
+    pub policy: OrphanPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrphanPolicy {
+    /// Hand satellites to the deleted body's own primary.
+    #[default]
+    Reparent,
+    /// Turn satellites loose as Newtonian bodies with their last world position and velocity.
+    Release,
+}
+
+impl OrphanPolicy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrphanPolicy::Reparent => "Reparent to grandparent",
+            OrphanPolicy::Release => "Release as Newtonian",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorldState {
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+/// The body that inherits a deleted body's satellites.
+pub struct NewPrimary {
+    pub id: String,
+    pub state: WorldState,
+    pub mass: f64,
+}
+
+/// Works out the motive a satellite should switch to at `time` once its primary is gone.
+/// `new_primary` is None when the deleted body had no primary of its own.
+///
+/// Reparenting falls back to releasing when there is nothing sensible to orbit:
+/// a Keplerian satellite with no grandparent, or one that isn't bound to it.
+pub fn orphan_selection(
+    policy: OrphanPolicy,
+    selection: &MotiveSelection,
+    child: WorldState,
+    new_primary: Option<&NewPrimary>,
+    time: Instant,
+    gravitational_constant: f64,
+) -> (TransitionEvent, MotiveSelection) {
+    let release = (TransitionEvent::Impulse, MotiveSelection::Newtonian {
+        position: child.position,
+        velocity: child.velocity,
+    });
+    if policy == OrphanPolicy::Release {
+        return release;
+    }
+
+    match (selection, new_primary) {
+        (MotiveSelection::Fixed { .. }, None) => (TransitionEvent::SOIChange, MotiveSelection::Fixed {
+            primary_id: None,
+            position: child.position,
+        }),
+        (MotiveSelection::Fixed { .. }, Some(primary)) => (TransitionEvent::SOIChange, MotiveSelection::Fixed {
+            primary_id: Some(primary.id.clone()),
+            position: child.position - primary.state.position,
+        }),
+        (MotiveSelection::Keplerian(_), Some(primary)) => {
+            let mu = gravitational_constant * primary.mass;
+            let local_position = child.position - primary.state.position;
+            let local_velocity = child.velocity - primary.state.velocity;
+            KeplerMotive::from_state_vectors(primary.id.clone(), local_position, local_velocity, mu, time)
+                .map(|kepler| (TransitionEvent::SOIChange, MotiveSelection::Keplerian(kepler)))
+                .unwrap_or(release)
+        }
+        _ => release,
+    }
+}
+
+struct BodySnapshot<'a> {
+    entity: Entity,
+    mass: f64,
+    motive: &'a Motive,
+    state: &'a BodyState,
+}
+
+/// Velocity in the world frame, found by walking up the primaries.
+fn world_velocity(id: &str, bodies: &HashMap<&str, BodySnapshot>, time: Instant, gravitational_constant: f64, depth: usize) -> DVec3 {
+    let Some(body) = bodies.get(id) else { return DVec3::ZERO };
+    if depth > bodies.len() {
+        warn!("Primaries of {} are circular", id);
+        return DVec3::ZERO;
+    }
+
+    match &body.motive.motive_at(time).1 {
+        MotiveSelection::Fixed { primary_id, .. } => primary_id.as_deref()
+            .map(|primary_id| world_velocity(primary_id, bodies, time, gravitational_constant, depth + 1))
+            .unwrap_or(DVec3::ZERO),
+        MotiveSelection::Newtonian { velocity, .. } => body.state.current_velocity.unwrap_or(*velocity),
+        MotiveSelection::Keplerian(kepler) => {
+            let primary_mass = bodies.get(kepler.primary_id.as_str()).map(|primary| primary.mass).unwrap_or(0.0);
+            let mu = gravitational_constant * primary_mass;
+            kepler.velocity(time, mu) + world_velocity(&kepler.primary_id, bodies, time, gravitational_constant, depth + 1)
+        }
+    }
+}
+
+/// World velocity of every body, by id.
+pub fn world_velocities<'a>(
+    bodies: impl Iterator<Item = (&'a BodyInfo, &'a Motive, &'a BodyState)>,
+    time: Instant,
+    gravitational_constant: f64,
+) -> HashMap<String, DVec3> {
+    let snapshots: HashMap<&str, BodySnapshot> = bodies
+        .map(|(info, motive, state)| (info.id.as_str(), BodySnapshot {
+            // Only needed for deleting
+            entity: Entity::PLACEHOLDER,
+            mass: info.mass,
+            motive,
+            state,
+        }))
+        .collect();
+    snapshots.keys()
+        .map(|id| (id.to_string(), world_velocity(id, &snapshots, time, gravitational_constant, 0)))
+        .collect()
+}
+
+/// World position and velocity of a body, for changing its motive in place.
+pub(super) fn world_state(
+    id: &str,
+    bodies: &Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>,
+    time: Instant,
+    gravitational_constant: f64,
+) -> Option<WorldState> {
+    let snapshots: HashMap<&str, BodySnapshot> = bodies.iter()
+        .map(|(entity, info, motive, state)| (info.id.as_str(), BodySnapshot {
+            entity,
+            mass: info.mass,
+            motive,
+            state,
+        }))
+        .collect();
+    let body = snapshots.get(id)?;
+    Some(WorldState {
+        position: body.state.current_position,
+        velocity: world_velocity(id, &snapshots, time, gravitational_constant, 0),
+    })
+}
+
+pub fn delete_bodies(
+    mut commands: Commands,
+    mut deletions: MessageReader<DeleteBody>,
+    mut universe: ResMut<Universe>,
+    mut graph: ResMut<PhysicsGraph>,
+    mut view_settings: ResMut<ViewSettings>,
+    mut calcs: MessageWriter<CalculateTrajectory>,
+    physics: Res<UniversePhysics>,
+    sim_time: Res<SimTime>,
+    mut bodies: Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>,
+) {
+    let time = sim_time.time;
+    let g = physics.gravitational_constant;
+    // Despawns are deferred, so bodies deleted earlier this frame are still in the query.
+    let mut deleted_this_frame: Vec<Entity> = Vec::new();
+
+    for deletion in deletions.read() {
+        let (deleted, orphans) = {
+            let snapshots: HashMap<&str, BodySnapshot> = bodies.iter()
+                .filter(|(entity, _, _, _)| !deleted_this_frame.contains(entity))
+                .map(|(entity, info, motive, state)| (info.id.as_str(), BodySnapshot {
+                    entity,
+                    mass: info.mass,
+                    motive,
+                    state,
+                }))
+                .collect();
+
+            let Some(deleted) = snapshots.get(deletion.id.as_str()) else {
+                warn!("Tried to delete {}, which doesn't exist", deletion.id);
+                continue;
+            };
+
+            let new_primary = deleted.motive.motive_at(time).1.primary_id()
+                .and_then(|id| snapshots.get(id).map(|primary| NewPrimary {
+                    id: id.to_string(),
+                    state: WorldState {
+                        position: primary.state.current_position,
+                        velocity: world_velocity(id, &snapshots, time, g, 0),
+                    },
+                    mass: primary.mass,
+                }));
+
+            let mut orphans = Vec::new();
+            for (id, body) in &snapshots {
+                let (_, selection) = body.motive.motive_at(time);
+                if selection.primary_id() != Some(deletion.id.as_str()) { continue; }
+
+                let child = WorldState {
+                    position: body.state.current_position,
+                    velocity: world_velocity(id, &snapshots, time, g, 0),
+                };
+                let (event, selection) = orphan_selection(deletion.policy, selection, child, new_primary.as_ref(), time, g);
+                orphans.push((body.entity, id.to_string(), event, selection));
+            }
+
+            (deleted.entity, orphans)
+        };
+
+        let mut orphan_ids = Vec::with_capacity(orphans.len());
+        for (entity, id, event, selection) in orphans {
+            let Ok((_, _, mut motive, mut state)) = bodies.get_mut(entity) else { continue };
+            if matches!(selection, MotiveSelection::Newtonian { .. }) {
+                // Start integrating from the new motive, not from any earlier Newtonian stint.
+                state.newtonian_init_time = None;
+            }
+            motive.insert_event(time, event, selection);
+            orphan_ids.push(id);
+        }
+
+        commands.entity(deleted).despawn();
+        deleted_this_frame.push(deleted);
+        universe.remove_by_id(&deletion.id);
+        for tag in view_settings.tags.values_mut() {
+            tag.members.retain(|member| member != &deletion.id);
+        }
+        graph.needs_rebuild = true;
+
+        if !orphan_ids.is_empty() {
+            calcs.write(CalculateTrajectory { selection: BodySelection::IDs(orphan_ids) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerRotation, KeplerShape, MeanAnomalyAtEpoch};
+
+    const G: f64 = 6.6743e-11;
+    const EARTH_MASS: f64 = 5.972e24;
+
+    fn deletion_instant() -> Instant {
+        Instant::from_seconds_since_j2000(86_400.0)
+    }
+
+    /// A planet whose moon is being orphaned, and where that moon is in the world.
+    fn planet_and_moon() -> (NewPrimary, KeplerMotive, WorldState) {
+        let time = deletion_instant();
+        let planet = NewPrimary {
+            id: String::from("planet"),
+            state: WorldState {
+                position: DVec3::new(1.5e11, -2.0e10, 3.0e8),
+                velocity: DVec3::new(-4.0e3, 2.9e4, 10.0),
+            },
+            mass: EARTH_MASS,
+        };
+        let moon = KeplerMotive {
+            primary_id: String::from("planet"),
+            shape: KeplerShape::EccentricitySMA(EccentricitySMA {
+                eccentricity: 0.05,
+                semi_major_axis: 3.8e8,
+            }),
+            rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+                inclination: 5.1,
+                longitude_of_ascending_node: 125.0,
+                argument_of_periapsis: 318.0,
+            }),
+            epoch: KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch {
+                epoch: Instant::from_seconds_since_j2000(0.0),
+                mean_anomaly: 2.0,
+            }),
+        };
+        let mu = G * planet.mass;
+        let world = WorldState {
+            position: planet.state.position + moon.displacement(time, mu).unwrap(),
+            velocity: planet.state.velocity + moon.velocity(time, mu),
+        };
+        (planet, moon, world)
+    }
+
+    fn assert_close(a: DVec3, b: DVec3) {
+        let error = (a - b).length() / b.length();
+        assert!(error < 1e-9, "{:?} != {:?} (relative error {})", a, b, error);
+    }
+
+    #[test]
+    fn test_release_preserves_world_position() {
+        let (planet, moon, world) = planet_and_moon();
+        let (_, selection) = orphan_selection(OrphanPolicy::Release, &MotiveSelection::Keplerian(moon), world, Some(&planet), deletion_instant(), G);
+        let MotiveSelection::Newtonian { position, velocity } = selection else { panic!("Released body should be Newtonian") };
+        assert_close(position, world.position);
+        assert_close(velocity, world.velocity);
+    }
+
+    #[test]
+    fn test_reparent_keplerian_preserves_world_position() {
+        let (planet, moon, world) = planet_and_moon();
+        let (_, selection) = orphan_selection(OrphanPolicy::Reparent, &MotiveSelection::Keplerian(moon), world, Some(&planet), deletion_instant(), G);
+        let MotiveSelection::Keplerian(refit) = selection else { panic!("Bound body should stay Keplerian") };
+        assert_eq!(refit.primary_id, "planet");
+        let position = planet.state.position + refit.displacement(deletion_instant(), G * planet.mass).unwrap();
+        assert_close(position, world.position);
+    }
+
+    #[test]
+    fn test_reparent_fixed_preserves_world_position() {
+        let (planet, _, world) = planet_and_moon();
+        let fixed = MotiveSelection::Fixed { primary_id: Some(String::from("deleted")), position: DVec3::new(1.0, 2.0, 3.0) };
+        let (_, selection) = orphan_selection(OrphanPolicy::Reparent, &fixed, world, Some(&planet), deletion_instant(), G);
+        let MotiveSelection::Fixed { primary_id, position } = selection else { panic!("Fixed body should stay Fixed") };
+        assert_eq!(primary_id.as_deref(), Some("planet"));
+        assert_close(planet.state.position + position, world.position);
+    }
+}
@@ -0,0 +1,161 @@
+//! What changed between two versions of a universe, body by body.
+
+use std::collections::HashMap;
+use crate::body::motive::kepler_motive::KeplerMotive;
+use crate::body::universe::save::{SomeBody, UniverseFileContents};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniverseDiff {
+    /// Only in the second universe.
+    Added(String),
+    /// Only in the first universe.
+    Removed(String),
+    /// In both, but `field` went from `old` to `new`.
+    Changed { id: String, field: &'static str, old: String, new: String },
+}
+
+/// The fields compared for each Keplerian motive, in order.
+fn elements(kepler: &KeplerMotive) -> [(&'static str, String); 7] {
+    let epoch = kepler.epoch.epoch();
+    [
+        ("primary", kepler.primary_id.clone()),
+        ("semi_major_axis", kepler.semi_major_axis().to_string()),
+        ("eccentricity", kepler.eccentricity().to_string()),
+        ("inclination", kepler.inclination().to_string()),
+        ("longitude_of_ascending_node", kepler.longitude_of_ascending_node_infallible(epoch).to_string()),
+        ("argument_of_periapsis", kepler.argument_of_periapsis(epoch).to_string()),
+        ("epoch", serde_json::to_string(&kepler.epoch).unwrap_or_default()),
+    ]
+}
+
+/// Fields every body has, in the order they're compared.
+const BODY_FIELDS: [&str; 7] = ["name", "mass", "major", "tags", "radius", "appearance", "motion"];
+
+/// Everything about a body compared field by field, in `BODY_FIELDS` order.
+/// `motion` is how it moves, elements included, the same whichever kind of entry it was saved as.
+fn body_fields(body: &SomeBody) -> Vec<(&'static str, String)> {
+    let (info, appearance, motive, rotation, properties) = body.clone().into_parts();
+    let events: Vec<_> = motive.iter_events().collect();
+    let motion = serde_json::json!({ "events": events, "rotation": rotation, "properties": properties });
+    let values = [
+        info.display_name(),
+        info.mass.to_string(),
+        info.major.to_string(),
+        info.tags.join(", "),
+        appearance.radius().to_string(),
+        serde_json::to_string(&appearance).unwrap_or_default(),
+        motion.to_string(),
+    ];
+    BODY_FIELDS.into_iter().zip(values).collect()
+}
+
+fn changes<I: IntoIterator<Item = ((&'static str, String), (&'static str, String))>>(id: &str, pairs: I) -> impl Iterator<Item = UniverseDiff> {
+    let id = id.to_string();
+    pairs.into_iter()
+        .filter(|((_, old), (_, new))| old != new)
+        .map(move |((field, old), (_, new))| UniverseDiff::Changed { id: id.clone(), field, old, new })
+}
+
+/// Bodies added to and removed from `a` to get `b`, in `b`'s and then `a`'s order, then the
+/// fields that changed on bodies in both. Keplerian elements are compared motive by motive
+/// when both have as many Keplerian motives, and `motion` is only reported when they don't
+/// explain the change.
+pub fn diff_universes(a: &UniverseFileContents, b: &UniverseFileContents) -> Vec<UniverseDiff> {
+    let in_a: HashMap<&str, &SomeBody> = a.bodies.iter().map(|body| (body.info().id.as_str(), body)).collect();
+    let in_b: HashMap<&str, &SomeBody> = b.bodies.iter().map(|body| (body.info().id.as_str(), body)).collect();
+
+    let mut diffs: Vec<UniverseDiff> = b.bodies.iter()
+        .map(|body| body.info().id.as_str())
+        .filter(|id| !in_a.contains_key(id))
+        .map(|id| UniverseDiff::Added(id.to_string()))
+        .collect();
+    diffs.extend(a.bodies.iter()
+        .map(|body| body.info().id.as_str())
+        .filter(|id| !in_b.contains_key(id))
+        .map(|id| UniverseDiff::Removed(id.to_string())));
+
+    for old_body in &a.bodies {
+        let id = old_body.info().id.as_str();
+        let Some(new_body) = in_b.get(id) else { continue };
+        let mut changed: Vec<UniverseDiff> = changes(id, body_fields(old_body).into_iter().zip(body_fields(new_body))).collect();
+
+        let (old_keplers, new_keplers) = (old_body.kepler_motives(), new_body.kepler_motives());
+        if old_keplers.len() == new_keplers.len() {
+            let element_changes: Vec<UniverseDiff> = old_keplers.iter().zip(&new_keplers)
+                .flat_map(|(old, new)| changes(id, elements(old).into_iter().zip(elements(new))))
+                .collect();
+            if !element_changes.is_empty() {
+                changed.retain(|diff| !matches!(diff, UniverseDiff::Changed { field: "motion", .. }));
+            }
+            changed.extend(element_changes);
+        }
+        diffs.extend(changed);
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::*;
+    use crate::body::appearance::Appearance;
+    use crate::body::motive::info::{BodyInfo, BodyProperties};
+    use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerShape};
+    use crate::body::motive::Motive;
+    use crate::body::rotation::BodyRotation;
+    use crate::body::universe::save::{UniversePhysics, ViewSettings};
+    use crate::body::universe::scenario::Scenario;
+    use crate::body::universe::solar_system::solar_system;
+    use crate::gui::planetarium::time::SimTime;
+
+    #[test]
+    fn test_added_removed_and_changed() {
+        let a = solar_system().contents;
+        assert!(diff_universes(&a, &solar_system().contents).is_empty());
+
+        let mut b = solar_system().contents;
+        b.bodies.retain(|body| body.info().id != "4-vesta");
+        let mut moon = solar_system().contents.bodies.into_iter().find(|body| body.info().id == "luna").unwrap();
+        moon.info_mut().id = "second-moon".into();
+        b.bodies.push(moon);
+        let Some(SomeBody::KeplerEntry(mars)) = b.bodies.iter_mut().find(|body| body.info().id == "mars") else { panic!("Mars isn't Keplerian") };
+        let old_semi_major_axis = mars.params.semi_major_axis();
+        mars.params.shape = KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: mars.params.eccentricity(), semi_major_axis: 2.5e11 });
+
+        let diffs = diff_universes(&a, &b);
+        assert_eq!(diffs, vec![
+            UniverseDiff::Added("second-moon".into()),
+            UniverseDiff::Removed("4-vesta".into()),
+            UniverseDiff::Changed {
+                id: "mars".into(),
+                field: "semi_major_axis",
+                old: old_semi_major_axis.to_string(),
+                new: 2.5e11.to_string(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_legacy_file_matches_its_snapshot() {
+        let toml = toml::to_string(&solar_system().contents).unwrap();
+        let legacy: UniverseFileContents = toml::from_str(&toml).unwrap();
+        assert!(legacy.bodies.iter().any(|body| matches!(body, SomeBody::KeplerEntry(_))));
+
+        let mut world = World::new();
+        for body in &legacy.bodies {
+            let (info, appearance, motive, rotation, properties) = body.clone().into_parts();
+            let mut entity = world.spawn((info, appearance, motive, properties));
+            if let Some(rotation) = rotation {
+                entity.insert(rotation);
+            }
+        }
+        let mut state: SystemState<Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>> =
+            SystemState::new(&mut world);
+        let snapshot = UniverseFileContents::snapshot(
+            &state.get(&world), &SimTime::default(), &ViewSettings::default(), &UniversePhysics::default(), &Scenario::default(), &[],
+        );
+
+        assert_eq!(diff_universes(&legacy, &snapshot), Vec::new());
+    }
+}
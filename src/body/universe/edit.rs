@@ -0,0 +1,841 @@
+//! Undoable edits to the universe.
+//!
+//! Every change the editor makes goes through an `EditCommand`, applied by `apply_edit`.
+//! Undo and redo use the same path, so an edit and its inverse can't drift apart.
+
+use bevy::ecs::system::SystemParam;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+use bevy::camera::visibility::NoFrustumCulling;
+use crate::body::appearance::{Appearance, AssetCache};
+use crate::body::motive::info::{BodyInfo, BodyProperties, BodyState};
+use crate::body::motive::kepler_motive::{KeplerEpoch, KeplerMotive, KeplerRotation, KeplerShape, TrajectoryDebounce};
+use crate::body::motive::{Motive, MotiveSelection, PhysicsGraph};
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::deletion::{DeleteBody, OrphanPolicy};
+use crate::body::universe::save::{insert_render_components, CompoundMotiveEntry, SomeBody, ViewSettings};
+use crate::body::universe::Universe;
+use crate::foundations::time::Instant;
+use crate::gui::menu::TagState;
+use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
+use crate::gui::planetarium::time::SimTime;
+
+/// How many edits can be undone.
+const UNDO_LIMIT: usize = 256;
+
+/// How far along its orbit a duplicate starts from the original, in radians of anomaly.
+const DUPLICATE_ANOMALY_OFFSET: f64 = 0.01;
+/// How far a duplicate that isn't orbiting starts from the original, in the original's radii.
+const DUPLICATE_RADII_OFFSET: f64 = 4.0;
+/// The least a duplicate that isn't orbiting is set apart, in meters, for bodies with little or no radius.
+const DUPLICATE_MIN_OFFSET: f64 = 1.0e6;
+
+#[derive(Message)]
+pub enum Edit {
+    /// Apply and record a command.
+    /// While `coalesce` is set, consecutive commands to the same field become one undo step,
+    /// so a drag is undone all at once.
+    Apply { command: EditCommand, coalesce: bool },
+    /// End the current coalesced command, e.g. when a drag is released.
+    Seal,
+    Undo,
+    Redo,
+}
+
+#[derive(Clone)]
+pub enum EditCommand {
+    SetMass { id: String, before: f64, after: f64 },
+    /// Position of a Fixed or Newtonian motive. `event_time` picks out the motive event being edited.
+    SetPosition { id: String, event_time: Instant, before: DVec3, after: DVec3 },
+    /// Velocity of a Newtonian motive.
+    SetVelocity { id: String, event_time: Instant, before: DVec3, after: DVec3 },
+    SetKeplerElement { id: String, event_time: Instant, element: KeplerElement, before: f64, after: f64 },
+    /// Describes a Keplerian motive's orbit another way. Both should be the same orbit.
+    SetKeplerShape { id: String, event_time: Instant, before: KeplerShape, after: KeplerShape },
+    /// A Keplerian motive's epoch, its way of being given included.
+    SetKeplerEpoch { id: String, event_time: Instant, before: KeplerEpoch, after: KeplerEpoch },
+    /// None takes the rotation off, or puts a body back to having none.
+    SetRotation { id: String, before: Option<BodyRotation>, after: Option<BodyRotation> },
+    SetAppearance { id: String, before: Appearance, after: Appearance },
+    SetProperties { id: String, before: BodyProperties, after: BodyProperties },
+    /// `satellites` are motives to restore on bodies that orbited this one before it was removed.
+    AddBody { body: Box<CompoundMotiveEntry>, satellites: Vec<(String, Motive)> },
+    /// `satellites` is filled in each time the command is applied, redo included, before
+    /// `delete_bodies` puts them under `policy`.
+    RemoveBody { body: Box<CompoundMotiveEntry>, policy: OrphanPolicy, satellites: Vec<(String, Motive)> },
+    /// Several commands that are undone and redone together, like the bodies of one import.
+    Batch(Vec<EditCommand>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeplerElement {
+    Eccentricity,
+    SemiMajorAxis,
+    Periapsis,
+    Apoapsis,
+    Inclination,
+    LongitudeOfAscendingNode,
+    ArgumentOfPeriapsis,
+    LongitudeOfPeriapsis,
+    MeanAnomaly,
+    TrueAnomaly,
+}
+
+impl KeplerElement {
+    /// The field this element is stored in, if this orbit's description has one.
+    fn slot<'a>(&self, kepler: &'a mut KeplerMotive) -> Option<&'a mut f64> {
+        match self {
+            KeplerElement::Eccentricity => match &mut kepler.shape {
+                KeplerShape::EccentricitySMA(esma) => Some(&mut esma.eccentricity),
+                KeplerShape::Apsides(_) => None,
+            },
+            KeplerElement::SemiMajorAxis => match &mut kepler.shape {
+                KeplerShape::EccentricitySMA(esma) => Some(&mut esma.semi_major_axis),
+                KeplerShape::Apsides(_) => None,
+            },
+            KeplerElement::Periapsis => match &mut kepler.shape {
+                KeplerShape::Apsides(apsides) => Some(&mut apsides.periapsis),
+                KeplerShape::EccentricitySMA(_) => None,
+            },
+            KeplerElement::Apoapsis => match &mut kepler.shape {
+                KeplerShape::Apsides(apsides) => Some(&mut apsides.apoapsis),
+                KeplerShape::EccentricitySMA(_) => None,
+            },
+            KeplerElement::Inclination => match &mut kepler.rotation {
+                KeplerRotation::EulerAngles(ea) => Some(&mut ea.inclination),
+                KeplerRotation::PrecessingEulerAngles(pea) => Some(&mut pea.inclination),
+                KeplerRotation::FlatAngles(_) => None,
+            },
+            KeplerElement::LongitudeOfAscendingNode => match &mut kepler.rotation {
+                KeplerRotation::EulerAngles(ea) => Some(&mut ea.longitude_of_ascending_node),
+                KeplerRotation::PrecessingEulerAngles(pea) => Some(&mut pea.longitude_of_ascending_node),
+                KeplerRotation::FlatAngles(_) => None,
+            },
+            KeplerElement::ArgumentOfPeriapsis => match &mut kepler.rotation {
+                KeplerRotation::EulerAngles(ea) => Some(&mut ea.argument_of_periapsis),
+                KeplerRotation::PrecessingEulerAngles(pea) => Some(&mut pea.argument_of_periapsis),
+                KeplerRotation::FlatAngles(_) => None,
+            },
+            KeplerElement::LongitudeOfPeriapsis => match &mut kepler.rotation {
+                KeplerRotation::FlatAngles(flat) => Some(&mut flat.longitude_of_periapsis),
+                _ => None,
+            },
+            KeplerElement::MeanAnomaly => match &mut kepler.epoch {
+                KeplerEpoch::MeanAnomaly(maae) => Some(&mut maae.mean_anomaly),
+                KeplerEpoch::J2000(j2000) => Some(&mut j2000.mean_anomaly),
+                _ => None,
+            },
+            KeplerElement::TrueAnomaly => match &mut kepler.epoch {
+                KeplerEpoch::TrueAnomaly(taae) => Some(&mut taae.true_anomaly),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn set(&self, kepler: &mut KeplerMotive, value: f64) -> bool {
+        match self.slot(kepler) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl EditCommand {
+    pub fn body_id(&self) -> &str {
+        match self {
+            EditCommand::SetMass { id, .. } => id,
+            EditCommand::SetPosition { id, .. } => id,
+            EditCommand::SetVelocity { id, .. } => id,
+            EditCommand::SetKeplerElement { id, .. } => id,
+            EditCommand::SetKeplerShape { id, .. } => id,
+            EditCommand::SetKeplerEpoch { id, .. } => id,
+            EditCommand::SetRotation { id, .. } => id,
+            EditCommand::SetAppearance { id, .. } => id,
+            EditCommand::SetProperties { id, .. } => id,
+            EditCommand::AddBody { body, .. } => &body.info.id,
+            EditCommand::RemoveBody { body, .. } => &body.info.id,
+            EditCommand::Batch(commands) => commands.first().map_or("", |command| command.body_id()),
+        }
+    }
+
+    pub fn inverse(&self) -> EditCommand {
+        match self.clone() {
+            EditCommand::SetMass { id, before, after } => EditCommand::SetMass { id, before: after, after: before },
+            EditCommand::SetPosition { id, event_time, before, after } => EditCommand::SetPosition { id, event_time, before: after, after: before },
+            EditCommand::SetVelocity { id, event_time, before, after } => EditCommand::SetVelocity { id, event_time, before: after, after: before },
+            EditCommand::SetKeplerElement { id, event_time, element, before, after } => EditCommand::SetKeplerElement { id, event_time, element, before: after, after: before },
+            EditCommand::SetKeplerShape { id, event_time, before, after } => EditCommand::SetKeplerShape { id, event_time, before: after, after: before },
+            EditCommand::SetKeplerEpoch { id, event_time, before, after } => EditCommand::SetKeplerEpoch { id, event_time, before: after, after: before },
+            EditCommand::SetRotation { id, before, after } => EditCommand::SetRotation { id, before: after, after: before },
+            EditCommand::SetAppearance { id, before, after } => EditCommand::SetAppearance { id, before: after, after: before },
+            EditCommand::SetProperties { id, before, after } => EditCommand::SetProperties { id, before: after, after: before },
+            EditCommand::AddBody { body, satellites } => EditCommand::RemoveBody { body, policy: OrphanPolicy::default(), satellites },
+            EditCommand::RemoveBody { body, satellites, .. } => EditCommand::AddBody { body, satellites },
+            EditCommand::Batch(commands) => EditCommand::Batch(commands.iter().rev().map(EditCommand::inverse).collect()),
+        }
+    }
+
+    /// Folds `next` into this command if both change the same field.
+    fn merge(&mut self, next: &EditCommand) -> bool {
+        match (self, next) {
+            (EditCommand::SetMass { id, after, .. }, EditCommand::SetMass { id: next_id, after: next_after, .. })
+                if *id == *next_id => { *after = *next_after; true }
+            (EditCommand::SetPosition { id, event_time, after, .. }, EditCommand::SetPosition { id: next_id, event_time: next_time, after: next_after, .. })
+                if *id == *next_id && *event_time == *next_time => { *after = *next_after; true }
+            (EditCommand::SetVelocity { id, event_time, after, .. }, EditCommand::SetVelocity { id: next_id, event_time: next_time, after: next_after, .. })
+                if *id == *next_id && *event_time == *next_time => { *after = *next_after; true }
+            (EditCommand::SetKeplerElement { id, event_time, element, after, .. }, EditCommand::SetKeplerElement { id: next_id, event_time: next_time, element: next_element, after: next_after, .. })
+                if *id == *next_id && *event_time == *next_time && *element == *next_element => { *after = *next_after; true }
+            (EditCommand::SetKeplerEpoch { id, event_time, after, .. }, EditCommand::SetKeplerEpoch { id: next_id, event_time: next_time, after: next_after, .. })
+                if *id == *next_id && *event_time == *next_time && after.kind() == next_after.kind() => { *after = next_after.clone(); true }
+            // Adding or removing the rotation is its own step
+            (EditCommand::SetRotation { id, before: Some(_), after: Some(after) }, EditCommand::SetRotation { id: next_id, before: Some(_), after: Some(next_after) })
+                if *id == *next_id => { *after = next_after.clone(); true }
+            (EditCommand::SetAppearance { id, after, .. }, EditCommand::SetAppearance { id: next_id, after: next_after, .. })
+                if *id == *next_id => { *after = next_after.clone(); true }
+            // Adding or removing a property is its own step
+            (EditCommand::SetProperties { id, after, .. }, EditCommand::SetProperties { id: next_id, after: next_after, .. })
+                if *id == *next_id && after.0.keys().eq(next_after.0.keys()) => { *after = next_after.clone(); true }
+            _ => false,
+        }
+    }
+
+    /// Applies an edit to a single body's components.
+    /// Returns false for commands that add or remove bodies or components, and for fields the body doesn't have.
+    pub fn apply_to(&self, info: &mut BodyInfo, motive: &mut Motive) -> bool {
+        match self {
+            EditCommand::SetMass { after, .. } => {
+                info.mass = *after;
+                true
+            }
+            EditCommand::SetPosition { event_time, after, .. } => match &mut motive.motive_at_mut(*event_time).1 {
+                MotiveSelection::Fixed { position, .. } | MotiveSelection::Newtonian { position, .. } => {
+                    *position = *after;
+                    true
+                }
+                MotiveSelection::Keplerian(_) => false,
+            },
+            EditCommand::SetVelocity { event_time, after, .. } => match &mut motive.motive_at_mut(*event_time).1 {
+                MotiveSelection::Newtonian { velocity, .. } => {
+                    *velocity = *after;
+                    true
+                }
+                _ => false,
+            },
+            EditCommand::SetKeplerElement { event_time, element, after, .. } => match &mut motive.motive_at_mut(*event_time).1 {
+                MotiveSelection::Keplerian(kepler) => element.set(kepler, *after),
+                _ => false,
+            },
+            EditCommand::SetKeplerShape { event_time, after, .. } => match &mut motive.motive_at_mut(*event_time).1 {
+                MotiveSelection::Keplerian(kepler) => {
+                    kepler.shape = after.clone();
+                    true
+                }
+                _ => false,
+            },
+            EditCommand::SetKeplerEpoch { event_time, after, .. } => match &mut motive.motive_at_mut(*event_time).1 {
+                MotiveSelection::Keplerian(kepler) => {
+                    kepler.epoch = after.clone();
+                    true
+                }
+                _ => false,
+            },
+            EditCommand::SetRotation { .. } | EditCommand::SetAppearance { .. } | EditCommand::SetProperties { .. }
+                | EditCommand::AddBody { .. } | EditCommand::RemoveBody { .. } | EditCommand::Batch(_) => false,
+        }
+    }
+}
+
+/// A copy of `body` with a fresh id and name, set a little apart from the original
+/// in whichever motive is active at `time`. Add it with `EditCommand::AddBody`.
+pub fn duplicate(body: &CompoundMotiveEntry, universe: &Universe, time: Instant) -> CompoundMotiveEntry {
+    let mut copy = body.clone();
+    copy.info.id = universe.generate_id(&body.info.id);
+
+    let base_name = format!("Copy of {}", body.info.display_name());
+    let mut name = base_name.clone();
+    let mut n = 2;
+    while universe.get_by_name(&name).is_some() {
+        name = format!("{} ({})", base_name, n);
+        n += 1;
+    }
+    copy.info.name = Some(name);
+
+    let offset = DVec3::X * (body.appearance.radius() * DUPLICATE_RADII_OFFSET).max(DUPLICATE_MIN_OFFSET);
+    match &mut copy.motive.motive_at_mut(time).1 {
+        MotiveSelection::Fixed { position, .. } | MotiveSelection::Newtonian { position, .. } => *position += offset,
+        MotiveSelection::Keplerian(kepler) => match &mut kepler.epoch {
+            KeplerEpoch::MeanAnomaly(maae) => maae.mean_anomaly += DUPLICATE_ANOMALY_OFFSET,
+            KeplerEpoch::TrueAnomaly(taae) => taae.true_anomaly += DUPLICATE_ANOMALY_OFFSET,
+            KeplerEpoch::J2000(j2000) => j2000.mean_anomaly += DUPLICATE_ANOMALY_OFFSET,
+            // Passing periapsis a minute sooner
+            KeplerEpoch::TimeAtPeriapsisPassage(passage) => {
+                *passage = Instant::from_seconds_since_j2000(passage.to_j2000_seconds() - 60.0);
+            }
+        },
+    }
+    copy
+}
+
+#[derive(Resource, Default)]
+pub struct UndoStack {
+    done: Vec<EditCommand>,
+    undone: Vec<EditCommand>,
+    /// The top of `done` may still absorb commands to the same field.
+    open: bool,
+}
+
+impl UndoStack {
+    pub fn record(&mut self, command: EditCommand, coalesce: bool) {
+        self.undone.clear();
+        if coalesce && self.open {
+            if let Some(top) = self.done.last_mut() {
+                if top.merge(&command) { return; }
+            }
+        }
+        self.done.push(command);
+        if self.done.len() > UNDO_LIMIT {
+            self.done.remove(0);
+        }
+        self.open = coalesce;
+    }
+
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    /// Moves the last edit to the redo stack, returning the command that reverts it.
+    pub fn undo(&mut self) -> Option<EditCommand> {
+        self.open = false;
+        let command = self.done.pop()?;
+        let inverse = command.inverse();
+        self.undone.push(command);
+        Some(inverse)
+    }
+
+    /// Moves the last undone edit back, returning the command that reapplies it.
+    pub fn redo(&mut self) -> Option<EditCommand> {
+        self.open = false;
+        let command = self.undone.pop()?;
+        self.done.push(command.clone());
+        Some(command)
+    }
+
+    /// Replaces the last edit with how it was actually applied, like a redone removal
+    /// that found its satellites again.
+    fn amend(&mut self, command: EditCommand) {
+        if let Some(top) = self.done.last_mut() {
+            *top = command;
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.open = false;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct EditTarget<'w, 's> {
+    commands: Commands<'w, 's>,
+    deletions: MessageWriter<'w, DeleteBody>,
+    calcs: MessageWriter<'w, CalculateTrajectory>,
+    graph: ResMut<'w, PhysicsGraph>,
+    universe: ResMut<'w, Universe>,
+    view_settings: ResMut<'w, ViewSettings>,
+    cache: ResMut<'w, AssetCache>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+    sim_time: Res<'w, SimTime>,
+    real_time: Res<'w, Time<Real>>,
+    debounce: ResMut<'w, TrajectoryDebounce>,
+    bodies: Query<'w, 's, (Entity, &'static mut BodyInfo, &'static mut Motive, &'static mut BodyState)>,
+}
+
+impl EditTarget<'_, '_> {
+    /// Applies a command for the first time. New bodies take a free id rather than failing,
+    /// and undo and redo reuse the one picked here.
+    fn apply_new(&mut self, command: &mut EditCommand, coalesce: bool) -> bool {
+        match command {
+            EditCommand::Batch(commands) => {
+                commands.retain_mut(|command| self.apply_new(command, coalesce));
+                !commands.is_empty()
+            }
+            _ => {
+                if let EditCommand::AddBody { body, .. } = command {
+                    if self.universe.get_by_id(&body.info.id).is_some() {
+                        body.info.id = self.universe.generate_id(&body.info.id);
+                    }
+                }
+                self.apply(command, coalesce)
+            }
+        }
+    }
+
+    /// Returns false if the command couldn't be applied, in which case it shouldn't be recorded.
+    /// Trajectories of bodies being dragged (`coalesce`) are left to `TrajectoryDebounce`.
+    fn apply(&mut self, command: &mut EditCommand, coalesce: bool) -> bool {
+        match command {
+            // Whatever couldn't be applied is dropped, so the batch only holds what it did
+            EditCommand::Batch(commands) => {
+                commands.retain_mut(|command| self.apply(command, coalesce));
+                !commands.is_empty()
+            }
+            EditCommand::AddBody { body, satellites } => {
+                let id = body.info.id.clone();
+                if self.universe.get_by_id(&id).is_some() {
+                    warn!("Can't add {}, a body with that id already exists", id);
+                    return false;
+                }
+
+                let name = body.info.name.clone().unwrap_or(format!("body_{}", id));
+                for tag in &body.info.tags {
+                    self.view_settings.tags.entry(tag.clone()).or_insert(TagState::default()).members.push(id.clone());
+                }
+                self.universe.insert(name, id.clone());
+                SomeBody::CompoundMotiveEntry((**body).clone())
+                    .spawn(&mut self.commands, &mut self.cache, &mut self.meshes, &mut self.materials, &mut self.images);
+
+                let mut changed = vec![id];
+                for (satellite_id, satellite_motive) in satellites.iter() {
+                    let Some((_, _, mut motive, mut state)) = self.bodies.iter_mut().find(|(_, info, _, _)| &info.id == satellite_id) else { continue };
+                    *motive = satellite_motive.clone();
+                    state.newtonian_init_time = None;
+                    changed.push(satellite_id.clone());
+                }
+
+                self.graph.needs_rebuild = true;
+                self.calcs.write(CalculateTrajectory { selection: BodySelection::IDs(changed) });
+                true
+            }
+            EditCommand::RemoveBody { body, policy, satellites } => {
+                let id = body.info.id.clone();
+                if self.universe.get_by_id(&id).is_none() {
+                    warn!("Can't remove {}, it doesn't exist", id);
+                    return false;
+                }
+
+                let time = self.sim_time.time;
+                *satellites = self.bodies.iter()
+                    .filter(|(_, _, motive, _)| motive.motive_at(time).1.primary_id() == Some(id.as_str()))
+                    .map(|(_, info, motive, _)| (info.id.clone(), motive.clone()))
+                    .collect();
+                self.deletions.write(DeleteBody { id, policy: *policy });
+                true
+            }
+            EditCommand::SetRotation { id, after, .. } => {
+                let Some((entity, ..)) = self.bodies.iter().find(|(_, info, _, _)| info.id == *id) else {
+                    warn!("Can't edit {}, it doesn't exist", id);
+                    return false;
+                };
+                match after {
+                    Some(rotation) => { self.commands.entity(entity).insert(rotation.clone()); }
+                    None => { self.commands.entity(entity).remove::<BodyRotation>(); }
+                }
+                true
+            }
+            EditCommand::SetProperties { id, after, .. } => {
+                let Some((entity, ..)) = self.bodies.iter().find(|(_, info, _, _)| info.id == *id) else {
+                    warn!("Can't edit {}, it doesn't exist", id);
+                    return false;
+                };
+                self.commands.entity(entity).insert(after.clone());
+                true
+            }
+            EditCommand::SetAppearance { id, after, .. } => {
+                let Some((entity, ..)) = self.bodies.iter().find(|(_, info, _, _)| info.id == *id) else {
+                    warn!("Can't edit {}, it doesn't exist", id);
+                    return false;
+                };
+                // Dressed again from scratch, since a ball and a star are drawn differently
+                let mut entity = self.commands.entity(entity);
+                entity.remove::<(Mesh3d, MeshMaterial3d<StandardMaterial>, PointLight, NoFrustumCulling)>();
+                entity.insert(after.clone());
+                insert_render_components(&mut entity, after, &mut self.cache, &mut self.meshes, &mut self.materials, &mut self.images);
+                true
+            }
+            _ => {
+                let id = command.body_id().to_string();
+                let Some((_, mut info, mut motive, mut state)) = self.bodies.iter_mut().find(|(_, info, _, _)| info.id == id) else {
+                    warn!("Can't edit {}, it doesn't exist", id);
+                    return false;
+                };
+                if !command.apply_to(&mut info, &mut motive) {
+                    return false;
+                }
+                if matches!(command, EditCommand::SetPosition { .. } | EditCommand::SetVelocity { .. } | EditCommand::SetKeplerElement { .. } | EditCommand::SetKeplerShape { .. } | EditCommand::SetKeplerEpoch { .. }) {
+                    // Newtonian and mutual gravity bodies restart from the edited state.
+                    state.newtonian_init_time = None;
+                }
+                // New masses are patched into the graph by calculate_body_positions
+                if !matches!(command, EditCommand::SetMass { .. }) {
+                    self.graph.needs_rebuild = true;
+                }
+                if coalesce {
+                    self.debounce.mark_dirty(&id, self.real_time.elapsed_secs_f64());
+                } else {
+                    self.calcs.write(CalculateTrajectory { selection: BodySelection::IDs(vec![id]) });
+                }
+                true
+            }
+        }
+    }
+}
+
+pub fn apply_edit(
+    mut edits: MessageReader<Edit>,
+    mut undo_stack: ResMut<UndoStack>,
+    mut target: EditTarget,
+) {
+    for edit in edits.read() {
+        match edit {
+            Edit::Apply { command, coalesce } => {
+                let mut command = command.clone();
+                if target.apply_new(&mut command, *coalesce) {
+                    undo_stack.record(command, *coalesce);
+                }
+            }
+            Edit::Seal => undo_stack.seal(),
+            Edit::Undo => {
+                if let Some(mut inverse) = undo_stack.undo() {
+                    target.apply(&mut inverse, false);
+                }
+            }
+            Edit::Redo => {
+                if let Some(mut command) = undo_stack.redo() {
+                    if target.apply(&mut command, false) {
+                        undo_stack.amend(command);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Edits don't carry over between universes.
+pub fn clear_undo_stack(mut undo_stack: ResMut<UndoStack>) {
+    undo_stack.clear();
+}
+
+/// Ctrl-Z to undo, Ctrl-Y or Ctrl-Shift-Z to redo.
+pub fn undo_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut edits: MessageWriter<Edit>,
+) {
+    // Text fields have their own undo.
+    if let Ok(ctx) = contexts.ctx_mut() {
+        if ctx.wants_keyboard_input() { return; }
+    }
+
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) { return; }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if keys.just_pressed(KeyCode::KeyZ) {
+        edits.write(if shift { Edit::Redo } else { Edit::Undo });
+    } else if keys.just_pressed(KeyCode::KeyY) {
+        edits.write(Edit::Redo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEulerAngles, MeanAnomalyAtJ2000};
+    use crate::body::universe::deletion::delete_bodies;
+    use crate::body::universe::save::UniversePhysics;
+
+    fn epoch() -> Instant {
+        Instant::from_seconds_since_j2000(0.0)
+    }
+
+    fn moon() -> (BodyInfo, Motive) {
+        let info = BodyInfo {
+            id: String::from("moon"),
+            mass: 7.342e22,
+            ..BodyInfo::default()
+        };
+        let mut motive = Motive::keplerian(
+            String::from("earth"),
+            KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.0549, semi_major_axis: 3.844e8 }),
+            KeplerRotation::EulerAngles(KeplerEulerAngles { inclination: 5.145, longitude_of_ascending_node: 125.08, argument_of_periapsis: 318.15 }),
+            KeplerEpoch::J2000(MeanAnomalyAtJ2000 { mean_anomaly: 2.0 }),
+        );
+        motive.insert_event(Instant::from_seconds_since_j2000(1000.0), crate::body::motive::TransitionEvent::Impulse, MotiveSelection::Newtonian {
+            position: DVec3::new(1.0, 2.0, 3.0),
+            velocity: DVec3::new(4.0, 5.0, 6.0),
+        });
+        (info, motive)
+    }
+
+    fn kepler(motive: &Motive) -> &KeplerMotive {
+        match &motive.motive_at(epoch()).1 {
+            MotiveSelection::Keplerian(kepler) => kepler,
+            _ => panic!("Expected a Keplerian motive"),
+        }
+    }
+
+    fn newtonian(motive: &Motive) -> (DVec3, DVec3) {
+        match &motive.motive_at(Instant::from_seconds_since_j2000(1000.0)).1 {
+            MotiveSelection::Newtonian { position, velocity } => (*position, *velocity),
+            _ => panic!("Expected a Newtonian motive"),
+        }
+    }
+
+    fn edit(stack: &mut UndoStack, info: &mut BodyInfo, motive: &mut Motive, command: EditCommand, coalesce: bool) {
+        assert!(command.apply_to(info, motive));
+        stack.record(command, coalesce);
+    }
+
+    #[test]
+    fn test_undo_restores_original_state() {
+        let (mut info, mut motive) = moon();
+        let (original_info, original_motive) = moon();
+        let mut stack = UndoStack::default();
+        let id = info.id.clone();
+        let newtonian_time = Instant::from_seconds_since_j2000(1000.0);
+
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetMass { id: id.clone(), before: 7.342e22, after: 1.0e23 }, false);
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetKeplerElement {
+            id: id.clone(), event_time: epoch(), element: KeplerElement::SemiMajorAxis, before: 3.844e8, after: 4.0e8,
+        }, false);
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetKeplerElement {
+            id: id.clone(), event_time: epoch(), element: KeplerElement::Inclination, before: 5.145, after: 30.0,
+        }, false);
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetPosition {
+            id: id.clone(), event_time: newtonian_time, before: DVec3::new(1.0, 2.0, 3.0), after: DVec3::ZERO,
+        }, false);
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetVelocity {
+            id: id.clone(), event_time: newtonian_time, before: DVec3::new(4.0, 5.0, 6.0), after: DVec3::ONE,
+        }, false);
+
+        assert_eq!(info.mass, 1.0e23);
+        assert_eq!(kepler(&motive).semi_major_axis(), 4.0e8);
+
+        while let Some(inverse) = stack.undo() {
+            assert!(inverse.apply_to(&mut info, &mut motive));
+        }
+
+        assert_eq!(info.mass, original_info.mass);
+        assert_eq!(kepler(&motive).semi_major_axis(), kepler(&original_motive).semi_major_axis());
+        assert_eq!(kepler(&motive).inclination(), kepler(&original_motive).inclination());
+        assert_eq!(newtonian(&motive), newtonian(&original_motive));
+
+        // And everything comes back with redo
+        while let Some(command) = stack.redo() {
+            assert!(command.apply_to(&mut info, &mut motive));
+        }
+        assert_eq!(info.mass, 1.0e23);
+        assert_eq!(kepler(&motive).inclination(), 30.0);
+        assert_eq!(newtonian(&motive), (DVec3::ZERO, DVec3::ONE));
+    }
+
+    #[test]
+    fn test_drag_coalesces_into_one_step() {
+        let (mut info, mut motive) = moon();
+        let mut stack = UndoStack::default();
+        let id = info.id.clone();
+
+        let mut mass = info.mass;
+        for _ in 0..10 {
+            let before = mass;
+            mass *= 1.1;
+            edit(&mut stack, &mut info, &mut motive, EditCommand::SetMass { id: id.clone(), before, after: mass }, true);
+        }
+        stack.seal();
+        // A second drag is its own step
+        edit(&mut stack, &mut info, &mut motive, EditCommand::SetMass { id: id.clone(), before: mass, after: 1.0 }, true);
+        stack.seal();
+
+        let inverse = stack.undo().unwrap();
+        assert!(inverse.apply_to(&mut info, &mut motive));
+        assert_eq!(info.mass, mass);
+
+        let inverse = stack.undo().unwrap();
+        assert!(inverse.apply_to(&mut info, &mut motive));
+        assert_eq!(info.mass, 7.342e22);
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn test_typing_a_property_is_one_step() {
+        let properties = |pairs: &[(&str, &str)]| BodyProperties(pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect());
+        let set = |before: BodyProperties, after: BodyProperties| EditCommand::SetProperties { id: "moon".into(), before, after };
+        let mut stack = UndoStack::default();
+
+        stack.record(set(properties(&[]), properties(&[("owner", "")])), false);
+        let mut typed = String::new();
+        for c in "Acme".chars() {
+            let before = properties(&[("owner", typed.as_str())]);
+            typed.push(c);
+            stack.record(set(before, properties(&[("owner", typed.as_str())])), true);
+        }
+        stack.seal();
+
+        let Some(EditCommand::SetProperties { after, .. }) = stack.undo() else { panic!("Expected a property edit") };
+        assert_eq!(after, properties(&[("owner", "")]));
+        let Some(EditCommand::SetProperties { after, .. }) = stack.undo() else { panic!("Expected a property edit") };
+        assert_eq!(after, properties(&[]));
+        assert!(!stack.can_undo());
+    }
+
+    #[test]
+    fn test_duplicate_keeps_elements_under_new_id() {
+        let (info, motive) = moon();
+        let original = CompoundMotiveEntry { info, motive, appearance: Default::default(), rotation: None, properties: Default::default() };
+        let mut universe = Universe::default();
+        universe.insert("Moon", "moon");
+
+        let copy = duplicate(&original, &universe, epoch());
+        assert_eq!(copy.info.id, "moon-2");
+        assert_eq!(copy.info.name.as_deref(), Some("Copy of moon"));
+
+        let (before, after) = (kepler(&original.motive), kepler(&copy.motive));
+        assert_eq!(after.eccentricity(), before.eccentricity());
+        assert_eq!(after.semi_major_axis(), before.semi_major_axis());
+        assert_eq!(after.inclination(), before.inclination());
+        let anomaly = |kepler: &KeplerMotive| kepler.epoch.mean_anomaly_at_epoch(kepler.eccentricity());
+        assert!((anomaly(after) - anomaly(before) - DUPLICATE_ANOMALY_OFFSET).abs() < 1e-12);
+        // Later motives are copied as they were
+        assert_eq!(newtonian(&copy.motive), newtonian(&original.motive));
+
+        universe.insert(copy.info.name.clone().unwrap(), copy.info.id.clone());
+        let second = duplicate(&original, &universe, epoch());
+        assert_eq!(second.info.id, "moon-3");
+        assert_eq!(second.info.name.as_deref(), Some("Copy of moon (2)"));
+    }
+
+    #[test]
+    fn test_duplicate_empty_body_set_apart() {
+        let info = BodyInfo { id: String::from("buoy"), ..BodyInfo::default() };
+        let original = CompoundMotiveEntry { info, motive: Motive::fixed(DVec3::ZERO), appearance: Appearance::Empty, rotation: None, properties: Default::default() };
+        let mut universe = Universe::default();
+        universe.insert("buoy", "buoy");
+
+        let copy = duplicate(&original, &universe, epoch());
+        match &copy.motive.motive_at(epoch()).1 {
+            MotiveSelection::Fixed { position, .. } => assert_eq!(*position, DVec3::X * DUPLICATE_MIN_OFFSET),
+            _ => panic!("Expected a fixed motive"),
+        }
+    }
+
+    /// Applies edits the way the planetarium does, with removed bodies deleted straight after.
+    fn edit_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<UndoStack>()
+            .init_resource::<PhysicsGraph>()
+            .init_resource::<Universe>()
+            .init_resource::<ViewSettings>()
+            .init_resource::<AssetCache>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<Time<Real>>()
+            .init_resource::<TrajectoryDebounce>()
+            .add_message::<Edit>()
+            .add_message::<DeleteBody>()
+            .add_message::<CalculateTrajectory>()
+            .add_systems(Update, (apply_edit, delete_bodies).chain());
+        app
+    }
+
+    /// Whether the body is in the universe, and what it orbits now if it is.
+    fn body_primary(app: &mut App, id: &str) -> Option<Option<String>> {
+        let time = app.world().resource::<SimTime>().time;
+        app.world().resource::<Universe>().get_by_id(id)?;
+        let mut bodies = app.world_mut().query::<(&BodyInfo, &Motive)>();
+        let (_, motive) = bodies.iter(app.world()).find(|(info, _)| info.id == id)?;
+        Some(motive.motive_at(time).1.primary_id().map(String::from))
+    }
+
+    #[test]
+    fn test_remove_undo_redo() {
+        let mut app = edit_app();
+        let earth = CompoundMotiveEntry {
+            info: BodyInfo { id: String::from("earth"), mass: 5.972e24, ..default() },
+            motive: Motive::fixed(DVec3::ZERO),
+            appearance: Appearance::Empty,
+            rotation: None,
+            properties: default(),
+        };
+        let (moon_info, moon_motive) = moon();
+        let mu = app.world().resource::<UniversePhysics>().gravitational_constant * earth.info.mass;
+        let moon_position = kepler(&moon_motive).displacement(epoch(), mu).unwrap();
+        let mut universe = app.world_mut().resource_mut::<Universe>();
+        universe.insert("Earth", "earth");
+        universe.insert("Moon", "moon");
+        app.world_mut().spawn((earth.info.clone(), earth.motive.clone(), BodyState::default(), Appearance::Empty));
+        app.world_mut().spawn((moon_info, moon_motive, BodyState { current_position: moon_position, ..default() }));
+
+        let remove = EditCommand::RemoveBody { body: Box::new(earth), policy: OrphanPolicy::Release, satellites: Vec::new() };
+        app.world_mut().write_message(Edit::Apply { command: remove, coalesce: false });
+        app.update();
+        assert_eq!(body_primary(&mut app, "earth"), None);
+        assert_eq!(body_primary(&mut app, "moon"), Some(None));
+
+        // Back, with the moon orbiting it again
+        app.world_mut().write_message(Edit::Undo);
+        app.update();
+        assert_eq!(body_primary(&mut app, "earth"), Some(None));
+        assert_eq!(body_primary(&mut app, "moon"), Some(Some(String::from("earth"))));
+
+        // Gone again, and the moon released again rather than left orbiting nothing
+        app.world_mut().write_message(Edit::Redo);
+        app.update();
+        assert_eq!(body_primary(&mut app, "earth"), None);
+        let mut motives = app.world_mut().query::<(&BodyInfo, &Motive)>();
+        let (_, motive) = motives.iter(app.world()).find(|(info, _)| info.id == "moon").unwrap();
+        assert!(motive.is_newtonian(epoch()));
+
+        app.world_mut().write_message(Edit::Undo);
+        app.update();
+        assert_eq!(body_primary(&mut app, "moon"), Some(Some(String::from("earth"))));
+    }
+
+    #[test]
+    fn test_batch_undone_at_once() {
+        let mut app = edit_app();
+        let rock = || EditCommand::AddBody {
+            body: Box::new(CompoundMotiveEntry {
+                info: BodyInfo { id: String::from("rock"), ..default() },
+                motive: Motive::fixed(DVec3::ZERO),
+                appearance: Appearance::Empty,
+                rotation: None,
+                properties: default(),
+            }),
+            satellites: Vec::new(),
+        };
+        app.world_mut().write_message(Edit::Apply { command: EditCommand::Batch(vec![rock(), rock()]), coalesce: false });
+        app.update();
+        // Each takes its own id, which redo keeps
+        assert_eq!(body_primary(&mut app, "rock"), Some(None));
+        assert_eq!(body_primary(&mut app, "rock-2"), Some(None));
+
+        app.world_mut().write_message(Edit::Undo);
+        app.update();
+        assert_eq!(body_primary(&mut app, "rock"), None);
+        assert_eq!(body_primary(&mut app, "rock-2"), None);
+        assert!(!app.world().resource::<UndoStack>().can_undo());
+
+        app.world_mut().write_message(Edit::Redo);
+        app.update();
+        assert_eq!(body_primary(&mut app, "rock"), Some(None));
+        assert_eq!(body_primary(&mut app, "rock-2"), Some(None));
+    }
+}
@@ -0,0 +1,176 @@
+//! Universes built out of other save files. Each include's bodies are merged in when the
+//! including file is loaded, so saving it afterwards writes them out as its own.
+//!
+//! Includes only apply on import. They're for putting a TOML file together out of parts,
+//! not for keeping a save linked to them: edits to the parts don't reach a universe that's
+//! been saved since, and .em files don't have includes at all.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::body::universe::save::{CompoundMotiveEntry, SomeBody, UniverseFile, UniverseFileContents, UniverseReadError};
+
+/// Another save file whose bodies belong in this one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Include {
+    /// Relative to the directory of the file doing the including.
+    pub path: PathBuf,
+    /// Put in front of every id from the file, and every reference to them, to keep them apart.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum IncludeError {
+    /// `path` couldn't be loaded.
+    Read { path: PathBuf, error: Box<UniverseReadError> },
+    /// `path` ends up including itself.
+    Cycle(PathBuf),
+    /// A body from `path` has the same id as one already in the universe.
+    DuplicateId { id: String, path: PathBuf },
+}
+
+/// Merges the bodies of everything `contents` includes, and everything they include, into
+/// its own, then clears its includes. `path` is where `contents` was loaded from.
+/// Only bodies come across; the included files' time, view, physics and scenarios are ignored.
+pub fn resolve_includes(contents: &mut UniverseFileContents, path: &Path) -> Result<(), IncludeError> {
+    let mut stack = vec![path.canonicalize().unwrap_or(path.to_path_buf())];
+    resolve(contents, path, &mut stack)
+}
+
+fn resolve(contents: &mut UniverseFileContents, path: &Path, stack: &mut Vec<PathBuf>) -> Result<(), IncludeError> {
+    let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut ids: HashSet<String> = contents.bodies.iter().map(|body| body.info().id.clone()).collect();
+
+    for include in std::mem::take(&mut contents.includes) {
+        let included_path = directory.join(&include.path);
+        let read_error = |error| IncludeError::Read { path: included_path.clone(), error: Box::new(error) };
+        let canonical = included_path.canonicalize().map_err(|e| read_error(UniverseReadError::IO(e)))?;
+        if stack.contains(&canonical) {
+            return Err(IncludeError::Cycle(included_path.clone()));
+        }
+
+        let mut included = UniverseFile::read_contents(&included_path).map_err(read_error)?;
+        stack.push(canonical);
+        resolve(&mut included, &included_path, stack)?;
+        stack.pop();
+
+        let bodies = match &include.prefix {
+            Some(prefix) => prefixed(included.bodies, prefix),
+            None => included.bodies,
+        };
+        for body in bodies {
+            if !ids.insert(body.info().id.clone()) {
+                return Err(IncludeError::DuplicateId { id: body.info().id.clone(), path: included_path });
+            }
+            contents.bodies.push(body);
+        }
+    }
+    Ok(())
+}
+
+/// `bodies` with `prefix` in front of their ids, and of their references to each other.
+/// References to bodies that aren't among them are left alone.
+fn prefixed(bodies: Vec<SomeBody>, prefix: &str) -> Vec<SomeBody> {
+    let renamed: HashMap<String, String> = bodies.iter()
+        .map(|body| (body.info().id.clone(), format!("{}{}", prefix, body.info().id)))
+        .collect();
+    bodies.into_iter().map(|body| {
+        let (mut info, appearance, mut motive, rotation, properties) = body.into_parts();
+        info.id = renamed[&info.id].clone();
+        for selection in motive.selections_mut() {
+            if let Some(primary_id) = selection.primary_id_mut() {
+                if let Some(new_id) = renamed.get(primary_id.as_str()) {
+                    *primary_id = new_id.clone();
+                }
+            }
+        }
+        SomeBody::CompoundMotiveEntry(CompoundMotiveEntry { info, motive, appearance, rotation, properties })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::universe::save::SaveFormat;
+    use crate::body::universe::solar_system;
+
+    #[test]
+    fn test_compose_two_files() {
+        let directory = std::env::temp_dir().join(format!("includes_{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("parts")).unwrap();
+        let write = |name: &str, contents: &UniverseFileContents| {
+            std::fs::write(directory.join(name), toml::to_string_pretty(contents).unwrap()).unwrap();
+        };
+
+        let count = solar_system::solar_system().contents.bodies.len();
+        let mut inner = solar_system::solar_system().contents;
+        inner.bodies.retain(|body| body.info().id != "sol");
+        write("parts/inner.toml", &inner);
+        // The same planets again, kept apart by a prefix
+        write("parts/again.toml", &UniverseFileContents {
+            includes: vec![Include { path: "inner.toml".into(), prefix: None }],
+            bodies: Vec::new(),
+            ..solar_system::solar_system().contents
+        });
+
+        let mut host = solar_system::solar_system().contents;
+        host.bodies.retain(|body| body.info().id == "sol");
+        host.includes = vec![
+            Include { path: "parts/inner.toml".into(), prefix: None },
+            Include { path: "parts/again.toml".into(), prefix: Some("copy-".into()) },
+        ];
+        write("host.toml", &host);
+
+        let loaded = UniverseFile::try_load_from_path(&directory.join("host.toml")).unwrap().contents;
+        assert!(loaded.includes.is_empty());
+        assert_eq!(loaded.bodies.len(), 1 + 2 * (count - 1));
+        let ids: HashSet<&str> = loaded.bodies.iter().map(|body| body.info().id.as_str()).collect();
+        assert!(ids.contains("earth") && ids.contains("copy-earth") && ids.contains("copy-luna"), "{:?}", ids);
+
+        // The copied moon goes round the copied earth, and the copied earth round the one sun
+        let (_, _, luna, _, _) = loaded.bodies.into_iter().find(|body| body.info().id == "copy-luna").unwrap().into_parts();
+        let primaries: Vec<_> = luna.iter_events().filter_map(|(_, _, selection)| selection.primary_id().map(str::to_string)).collect();
+        assert_eq!(primaries, vec!["copy-earth".to_string()]);
+
+        // Without the prefix the ids collide
+        host.includes[1].prefix = None;
+        write("host.toml", &host);
+        let collision = UniverseFile::try_load_from_path(&directory.join("host.toml"));
+        assert!(matches!(collision, Err(UniverseReadError::Include(IncludeError::DuplicateId { .. }))));
+
+        // A file including itself
+        write("parts/inner.toml", &UniverseFileContents {
+            includes: vec![Include { path: "again.toml".into(), prefix: None }],
+            ..solar_system::solar_system().contents
+        });
+        let cycle = UniverseFile::try_load_from_path(&directory.join("parts/again.toml"));
+        assert!(matches!(cycle, Err(UniverseReadError::Include(IncludeError::Cycle(_)))));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_em_save_stands_alone() {
+        let directory = std::env::temp_dir().join(format!("flattened_includes_{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("parts")).unwrap();
+        let count = solar_system::solar_system().contents.bodies.len();
+        let mut inner = solar_system::solar_system().contents;
+        inner.bodies.retain(|body| body.info().id != "sol");
+        std::fs::write(directory.join("parts/inner.toml"), toml::to_string_pretty(&inner).unwrap()).unwrap();
+        let mut host = solar_system::solar_system().contents;
+        host.bodies.retain(|body| body.info().id == "sol");
+        host.includes = vec![Include { path: "parts/inner.toml".into(), prefix: None }];
+        std::fs::write(directory.join("host.toml"), toml::to_string_pretty(&host).unwrap()).unwrap();
+
+        let mut loaded = UniverseFile::try_load_from_path(&directory.join("host.toml")).unwrap();
+        loaded.save_as(directory.join("host.em"), SaveFormat::Sqlite).unwrap();
+        // The parts aren't needed any more
+        std::fs::remove_dir_all(directory.join("parts")).unwrap();
+
+        let saved = UniverseFile::try_load_from_path(&directory.join("host.em")).unwrap().contents;
+        assert!(saved.includes.is_empty());
+        assert_eq!(saved.bodies.len(), count);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
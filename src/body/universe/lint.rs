@@ -0,0 +1,99 @@
+//! Sanity checks for universe data, to catch typos like a planet weighing a few kilograms.
+
+use std::fmt;
+use crate::body::universe::save::UniverseFileContents;
+
+/// Major bodies lighter than this barely pull on anything, so are probably a typo.
+/// About the mass of a small comet nucleus.
+pub const MAJOR_MASS_FLOOR: f64 = 1e12;
+
+pub struct Lint {
+    pub body_id: String,
+    pub problem: LintProblem,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LintProblem {
+    NonPositiveMass(f64),
+    /// A major body lighter than `MAJOR_MASS_FLOOR`.
+    LightMajorBody(f64),
+    /// Tagged as a planet but not on a closed orbit.
+    UnboundPlanet { eccentricity: f64 },
+    NegativeSemiMajorAxis(f64),
+    /// Big enough to reach its primary's center at periapsis.
+    LargerThanOrbit { radius: f64, periapsis: f64 },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.body_id)?;
+        match self.problem {
+            LintProblem::NonPositiveMass(mass) => write!(f, "mass of {} kg isn't positive", mass),
+            LintProblem::LightMajorBody(mass) => write!(f, "major body with a mass of only {} kg", mass),
+            LintProblem::UnboundPlanet { eccentricity } => write!(f, "planet with an eccentricity of {}", eccentricity),
+            LintProblem::NegativeSemiMajorAxis(semi_major_axis) => write!(f, "negative semi-major axis of {} m", semi_major_axis),
+            LintProblem::LargerThanOrbit { radius, periapsis } => write!(f, "radius of {} m is larger than its periapsis of {} m", radius, periapsis),
+        }
+    }
+}
+
+pub fn lint_universe(contents: &UniverseFileContents) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for body in &contents.bodies {
+        let info = body.info();
+        let mut flag = |problem| lints.push(Lint { body_id: info.id.clone(), problem });
+
+        if info.mass <= 0.0 {
+            flag(LintProblem::NonPositiveMass(info.mass));
+        } else if info.major && info.mass < MAJOR_MASS_FLOOR {
+            flag(LintProblem::LightMajorBody(info.mass));
+        }
+
+        let radius = body.appearance().radius();
+        for kepler in body.kepler_motives() {
+            let eccentricity = kepler.eccentricity();
+            if eccentricity >= 1.0 && info.tags.iter().any(|tag| tag == "Planet") {
+                flag(LintProblem::UnboundPlanet { eccentricity });
+            }
+            if kepler.semi_major_axis() < 0.0 && eccentricity < 1.0 {
+                flag(LintProblem::NegativeSemiMajorAxis(kepler.semi_major_axis()));
+            }
+            if radius > kepler.periapsis() {
+                flag(LintProblem::LargerThanOrbit { radius, periapsis: kepler.periapsis() });
+            }
+        }
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::universe::solar_system::{earth_moon, solar_system};
+
+    #[test]
+    fn test_templates_are_clean() {
+        for template in [solar_system(), earth_moon()] {
+            let lints = lint_universe(&template.contents);
+            assert!(lints.is_empty(), "{}", lints.iter().map(|lint| lint.to_string()).collect::<Vec<_>>().join("\n"));
+        }
+    }
+
+    #[test]
+    fn test_flags_typos() {
+        let mut file = solar_system();
+        for body in &mut file.contents.bodies {
+            match body.id().as_str() {
+                "mars" => body.info_mut().mass = 6.4171,
+                "earth" => body.info_mut().mass = 0.0,
+                _ => {}
+            }
+        }
+        let lints = lint_universe(&file.contents);
+        let problems: Vec<_> = lints.iter().map(|lint| (lint.body_id.as_str(), &lint.problem)).collect();
+        assert_eq!(problems, vec![
+            ("earth", &LintProblem::NonPositiveMass(0.0)),
+            ("mars", &LintProblem::LightMajorBody(6.4171)),
+        ]);
+    }
+}
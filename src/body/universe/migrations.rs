@@ -2,6 +2,10 @@
 //!
 //! This module provides a versioned migration system for SQLite save files.
 //! Each migration has an "up" and "down" SQL statement to move between versions.
+//!
+//! Downs can use `ALTER TABLE ... DROP COLUMN`, which needs SQLite 3.35. rusqlite's `bundled`
+//! feature always links its own copy (3.46 with libsqlite3-sys 0.30), never the system's.
+//! The older downs that rebuild their table were written before that was relied on.
 
 use rusqlite::{Connection, Result as SqlResult};
 
@@ -220,7 +224,7 @@ pub static MIGRATIONS: &[Migration] = &[
             ALTER TABLE sim_time ADD COLUMN max_frame_time REAL NOT NULL DEFAULT 0.016;
         "#,
         down: r#"
-            -- SQLite doesn't support DROP COLUMN directly, so we recreate the table
+            -- Recreates the table, from before downs relied on DROP COLUMN
             CREATE TABLE sim_time_new (
                 id INTEGER PRIMARY KEY CHECK (id = 1),
                 time_julian_days REAL NOT NULL DEFAULT 2451545.0
@@ -231,6 +235,355 @@ pub static MIGRATIONS: &[Migration] = &[
             ALTER TABLE sim_time_new RENAME TO sim_time;
         "#,
     },
+    // Version 2 -> 3: Body rotation
+    Migration {
+        description: "Add body_rotations table",
+        up: r#"
+            CREATE TABLE IF NOT EXISTS body_rotations (
+                body_id TEXT PRIMARY KEY NOT NULL,
+                period REAL NOT NULL,
+                axial_tilt REAL NOT NULL DEFAULT 0.0,
+                node_longitude REAL NOT NULL DEFAULT 0.0,
+                prime_meridian_at_epoch REAL NOT NULL DEFAULT 0.0,
+                epoch_julian_day REAL NOT NULL DEFAULT 2451545.0,
+                FOREIGN KEY (body_id) REFERENCES bodies(id) ON DELETE CASCADE
+            );
+        "#,
+        down: r#"
+            DROP TABLE IF EXISTS body_rotations;
+        "#,
+    },
+    // Version 3 -> 4: Sphere of influence toggle
+    Migration {
+        description: "Add show_soi column to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN show_soi INTEGER NOT NULL DEFAULT 0;
+        "#,
+        down: r#"
+            -- Recreates the table, from before downs relied on DROP COLUMN
+            CREATE TABLE view_settings_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                distance_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_distance_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_distance_base REAL NOT NULL DEFAULT 10.0,
+                body_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_body_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_body_base REAL NOT NULL DEFAULT 10.0,
+                show_labels INTEGER NOT NULL DEFAULT 1,
+                show_trajectories INTEGER NOT NULL DEFAULT 1,
+                trajectory_resolution INTEGER NOT NULL DEFAULT 120
+            );
+            INSERT INTO view_settings_new
+                SELECT id, distance_scale, logarithmic_distance_scale, logarithmic_distance_base,
+                       body_scale, logarithmic_body_scale, logarithmic_body_base,
+                       show_labels, show_trajectories, trajectory_resolution
+                FROM view_settings;
+            DROP TABLE view_settings;
+            ALTER TABLE view_settings_new RENAME TO view_settings;
+        "#,
+    },
+    // Version 4 -> 5: Scripted scenario events
+    Migration {
+        description: "Add scenario_events table",
+        up: r#"
+            -- Bodies spawned by an event are stored in bodies like any other,
+            -- but only enter the universe when their event fires.
+            CREATE TABLE IF NOT EXISTS scenario_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                time_seconds REAL NOT NULL,
+                event_type TEXT NOT NULL,  -- 'Impulse', 'SpawnBody', 'SetGravitationalConstant'
+                body_id TEXT,
+                delta_v_x REAL,
+                delta_v_y REAL,
+                delta_v_z REAL,
+                gravitational_constant REAL
+            );
+            CREATE INDEX IF NOT EXISTS idx_scenario_events_time ON scenario_events(time_seconds);
+        "#,
+        down: r#"
+            DELETE FROM bodies WHERE id IN (SELECT body_id FROM scenario_events WHERE event_type = 'SpawnBody');
+            DROP TABLE IF EXISTS scenario_events;
+        "#,
+    },
+    // Version 5 -> 6: Light-time correction toggle
+    Migration {
+        description: "Add apply_light_time column to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN apply_light_time INTEGER NOT NULL DEFAULT 0;
+        "#,
+        down: r#"
+            CREATE TABLE view_settings_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                distance_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_distance_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_distance_base REAL NOT NULL DEFAULT 10.0,
+                body_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_body_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_body_base REAL NOT NULL DEFAULT 10.0,
+                show_labels INTEGER NOT NULL DEFAULT 1,
+                show_trajectories INTEGER NOT NULL DEFAULT 1,
+                trajectory_resolution INTEGER NOT NULL DEFAULT 120,
+                show_soi INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO view_settings_new
+                SELECT id, distance_scale, logarithmic_distance_scale, logarithmic_distance_base,
+                       body_scale, logarithmic_body_scale, logarithmic_body_base,
+                       show_labels, show_trajectories, trajectory_resolution, show_soi
+                FROM view_settings;
+            DROP TABLE view_settings;
+            ALTER TABLE view_settings_new RENAME TO view_settings;
+        "#,
+    },
+    // Version 6 -> 7: Body the view is centered on
+    Migration {
+        description: "Add center_body_id column to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN center_body_id TEXT;
+        "#,
+        down: r#"
+            CREATE TABLE view_settings_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                distance_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_distance_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_distance_base REAL NOT NULL DEFAULT 10.0,
+                body_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_body_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_body_base REAL NOT NULL DEFAULT 10.0,
+                show_labels INTEGER NOT NULL DEFAULT 1,
+                show_trajectories INTEGER NOT NULL DEFAULT 1,
+                trajectory_resolution INTEGER NOT NULL DEFAULT 120,
+                show_soi INTEGER NOT NULL DEFAULT 0,
+                apply_light_time INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO view_settings_new
+                SELECT id, distance_scale, logarithmic_distance_scale, logarithmic_distance_base,
+                       body_scale, logarithmic_body_scale, logarithmic_body_base,
+                       show_labels, show_trajectories, trajectory_resolution, show_soi, apply_light_time
+                FROM view_settings;
+            DROP TABLE view_settings;
+            ALTER TABLE view_settings_new RENAME TO view_settings;
+        "#,
+    },
+    // Version 7 -> 8: Named camera views
+    Migration {
+        description: "Add camera_bookmarks table",
+        up: r#"
+            CREATE TABLE IF NOT EXISTS camera_bookmarks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                pos_x REAL NOT NULL,
+                pos_y REAL NOT NULL,
+                pos_z REAL NOT NULL,
+                yaw REAL NOT NULL,
+                pitch REAL NOT NULL,
+                roll REAL NOT NULL,
+                target_body_id TEXT
+            );
+        "#,
+        down: r#"
+            DROP TABLE IF EXISTS camera_bookmarks;
+        "#,
+    },
+    // Version 8 -> 9: Reference plane grid toggle
+    Migration {
+        description: "Add show_grid column to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN show_grid INTEGER NOT NULL DEFAULT 0;
+        "#,
+        down: r#"
+            CREATE TABLE view_settings_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                distance_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_distance_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_distance_base REAL NOT NULL DEFAULT 10.0,
+                body_scale REAL NOT NULL DEFAULT 1e-9,
+                logarithmic_body_scale INTEGER NOT NULL DEFAULT 0,
+                logarithmic_body_base REAL NOT NULL DEFAULT 10.0,
+                show_labels INTEGER NOT NULL DEFAULT 1,
+                show_trajectories INTEGER NOT NULL DEFAULT 1,
+                trajectory_resolution INTEGER NOT NULL DEFAULT 120,
+                show_soi INTEGER NOT NULL DEFAULT 0,
+                apply_light_time INTEGER NOT NULL DEFAULT 0,
+                center_body_id TEXT
+            );
+            INSERT INTO view_settings_new
+                SELECT id, distance_scale, logarithmic_distance_scale, logarithmic_distance_base,
+                       body_scale, logarithmic_body_scale, logarithmic_body_base,
+                       show_labels, show_trajectories, trajectory_resolution, show_soi, apply_light_time,
+                       center_body_id
+                FROM view_settings;
+            DROP TABLE view_settings;
+            ALTER TABLE view_settings_new RENAME TO view_settings;
+        "#,
+    },
+    // Version 9 -> 10: Major bodies pulling on each other
+    Migration {
+        description: "Add mutual_gravity column to physics",
+        up: r#"
+            ALTER TABLE physics ADD COLUMN mutual_gravity INTEGER NOT NULL DEFAULT 0;
+        "#,
+        down: r#"
+            CREATE TABLE physics_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                gravitational_constant REAL NOT NULL DEFAULT 6.6743015e-11
+            );
+            INSERT INTO physics_new SELECT id, gravitational_constant FROM physics;
+            DROP TABLE physics;
+            ALTER TABLE physics_new RENAME TO physics;
+        "#,
+    },
+    // Version 10 -> 11: What bodies do when they touch
+    Migration {
+        description: "Add collision_behavior and restitution columns to physics",
+        up: r#"
+            ALTER TABLE physics ADD COLUMN collision_behavior TEXT NOT NULL DEFAULT 'Ignore';
+            ALTER TABLE physics ADD COLUMN restitution REAL NOT NULL DEFAULT 0.5;
+        "#,
+        down: r#"
+            CREATE TABLE physics_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                gravitational_constant REAL NOT NULL DEFAULT 6.6743015e-11,
+                mutual_gravity INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO physics_new SELECT id, gravitational_constant, mutual_gravity FROM physics;
+            DROP TABLE physics;
+            ALTER TABLE physics_new RENAME TO physics;
+        "#,
+    },
+    // Version 11 -> 12: Notes attached to bodies
+    Migration {
+        description: "Add body_properties table",
+        up: r#"
+            CREATE TABLE IF NOT EXISTS body_properties (
+                body_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (body_id, key),
+                FOREIGN KEY (body_id) REFERENCES bodies(id) ON DELETE CASCADE
+            );
+        "#,
+        down: r#"
+            DROP TABLE IF EXISTS body_properties;
+        "#,
+    },
+    // Version 12 -> 13: How Newtonian bodies are stepped
+    Migration {
+        description: "Add integrator column to physics",
+        up: r#"
+            ALTER TABLE physics ADD COLUMN integrator TEXT NOT NULL DEFAULT 'Euler';
+        "#,
+        down: r#"
+            CREATE TABLE physics_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                gravitational_constant REAL NOT NULL DEFAULT 6.6743015e-11,
+                mutual_gravity INTEGER NOT NULL DEFAULT 0,
+                collision_behavior TEXT NOT NULL DEFAULT 'Ignore',
+                restitution REAL NOT NULL DEFAULT 0.5
+            );
+            INSERT INTO physics_new SELECT id, gravitational_constant, mutual_gravity, collision_behavior, restitution FROM physics;
+            DROP TABLE physics;
+            ALTER TABLE physics_new RENAME TO physics;
+        "#,
+    },
+    // Version 13 -> 14: Cap on adaptive substeps
+    Migration {
+        description: "Add max_substeps column to physics",
+        up: r#"
+            ALTER TABLE physics ADD COLUMN max_substeps INTEGER NOT NULL DEFAULT 1000;
+        "#,
+        down: r#"
+            CREATE TABLE physics_new (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                gravitational_constant REAL NOT NULL DEFAULT 6.6743015e-11,
+                mutual_gravity INTEGER NOT NULL DEFAULT 0,
+                collision_behavior TEXT NOT NULL DEFAULT 'Ignore',
+                restitution REAL NOT NULL DEFAULT 0.5,
+                integrator TEXT NOT NULL DEFAULT 'Euler'
+            );
+            INSERT INTO physics_new SELECT id, gravitational_constant, mutual_gravity, collision_behavior, restitution, integrator FROM physics;
+            DROP TABLE physics;
+            ALTER TABLE physics_new RENAME TO physics;
+        "#,
+    },
+    // Version 14 -> 15: Which way precession turns
+    Migration {
+        description: "Add apsidal_prograde and nodal_prograde columns to motive_keplerian",
+        up: r#"
+            ALTER TABLE motive_keplerian ADD COLUMN apsidal_prograde INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE motive_keplerian ADD COLUMN nodal_prograde INTEGER NOT NULL DEFAULT 1;
+        "#,
+        down: r#"
+            ALTER TABLE motive_keplerian DROP COLUMN nodal_prograde;
+            ALTER TABLE motive_keplerian DROP COLUMN apsidal_prograde;
+        "#,
+    },
+    // Version 15 -> 16: What's drawn behind the bodies
+    Migration {
+        description: "Add background columns to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN background TEXT NOT NULL DEFAULT 'Solid';
+            ALTER TABLE view_settings ADD COLUMN background_color INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE view_settings ADD COLUMN starfield_seed INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE view_settings ADD COLUMN star_count INTEGER NOT NULL DEFAULT 2000;
+        "#,
+        down: r#"
+            ALTER TABLE view_settings DROP COLUMN star_count;
+            ALTER TABLE view_settings DROP COLUMN starfield_seed;
+            ALTER TABLE view_settings DROP COLUMN background_color;
+            ALTER TABLE view_settings DROP COLUMN background;
+        "#,
+    },
+    // Version 16 -> 17: Which way orbit lines fade
+    Migration {
+        description: "Add trajectory_fade column to view_settings",
+        up: r#"
+            ALTER TABLE view_settings ADD COLUMN trajectory_fade TEXT NOT NULL DEFAULT 'Trail';
+        "#,
+        down: r#"
+            ALTER TABLE view_settings DROP COLUMN trajectory_fade;
+        "#,
+    },
+    // Version 17 -> 18: When scenario events that have happened were applied
+    Migration {
+        description: "Add fired_at_seconds column to scenario_events",
+        up: r#"
+            ALTER TABLE scenario_events ADD COLUMN fired_at_seconds REAL;
+        "#,
+        down: r#"
+            ALTER TABLE scenario_events DROP COLUMN fired_at_seconds;
+        "#,
+    },
+    // Version 18 -> 19: Motive time keys sort the way their times do, with both zeros as one
+    // Keys are parked on -id first, a NaN pattern neither encoding gives a real time, so no
+    // rewritten key can collide with one that hasn't been rewritten yet.
+    Migration {
+        description: "Rewrite motive time keys in the order-preserving encoding",
+        up: r#"
+            CREATE TEMP TABLE old_motive_keys AS SELECT id, time_key FROM motives;
+            UPDATE motives SET time_key = -id;
+            UPDATE motives SET time_key = (
+                SELECT CASE
+                    WHEN old.time_key = 0 OR old.time_key = -9223372036854775807 - 1 THEN -9223372036854775807 - 1
+                    WHEN old.time_key > 0 THEN old.time_key | (-9223372036854775807 - 1)
+                    ELSE ~old.time_key
+                END
+                FROM old_motive_keys AS old WHERE old.id = motives.id
+            );
+            DROP TABLE old_motive_keys;
+        "#,
+        down: r#"
+            CREATE TEMP TABLE new_motive_keys AS SELECT id, time_key FROM motives;
+            UPDATE motives SET time_key = -id;
+            UPDATE motives SET time_key = (
+                SELECT CASE
+                    WHEN new.time_key < 0 THEN new.time_key & 9223372036854775807
+                    ELSE ~new.time_key
+                END
+                FROM new_motive_keys AS new WHERE new.id = motives.id
+            );
+            DROP TABLE new_motive_keys;
+        "#,
+    },
 ];
 
 /// Get the current program version (number of migrations available)
@@ -361,4 +714,45 @@ mod tests {
         let version = get_db_version(&conn).unwrap();
         assert_eq!(version, program_version() - 1);
     }
+
+    #[test]
+    fn test_drop_column_downs() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+
+        // Back past every down that drops columns, then up again
+        while get_db_version(&conn).unwrap() > 14 {
+            rollback_migration(&conn).unwrap();
+        }
+        assert_eq!(run_migrations(&conn).unwrap(), program_version() - 14);
+    }
+
+    #[test]
+    fn test_motive_keys_rewritten() {
+        use crate::util::bitfutz;
+
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        rollback_migration(&conn).unwrap();
+        assert_eq!(get_db_version(&conn).unwrap(), 18);
+
+        // Keyed by raw bits, as before version 19, where -1's old key is 1's new one
+        let times = [0.0, -0.0, 1.0, -1.0, 86400.0, -3.2e9];
+        for (i, time) in times.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO motives (body_id, time_key, time_seconds, transition_event, motive_type) VALUES (?1, ?2, ?3, 'Epoch', 'Fixed')",
+                rusqlite::params![if i == 1 { "other" } else { "body" }, f64::to_bits(*time) as i64, *time],
+            ).unwrap();
+        }
+
+        run_migrations(&conn).unwrap();
+        let mut statement = conn.prepare("SELECT time_key, time_seconds FROM motives").unwrap();
+        let rows: Vec<(i64, f64)> = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), times.len());
+        for (key, time) in rows {
+            assert_eq!(key, bitfutz::f64::to_u64(time) as i64, "{}", time);
+        }
+    }
 }
@@ -3,7 +3,10 @@ use std::default::Default;
 use std::path::PathBuf;
 use bevy::prelude::*;
 use std::collections::HashMap;
+use bevy::math::DVec3;
+use crate::body::motive::info::{BodyInfo, BodyState};
 use crate::body::universe::save::UniverseFile;
+use crate::foundations::gravity;
 use crate::foundations::time::Instant;
 use crate::gui::planetarium::time::SimTime;
 
@@ -11,6 +14,13 @@ pub mod save;
 pub mod save_sqlite;
 pub mod migrations;
 pub mod solar_system;
+pub mod deletion;
+pub mod edit;
+pub mod scenario;
+pub mod lint;
+pub mod collision;
+pub mod include;
+pub mod diff;
 
 #[derive(Resource)]
 pub struct Universe {
@@ -69,16 +79,15 @@ impl Universe {
     }
 
     pub fn remove_by_name<T: AsRef<str>>(&mut self, name: T) {
-        self.name_to_id.remove(name.as_ref());
-        if let Some(id) = self.name_to_id.get(name.as_ref()) {
-            self.id_to_name.remove(id);
+        if let Some(id) = self.name_to_id.remove(name.as_ref()) {
+            self.id_to_name.remove(&id);
         }
     }
 
+    /// Despawning the body and looking after its satellites is `deletion::delete_bodies`' job.
     pub fn remove_by_id<T: AsRef<str>>(&mut self, id: T) {
-        self.id_to_name.remove(id.as_ref());
-        if let Some(name) = self.id_to_name.get(id.as_ref()) {
-            self.name_to_id.remove(name);
+        if let Some(name) = self.id_to_name.remove(id.as_ref()) {
+            self.name_to_id.remove(&name);
         }
     }
 
@@ -86,6 +95,48 @@ impl Universe {
         self.id_to_name.iter()
     }
 
+    /// Mass-weighted centroid of the current positions of the bodies with these ids.
+    pub fn barycenter(ids: &[String], bodies: &Query<(&BodyInfo, &BodyState)>) -> Option<DVec3> {
+        gravity::barycenter(bodies.iter()
+            .filter(|(info, _)| ids.contains(&info.id))
+            .map(|(info, state)| (info.mass, state.current_position)))
+    }
+
+    /// Ids and current positions of the bodies within `radius` of `center`, nearest first.
+    /// The boundary counts as inside. One-off queries like this check every body; anything
+    /// asking many times a frame wants a `ChunkGrid` instead.
+    pub fn bodies_within(bodies: &Query<(&BodyInfo, &BodyState)>, center: DVec3, radius: f64) -> Vec<(String, DVec3)> {
+        let mut found: Vec<(String, DVec3)> = bodies.iter()
+            .filter(|(_, state)| state.current_position.distance_squared(center) <= radius * radius)
+            .map(|(info, state)| (info.id.clone(), state.current_position))
+            .collect();
+        found.sort_by(|a, b| a.1.distance_squared(center).total_cmp(&b.1.distance_squared(center)));
+        found
+    }
+
+    /// Like `bodies_within`, around another body and not including it.
+    /// Empty if there's no body with that id.
+    pub fn bodies_near_body<T: AsRef<str>>(bodies: &Query<(&BodyInfo, &BodyState)>, id: T, radius: f64) -> Vec<(String, DVec3)> {
+        let Some((_, center)) = bodies.iter().find(|(info, _)| info.id == id.as_ref()) else { return Vec::new() };
+        let mut found = Self::bodies_within(bodies, center.current_position, radius);
+        found.retain(|(found_id, _)| found_id != id.as_ref());
+        found
+    }
+
+    /// An id no body has yet: `base` as a slug, with `-2`, `-3`, ... appended until it's free.
+    /// An empty slug becomes `body-1`, `body-2`, ...
+    pub fn generate_id(&self, base: &str) -> String {
+        let slug = slugify(base);
+        let (stem, mut n) = if slug.is_empty() { ("body", 1) } else { (slug.as_str(), 0) };
+        loop {
+            let candidate = if n == 0 { stem.to_string() } else { format!("{}-{}", stem, n) };
+            if !self.id_to_name.contains_key(&candidate) {
+                return candidate;
+            }
+            n = if n == 0 { 2 } else { n + 1 };
+        }
+    }
+
     pub fn get_by_id<T: AsRef<str>>(&self, id: T) -> Option<&String> {
         self.id_to_name.get(id.as_ref())
     }
@@ -95,47 +146,92 @@ impl Universe {
     }
 }
 
+/// Lowercase, spaces to hyphens, and nothing else but letters, digits and hyphens.
+pub(crate) fn slugify(text: &str) -> String {
+    text.trim()
+        .chars()
+        .map(|c| if c.is_whitespace() { '-' } else { c.to_ascii_lowercase() })
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect()
+}
+
 pub fn advance_time(mut sim_time: ResMut<SimTime>, time: Res<Time>) {
-    if !sim_time.playing {
+    if !sim_time.playing || sim_time.held {
         return;
     }
     
-    let real_delta = time.delta_secs_f64();
-    let step = sim_time.step;
-    
-    // Calculate how much sim time we WANT to advance based on gui_speed
-    let desired_sim_delta = sim_time.gui_speed * real_delta;
-    
-    // Accumulate the desired time
-    sim_time.accumulated_time += desired_sim_delta;
-    
-    // Calculate how many FULL steps one frame's worth of sim time requires
-    let full_steps = (sim_time.accumulated_time / step).floor() as usize;
-    
-    if full_steps == 0 {
-        return;
+    sim_time.advance(time.delta());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("1 Ceres"), "1-ceres");
+        assert_eq!(slugify("  Halley's Comet (1P) "), "halleys-comet-1p");
+        assert_eq!(slugify("NTB-A"), "ntb-a");
+        assert_eq!(slugify("!!!"), "");
     }
-    
-    // Consume the full amount from the accumulator
-    sim_time.accumulated_time -= full_steps as f64 * step;
-    
-    // If speed was reduced, the queue from a faster speed may be oversized — trim it.
-    // Keeps the front (earliest steps) since those must be simulated in order.
-    sim_time.previous_times.truncate(full_steps);
-    
-    // Only add enough steps to reach full_steps total — leftovers count toward the cap.
-    let already_queued = sim_time.previous_times.len();
-    if already_queued >= full_steps {
-        return;
+
+    #[test]
+    fn test_generate_id_avoids_collisions() {
+        let mut universe = Universe::default();
+        assert_eq!(universe.generate_id("Earth"), "earth");
+        universe.insert("Earth", "earth");
+        assert_eq!(universe.generate_id("Earth"), "earth-2");
+        universe.insert("Earth 2", "earth-2");
+        assert_eq!(universe.generate_id("earth"), "earth-3");
+
+        assert_eq!(universe.generate_id(""), "body-1");
+        universe.insert("Unnamed", "body-1");
+        assert_eq!(universe.generate_id("?"), "body-2");
     }
-    let steps_to_add = full_steps - already_queued;
-    
-    // Append new steps after the last queued time (or current time if queue is empty)
-    let last_queued_time = sim_time.previous_times.last()
-        .unwrap_or(sim_time.time.to_j2000_seconds());
-    sim_time.previous_times.expand(last_queued_time + step, steps_to_add, step);
-    
-    // NOTE: We do NOT update time_seconds here.
-    // time_seconds is updated by calculate_body_positions to reflect
-    // what was actually processed, not what we're trying to reach.
-}
\ No newline at end of file
+
+    #[test]
+    fn test_file_epoch_in_seconds_since_j2000() {
+        use crate::body::universe::save::{UniverseFileContents, UniverseFileTime, UniversePhysics, ViewSettings};
+        use crate::foundations::time::J2000_JD;
+
+        // Midnight before J2000, as the bundled templates start
+        let file = UniverseFile {
+            file: None,
+            contents: UniverseFileContents {
+                version: "0.0".into(),
+                time: UniverseFileTime { time_julian_days: J2000_JD - 0.5, step: 1.0, gui_speed: 1.0, max_frame_time: 0.016 },
+                view: ViewSettings::default(),
+                physics: UniversePhysics::default(),
+                bodies: Vec::new(),
+                scenario: Vec::new(),
+                camera_bookmarks: Vec::new(),
+                includes: Vec::new(),
+            },
+        };
+        let (_, sim_time) = Universe::from_file(&file);
+        assert_eq!(sim_time.time.to_j2000_seconds(), -43_200.0);
+        assert_eq!(sim_time.time.to_julian_day(), J2000_JD - 0.5);
+        assert_eq!(Instant::J2000.to_j2000_seconds(), 0.0);
+    }
+
+    #[test]
+    fn test_bodies_within_radius() {
+        use bevy::ecs::system::SystemState;
+
+        let mut world = World::new();
+        for (id, x) in [("center", 0.0), ("inside", 5.0), ("boundary", 10.0), ("outside", 10.001), ("behind", -3.0)] {
+            world.spawn((
+                BodyInfo { id: id.into(), ..Default::default() },
+                BodyState { current_position: DVec3::new(x, 0.0, 0.0), ..Default::default() },
+            ));
+        }
+        let mut state: SystemState<Query<(&BodyInfo, &BodyState)>> = SystemState::new(&mut world);
+        let bodies = state.get(&world);
+
+        let ids = |found: Vec<(String, DVec3)>| found.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids(Universe::bodies_within(&bodies, DVec3::ZERO, 10.0)), ["center", "behind", "inside", "boundary"]);
+        assert_eq!(ids(Universe::bodies_near_body(&bodies, "center", 5.0)), ["behind", "inside"]);
+        assert_eq!(ids(Universe::bodies_near_body(&bodies, "outside", 1.0)), ["boundary"]);
+        assert!(Universe::bodies_near_body(&bodies, "nowhere", 100.0).is_empty());
+    }
+}
@@ -1,20 +1,30 @@
 use std::path::PathBuf;
 use std::ffi::OsStr;
-use bevy::math::DVec3;
+use bevy::math::{DQuat, DVec3};
 use bevy::prelude::*;
 use std::collections::HashMap;
 use bevy::camera::visibility::NoFrustumCulling;
 use serde::{Deserialize, Serialize};
 use crate::body::appearance::Appearance;
 use crate::body::appearance::AssetCache;
-use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::info::{BodyInfo, BodyProperties, BodyState};
 use crate::body::motive::kepler_motive::KeplerMotive;
-use crate::body::motive::Motive;
+use crate::body::motive::{Motive, MotiveSelection};
+use crate::body::rotation::BodyRotation;
 use crate::body::SimulationObject;
 use crate::body::universe::{Major, Minor};
+use crate::body::universe::collision::CollisionBehavior;
+use crate::body::universe::include::{self, Include, IncludeError};
+use crate::body::motive::integrator::{self, IntegratorKind};
 use crate::body::universe::save_sqlite;
+use crate::body::universe::migrations;
+use crate::body::universe::scenario::{Scenario, ScenarioEvent, ScheduledEvent};
+use crate::foundations::gravity;
+use crate::foundations::time::{Instant, J2000_JD, JD_SECONDS_PER_JULIAN_DAY};
 use crate::gui::menu::TagState;
+use crate::gui::planetarium::time::SimTime;
 use crate::util::mappings;
+use crate::util::patched_conics;
 
 /// Supported save file formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,11 +62,42 @@ pub struct UniverseFile {
 impl UniverseFile {
     /// Load from any supported format (auto-detected from extension)
     pub fn load_from_path(path: &PathBuf) -> Option<Self> {
-        let format = SaveFormat::from_path(path)?;
-        match format {
-            SaveFormat::Toml => Self::load_from_path_toml(path),
-            SaveFormat::Sqlite => Self::load_from_path_sqlite(path),
-        }
+        Self::try_load_from_path(path).ok()
+    }
+
+    /// Like `load_from_path`, but says what went wrong.
+    pub fn try_load_from_path(path: &PathBuf) -> Result<Self, UniverseReadError> {
+        let mut contents = Self::read_contents(path)?;
+        include::resolve_includes(&mut contents, path)?;
+        Ok(Self {
+            file: Some(path.clone()),
+            contents,
+        })
+    }
+
+    /// Like `try_load_from_path`, but an .em file comes with the connection that keeps it locked.
+    pub fn try_load_and_hold(path: &PathBuf) -> Result<(Self, Option<rusqlite::Connection>), UniverseReadError> {
+        let (mut contents, held) = match SaveFormat::from_path(path) {
+            Some(SaveFormat::Sqlite) => {
+                let (contents, conn) = save_sqlite::load_and_hold_em(path)?;
+                (contents, Some(conn))
+            }
+            _ => (Self::read_contents(path)?, None),
+        };
+        include::resolve_includes(&mut contents, path)?;
+        Ok((Self { file: Some(path.clone()), contents }, held))
+    }
+
+    /// The file as written, includes and all.
+    pub(crate) fn read_contents(path: &PathBuf) -> Result<UniverseFileContents, UniverseReadError> {
+        let format = SaveFormat::from_path(path).ok_or(UniverseReadError::UnknownFormat)?;
+        Ok(match format {
+            SaveFormat::Toml => {
+                let string = std::fs::read_to_string(path).map_err(UniverseReadError::IO)?;
+                toml::from_str(&string).map_err(UniverseReadError::Toml)?
+            }
+            SaveFormat::Sqlite => save_sqlite::load_from_em(path)?,
+        })
     }
 
     /// Load from TOML format
@@ -81,6 +122,27 @@ impl UniverseFile {
     }
 }
 
+#[derive(Debug)]
+pub enum UniverseReadError {
+    Toml(toml::de::Error),
+    Sqlite(save_sqlite::SqliteSaveError),
+    IO(std::io::Error),
+    UnknownFormat,
+    Include(IncludeError),
+}
+
+impl From<save_sqlite::SqliteSaveError> for UniverseReadError {
+    fn from(e: save_sqlite::SqliteSaveError) -> Self {
+        UniverseReadError::Sqlite(e)
+    }
+}
+
+impl From<IncludeError> for UniverseReadError {
+    fn from(e: IncludeError) -> Self {
+        UniverseReadError::Include(e)
+    }
+}
+
 #[derive(Debug)]
 pub enum UniverseWriteError {
     Toml(toml::ser::Error),
@@ -167,6 +229,72 @@ pub struct UniverseFileContents {
     pub view: ViewSettings,
     pub physics: UniversePhysics,
     pub bodies: Vec<SomeBody>,
+    /// Events scripted to happen as time passes.
+    #[serde(default)]
+    pub scenario: Vec<ScheduledEvent>,
+    #[serde(default)]
+    pub camera_bookmarks: Vec<CameraBookmark>,
+    /// Other files whose bodies are merged in on load. Only TOML files have these;
+    /// once loaded they're gone, and saving writes the merged bodies instead.
+    #[serde(default)]
+    pub includes: Vec<Include>,
+}
+
+impl UniverseFileContents {
+    /// The universe as it is right now. Bodies a scenario has yet to spawn are left to the scenario.
+    /// Ones it has spawned are saved as they are now, both as bodies and in their spawn events,
+    /// so rewinding past the spawn and coming forward again brings them back the same.
+    pub fn snapshot(
+        bodies: &Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>,
+        sim_time: &SimTime,
+        view: &ViewSettings,
+        physics: &UniversePhysics,
+        scenario_state: &Scenario,
+        camera_bookmarks: &[CameraBookmark],
+    ) -> Self {
+        let mut scenario: Vec<ScheduledEvent> = scenario_state.scheduled().cloned().collect();
+        // Loading reapplies whatever the fired events changed
+        let gravitational_constant = scenario_state.unscripted_gravitational_constant(physics.gravitational_constant);
+        let entries: Vec<CompoundMotiveEntry> = bodies.iter()
+            .map(|(info, motive, appearance, rotation, properties)| CompoundMotiveEntry {
+                info: info.clone(),
+                motive: motive.clone(),
+                appearance: appearance.clone(),
+                rotation: rotation.cloned(),
+                properties: properties.cloned().unwrap_or_default(),
+            })
+            .collect();
+
+        let mut unspawned: Vec<String> = Vec::new();
+        for scheduled in &mut scenario {
+            let ScenarioEvent::SpawnBody(entry) = &mut scheduled.event else { continue };
+            if scheduled.fired_at.is_none() {
+                unspawned.push(entry.info.id.clone());
+            } else if let Some(live) = entries.iter().find(|live| live.info.id == entry.info.id) {
+                **entry = live.clone();
+            }
+        }
+        let bodies = entries.into_iter()
+            .filter(|entry| !unspawned.contains(&entry.info.id))
+            .map(SomeBody::CompoundMotiveEntry)
+            .collect();
+
+        Self {
+            version: format!("em-{}", migrations::program_version()),
+            time: UniverseFileTime {
+                time_julian_days: J2000_JD + sim_time.time.to_j2000_seconds() / JD_SECONDS_PER_JULIAN_DAY,
+                step: sim_time.step,
+                gui_speed: sim_time.gui_speed,
+                max_frame_time: sim_time.max_frame_time,
+            },
+            view: view.clone(),
+            physics: UniversePhysics { gravitational_constant, ..physics.clone() },
+            bodies,
+            scenario,
+            camera_bookmarks: camera_bookmarks.to_vec(),
+            includes: Vec::new(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -187,20 +315,44 @@ fn default_step() -> f64 { 0.1 }
 fn default_gui_speed() -> f64 { 1.0 }
 fn default_max_frame_time() -> f64 { 0.016 }
 
-#[derive(Resource, Serialize, Deserialize)]
+#[derive(Resource, Serialize, Deserialize, Clone)]
 pub struct UniversePhysics {
     pub gravitational_constant: f64,
+    /// Major bodies pull on each other, integrated from where their motives put them,
+    /// instead of following their motives.
+    #[serde(default)]
+    pub mutual_gravity: bool,
+    /// What happens when bodies touch.
+    #[serde(default)]
+    pub collision_behavior: CollisionBehavior,
+    /// Share of approach speed bodies keep when they bounce apart, from 0 to 1.
+    #[serde(default = "default_restitution")]
+    pub restitution: f64,
+    /// How Newtonian bodies, and mutual gravity bodies, are stepped.
+    #[serde(default)]
+    pub integrator: IntegratorKind,
+    /// Most pieces an adaptive integrator may split one step into.
+    #[serde(default = "default_max_substeps")]
+    pub max_substeps: usize,
 }
 
+fn default_restitution() -> f64 { 0.5 }
+fn default_max_substeps() -> usize { integrator::DEFAULT_MAX_SUBSTEPS }
+
 impl Default for UniversePhysics {
     fn default() -> Self {
         Self {
-            gravitational_constant: 6.6743015e-11, // Standard G in m³ kg⁻¹ s⁻²
+            gravitational_constant: gravity::G,
+            mutual_gravity: false,
+            collision_behavior: CollisionBehavior::default(),
+            restitution: default_restitution(),
+            integrator: IntegratorKind::default(),
+            max_substeps: default_max_substeps(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Resource, Debug)]
+#[derive(Serialize, Deserialize, Resource, Debug, Clone)]
 pub struct ViewSettings {
     pub distance_scale: f64,
     pub logarithmic_distance_scale: bool,
@@ -212,6 +364,98 @@ pub struct ViewSettings {
     pub show_trajectories: bool,
     pub tags: HashMap<String, TagState>,
     pub trajectory_resolution: usize,
+    #[serde(default)]
+    pub show_soi: bool,
+    /// Draw bodies where the light reaching the camera left them, rather than where they are now.
+    #[serde(default)]
+    pub apply_light_time: bool,
+    #[serde(default)]
+    pub center: ViewCenter,
+    /// Distance rings in the reference plane, around the scene's origin.
+    #[serde(default)]
+    pub show_grid: bool,
+    #[serde(default)]
+    pub background: Background,
+    #[serde(default)]
+    pub trajectory_fade: TrajectoryFade,
+}
+
+/// A named camera pose to come back to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CameraBookmark {
+    pub name: String,
+    /// `Freecam::bevy_pos`.
+    pub position: DVec3,
+    /// Euler angles of the camera's rotation, in `EulerRot::YXZ` order.
+    pub yaw: f64,
+    pub pitch: f64,
+    pub roll: f64,
+    /// Body the camera was revolving around, if any.
+    #[serde(default)]
+    pub target_id: Option<String>,
+}
+
+impl CameraBookmark {
+    pub fn new(name: String, position: DVec3, rotation: Quat, target_id: Option<String>) -> Self {
+        let (yaw, pitch, roll) = rotation.as_dquat().to_euler(EulerRot::YXZ);
+        Self { name, position, yaw, pitch, roll, target_id }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        DQuat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, self.roll).as_quat()
+    }
+}
+
+/// What's drawn at the origin of the scene.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum ViewCenter {
+    /// Positions as simulated.
+    #[default]
+    Origin,
+    /// This body, with its satellites drawn relative to it, like most orbit diagrams.
+    Body(String),
+}
+
+/// What's drawn behind everything else.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Background {
+    /// One sRGB color.
+    Solid([u8; 3]),
+    /// Points of light at infinity. The same seed always gives the same sky.
+    Starfield { seed: u64, count: usize },
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Solid([0, 0, 0])
+    }
+}
+
+/// Which way along its trajectory a body's orbit line fades out from it, to show which way it's going.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum TrajectoryFade {
+    /// The same brightness all the way round.
+    Uniform,
+    /// A trail behind the body.
+    #[default]
+    Trail,
+    /// The path ahead of the body.
+    Ahead,
+    /// Both ways, darkest opposite the body.
+    Both,
+}
+
+impl TrajectoryFade {
+    pub const ALL: [TrajectoryFade; 4] = [TrajectoryFade::Uniform, TrajectoryFade::Trail, TrajectoryFade::Ahead, TrajectoryFade::Both];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrajectoryFade::Uniform => "Uniform",
+            TrajectoryFade::Trail => "Trail",
+            TrajectoryFade::Ahead => "Ahead",
+            TrajectoryFade::Both => "Both ways",
+        }
+    }
 }
 
 impl Default for ViewSettings {
@@ -227,6 +471,12 @@ impl Default for ViewSettings {
             show_trajectories: true,
             tags: HashMap::new(),
             trajectory_resolution: 120,
+            show_soi: false,
+            apply_light_time: false,
+            center: ViewCenter::Origin,
+            show_grid: false,
+            background: Background::default(),
+            trajectory_fade: TrajectoryFade::default(),
         }
     }
 }
@@ -249,7 +499,46 @@ impl ViewSettings {
         }
         false
     }
-    
+
+    pub fn soloing(&self) -> bool {
+        self.tags.values().any(|tag| tag.solo)
+    }
+
+    /// Solos only this tag, or un-solos it if it already was. The shown flags are left alone,
+    /// so un-soloing puts visibility back how it was.
+    pub fn toggle_solo<T: AsRef<str>>(&mut self, tag_name: T) {
+        let was_solo = self.tags.get(tag_name.as_ref()).is_some_and(|tag| tag.solo);
+        for (name, tag) in &mut self.tags {
+            tag.solo = !was_solo && name == tag_name.as_ref();
+        }
+    }
+
+    /// Whether solo and mute let a body be drawn at all.
+    pub fn body_in_scope<T: AsRef<str>>(&self, body_id: T) -> bool {
+        let mut soloed = false;
+        for tag in self.tags.values() {
+            if !tag.members.iter().any(|member| member == body_id.as_ref()) {
+                continue;
+            }
+            if tag.muted {
+                return false;
+            }
+            soloed |= tag.solo;
+        }
+        soloed || !self.soloing()
+    }
+
+    /// Soloing a tag shows its labels even if they're otherwise hidden.
+    pub fn label_visible<T: AsRef<str>>(&self, body_id: T) -> bool {
+        self.body_in_scope(body_id.as_ref())
+            && (self.soloing() || self.show_labels || self.body_in_any_visible_tag(body_id.as_ref()))
+    }
+
+    pub fn trajectory_visible<T: AsRef<str>>(&self, body_id: T) -> bool {
+        self.body_in_scope(body_id.as_ref())
+            && (self.show_trajectories || self.body_in_any_trajectory_tag(body_id.as_ref()))
+    }
+
     pub fn distance_factor(&self) -> f64 {
         if self.logarithmic_distance_scale {
             mappings::log_scale(self.distance_scale, self.logarithmic_distance_base)
@@ -268,7 +557,7 @@ impl ViewSettings {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum SomeBody {
     /// Legacy fixed motive - loaded as Motive with single Fixed entry
     FixedEntry(FixedEntry),
@@ -291,12 +580,44 @@ impl SomeBody {
         materials: &mut ResMut<Assets<StandardMaterial>>,
         images: &mut ResMut<Assets<Image>>,
     )  -> Entity {
+        let appearance = self.appearance().clone();
+        let entity = self.spawn_bare(commands);
+        insert_render_components(&mut commands.entity(entity), &appearance, cache, meshes, materials, images);
+        entity
+    }
+
+    /// Spawns the body with everything the physics needs but nothing to draw it with.
+    pub fn spawn_bare(self, commands: &mut Commands) -> Entity {
         let mut entity = commands.spawn((
             SimulationObject,
             Transform::default(),
             BodyState::default(),
         ));
 
+        let (info, appearance, motive, rotation, properties) = self.into_parts();
+
+        // Insert the compound motive
+        entity.insert(motive);
+
+        if info.major {
+            entity.insert(Major);
+        } else {
+            entity.insert(Minor);
+        }
+        entity.insert(info);
+        if let Some(rotation) = rotation {
+            entity.insert(rotation);
+        }
+        entity.insert(properties);
+        entity.insert(appearance);
+
+        entity.id()
+    }
+
+    /// The components a body is made of, with legacy formats converted to a compound motive.
+    pub fn into_parts(self) -> (BodyInfo, Appearance, Motive, Option<BodyRotation>, BodyProperties) {
+        let mut rotation = None;
+        let mut properties = BodyProperties::default();
         let (info, appearance, motive) = match self {
             SomeBody::FixedEntry(entry) => {
                 // Convert legacy FixedEntry to Motive with single Fixed entry at Epoch
@@ -305,7 +626,7 @@ impl SomeBody {
             },
             SomeBody::NewtonEntry(entry) => {
                 // Convert legacy NewtonEntry to Motive with single Newtonian entry at Epoch
-                let motive = Motive::newtonian(entry.position, entry.velocity);
+                let motive = entry.motive();
                 (entry.info, entry.appearance, motive)
             },
             SomeBody::KeplerEntry(entry) => {
@@ -319,45 +640,18 @@ impl SomeBody {
                 (entry.info, entry.appearance, motive)
             },
             SomeBody::CompoundEntry(entry) => {
-                // Legacy patched conics - create empty motive for now
-                // TODO: Convert old route HashMap to new Motive format if needed
-                let motive = Motive::fixed(DVec3::ZERO);
+                // Legacy patched conics - each route segment becomes a Keplerian motive at its SOI change
+                let motive = patched_conics::to_motive(&entry.route).unwrap_or_else(|| Motive::fixed(DVec3::ZERO));
                 (entry.info, entry.appearance, motive)
             },
             SomeBody::CompoundMotiveEntry(entry) => {
                 // New compound motive format - use directly
+                rotation = entry.rotation;
+                properties = entry.properties;
                 (entry.info, entry.appearance, entry.motive)
             },
         };
-
-        // Insert the compound motive
-        entity.insert(motive);
-
-        if info.major {
-            entity.insert(Major);
-        } else {
-            entity.insert(Minor);
-        }
-        entity.insert(info);
-
-        match &appearance {
-            Appearance::Empty => {}
-            Appearance::DebugBall(debug_ball) => {
-                let (mesh, material) = debug_ball.pbr_bundle(cache, meshes, materials, images);
-                entity.insert(mesh);
-                entity.insert(material);
-            }
-            Appearance::Star(star_ball) => {
-                let (mesh, material, light) = star_ball.pbr_bundle(cache, meshes, materials, images);
-                entity.insert(mesh);
-                entity.insert(material);
-                entity.insert(light);
-                entity.insert(NoFrustumCulling);
-            }
-        }
-        entity.insert(appearance);
-
-        entity.id()
+        (info, appearance, motive, rotation, properties)
     }
 
     pub fn id(&self) -> String {
@@ -389,24 +683,105 @@ impl SomeBody {
             SomeBody::CompoundMotiveEntry(entry) => &entry.info.tags,
         }
     }
+
+    pub fn info(&self) -> &BodyInfo {
+        match self {
+            SomeBody::FixedEntry(entry) => &entry.info,
+            SomeBody::NewtonEntry(entry) => &entry.info,
+            SomeBody::KeplerEntry(entry) => &entry.info,
+            SomeBody::CompoundEntry(entry) => &entry.info,
+            SomeBody::CompoundMotiveEntry(entry) => &entry.info,
+        }
+    }
+
+    /// Every Keplerian orbit the body follows at some point.
+    pub fn kepler_motives(&self) -> Vec<&KeplerMotive> {
+        match self {
+            SomeBody::FixedEntry(_) | SomeBody::NewtonEntry(_) => Vec::new(),
+            SomeBody::KeplerEntry(entry) => vec![&entry.params],
+            SomeBody::CompoundEntry(entry) => entry.route.values().collect(),
+            SomeBody::CompoundMotiveEntry(entry) => entry.motive.iter_events()
+                .filter_map(|(_, _, selection)| match selection {
+                    MotiveSelection::Keplerian(kepler) => Some(kepler),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn info_mut(&mut self) -> &mut BodyInfo {
+        match self {
+            SomeBody::FixedEntry(entry) => &mut entry.info,
+            SomeBody::NewtonEntry(entry) => &mut entry.info,
+            SomeBody::KeplerEntry(entry) => &mut entry.info,
+            SomeBody::CompoundEntry(entry) => &mut entry.info,
+            SomeBody::CompoundMotiveEntry(entry) => &mut entry.info,
+        }
+    }
+
+    pub fn appearance(&self) -> &Appearance {
+        match self {
+            SomeBody::FixedEntry(entry) => &entry.appearance,
+            SomeBody::NewtonEntry(entry) => &entry.appearance,
+            SomeBody::KeplerEntry(entry) => &entry.appearance,
+            SomeBody::CompoundEntry(entry) => &entry.appearance,
+            SomeBody::CompoundMotiveEntry(entry) => &entry.appearance,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Meshes, materials and lights for drawing a body.
+pub fn insert_render_components(
+    entity: &mut EntityCommands,
+    appearance: &Appearance,
+    cache: &mut ResMut<AssetCache>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    images: &mut ResMut<Assets<Image>>,
+) {
+    match appearance {
+        Appearance::Empty => {}
+        Appearance::DebugBall(debug_ball) => {
+            let (mesh, material) = debug_ball.pbr_bundle(cache, meshes, materials, images);
+            entity.insert(mesh);
+            entity.insert(material);
+        }
+        Appearance::Star(star_ball) => {
+            let (mesh, material, light) = star_ball.pbr_bundle(cache, meshes, materials, images);
+            entity.insert(mesh);
+            entity.insert(material);
+            entity.insert(light);
+            entity.insert(NoFrustumCulling);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FixedEntry {
     pub info: BodyInfo,
     pub position: DVec3,
     pub appearance: Appearance,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct NewtonEntry {
     pub info: BodyInfo,
     pub position: DVec3,
     pub velocity: DVec3,
     pub appearance: Appearance,
+    /// Time the position and velocity are for, J2000 if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<Instant>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl NewtonEntry {
+    pub fn motive(&self) -> Motive {
+        let epoch = self.epoch.unwrap_or(Instant::from_seconds_since_j2000(0.0));
+        Motive::newtonian_at(epoch, self.position, self.velocity)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct KeplerEntry {
     pub info: BodyInfo,
     pub params: KeplerMotive,
@@ -414,7 +789,7 @@ pub struct KeplerEntry {
 }
 
 /// Legacy format - use CompoundMotiveEntry for new saves
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PatchedConicsEntry {
     pub info: BodyInfo,
     pub route: HashMap<u64, KeplerMotive>,
@@ -422,9 +797,51 @@ pub struct PatchedConicsEntry {
 }
 
 /// The new compound motive format that supports motive transitions over time
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CompoundMotiveEntry {
     pub info: BodyInfo,
     pub motive: Motive,
     pub appearance: Appearance,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<BodyRotation>,
+    #[serde(default, skip_serializing_if = "BodyProperties::is_empty")]
+    pub properties: BodyProperties,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(shown: bool, members: &[&str]) -> TagState {
+        TagState { shown, members: members.iter().map(|m| m.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_solo_and_restore() {
+        let mut view = ViewSettings { show_labels: false, ..Default::default() };
+        view.tags.insert("planets".into(), tag(true, &["earth", "mars"]));
+        view.tags.insert("moons".into(), tag(false, &["luna"]));
+        view.tags.insert("probes".into(), tag(true, &["voyager"]));
+        let visible = |view: &ViewSettings| -> Vec<bool> {
+            ["earth", "mars", "luna", "voyager", "untagged"].iter().map(|id| view.label_visible(id)).collect()
+        };
+        let before = visible(&view);
+        assert_eq!(before, [true, true, false, true, false]);
+
+        view.toggle_solo("moons");
+        assert_eq!(visible(&view), [false, false, true, false, false]);
+
+        // Soloing another tag replaces the first
+        view.toggle_solo("planets");
+        assert_eq!(visible(&view), [true, true, false, false, false]);
+        assert!(!view.tags["moons"].solo);
+
+        view.tags.get_mut("planets").unwrap().muted = true;
+        assert_eq!(visible(&view), [false; 5]);
+        view.tags.get_mut("planets").unwrap().muted = false;
+
+        view.toggle_solo("planets");
+        assert!(!view.soloing());
+        assert_eq!(visible(&view), before);
+    }
 }
@@ -4,11 +4,14 @@
 
 use std::path::PathBuf;
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
 use bevy::math::DVec3;
-use rusqlite::{Connection, Result as SqlResult, params};
+use bevy::prelude::Resource;
+use rusqlite::{Connection, ErrorCode, Result as SqlResult, params};
 
 use crate::body::appearance::{Appearance, AppearanceColor, DebugBall, StarBall};
-use crate::body::motive::info::BodyInfo;
+use crate::body::motive::info::{BodyInfo, BodyProperties};
 use crate::body::motive::kepler_motive::{
     KeplerMotive, KeplerShape, KeplerRotation, KeplerEpoch,
     EccentricitySMA, Apsides,
@@ -16,11 +19,15 @@ use crate::body::motive::kepler_motive::{
     MeanAnomalyAtEpoch, TrueAnomalyAtEpoch, MeanAnomalyAtJ2000,
 };
 use crate::body::motive::{Motive, MotiveSelection, TransitionEvent};
+use crate::body::rotation::BodyRotation;
 use crate::body::universe::save::{
-    UniverseFileContents, UniverseFileTime, UniversePhysics, ViewSettings,
+    Background, CameraBookmark, TrajectoryFade, UniverseFileContents, UniverseFileTime, UniversePhysics, ViewCenter, ViewSettings,
     SomeBody, CompoundMotiveEntry,
 };
-use crate::foundations::time::{Instant, TimeLength};
+use crate::body::universe::collision::CollisionBehavior;
+use crate::body::motive::integrator::IntegratorKind;
+use crate::body::universe::scenario::{ScenarioEvent, ScheduledEvent};
+use crate::foundations::time::{Instant, TimeLength, J2000_JD};
 use crate::gui::menu::TagState;
 use crate::util::bitfutz;
 
@@ -32,6 +39,8 @@ pub enum SqliteSaveError {
     Sqlite(rusqlite::Error),
     InvalidData(String),
     IO(std::io::Error),
+    /// Another connection, probably another copy of the program, has the file open.
+    Locked(PathBuf),
 }
 
 impl From<rusqlite::Error> for SqliteSaveError {
@@ -46,9 +55,11 @@ impl From<std::io::Error> for SqliteSaveError {
     }
 }
 
-/// Open a connection to an .em file, running migrations as needed
+/// Open a connection to an .em file, running migrations as needed.
+/// The file stays locked against other connections until this one is dropped.
 pub fn open_em_file(path: &PathBuf) -> Result<Connection, SqliteSaveError> {
     let conn = Connection::open(path)?;
+    lock(&conn, path)?;
     
     // Enable foreign keys
     conn.execute("PRAGMA foreign_keys = ON", [])?;
@@ -61,12 +72,14 @@ pub fn open_em_file(path: &PathBuf) -> Result<Connection, SqliteSaveError> {
 
 /// Create a new .em file with the initial schema
 pub fn create_em_file(path: &PathBuf) -> Result<Connection, SqliteSaveError> {
-    // Remove existing file if present
+    // Remove existing file if present, unless it's in use
     if path.exists() {
+        ensure_unlocked(path)?;
         std::fs::remove_file(path)?;
     }
     
     let conn = Connection::open(path)?;
+    lock(&conn, path)?;
     
     // Enable foreign keys
     conn.execute("PRAGMA foreign_keys = ON", [])?;
@@ -77,21 +90,88 @@ pub fn create_em_file(path: &PathBuf) -> Result<Connection, SqliteSaveError> {
     Ok(conn)
 }
 
+/// WAL with exclusive locking, so the lock is taken now and held for the life of the connection
+/// rather than per transaction. The OS drops it if the program dies.
+fn lock(conn: &Connection, path: &PathBuf) -> Result<(), SqliteSaveError> {
+    // Fail straight away rather than waiting for the other side to let go
+    conn.busy_timeout(Duration::ZERO)?;
+    let locked = |e: rusqlite::Error| if is_busy(&e) { SqliteSaveError::Locked(path.clone()) } else { e.into() };
+    conn.pragma_update_and_check(None, "locking_mode", "EXCLUSIVE", |row| row.get::<_, String>(0))
+        .map_err(locked)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .map_err(locked)?;
+    conn.execute_batch("BEGIN EXCLUSIVE; COMMIT;").map_err(locked)?;
+    Ok(())
+}
+
+/// Errors if another connection holds the file, without taking the lock.
+fn ensure_unlocked(path: &PathBuf) -> Result<(), SqliteSaveError> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(Duration::ZERO)?;
+    conn.execute_batch("BEGIN EXCLUSIVE; ROLLBACK;").map_err(|e| {
+        if is_busy(&e) { SqliteSaveError::Locked(path.clone()) } else { e.into() }
+    })
+}
+
+fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(e, rusqlite::Error::SqliteFailure(failure, _)
+        if failure.code == ErrorCode::DatabaseBusy || failure.code == ErrorCode::DatabaseLocked)
+}
+
+/// The connection to the .em file being worked on, kept open so the file stays locked
+/// against other copies of the program until the planetarium is left.
+#[derive(Resource, Default)]
+pub struct HeldEmFile(Option<Mutex<Connection>>);
+
+impl HeldEmFile {
+    pub fn new(conn: Option<Connection>) -> Self {
+        Self(conn.map(Mutex::new))
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Reads the held file as it is on disk, since nothing else can open it meanwhile.
+    pub fn read(&self) -> Option<Result<UniverseFileContents, SqliteSaveError>> {
+        let conn = self.0.as_ref()?.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some(load_contents(&conn))
+    }
+
+    pub fn release(&mut self) {
+        self.0 = None;
+    }
+}
+
 /// Load a UniverseFileContents from an .em file
 pub fn load_from_em(path: &PathBuf) -> Result<UniverseFileContents, SqliteSaveError> {
+    load_and_hold_em(path).map(|(contents, _)| contents)
+}
+
+/// Like `load_from_em`, but hands back the connection, which keeps the file locked while it's alive.
+pub fn load_and_hold_em(path: &PathBuf) -> Result<(UniverseFileContents, Connection), SqliteSaveError> {
     let conn = open_em_file(path)?;
-    
+    let contents = load_contents(&conn)?;
+    Ok((contents, conn))
+}
+
+fn load_contents(conn: &Connection) -> Result<UniverseFileContents, SqliteSaveError> {
     // Load physics
-    let physics = load_physics(&conn)?;
+    let physics = load_physics(conn)?;
     
     // Load time
-    let time = load_time(&conn)?;
+    let time = load_time(conn)?;
     
     // Load view settings
-    let view = load_view_settings(&conn)?;
+    let view = load_view_settings(conn)?;
     
     // Load bodies with their motives
-    let bodies = load_bodies(&conn)?;
+    let bodies = load_bodies(conn)?;
+    
+    // Load scripted events
+    let scenario = load_scenario(conn)?;
+
+    let camera_bookmarks = load_camera_bookmarks(conn)?;
     
     Ok(UniverseFileContents {
         version: format!("em-{}", migrations::program_version()),
@@ -99,33 +179,78 @@ pub fn load_from_em(path: &PathBuf) -> Result<UniverseFileContents, SqliteSaveEr
         view,
         physics,
         bodies,
+        scenario,
+        camera_bookmarks,
+        // Flattened before saving
+        includes: Vec::new(),
     })
 }
 
-/// Save a UniverseFileContents to an .em file
+/// Save a UniverseFileContents to an .em file.
+/// The previous file is only replaced once the new one is completely written.
 pub fn save_to_em(path: &PathBuf, contents: &UniverseFileContents) -> Result<(), SqliteSaveError> {
-    let conn = create_em_file(path)?;
-    
-    // Save in a transaction
-    conn.execute("BEGIN TRANSACTION", [])?;
-    
-    match (|| -> Result<(), SqliteSaveError> {
-        save_physics(&conn, &contents.physics)?;
-        save_time(&conn, &contents.time)?;
+    save_atomically(path, |conn| {
+        save_physics(conn, &contents.physics)?;
+        save_time(conn, &contents.time)?;
         // Save bodies first - this creates tags from body.info.tags
-        save_bodies(&conn, &contents.bodies)?;
+        save_bodies(conn, &contents.bodies)?;
+        save_scenario(conn, &contents.scenario, &contents.bodies)?;
         // Then save view settings - this updates tag display settings (shown/trajectory)
-        save_view_settings(&conn, &contents.view)?;
+        save_view_settings(conn, &contents.view)?;
+        save_camera_bookmarks(conn, &contents.camera_bookmarks)?;
         Ok(())
-    })() {
-        Ok(()) => {
-            conn.execute("COMMIT", [])?;
-            Ok(())
+    })
+}
+
+/// Runs `write` in a transaction on a fresh file beside `path`, then moves it over `path`.
+/// If anything fails the temporary file is removed and `path` is left as it was.
+fn save_atomically<F>(path: &PathBuf, write: F) -> Result<(), SqliteSaveError>
+where
+    F: FnOnce(&Connection) -> Result<(), SqliteSaveError>,
+{
+    let temp_path = temp_path_for(path);
+    let result = (|| -> Result<(), SqliteSaveError> {
+        if path.exists() {
+            ensure_unlocked(path)?;
         }
-        Err(e) => {
-            let _ = conn.execute("ROLLBACK", []);
-            Err(e)
+        let conn = create_em_file(&temp_path)?;
+        conn.execute("BEGIN TRANSACTION", [])?;
+        match write(&conn) {
+            Ok(()) => conn.execute("COMMIT", [])?,
+            Err(e) => {
+                let _ = conn.execute("ROLLBACK", []);
+                return Err(e);
+            }
+        };
+        // Closed before the move, which Windows won't do to an open file
+        conn.close().map_err(|(_, e)| e)?;
+        replace_file(&temp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() && temp_path.exists() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn temp_path_for(path: &PathBuf) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Renames over an existing file. That's atomic elsewhere, but on Windows it can be refused
+/// while something else holds the target, so there it falls back to removing it first.
+fn replace_file(from: &PathBuf, to: &PathBuf) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        #[cfg(windows)]
+        Err(_) if to.exists() => {
+            std::fs::remove_file(to)?;
+            std::fs::rename(from, to)
         }
+        Err(e) => Err(e),
     }
 }
 
@@ -134,19 +259,46 @@ pub fn save_to_em(path: &PathBuf, contents: &UniverseFileContents) -> Result<(),
 // ============================================================================
 
 fn load_physics(conn: &Connection) -> Result<UniversePhysics, SqliteSaveError> {
-    let gravitational_constant: f64 = conn.query_row(
-        "SELECT gravitational_constant FROM physics WHERE id = 1",
+    let (gravitational_constant, mutual_gravity, collision_behavior, restitution, integrator, max_substeps) = conn.query_row(
+        "SELECT gravitational_constant, mutual_gravity, collision_behavior, restitution, integrator, max_substeps FROM physics WHERE id = 1",
         [],
-        |row| row.get(0),
+        |row| Ok((row.get::<_, f64>(0)?, row.get::<_, i32>(1)? != 0, row.get::<_, String>(2)?, row.get::<_, f64>(3)?, row.get::<_, String>(4)?, row.get::<_, i64>(5)?)),
     )?;
+    let collision_behavior = match collision_behavior.as_str() {
+        "Ignore" => CollisionBehavior::Ignore,
+        "Merge" => CollisionBehavior::Merge,
+        "Bounce" => CollisionBehavior::Bounce,
+        _ => return Err(SqliteSaveError::InvalidData(format!("Unknown collision behavior: {}", collision_behavior))),
+    };
+    let integrator = match integrator.as_str() {
+        "Euler" => IntegratorKind::Euler,
+        "SemiImplicitEuler" => IntegratorKind::SemiImplicitEuler,
+        "Verlet" => IntegratorKind::Verlet,
+        "Rk4" => IntegratorKind::Rk4,
+        "Rk45" => IntegratorKind::Rk45,
+        _ => return Err(SqliteSaveError::InvalidData(format!("Unknown integrator: {}", integrator))),
+    };
+    let max_substeps = max_substeps.max(1) as usize;
     
-    Ok(UniversePhysics { gravitational_constant })
+    Ok(UniversePhysics { gravitational_constant, mutual_gravity, collision_behavior, restitution, integrator, max_substeps })
 }
 
 fn save_physics(conn: &Connection, physics: &UniversePhysics) -> Result<(), SqliteSaveError> {
+    let collision_behavior = match physics.collision_behavior {
+        CollisionBehavior::Ignore => "Ignore",
+        CollisionBehavior::Merge => "Merge",
+        CollisionBehavior::Bounce => "Bounce",
+    };
+    let integrator = match physics.integrator {
+        IntegratorKind::Euler => "Euler",
+        IntegratorKind::SemiImplicitEuler => "SemiImplicitEuler",
+        IntegratorKind::Verlet => "Verlet",
+        IntegratorKind::Rk4 => "Rk4",
+        IntegratorKind::Rk45 => "Rk45",
+    };
     conn.execute(
-        "UPDATE physics SET gravitational_constant = ?1 WHERE id = 1",
-        [physics.gravitational_constant],
+        "UPDATE physics SET gravitational_constant = ?1, mutual_gravity = ?2, collision_behavior = ?3, restitution = ?4, integrator = ?5, max_substeps = ?6 WHERE id = 1",
+        params![physics.gravitational_constant, physics.mutual_gravity as i32, collision_behavior, physics.restitution, integrator, physics.max_substeps as i64],
     )?;
     Ok(())
 }
@@ -193,7 +345,9 @@ fn load_view_settings(conn: &Connection) -> Result<ViewSettings, SqliteSaveError
     let row = conn.query_row(
         "SELECT distance_scale, logarithmic_distance_scale, logarithmic_distance_base,
                 body_scale, logarithmic_body_scale, logarithmic_body_base,
-                show_labels, show_trajectories, trajectory_resolution
+                show_labels, show_trajectories, trajectory_resolution, show_soi, apply_light_time,
+                center_body_id, show_grid, background, background_color, starfield_seed, star_count,
+                trajectory_fade
          FROM view_settings WHERE id = 1",
         [],
         |row| {
@@ -207,9 +361,33 @@ fn load_view_settings(conn: &Connection) -> Result<ViewSettings, SqliteSaveError
                 row.get::<_, i32>(6)? != 0,
                 row.get::<_, i32>(7)? != 0,
                 row.get::<_, usize>(8)?,
+                row.get::<_, i32>(9)? != 0,
+                row.get::<_, i32>(10)? != 0,
+                row.get::<_, Option<String>>(11)?,
+                row.get::<_, i32>(12)? != 0,
+                row.get::<_, String>(13)?,
+                row.get::<_, u32>(14)?,
+                row.get::<_, i64>(15)?,
+                row.get::<_, usize>(16)?,
+                row.get::<_, String>(17)?,
             ))
         },
     )?;
+    let background = match row.13.as_str() {
+        "Solid" => {
+            let [_, r, g, b] = row.14.to_be_bytes();
+            Background::Solid([r, g, b])
+        }
+        "Starfield" => Background::Starfield { seed: row.15 as u64, count: row.16 },
+        other => return Err(SqliteSaveError::InvalidData(format!("Unknown background: {}", other))),
+    };
+    let trajectory_fade = match row.17.as_str() {
+        "Uniform" => TrajectoryFade::Uniform,
+        "Trail" => TrajectoryFade::Trail,
+        "Ahead" => TrajectoryFade::Ahead,
+        "Both" => TrajectoryFade::Both,
+        other => return Err(SqliteSaveError::InvalidData(format!("Unknown trajectory fade: {}", other))),
+    };
     
     // Load tags
     let tags = load_tags(conn)?;
@@ -225,10 +403,29 @@ fn load_view_settings(conn: &Connection) -> Result<ViewSettings, SqliteSaveError
         show_trajectories: row.7,
         tags,
         trajectory_resolution: row.8,
+        show_soi: row.9,
+        apply_light_time: row.10,
+        center: match row.11 {
+            Some(id) => ViewCenter::Body(id),
+            None => ViewCenter::Origin,
+        },
+        show_grid: row.12,
+        background,
+        trajectory_fade,
     })
 }
 
 fn save_view_settings(conn: &Connection, view: &ViewSettings) -> Result<(), SqliteSaveError> {
+    let (background, background_color, starfield_seed, star_count) = match view.background {
+        Background::Solid([r, g, b]) => ("Solid", u32::from_be_bytes([0, r, g, b]), 0, 2000),
+        Background::Starfield { seed, count } => ("Starfield", 0, seed as i64, count as i64),
+    };
+    let trajectory_fade = match view.trajectory_fade {
+        TrajectoryFade::Uniform => "Uniform",
+        TrajectoryFade::Trail => "Trail",
+        TrajectoryFade::Ahead => "Ahead",
+        TrajectoryFade::Both => "Both",
+    };
     conn.execute(
         "UPDATE view_settings SET
             distance_scale = ?1,
@@ -239,7 +436,16 @@ fn save_view_settings(conn: &Connection, view: &ViewSettings) -> Result<(), Sqli
             logarithmic_body_base = ?6,
             show_labels = ?7,
             show_trajectories = ?8,
-            trajectory_resolution = ?9
+            trajectory_resolution = ?9,
+            show_soi = ?10,
+            apply_light_time = ?11,
+            center_body_id = ?12,
+            show_grid = ?13,
+            background = ?14,
+            background_color = ?15,
+            starfield_seed = ?16,
+            star_count = ?17,
+            trajectory_fade = ?18
          WHERE id = 1",
         params![
             view.distance_scale,
@@ -251,6 +457,18 @@ fn save_view_settings(conn: &Connection, view: &ViewSettings) -> Result<(), Sqli
             view.show_labels as i32,
             view.show_trajectories as i32,
             view.trajectory_resolution as i32,
+            view.show_soi as i32,
+            view.apply_light_time as i32,
+            match &view.center {
+                ViewCenter::Origin => None,
+                ViewCenter::Body(id) => Some(id.as_str()),
+            },
+            view.show_grid as i32,
+            background,
+            background_color,
+            starfield_seed,
+            star_count,
+            trajectory_fade,
         ],
     )?;
     
@@ -260,6 +478,46 @@ fn save_view_settings(conn: &Connection, view: &ViewSettings) -> Result<(), Sqli
     Ok(())
 }
 
+// ============================================================================
+// Camera Bookmarks
+// ============================================================================
+
+fn load_camera_bookmarks(conn: &Connection) -> Result<Vec<CameraBookmark>, SqliteSaveError> {
+    let mut stmt = conn.prepare(
+        "SELECT name, pos_x, pos_y, pos_z, yaw, pitch, roll, target_body_id
+         FROM camera_bookmarks ORDER BY id"
+    )?;
+    let bookmarks = stmt.query_map([], |row| {
+        Ok(CameraBookmark {
+            name: row.get(0)?,
+            position: DVec3::new(row.get(1)?, row.get(2)?, row.get(3)?),
+            yaw: row.get(4)?,
+            pitch: row.get(5)?,
+            roll: row.get(6)?,
+            target_id: row.get(7)?,
+        })
+    })?.collect::<SqlResult<Vec<_>>>()?;
+
+    Ok(bookmarks)
+}
+
+fn save_camera_bookmarks(conn: &Connection, bookmarks: &[CameraBookmark]) -> Result<(), SqliteSaveError> {
+    for bookmark in bookmarks {
+        conn.execute(
+            "INSERT INTO camera_bookmarks (name, pos_x, pos_y, pos_z, yaw, pitch, roll, target_body_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                bookmark.name,
+                bookmark.position.x, bookmark.position.y, bookmark.position.z,
+                bookmark.yaw, bookmark.pitch, bookmark.roll,
+                bookmark.target_id,
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
 // ============================================================================
 // Tags
 // ============================================================================
@@ -291,7 +549,7 @@ fn load_tags(conn: &Connection) -> Result<HashMap<String, TagState>, SqliteSaveE
             .filter_map(|r| r.ok())
             .collect();
         
-        tags.insert(name, TagState { shown, trajectory, members });
+        tags.insert(name, TagState { shown, trajectory, members, ..Default::default() });
     }
     
     Ok(tags)
@@ -326,122 +584,244 @@ fn save_tags(conn: &Connection, tags: &HashMap<String, TagState>) -> Result<(),
 // ============================================================================
 
 fn load_bodies(conn: &Connection) -> Result<Vec<SomeBody>, SqliteSaveError> {
-    let mut bodies = Vec::new();
-    
+    // Bodies waiting on a scenario event to spawn them aren't in the universe yet
     let mut stmt = conn.prepare(
-        "SELECT id, name, mass, major, designation FROM bodies"
+        "SELECT id FROM bodies WHERE id NOT IN (
+            SELECT body_id FROM scenario_events
+            WHERE event_type = 'SpawnBody' AND body_id IS NOT NULL AND fired_at_seconds IS NULL
+         )"
     )?;
+    let ids: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<SqlResult<_>>()?;
+
+    let mut bodies = Vec::with_capacity(ids.len());
+    for id in ids {
+        bodies.push(SomeBody::CompoundMotiveEntry(load_body(conn, &id)?));
+    }
+
+    Ok(bodies)
+}
+
+fn load_body(conn: &Connection, id: &str) -> Result<CompoundMotiveEntry, SqliteSaveError> {
+    let (name, mass, major, designation) = conn.query_row(
+        "SELECT name, mass, major, designation FROM bodies WHERE id = ?1",
+        [id],
+        |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, i32>(2)? != 0,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        },
+    )?;
+
+    // Load tags for this body
+    let mut tag_stmt = conn.prepare(
+        "SELECT tag_name FROM tag_members WHERE body_id = ?1"
+    )?;
+    let tags: Vec<String> = tag_stmt
+        .query_map([id], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let info = BodyInfo {
+        id: id.to_string(),
+        name,
+        mass,
+        major,
+        designation,
+        tags,
+    };
+
+    // Load appearance
+    let appearance = load_appearance(conn, id)?;
+
+    // Load motive
+    let motive = load_motive(conn, id)?;
+
+    // Load rotation
+    let rotation = load_rotation(conn, id)?;
+
+    let properties = load_properties(conn, id)?;
+
+    Ok(CompoundMotiveEntry {
+        info,
+        motive,
+        appearance,
+        rotation,
+        properties,
+    })
+}
+
+fn save_bodies(conn: &Connection, bodies: &[SomeBody]) -> Result<(), SqliteSaveError> {
+    for body in bodies {
+        save_body(conn, body)?;
+    }
+
+    Ok(())
+}
+
+fn save_body(conn: &Connection, body: &SomeBody) -> Result<(), SqliteSaveError> {
+    let mut rotation = None;
+    let mut properties = None;
+    let (info, appearance, motive) = match body {
+        SomeBody::FixedEntry(e) => {
+            let m = Motive::fixed(e.position);
+            (&e.info, &e.appearance, m)
+        }
+        SomeBody::NewtonEntry(e) => {
+            let m = e.motive();
+            (&e.info, &e.appearance, m)
+        }
+        SomeBody::KeplerEntry(e) => {
+            let m = Motive::keplerian(
+                e.params.primary_id.clone(),
+                e.params.shape.clone(),
+                e.params.rotation.clone(),
+                e.params.epoch.clone(),
+            );
+            (&e.info, &e.appearance, m)
+        }
+        SomeBody::CompoundEntry(e) => {
+            let m = Motive::fixed(DVec3::ZERO);
+            (&e.info, &e.appearance, m)
+        }
+        SomeBody::CompoundMotiveEntry(e) => {
+            rotation = e.rotation.as_ref();
+            properties = Some(&e.properties);
+            (&e.info, &e.appearance, e.motive.clone())
+        }
+    };
     
-    let body_iter = stmt.query_map([], |row| {
-        Ok((
-            row.get::<_, String>(0)?,
-            row.get::<_, Option<String>>(1)?,
-            row.get::<_, f64>(2)?,
-            row.get::<_, i32>(3)? != 0,
-            row.get::<_, Option<String>>(4)?,
-        ))
-    })?;
+    // Insert body
+    conn.execute(
+        "INSERT INTO bodies (id, name, mass, major, designation)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            info.id,
+            info.name,
+            info.mass,
+            info.major as i32,
+            info.designation,
+        ],
+    )?;
     
-    for body_result in body_iter {
-        let (id, name, mass, major, designation) = body_result?;
-        
-        // Load tags for this body
-        let mut tag_stmt = conn.prepare(
-            "SELECT tag_name FROM tag_members WHERE body_id = ?1"
+    // Save body's tags to tag_members
+    for tag in &info.tags {
+        // Ensure the tag exists in the tags table
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name, shown, trajectory) VALUES (?1, 1, 0)",
+            [tag],
+        )?;
+        // Add body as member of this tag
+        conn.execute(
+            "INSERT OR IGNORE INTO tag_members (tag_name, body_id) VALUES (?1, ?2)",
+            params![tag, info.id],
         )?;
-        let tags: Vec<String> = tag_stmt
-            .query_map([&id], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
-        
-        let info = BodyInfo {
-            id: id.clone(),
-            name,
-            mass,
-            major,
-            designation,
-            tags,
-        };
-        
-        // Load appearance
-        let appearance = load_appearance(conn, &id)?;
-        
-        // Load motive
-        let motive = load_motive(conn, &id)?;
-        
-        bodies.push(SomeBody::CompoundMotiveEntry(CompoundMotiveEntry {
-            info,
-            motive,
-            appearance,
-        }));
     }
     
-    Ok(bodies)
+    // Save appearance
+    save_appearance(conn, &info.id, appearance)?;
+    
+    // Save motive
+    save_motive(conn, &info.id, &motive)?;
+    
+    // Save rotation
+    if let Some(rotation) = rotation {
+        save_rotation(conn, &info.id, rotation)?;
+    }
+
+    if let Some(properties) = properties {
+        save_properties(conn, &info.id, properties)?;
+    }
+
+    Ok(())
 }
 
-fn save_bodies(conn: &Connection, bodies: &[SomeBody]) -> Result<(), SqliteSaveError> {
-    for body in bodies {
-        let (info, appearance, motive) = match body {
-            SomeBody::FixedEntry(e) => {
-                let m = Motive::fixed(e.position);
-                (&e.info, &e.appearance, m)
-            }
-            SomeBody::NewtonEntry(e) => {
-                let m = Motive::newtonian(e.position, e.velocity);
-                (&e.info, &e.appearance, m)
+// ============================================================================
+// Scenario
+// ============================================================================
+
+fn load_scenario(conn: &Connection) -> Result<Vec<ScheduledEvent>, SqliteSaveError> {
+    let mut stmt = conn.prepare(
+        "SELECT time_seconds, event_type, body_id, delta_v_x, delta_v_y, delta_v_z, gravitational_constant, fired_at_seconds
+         FROM scenario_events ORDER BY time_seconds"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, f64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<f64>>(3)?,
+            row.get::<_, Option<f64>>(4)?,
+            row.get::<_, Option<f64>>(5)?,
+            row.get::<_, Option<f64>>(6)?,
+            row.get::<_, Option<f64>>(7)?,
+        ))
+    })?.collect::<SqlResult<Vec<_>>>()?;
+
+    let mut events = Vec::with_capacity(rows.len());
+    for (time_seconds, event_type, body_id, dv_x, dv_y, dv_z, gravitational_constant, fired_at_seconds) in rows {
+        let missing = |column: &str| SqliteSaveError::InvalidData(format!("{} scenario event is missing {}", event_type, column));
+        let event = match event_type.as_str() {
+            "Impulse" => ScenarioEvent::Impulse {
+                body_id: body_id.ok_or_else(|| missing("body_id"))?,
+                delta_v: DVec3::new(dv_x.unwrap_or(0.0), dv_y.unwrap_or(0.0), dv_z.unwrap_or(0.0)),
+            },
+            "SpawnBody" => {
+                let body_id = body_id.ok_or_else(|| missing("body_id"))?;
+                ScenarioEvent::SpawnBody(Box::new(load_body(conn, &body_id)?))
             }
-            SomeBody::KeplerEntry(e) => {
-                let m = Motive::keplerian(
-                    e.params.primary_id.clone(),
-                    e.params.shape.clone(),
-                    e.params.rotation.clone(),
-                    e.params.epoch.clone(),
-                );
-                (&e.info, &e.appearance, m)
+            "SetGravitationalConstant" => ScenarioEvent::SetGravitationalConstant(
+                gravitational_constant.ok_or_else(|| missing("gravitational_constant"))?,
+            ),
+            _ => return Err(SqliteSaveError::InvalidData(format!("Unknown scenario event: {}", event_type))),
+        };
+        events.push(ScheduledEvent {
+            time: Instant::from_seconds_since_j2000(time_seconds),
+            event,
+            fired_at: fired_at_seconds.map(Instant::from_seconds_since_j2000),
+        });
+    }
+
+    Ok(events)
+}
+
+/// A spawned body that's also among `bodies` shares its row with them.
+fn save_scenario(conn: &Connection, events: &[ScheduledEvent], bodies: &[SomeBody]) -> Result<(), SqliteSaveError> {
+    for scheduled in events {
+        let time_seconds = scheduled.time.to_j2000_seconds();
+        let fired_at_seconds = scheduled.fired_at.map(|fired_at| fired_at.to_j2000_seconds());
+        match &scheduled.event {
+            ScenarioEvent::Impulse { body_id, delta_v } => {
+                conn.execute(
+                    "INSERT INTO scenario_events (time_seconds, event_type, body_id, delta_v_x, delta_v_y, delta_v_z, fired_at_seconds)
+                     VALUES (?1, 'Impulse', ?2, ?3, ?4, ?5, ?6)",
+                    params![time_seconds, body_id, delta_v.x, delta_v.y, delta_v.z, fired_at_seconds],
+                )?;
             }
-            SomeBody::CompoundEntry(e) => {
-                let m = Motive::fixed(DVec3::ZERO);
-                (&e.info, &e.appearance, m)
+            ScenarioEvent::SpawnBody(entry) => {
+                if !bodies.iter().any(|body| body.id() == entry.info.id) {
+                    save_body(conn, &SomeBody::CompoundMotiveEntry((**entry).clone()))?;
+                }
+                conn.execute(
+                    "INSERT INTO scenario_events (time_seconds, event_type, body_id, fired_at_seconds)
+                     VALUES (?1, 'SpawnBody', ?2, ?3)",
+                    params![time_seconds, entry.info.id, fired_at_seconds],
+                )?;
             }
-            SomeBody::CompoundMotiveEntry(e) => {
-                (&e.info, &e.appearance, e.motive.clone())
+            ScenarioEvent::SetGravitationalConstant(gravitational_constant) => {
+                conn.execute(
+                    "INSERT INTO scenario_events (time_seconds, event_type, gravitational_constant, fired_at_seconds)
+                     VALUES (?1, 'SetGravitationalConstant', ?2, ?3)",
+                    params![time_seconds, gravitational_constant, fired_at_seconds],
+                )?;
             }
-        };
-        
-        // Insert body
-        conn.execute(
-            "INSERT INTO bodies (id, name, mass, major, designation)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                info.id,
-                info.name,
-                info.mass,
-                info.major as i32,
-                info.designation,
-            ],
-        )?;
-        
-        // Save body's tags to tag_members
-        for tag in &info.tags {
-            // Ensure the tag exists in the tags table
-            conn.execute(
-                "INSERT OR IGNORE INTO tags (name, shown, trajectory) VALUES (?1, 1, 0)",
-                [tag],
-            )?;
-            // Add body as member of this tag
-            conn.execute(
-                "INSERT OR IGNORE INTO tag_members (tag_name, body_id) VALUES (?1, ?2)",
-                params![tag, info.id],
-            )?;
         }
-        
-        // Save appearance
-        save_appearance(conn, &info.id, appearance)?;
-        
-        // Save motive
-        save_motive(conn, &info.id, &motive)?;
     }
-    
+
     Ok(())
 }
 
@@ -551,6 +931,71 @@ fn save_appearance(conn: &Connection, body_id: &str, appearance: &Appearance) ->
     Ok(())
 }
 
+// ============================================================================
+// Rotations
+// ============================================================================
+
+fn load_rotation(conn: &Connection, body_id: &str) -> Result<Option<BodyRotation>, SqliteSaveError> {
+    let result = conn.query_row(
+        "SELECT period, axial_tilt, node_longitude, prime_meridian_at_epoch, epoch_julian_day
+         FROM body_rotations WHERE body_id = ?1",
+        [body_id],
+        |row| {
+            Ok(BodyRotation {
+                period: row.get(0)?,
+                axial_tilt: row.get(1)?,
+                node_longitude: row.get(2)?,
+                prime_meridian_at_epoch: row.get(3)?,
+                epoch: Instant::from_julian_day(row.get(4)?),
+            })
+        },
+    );
+    
+    match result {
+        Ok(rotation) => Ok(Some(rotation)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// ============================================================================
+// Properties
+// ============================================================================
+
+fn load_properties(conn: &Connection, body_id: &str) -> Result<BodyProperties, SqliteSaveError> {
+    let mut stmt = conn.prepare("SELECT key, value FROM body_properties WHERE body_id = ?1")?;
+    let properties = stmt
+        .query_map([body_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<SqlResult<_>>()?;
+    Ok(BodyProperties(properties))
+}
+
+fn save_properties(conn: &Connection, body_id: &str, properties: &BodyProperties) -> Result<(), SqliteSaveError> {
+    for (key, value) in &properties.0 {
+        conn.execute(
+            "INSERT INTO body_properties (body_id, key, value) VALUES (?1, ?2, ?3)",
+            params![body_id, key, value],
+        )?;
+    }
+    Ok(())
+}
+
+fn save_rotation(conn: &Connection, body_id: &str, rotation: &BodyRotation) -> Result<(), SqliteSaveError> {
+    conn.execute(
+        "INSERT INTO body_rotations (body_id, period, axial_tilt, node_longitude, prime_meridian_at_epoch, epoch_julian_day)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            body_id,
+            rotation.period,
+            rotation.axial_tilt,
+            rotation.node_longitude,
+            rotation.prime_meridian_at_epoch,
+            rotation.epoch.to_julian_day(),
+        ],
+    )?;
+    Ok(())
+}
+
 // ============================================================================
 // Motives
 // ============================================================================
@@ -623,7 +1068,8 @@ fn load_keplerian(conn: &Connection, motive_id: i64) -> Result<KeplerMotive, Sql
         "SELECT primary_id, shape_type, eccentricity, semi_major_axis, periapsis, apoapsis,
                 rotation_type, inclination, longitude_of_ascending_node, argument_of_periapsis,
                 apsidal_precession_period, nodal_precession_period, longitude_of_periapsis,
-                epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day
+                epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day,
+                apsidal_prograde, nodal_prograde
          FROM motive_keplerian WHERE motive_id = ?1",
         [motive_id],
         |row| {
@@ -646,6 +1092,8 @@ fn load_keplerian(conn: &Connection, motive_id: i64) -> Result<KeplerMotive, Sql
                 row.get::<_, Option<f64>>(15)?,  // mean_anomaly
                 row.get::<_, Option<f64>>(16)?,  // true_anomaly
                 row.get::<_, Option<f64>>(17)?,  // periapsis_time_julian_day
+                row.get::<_, i32>(18)? != 0,  // apsidal_prograde
+                row.get::<_, i32>(19)? != 0,  // nodal_prograde
             ))
         },
     )?;
@@ -653,7 +1101,8 @@ fn load_keplerian(conn: &Connection, motive_id: i64) -> Result<KeplerMotive, Sql
     let (primary_id, shape_type, eccentricity, semi_major_axis, periapsis, apoapsis,
          rotation_type, inclination, longitude_of_ascending_node, argument_of_periapsis,
          apsidal_precession_period, nodal_precession_period, longitude_of_periapsis,
-         epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day) = row;
+         epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day,
+         apsidal_prograde, nodal_prograde) = row;
     
     // Parse shape
     let shape = match shape_type.as_str() {
@@ -684,6 +1133,8 @@ fn load_keplerian(conn: &Connection, motive_id: i64) -> Result<KeplerMotive, Sql
             argument_of_periapsis: argument_of_periapsis.unwrap_or(0.0),
             apsidal_precession_period: TimeLength::period_from_julian_day(apsidal_precession_period.unwrap_or(0.0)),
             nodal_precession_period: TimeLength::period_from_julian_day(nodal_precession_period.unwrap_or(0.0)),
+            apsidal_prograde,
+            nodal_prograde,
         }),
         _ => return Err(SqliteSaveError::InvalidData(format!("Unknown rotation type: {}", rotation_type))),
     };
@@ -691,12 +1142,12 @@ fn load_keplerian(conn: &Connection, motive_id: i64) -> Result<KeplerMotive, Sql
     // Parse epoch
     let epoch = match epoch_type.as_str() {
         "MeanAnomaly" => KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch {
-            epoch: Instant::from_julian_day(epoch_julian_day.unwrap_or(2451545.0)),
+            epoch: Instant::from_julian_day(epoch_julian_day.unwrap_or(J2000_JD)),
             mean_anomaly: mean_anomaly.unwrap_or(0.0),
         }),
-        "TimeAtPeriapsisPassage" => KeplerEpoch::TimeAtPeriapsisPassage(Instant::from_julian_day(periapsis_time_julian_day.unwrap_or(2451545.0))),
+        "TimeAtPeriapsisPassage" => KeplerEpoch::TimeAtPeriapsisPassage(Instant::from_julian_day(periapsis_time_julian_day.unwrap_or(J2000_JD))),
         "TrueAnomaly" => KeplerEpoch::TrueAnomaly(TrueAnomalyAtEpoch {
-            epoch: Instant::from_julian_day(epoch_julian_day.unwrap_or(2451545.0)),
+            epoch: Instant::from_julian_day(epoch_julian_day.unwrap_or(J2000_JD)),
             true_anomaly: true_anomaly.unwrap_or(0.0),
         }),
         "J2000" => KeplerEpoch::J2000(MeanAnomalyAtJ2000 {
@@ -776,6 +1227,10 @@ fn save_keplerian(conn: &Connection, motive_id: i64, kepler: &KeplerMotive) -> R
     };
     
     // Extract rotation data
+    let (apsidal_prograde, nodal_prograde) = match &kepler.rotation {
+        KeplerRotation::PrecessingEulerAngles(pea) => (pea.apsidal_prograde, pea.nodal_prograde),
+        _ => (true, true),
+    };
     let (rotation_type, inclination, longitude_of_ascending_node, argument_of_periapsis,
          apsidal_precession_period, nodal_precession_period, longitude_of_periapsis_val) = match &kepler.rotation {
         KeplerRotation::EulerAngles(ea) => (
@@ -845,8 +1300,9 @@ fn save_keplerian(conn: &Connection, motive_id: i64, kepler: &KeplerMotive) -> R
             shape_type, eccentricity, semi_major_axis, periapsis, apoapsis,
             rotation_type, inclination, longitude_of_ascending_node, argument_of_periapsis,
             apsidal_precession_period, nodal_precession_period, longitude_of_periapsis,
-            epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day
-         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            epoch_type, epoch_julian_day, mean_anomaly, true_anomaly, periapsis_time_julian_day,
+            apsidal_prograde, nodal_prograde
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
         params![
             motive_id,
             kepler.primary_id,
@@ -867,6 +1323,8 @@ fn save_keplerian(conn: &Connection, motive_id: i64, kepler: &KeplerMotive) -> R
             mean_anomaly,
             true_anomaly_val,
             periapsis_time_julian_day,
+            apsidal_prograde as i32,
+            nodal_prograde as i32,
         ],
     )?;
     
@@ -891,3 +1349,111 @@ fn serialize_transition_event(event: &TransitionEvent) -> &'static str {
         TransitionEvent::Release => "Release",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::universe::solar_system::solar_system;
+
+    #[test]
+    fn test_interrupted_save_keeps_original() {
+        let path = std::env::temp_dir().join(format!("interrupted_save_{}.em", std::process::id()));
+        let contents = solar_system().contents;
+        save_to_em(&path, &contents).unwrap();
+        let original = std::fs::read(&path).unwrap();
+
+        let result = save_atomically(&path, |conn| {
+            save_physics(conn, &UniversePhysics { gravitational_constant: 1.0, ..Default::default() })?;
+            Err(SqliteSaveError::InvalidData("interrupted before bodies".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), original);
+        assert!(!temp_path_for(&path).exists());
+
+        let loaded = load_from_em(&path).unwrap();
+        assert_eq!(loaded.physics.gravitational_constant, contents.physics.gravitational_constant);
+        assert_eq!(loaded.bodies.len(), contents.bodies.len());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_camera_bookmarks_round_trip() {
+        let path = std::env::temp_dir().join(format!("camera_bookmarks_{}.em", std::process::id()));
+        let mut contents = solar_system().contents;
+        contents.camera_bookmarks = vec![
+            CameraBookmark {
+                name: "Over the ecliptic".into(),
+                position: DVec3::new(12.5, 300.0, -0.25),
+                yaw: 1.25,
+                pitch: -1.5,
+                roll: 0.0,
+                target_id: None,
+            },
+            CameraBookmark {
+                name: "Earthrise".into(),
+                position: DVec3::new(-149.6, 0.001, 3.0e-7),
+                yaw: -3.1,
+                pitch: 0.2,
+                roll: 0.05,
+                target_id: Some("earth".into()),
+            },
+        ];
+        save_to_em(&path, &contents).unwrap();
+
+        let loaded = load_from_em(&path).unwrap();
+        assert_eq!(loaded.camera_bookmarks, contents.camera_bookmarks);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_body_properties_round_trip() {
+        let path = std::env::temp_dir().join(format!("body_properties_{}.em", std::process::id()));
+        let mut contents = solar_system().contents;
+        let properties = BodyProperties([
+            ("spectral type", "G2V"),
+            ("discovered", "antiquity"),
+            ("source", "https://ssd.jpl.nasa.gov/, \"Horizons\""),
+        ].into_iter().map(|(key, value)| (key.to_string(), value.to_string())).collect());
+        // Only compound entries carry properties, so the sun is swapped for one
+        let SomeBody::FixedEntry(sun) = contents.bodies.remove(0) else { panic!("The solar system doesn't start with the sun") };
+        contents.bodies.insert(0, SomeBody::CompoundMotiveEntry(CompoundMotiveEntry {
+            motive: Motive::fixed(sun.position),
+            info: sun.info,
+            appearance: sun.appearance,
+            rotation: None,
+            properties: properties.clone(),
+        }));
+        save_to_em(&path, &contents).unwrap();
+
+        let loaded = load_from_em(&path).unwrap();
+        for body in &loaded.bodies {
+            let SomeBody::CompoundMotiveEntry(entry) = body else { panic!("{} wasn't loaded as a compound motive", body.id()) };
+            if entry.info.id == "sol" {
+                assert_eq!(entry.properties, properties);
+            } else {
+                assert!(entry.properties.is_empty(), "{}", entry.info.id);
+            }
+        }
+
+        // Deleting the body takes its properties with it
+        let conn = open_em_file(&path).unwrap();
+        conn.execute("DELETE FROM bodies WHERE id = 'sol'", []).unwrap();
+        assert!(load_properties(&conn, "sol").unwrap().is_empty());
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_second_open_is_locked() {
+        let path = std::env::temp_dir().join(format!("locked_save_{}.em", std::process::id()));
+        save_to_em(&path, &solar_system().contents).unwrap();
+
+        let held = open_em_file(&path).unwrap();
+        assert!(matches!(open_em_file(&path), Err(SqliteSaveError::Locked(_))));
+        assert!(matches!(save_to_em(&path, &solar_system().contents), Err(SqliteSaveError::Locked(_))));
+        drop(held);
+
+        assert!(open_em_file(&path).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,242 @@
+//! Scripted events that happen as simulation time passes.
+//!
+//! Events fire once when time moves forward past them. Playing backwards doesn't re-fire
+//! anything; rewinding past an event undoes it, so it fires again on the way forward.
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::{Motive, MotiveSelection, PhysicsGraph, TransitionEvent};
+use crate::body::universe::deletion;
+use crate::body::universe::save::{CompoundMotiveEntry, SomeBody, UniversePhysics, ViewSettings};
+use crate::body::universe::Universe;
+use crate::foundations::time::Instant;
+use crate::gui::menu::TagState;
+use crate::gui::planetarium::time::SimTime;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ScenarioEvent {
+    /// Instantaneous change in world-frame velocity, in m/s. The body becomes Newtonian.
+    Impulse { body_id: String, delta_v: DVec3 },
+    SpawnBody(Box<CompoundMotiveEntry>),
+    SetGravitationalConstant(f64),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledEvent {
+    pub time: Instant,
+    pub event: ScenarioEvent,
+    /// Sim time the event was applied at, which can be a step after it was scheduled.
+    /// Saved, so that a reloaded scenario can still undo it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fired_at: Option<Instant>,
+}
+
+pub enum Crossing {
+    Fire,
+    Undo { fired_at: Instant },
+}
+
+#[derive(Resource, Default)]
+pub struct Scenario {
+    events: Vec<ScheduledEvent>,
+    /// G before any event changed it.
+    base_gravitational_constant: f64,
+    checked_until: Option<Instant>,
+}
+
+impl Scenario {
+    /// Events that have `fired_at` set are already in effect, so `gravitational_constant` is G from before them.
+    /// Other events at or before `start` are treated as not yet happened, and fire if time comes back past them.
+    pub fn new(mut events: Vec<ScheduledEvent>, start: Instant, gravitational_constant: f64) -> Self {
+        events.sort_by(|a, b| a.time.to_j2000_seconds().total_cmp(&b.time.to_j2000_seconds()));
+        Self {
+            events,
+            base_gravitational_constant: gravitational_constant,
+            checked_until: Some(start),
+        }
+    }
+
+    pub fn scheduled(&self) -> impl Iterator<Item = &ScheduledEvent> {
+        self.events.iter()
+    }
+
+    /// G as it would be without the fired events, given G as it is now.
+    /// This is what a save stores, since loading it reapplies them.
+    pub fn unscripted_gravitational_constant(&self, current: f64) -> f64 {
+        let changed = self.events.iter()
+            .any(|scheduled| scheduled.fired_at.is_some() && matches!(scheduled.event, ScenarioEvent::SetGravitationalConstant(_)));
+        if changed { self.base_gravitational_constant } else { current }
+    }
+
+    /// Moves the scenario to `time`, returning the events crossed on the way in the order to apply them.
+    /// Forward, that's unfired events after the last checked time up to and including `time`.
+    /// Backward, that's fired events after `time`, latest first.
+    pub fn cross(&mut self, time: Instant) -> Vec<(ScheduledEvent, Crossing)> {
+        let Some(previous) = self.checked_until.replace(time) else { return Vec::new() };
+        let mut crossed = Vec::new();
+
+        if time > previous {
+            for scheduled in &mut self.events {
+                let t = scheduled.time;
+                if scheduled.fired_at.is_none() && previous < t && t <= time {
+                    scheduled.fired_at = Some(time);
+                    crossed.push((scheduled.clone(), Crossing::Fire));
+                }
+            }
+        } else if time < previous {
+            for scheduled in self.events.iter_mut().rev() {
+                if scheduled.time <= time { continue; }
+                if let Some(fired_at) = scheduled.fired_at.take() {
+                    crossed.push((scheduled.clone(), Crossing::Undo { fired_at }));
+                }
+            }
+        }
+        crossed
+    }
+
+    /// G as set by the latest fired event.
+    pub fn gravitational_constant(&self) -> f64 {
+        self.events.iter().rev()
+            .filter(|scheduled| scheduled.fired_at.is_some())
+            .find_map(|scheduled| match scheduled.event {
+                ScenarioEvent::SetGravitationalConstant(g) => Some(g),
+                _ => None,
+            })
+            .unwrap_or(self.base_gravitational_constant)
+    }
+}
+
+pub fn run_scenario(
+    mut commands: Commands,
+    mut scenario: ResMut<Scenario>,
+    mut physics: ResMut<UniversePhysics>,
+    mut graph: ResMut<PhysicsGraph>,
+    mut universe: Option<ResMut<Universe>>,
+    mut view_settings: Option<ResMut<ViewSettings>>,
+    sim_time: Res<SimTime>,
+    mut bodies: Query<(Entity, &BodyInfo, &mut Motive, &mut BodyState)>,
+) {
+    let time = sim_time.time;
+    let crossed = scenario.cross(time);
+    if crossed.is_empty() { return; }
+
+    for (scheduled, crossing) in crossed {
+        match (scheduled.event, crossing) {
+            (ScenarioEvent::Impulse { body_id, delta_v }, Crossing::Fire) => {
+                let Some(world) = deletion::world_state(&body_id, &bodies, time, physics.gravitational_constant) else {
+                    warn!("Scenario impulse on {}, which doesn't exist", body_id);
+                    continue;
+                };
+                let Some((_, _, mut motive, mut state)) = bodies.iter_mut().find(|(_, info, _, _)| info.id == body_id) else { continue };
+                motive.insert_event(time, TransitionEvent::Impulse, MotiveSelection::Newtonian {
+                    position: world.position,
+                    velocity: world.velocity + delta_v,
+                });
+                state.newtonian_init_time = None;
+            }
+            (ScenarioEvent::Impulse { body_id, .. }, Crossing::Undo { fired_at }) => {
+                let Some((_, _, mut motive, mut state)) = bodies.iter_mut().find(|(_, info, _, _)| info.id == body_id) else { continue };
+                motive.remove_event(fired_at);
+                state.newtonian_init_time = None;
+            }
+            (ScenarioEvent::SpawnBody(entry), Crossing::Fire) => {
+                if bodies.iter().any(|(_, info, _, _)| info.id == entry.info.id) {
+                    warn!("Scenario tried to spawn {}, which already exists", entry.info.id);
+                    continue;
+                }
+                let body = SomeBody::CompoundMotiveEntry(*entry);
+                if let Some(universe) = universe.as_mut() {
+                    universe.insert(body.name(), body.id());
+                }
+                if let Some(view_settings) = view_settings.as_mut() {
+                    for tag in body.tags() {
+                        view_settings.tags.entry(tag.clone()).or_insert(TagState::default()).members.push(body.id());
+                    }
+                }
+                body.spawn_bare(&mut commands);
+            }
+            (ScenarioEvent::SpawnBody(entry), Crossing::Undo { .. }) => {
+                let id = &entry.info.id;
+                if let Some((entity, _, _, _)) = bodies.iter().find(|(_, info, _, _)| info.id == *id) {
+                    commands.entity(entity).despawn();
+                }
+                if let Some(universe) = universe.as_mut() {
+                    universe.remove_by_id(id);
+                }
+                if let Some(view_settings) = view_settings.as_mut() {
+                    for tag in view_settings.tags.values_mut() {
+                        tag.members.retain(|member| member != id);
+                    }
+                }
+            }
+            (ScenarioEvent::SetGravitationalConstant(_), _) => {
+                physics.gravitational_constant = scenario.gravitational_constant();
+            }
+        }
+    }
+    graph.needs_rebuild = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn impulse(seconds: f64) -> ScheduledEvent {
+        ScheduledEvent {
+            time: Instant::from_seconds_since_j2000(seconds),
+            event: ScenarioEvent::Impulse { body_id: "probe".into(), delta_v: DVec3::X },
+            fired_at: None,
+        }
+    }
+
+    fn fired(scenario: &mut Scenario, seconds: f64) -> usize {
+        scenario.cross(Instant::from_seconds_since_j2000(seconds)).iter()
+            .filter(|(_, crossing)| matches!(crossing, Crossing::Fire))
+            .count()
+    }
+
+    #[test]
+    fn test_fires_once_and_again_after_rewind() {
+        let mut scenario = Scenario::new(vec![impulse(10.0)], Instant::from_seconds_since_j2000(0.0), 1.0);
+        let forward: usize = (1..=20).map(|t| fired(&mut scenario, t as f64)).sum();
+        assert_eq!(forward, 1);
+
+        // Backwards but not past it
+        assert!(scenario.cross(Instant::from_seconds_since_j2000(15.0)).is_empty());
+        assert_eq!(fired(&mut scenario, 20.0), 0);
+
+        let undone = scenario.cross(Instant::from_seconds_since_j2000(5.0));
+        assert!(matches!(undone.as_slice(), [(_, Crossing::Undo { .. })]));
+        assert_eq!(fired(&mut scenario, 10.0), 1);
+    }
+
+    #[test]
+    fn test_gravitational_constant_follows_fired_events() {
+        let set = |seconds: f64, g: f64| ScheduledEvent {
+            time: Instant::from_seconds_since_j2000(seconds),
+            event: ScenarioEvent::SetGravitationalConstant(g),
+            fired_at: None,
+        };
+        let mut scenario = Scenario::new(vec![set(20.0, 3.0), set(10.0, 2.0)], Instant::from_seconds_since_j2000(0.0), 1.0);
+        scenario.cross(Instant::from_seconds_since_j2000(15.0));
+        assert_eq!(scenario.gravitational_constant(), 2.0);
+        scenario.cross(Instant::from_seconds_since_j2000(25.0));
+        assert_eq!(scenario.gravitational_constant(), 3.0);
+        scenario.cross(Instant::from_seconds_since_j2000(0.0));
+        assert_eq!(scenario.gravitational_constant(), 1.0);
+    }
+
+    #[test]
+    fn test_fired_events_from_a_save_can_be_undone() {
+        let mut loaded = impulse(10.0);
+        loaded.fired_at = Some(Instant::from_seconds_since_j2000(11.0));
+        let mut scenario = Scenario::new(vec![loaded], Instant::from_seconds_since_j2000(20.0), 1.0);
+        assert_eq!(fired(&mut scenario, 30.0), 0);
+
+        let undone = scenario.cross(Instant::from_seconds_since_j2000(5.0));
+        assert!(matches!(undone.as_slice(), [(_, Crossing::Undo { fired_at })] if fired_at.to_j2000_seconds() == 11.0));
+        assert_eq!(fired(&mut scenario, 20.0), 1);
+    }
+}
@@ -26,6 +26,9 @@ pub fn solar_system() -> UniverseFile {
             },
             physics: UniversePhysics::default(),
             view: ViewSettings::default(),
+            scenario: Vec::new(),
+            camera_bookmarks: Vec::new(),
+            includes: Vec::new(),
             bodies: vec![
                 SomeBody::FixedEntry(FixedEntry {
                     info: BodyInfo {
@@ -159,7 +162,7 @@ pub fn solar_system() -> UniverseFile {
                     info: BodyInfo {
                         name: Some("Mars".into()),
                         id: "mars".to_string(),
-                        mass: 6.4171,
+                        mass: 6.4171e23,
                         major: true,
                         designation: None,
                         tags: vec!["Planet".into(), "Major Planet".into()],
@@ -227,7 +230,7 @@ pub fn solar_system() -> UniverseFile {
                     info: BodyInfo {
                         name: Some("Vesta".into()),
                         id: "4-vesta".to_string(),
-                        mass: 6.4171,
+                        mass: 2.59076e20,
                         major: true,
                         designation: Some("4 Vesta".into()),
                         tags: vec!["Planet".into(), "Minor Planet".into()],
@@ -280,6 +283,8 @@ pub fn solar_system() -> UniverseFile {
                             argument_of_periapsis: 3.081359034620368e2,
                             apsidal_precession_period: TimeLength::period_from_julian_day(3231.50),
                             nodal_precession_period: TimeLength::period_from_julian_day(6798.38),
+                            apsidal_prograde: true,
+                            nodal_prograde: false,
                         }),
                         epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 {
                             mean_anomaly: 1.407402571142365e02,
@@ -521,6 +526,9 @@ pub fn earth_moon() -> UniverseFile {
             },
             physics: UniversePhysics::default(),
             view: ViewSettings::default(),
+            scenario: Vec::new(),
+            camera_bookmarks: Vec::new(),
+            includes: Vec::new(),
             bodies: vec![
                 /*SomeBody::FixedEntry(FixedEntry {
                     info: BodyInfo {
@@ -572,7 +580,7 @@ pub fn earth_moon() -> UniverseFile {
                     info: BodyInfo {
                         name: Some("Luna".into()),
                         id: "luna".to_string(),
-                        mass: 6.4171,
+                        mass: 7.346e22,
                         major: true,
                         designation: Some("Earth I".into()),
                         tags: vec!["Moon".into()],
@@ -588,8 +596,10 @@ pub fn earth_moon() -> UniverseFile {
                             inclination: 5.240010829674768e0,
                             longitude_of_ascending_node: 1.239837028145578e2,
                             argument_of_periapsis: 3.081359034620368e2,
-                            apsidal_precession_period: 3231.50,
-                            nodal_precession_period: 6798.38,
+                            apsidal_precession_period: TimeLength::period_from_julian_day(3231.50),
+                            nodal_precession_period: TimeLength::period_from_julian_day(6798.38),
+                            apsidal_prograde: true,
+                            nodal_prograde: false,
                         }),
                         epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 {
                             mean_anomaly: 1.407402571142365e02,
@@ -623,6 +633,7 @@ pub fn earth_moon() -> UniverseFile {
                             b: 0,
                         },
                     }),
+                    epoch: None,
                 }), // Test Newtonian Body A
                 SomeBody::NewtonEntry(NewtonEntry {
                     info: BodyInfo {
@@ -643,6 +654,7 @@ pub fn earth_moon() -> UniverseFile {
                             b: 0,
                         },
                     }),
+                    epoch: None,
                 }), // Test Newtonian Body B
             ]
         },
@@ -1,5 +1,8 @@
-use bevy::math::DVec3;
+use bevy::math::{DQuat, DVec3};
+use crate::util::patched_conics;
 
+/// Newtonian constant of gravitation, CODATA 2018, in m³ kg⁻¹ s⁻².
+pub const G: f64 = 6.6743015e-11;
 
 /// Mu value is mass of attractor in grams * gravity constant
 /// Displacement in meters
@@ -9,3 +12,191 @@ pub fn one_body_acceleration(local_gravity_mu: f64, a_to_b: DVec3) -> DVec3 {
     let directionless = -(local_gravity_mu / (distance * distance *  distance));
     directionless * a_to_b
 }
+
+/// Accelerations of bodies a and b as they pull on each other, `a_to_b` meters apart.
+/// The mu values are each body's mass times the gravity constant.
+pub fn pair_acceleration(mu_a: f64, mu_b: f64, a_to_b: DVec3) -> (DVec3, DVec3) {
+    (one_body_acceleration(mu_b, -a_to_b), one_body_acceleration(mu_a, a_to_b))
+}
+
+/// Gravitational potential energy of two masses `r` meters apart, in joules.
+/// Zero when they're infinitely far apart, so always negative.
+pub fn potential_energy(g: f64, m_a: f64, m_b: f64, r: f64) -> f64 {
+    -g * m_a * m_b / r
+}
+
+/// Radius within which the body of mass `m` dominates the tidal pull of its primary of mass `big_m`,
+/// for an orbit of semi-major axis `a` meters and eccentricity `e`. Taken at periapsis.
+pub fn hill_radius(a: f64, e: f64, m: f64, big_m: f64) -> f64 {
+    a * (1.0 - e) * (m / (3.0 * big_m)).cbrt()
+}
+
+/// Laplace sphere of influence, the boundary used when patching conics together.
+pub fn soi_radius(a: f64, m: f64, big_m: f64) -> f64 {
+    patched_conics::laplace(a, m, big_m)
+}
+
+/// Mass-weighted centroid of (mass, position) pairs. None if there's no mass to weigh by.
+pub fn barycenter<I: IntoIterator<Item = (f64, DVec3)>>(bodies: I) -> Option<DVec3> {
+    let (total_mass, weighted) = bodies.into_iter()
+        .fold((0.0, DVec3::ZERO), |(mass_sum, weighted_sum), (mass, position)| {
+            (mass_sum + mass, weighted_sum + position * mass)
+        });
+    if total_mass > 0.0 {
+        Some(weighted / total_mass)
+    } else {
+        None
+    }
+}
+
+/// The five Lagrange points of a two-body system, L1 through L5.
+///
+/// The lighter body is treated as the secondary, so L1 and L2 sit either side of it.
+/// Only positions are known, so the orbit is assumed to be normal to reference +Z;
+/// this only matters for which way L4 and L5 lead and trail.
+pub fn lagrange_points(m1: f64, pos1: DVec3, m2: f64, pos2: DVec3) -> [DVec3; 5] {
+    if m2 > m1 {
+        return lagrange_points(m2, pos2, m1, pos1);
+    }
+    let separation = pos2 - pos1;
+    let distance = separation.length();
+    if m1 + m2 <= 0.0 || distance == 0.0 {
+        return [pos1; 5];
+    }
+    let axis = separation / distance;
+    let mu = m2.max(0.0) / (m1 + m2);
+
+    // Collinear points, solved along the axis in units of the separation with the barycenter at 0
+    let barycenter = pos1 + separation * mu;
+    let along = |x: f64| barycenter + axis * (x * distance);
+    let (l1, l2) = if mu == 0.0 {
+        (pos2, pos2)
+    } else {
+        let hill = (mu / 3.0).cbrt();
+        (
+            along(collinear_lagrange_point(mu, 1.0 - mu - hill)),
+            along(collinear_lagrange_point(mu, 1.0 - mu + hill)),
+        )
+    };
+    let l3 = along(collinear_lagrange_point(mu, -1.0 - 5.0 * mu / 12.0));
+
+    // Triangular points complete equilateral triangles with the two bodies
+    let reference_normal = if axis.z.abs() < 1.0 - 1e-9 { DVec3::Z } else { DVec3::X };
+    let normal = (reference_normal - axis * axis.dot(reference_normal)).normalize();
+    let sixty = 60f64.to_radians();
+    let l4 = pos1 + DQuat::from_axis_angle(normal, sixty) * separation;
+    let l5 = pos1 + DQuat::from_axis_angle(normal, -sixty) * separation;
+
+    [l1, l2, l3, l4, l5]
+}
+
+/// Newton iteration on the net force along the axis in the rotating frame.
+/// Bodies sit at -mu and 1 - mu.
+fn collinear_lagrange_point(mu: f64, guess: f64) -> f64 {
+    let mut x = guess;
+    for _ in 0..50 {
+        let to_primary = x + mu;
+        let to_secondary = x - 1.0 + mu;
+        let r1 = to_primary.abs().powi(3);
+        let r2 = to_secondary.abs().powi(3);
+        let force = x - (1.0 - mu) * to_primary / r1 - mu * to_secondary / r2;
+        let slope = 1.0 + 2.0 * (1.0 - mu) / r1 + 2.0 * mu / r2;
+        let step = force / slope;
+        x -= step;
+        if step.abs() < 1e-15 {
+            break;
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_acceleration() {
+        let (earth, moon) = (5.972e24, 7.342e22);
+        let a_to_b = DVec3::new(3.0e8, -2.0e8, 1.0e7);
+        let (earth_acceleration, moon_acceleration) = pair_acceleration(G * earth, G * moon, a_to_b);
+
+        // Each pulled toward the other, with equal and opposite forces
+        assert!(earth_acceleration.dot(a_to_b) > 0.0);
+        assert!(moon_acceleration.dot(a_to_b) < 0.0);
+        assert!((earth_acceleration * earth + moon_acceleration * moon).length() < 1e-9 * (moon_acceleration * moon).length());
+        assert!((moon_acceleration.length() / earth_acceleration.length() - earth / moon).abs() < 1e-9);
+        assert_eq!(moon_acceleration, one_body_acceleration(G * earth, a_to_b));
+    }
+
+    #[test]
+    fn test_potential_energy() {
+        let (earth, moon, r) = (5.972e24, 7.342e22, 3.844e8);
+        let energy = potential_energy(G, earth, moon, r);
+        assert!(energy < 0.0);
+        assert!((potential_energy(G, earth, moon, 2.0 * r) / energy - 0.5).abs() < 1e-12);
+        assert_eq!(potential_energy(G, moon, earth, r), energy);
+    }
+
+    #[test]
+    fn test_barycenter() {
+        let a = DVec3::new(1.0e8, -2.0e8, 3.0e6);
+        let b = DVec3::new(-5.0e8, 4.0e8, 0.0);
+        assert_eq!(barycenter([(7.0e22, a), (7.0e22, b)]), Some((a + b) / 2.0));
+
+        // Twice as heavy, so the barycenter is a third of the way from a to b
+        let weighted = barycenter([(2.0e22, a), (1.0e22, b)]).unwrap();
+        assert!((weighted - (a + (b - a) / 3.0)).length() < 1e-6);
+
+        assert_eq!(barycenter([]), None);
+        assert_eq!(barycenter([(0.0, a)]), None);
+    }
+
+    #[test]
+    fn test_earth_soi_and_hill_radius() {
+        let (a, e, earth, sun) = (1.496e11, 0.0167, 5.972e24, 1.989e30);
+        let soi = soi_radius(a, earth, sun);
+        assert!((soi - 0.925e9).abs() < 0.01e9, "{}", soi);
+        let hill = hill_radius(a, e, earth, sun);
+        assert!((hill - 1.47e9).abs() < 0.01e9, "{}", hill);
+        assert!(hill_radius(a, 0.0, earth, sun) > hill);
+    }
+
+    #[test]
+    fn test_sun_earth_lagrange_points() {
+        let sun = DVec3::new(1.0e9, 0.0, 0.0);
+        let earth = sun + DVec3::new(0.0, 1.496e11, 0.0);
+        let [l1, l2, l3, l4, l5] = lagrange_points(1.989e30, sun, 5.972e24, earth);
+
+        // About 1.5 million km either side of Earth, L2 slightly further out
+        let l1_distance = (earth - l1).length();
+        let l2_distance = (l2 - earth).length();
+        assert!((l1_distance - 1.4915e9).abs() < 0.005e9, "{}", l1_distance);
+        assert!((l2_distance - 1.5015e9).abs() < 0.005e9, "{}", l2_distance);
+        assert!(l1_distance < l2_distance);
+        assert!((l1 - sun).length() < (earth - sun).length());
+        assert!((l2 - sun).length() > (earth - sun).length());
+
+        // L3 is just beyond one orbital radius on the far side of the Sun
+        assert!((l3 - sun).normalize().dot((earth - sun).normalize()) < -0.999);
+        assert!(((l3 - sun).length() / 1.496e11 - 1.0).abs() < 1e-5);
+
+        for triangular in [l4, l5] {
+            assert!(((triangular - sun).length() - 1.496e11).abs() < 1.0);
+            assert!(((triangular - earth).length() - 1.496e11).abs() < 1.0);
+        }
+
+        // Same points whichever body is passed first
+        let swapped = lagrange_points(5.972e24, earth, 1.989e30, sun);
+        assert!((swapped[0] - l1).length() < 1.0);
+    }
+
+    #[test]
+    fn test_massless_secondary_lagrange_points() {
+        let earth = DVec3::new(1.0e11, 0.0, 0.0);
+        let [l1, l2, l3, _, _] = lagrange_points(1.0, DVec3::ZERO, 0.0, earth);
+        assert_eq!(l1, earth);
+        assert_eq!(l2, earth);
+        assert!((l3 + earth).length() < 1e-3);
+        assert_eq!(lagrange_points(0.0, DVec3::ZERO, 0.0, earth), [DVec3::ZERO; 5]);
+    }
+}
@@ -48,7 +48,7 @@ pub mod local {
         }
 
         pub fn from_elements2_infallible(semi_major_axis: f64, eccentricity: f64, true_anomaly: f64) -> f64 {
-            from_elements2(eccentricity, semi_major_axis, true_anomaly).unwrap_or(f64::INFINITY)
+            from_elements2(semi_major_axis, eccentricity, true_anomaly).unwrap_or(f64::INFINITY)
         }
 
         pub fn from_eccentric_anomaly(semi_major_axis: f64, eccentricity: f64, eccentric_anomaly: f64) -> f64 {
@@ -135,10 +135,8 @@ pub mod eccentricity {
 }
 
 pub mod semi_parameter {
-    use crate::util::common::unit_circle_xy;
-
     pub fn definition(semi_major_axis: f64, eccentricity: f64) -> f64 {
-        semi_major_axis * unit_circle_xy(eccentricity)
+        semi_major_axis * (1.0 - eccentricity * eccentricity)
     }
 }
 
@@ -160,20 +158,29 @@ pub mod apoapsis {
 }
 
 pub mod eccentric_anomaly {
+    use std::f64::consts::TAU;
     use crate::util::common::unit_circle_xy;
 
+    /// In the same revolution as `true_anomaly`, so they agree at apsides and go round together.
     pub fn from_true_anomaly(eccentricity: f64, true_anomaly: f64) -> f64 {
         let numerator = unit_circle_xy(eccentricity) * f64::sin(true_anomaly);
         let denominator = eccentricity + f64::cos(true_anomaly);
-        let fraction = numerator / denominator;
-        f64::atan(fraction)
+        let eccentric_anomaly = f64::atan2(numerator, denominator);
+        let revolutions = ((true_anomaly - eccentric_anomaly) / TAU).round();
+        eccentric_anomaly + revolutions * TAU
     }
 }
 
 pub mod true_anomaly {
+    use std::f64::consts::TAU;
     use bevy::math::DVec3;
     use crate::util::common::{unit_circle_xy};
     use scilib::math::bessel;
+    use super::mean_anomaly;
+
+    /// Highest eccentricity `at_mean_anomaly` uses the Bessel series for. The series only converges
+    /// below about 0.66, and by e = 0.1 ten harmonics are already down to about 1e-9 radians.
+    pub const SERIES_ECCENTRICITY_LIMIT: f64 = 0.1;
 
     pub fn at_time(eccentric_anomaly: f64, eccentricity: f64) -> f64 {
         let beta = eccentricity / (1.0 + unit_circle_xy(eccentricity));
@@ -194,27 +201,71 @@ pub mod true_anomaly {
         answer
     }
 
-    /// This is the Fourier expansion up to e^3
+    /// The equation of the center up to e^3. Quick, and good to about 1e-5 radians below e = 0.05.
     pub fn from_mean_anomaly(mean_anomaly: f64, eccentricity: f64) -> f64 {
+        let e = eccentricity;
         let first_term = mean_anomaly;
-        let second_term = (2.0 - (1.0 / 4.0) * eccentricity * eccentricity * eccentricity) * f64::sin(mean_anomaly);
-        let third_term = (5.0 / 4.0) * eccentricity * eccentricity * eccentricity * f64::sin(2.0 * mean_anomaly);
-        let fourth_term = (13.0 / 12.0) * eccentricity * eccentricity * eccentricity * f64::sin(3.0 * mean_anomaly);
+        let second_term = (2.0 * e - (1.0 / 4.0) * e * e * e) * f64::sin(mean_anomaly);
+        let third_term = (5.0 / 4.0) * e * e * f64::sin(2.0 * mean_anomaly);
+        let fourth_term = (13.0 / 12.0) * e * e * e * f64::sin(3.0 * mean_anomaly);
         first_term + second_term + third_term + fourth_term
     }
 
+    /// The full Bessel series for the equation of the center, to `iterations` harmonics.
+    /// Converges for eccentricities below about 0.66.
     pub fn fourier_expansion(mean_anomaly: f64, eccentricity: f64, iterations: usize) -> f64 {
+        let beta = eccentricity / (1.0 + unit_circle_xy(eccentricity));
         let mut true_anomaly = mean_anomaly;
 
         for k in 1..=iterations {
-            let order = k  as i32;
+            let order = k as i32;
             let k: f64 = k as f64;
-            let term = (2.0 / k) * bessel::j_n(order, eccentricity) * f64::sin(k * mean_anomaly);
-            true_anomaly += term;
+            let x = k * eccentricity;
+            let mut coefficient = bessel_j(order, x);
+            let mut beta_power = 1.0;
+            for p in 1..=iterations as i32 {
+                beta_power *= beta;
+                coefficient += beta_power * (bessel_j(order - p, x) + bessel_j(order + p, x));
+            }
+            true_anomaly += (2.0 / k) * coefficient * f64::sin(k * mean_anomaly);
         }
 
         true_anomaly
     }
+
+    /// Solves Kepler's equation by Newton's method. Good for any closed orbit.
+    pub fn by_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+        let revolutions = (mean_anomaly / TAU).round() * TAU;
+        let mean_anomaly = mean_anomaly - revolutions;
+        let mut eccentric_anomaly = mean_anomaly + 0.85 * eccentricity * mean_anomaly.sin().signum();
+        for _ in 0..50 {
+            let error = mean_anomaly::kepler(eccentric_anomaly, eccentricity) - mean_anomaly;
+            let step = error / (1.0 - eccentricity * eccentric_anomaly.cos());
+            eccentric_anomaly -= step;
+            if step.abs() < 1e-15 { break; }
+        }
+        at_time(eccentric_anomaly, eccentricity) + revolutions
+    }
+
+    /// The Bessel series to `iterations` harmonics while it converges quickly, Kepler's equation past that.
+    pub fn at_mean_anomaly(mean_anomaly: f64, eccentricity: f64, iterations: usize) -> f64 {
+        if eccentricity <= SERIES_ECCENTRICITY_LIMIT {
+            fourier_expansion(mean_anomaly, eccentricity, iterations)
+        } else {
+            by_kepler(mean_anomaly, eccentricity)
+        }
+    }
+
+    /// J_n for negative orders too, where J_-n = (-1)^n J_n.
+    fn bessel_j(order: i32, x: f64) -> f64 {
+        if order >= 0 {
+            bessel::j_n(order, x)
+        } else if order % 2 == 0 {
+            bessel::j_n(-order, x)
+        } else {
+            -bessel::j_n(-order, x)
+        }
+    }
 }
 
 pub mod apsides {
@@ -247,6 +298,13 @@ pub mod period {
     }
 }
 
+pub mod speed {
+    /// Orbital speed `radius` from the primary, on an orbit with this semi-major axis.
+    pub fn vis_viva(gravitational_parameter: f64, radius: f64, semi_major_axis: f64) -> f64 {
+        (gravitational_parameter * (2.0 / radius - 1.0 / semi_major_axis)).max(0.0).sqrt()
+    }
+}
+
 pub mod gravitational_parameter {
     use crate::foundations::kepler::third_law::reused_term;
 
@@ -257,10 +315,12 @@ pub mod gravitational_parameter {
 
 pub mod eccentricity_vector {
     use bevy::math::DVec3;
+    use crate::foundations::kepler::eccentricity;
 
+    /// Points at periapsis, with length equal to the eccentricity.
+    /// Same as `eccentricity::vector::definition`, with the arguments in another order.
     pub fn definition(mu: f64, displacement: DVec3, velocity: DVec3) -> DVec3 {
-        let specific_angular_momentum = displacement.cross(velocity);
-        (1.0 / mu) * (velocity * specific_angular_momentum) - displacement.normalize()
+        eccentricity::vector::definition(displacement, velocity, mu)
     }
 }
 
@@ -297,3 +357,120 @@ pub mod energy {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::DVec3;
+
+    #[test]
+    fn test_radius_infallible_agrees() {
+        for (semi_major_axis, eccentricity) in [(1.5e11, 0.0167), (3.844e8, 0.0549), (7.0e6, 0.7), (-2.0e7, 1.5)] {
+            for degrees in (0..360).step_by(15) {
+                let true_anomaly = (degrees as f64).to_radians();
+                let Some(radius) = local::radius::from_elements2(semi_major_axis, eccentricity, true_anomaly) else { continue };
+                assert_eq!(local::radius::from_elements2_infallible(semi_major_axis, eccentricity, true_anomaly), radius);
+            }
+        }
+        // At periapsis
+        assert!((local::radius::from_elements2_infallible(7.0e6, 0.7, 0.0) - 7.0e6 * 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_eccentric_anomaly_all_quadrants() {
+        // From the half-angle relation tan(E/2) = sqrt((1 - e) / (1 + e)) tan(ν/2)
+        let expected = [(30.0, 0.3882887495659024), (150.0, 2.441372580894839), (210.0, 3.841812726284747), (330.0, 5.894896557613683)];
+        for (degrees, eccentric_anomaly) in expected {
+            let true_anomaly = f64::to_radians(degrees);
+            let found = eccentric_anomaly::from_true_anomaly(0.3, true_anomaly);
+            assert!((found - eccentric_anomaly).abs() < 1e-12, "{}°: {}", degrees, found);
+            // A revolution later
+            let found = eccentric_anomaly::from_true_anomaly(0.3, true_anomaly + std::f64::consts::TAU);
+            assert!((found - (eccentric_anomaly + std::f64::consts::TAU)).abs() < 1e-12, "{}°: {}", degrees, found);
+        }
+    }
+
+    #[test]
+    fn test_true_anomaly_series() {
+        for eccentricity in [0.01, 0.0167, 0.05] {
+            for step in 0..64 {
+                let mean_anomaly = step as f64 * std::f64::consts::TAU / 64.0;
+                let truth = true_anomaly::by_kepler(mean_anomaly, eccentricity);
+                let quick = true_anomaly::from_mean_anomaly(mean_anomaly, eccentricity);
+                let full = true_anomaly::fourier_expansion(mean_anomaly, eccentricity, 10);
+                assert!((quick - truth).abs() < 1e-5, "e = {}, M = {}: {} vs {}", eccentricity, mean_anomaly, quick, truth);
+                assert!((full - truth).abs() < 1e-10, "e = {}, M = {}: {} vs {}", eccentricity, mean_anomaly, full, truth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_true_anomaly_high_eccentricity() {
+        for eccentricity in [0.3, 0.7, 0.9, 0.99] {
+            for step in -64..192 {
+                let mean_anomaly = step as f64 * std::f64::consts::TAU / 64.0;
+                let found = true_anomaly::at_mean_anomaly(mean_anomaly, eccentricity, 10);
+                assert_eq!(found, true_anomaly::by_kepler(mean_anomaly, eccentricity));
+                // Back through Kepler's equation, in the same revolution
+                let eccentric_anomaly = eccentric_anomaly::from_true_anomaly(eccentricity, found);
+                let round_trip = mean_anomaly::kepler(eccentric_anomaly, eccentricity);
+                assert!((round_trip - mean_anomaly).abs() < 1e-11, "e = {}, M = {}: {}", eccentricity, mean_anomaly, round_trip);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eccentricity_vector_points_at_periapsis() {
+        // At periapsis on +X, moving +Y at the speed for e = 0.3 around a unit-μ primary
+        let (mu, eccentricity, periapsis) = (1.0, 0.3, 2.0);
+        let speed = f64::sqrt(mu * (1.0 + eccentricity) / periapsis);
+        let position = DVec3::new(periapsis, 0.0, 0.0);
+        let velocity = DVec3::new(0.0, speed, 0.0);
+
+        let expected = DVec3::new(eccentricity, 0.0, 0.0);
+        let vector = eccentricity_vector::definition(mu, position, velocity);
+        assert!((vector - expected).length() < 1e-12, "{}", vector);
+        assert_eq!(vector, eccentricity::vector::definition(position, velocity, mu));
+
+        // A quarter of the way round, where r is the semi-latus rectum
+        let position = DVec3::new(0.0, 2.6, 0.0);
+        let velocity = f64::sqrt(mu / 2.6) * DVec3::new(-1.0, eccentricity, 0.0);
+        let vector = eccentricity_vector::definition(mu, position, velocity);
+        assert!((vector - expected).length() < 1e-12, "{}", vector);
+    }
+
+    #[test]
+    fn test_apsides_from_elements() {
+        // Earth's orbit: perihelion 147,098,450 km and aphelion 152,097,597 km
+        let (semi_major_axis, eccentricity) = (1.49598023e11, 0.0167086);
+        let perihelion = periapsis::definition(semi_major_axis, eccentricity);
+        let aphelion = apoapsis::definition(semi_major_axis, eccentricity).unwrap();
+        assert!((perihelion - 1.47098450e11).abs() < 1e3, "{}", perihelion);
+        assert!((aphelion - 1.52097597e11).abs() < 1e3, "{}", aphelion);
+        // Back again, by way of the semi-latus rectum
+        assert!((semi_major_axis::radii(perihelion, aphelion) - semi_major_axis).abs() < 1e-3);
+        assert!((eccentricity::radii(perihelion, aphelion) - eccentricity).abs() < 1e-12);
+        let semi_parameter = semi_parameter::definition(semi_major_axis, eccentricity);
+        assert!((semi_parameter - 2.0 * perihelion * aphelion / (perihelion + aphelion)).abs() < 1e-2);
+
+        // A hyperbola has a periapsis but no apoapsis
+        assert!((periapsis::definition(-2.0e7, 1.5) - 1.0e7).abs() < 1e-6);
+        assert_eq!(apoapsis::definition(-2.0e7, 1.5), None);
+    }
+
+    #[test]
+    fn test_vis_viva_at_apsides() {
+        // Earth's orbit, 147.1 and 152.1 million km out at perihelion and aphelion
+        let mu = 1.32712440018e20;
+        let (perihelion, aphelion) = (1.47095e11, 1.52100e11);
+        let semi_major_axis = semi_major_axis::radii(perihelion, aphelion);
+        let fastest = speed::vis_viva(mu, perihelion, semi_major_axis);
+        let slowest = speed::vis_viva(mu, aphelion, semi_major_axis);
+        assert!((fastest - 30_290.0).abs() < 10.0, "{}", fastest);
+        assert!((slowest - 29_290.0).abs() < 10.0, "{}", slowest);
+        // Angular momentum is the same at both ends
+        assert!((fastest * perihelion - slowest * aphelion).abs() / (fastest * perihelion) < 1e-12);
+        // On a circle, the circular speed
+        assert!((speed::vis_viva(mu, 1.5e11, 1.5e11) - (mu / 1.5e11).sqrt()).abs() < 1e-9);
+    }
+}
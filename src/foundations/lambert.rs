@@ -0,0 +1,202 @@
+//! Lambert's problem: the orbit that gets from one position to another in a given time.
+//! Solved with universal variables, after Vallado and Curtis.
+
+use bevy::math::DVec3;
+use crate::foundations::time::TimeDelta;
+
+/// Halvings of the search interval. Each is a bit, so this runs out well past f64 precision.
+const BISECTIONS: usize = 200;
+
+/// Stumpff functions C(ψ) and S(ψ).
+fn stumpff(psi: f64) -> (f64, f64) {
+    if psi > 1e-6 {
+        let s = psi.sqrt();
+        ((1.0 - s.cos()) / psi, (s - s.sin()) / (s * s * s))
+    } else if psi < -1e-6 {
+        let s = (-psi).sqrt();
+        ((s.cosh() - 1.0) / -psi, (s.sinh() - s) / (s * s * s))
+    } else {
+        (0.5, 1.0 / 6.0)
+    }
+}
+
+/// The geometry of one transfer, for trying values of ψ against.
+struct Transfer {
+    r1: DVec3,
+    r2: DVec3,
+    a: f64,
+    mu: f64,
+}
+
+impl Transfer {
+    fn new(r1: DVec3, r2: DVec3, mu: f64, prograde: bool) -> Option<Self> {
+        let (l1, l2) = (r1.length(), r2.length());
+        if l1 <= 0.0 || l2 <= 0.0 || mu <= 0.0 {
+            return None;
+        }
+        let cos_dnu = (r1.dot(r2) / (l1 * l2)).clamp(-1.0, 1.0);
+        // Prograde transfers go anticlockwise seen from +Z
+        let mut dnu = cos_dnu.acos();
+        if prograde == (r1.cross(r2).z < 0.0) {
+            dnu = std::f64::consts::TAU - dnu;
+        }
+        let a = dnu.sin() * (l1 * l2 / (1.0 - cos_dnu)).sqrt();
+        // Straight out or half way round, there's no one plane to transfer in
+        if !a.is_finite() || a.abs() < 1e-12 * (l1 + l2) {
+            return None;
+        }
+        Some(Self { r1, r2, a, mu })
+    }
+
+    fn y(&self, psi: f64) -> f64 {
+        let (c2, c3) = stumpff(psi);
+        self.r1.length() + self.r2.length() + self.a * (psi * c3 - 1.0) / c2.sqrt()
+    }
+
+    /// Seconds to get there at this ψ. Infinite where ψ gives no transfer.
+    fn time_of_flight(&self, psi: f64) -> f64 {
+        let y = self.y(psi);
+        if y < 0.0 { return f64::INFINITY; }
+        let (c2, c3) = stumpff(psi);
+        let chi = (y / c2).sqrt();
+        (chi * chi * chi * c3 + self.a * y.sqrt()) / self.mu.sqrt()
+    }
+
+    fn velocities(&self, psi: f64) -> Option<(DVec3, DVec3)> {
+        let y = self.y(psi);
+        if y < 0.0 { return None; }
+        let f = 1.0 - y / self.r1.length();
+        let g = self.a * (y / self.mu).sqrt();
+        let g_dot = 1.0 - y / self.r2.length();
+        let v1 = (self.r2 - f * self.r1) / g;
+        let v2 = (g_dot * self.r2 - self.r1) / g;
+        (v1.is_finite() && v2.is_finite()).then_some((v1, v2))
+    }
+}
+
+/// Bisects for the ψ in `low..high` taking `tof` seconds, where time of flight rises with ψ
+/// if `rising` and falls otherwise.
+fn bisect(transfer: &Transfer, mut low: f64, mut high: f64, tof: f64, rising: bool) -> f64 {
+    for _ in 0..BISECTIONS {
+        let psi = (low + high) / 2.0;
+        let too_short = transfer.time_of_flight(psi) < tof;
+        if too_short == rising { low = psi; } else { high = psi; }
+    }
+    (low + high) / 2.0
+}
+
+/// Departure and arrival velocities of a transfer from `r1` to `r2`, relative to the primary,
+/// taking `tof`. `prograde` goes anticlockwise around +Z. Less than one revolution;
+/// see `lambert_revolutions` for more.
+/// None for negative times, or positions straight out from or opposite each other.
+pub fn lambert(r1: DVec3, r2: DVec3, tof: TimeDelta, mu: f64, prograde: bool) -> Option<(DVec3, DVec3)> {
+    let tof = tof.to_seconds();
+    if tof <= 0.0 { return None; }
+    let transfer = Transfer::new(r1, r2, mu, prograde)?;
+    let limit = std::f64::consts::TAU * std::f64::consts::TAU;
+    // Above 4π² the transfer would go all the way round first
+    let psi = bisect(&transfer, -limit, limit * (1.0 - 1e-9), tof, true);
+    // Pinned against either end, there's no transfer that slow or that quick
+    if (transfer.time_of_flight(psi) - tof).abs() > tof * 1e-6 { return None; }
+    transfer.velocities(psi)
+}
+
+/// Like `lambert`, going round `revolutions` whole times on the way. There are two such transfers
+/// if there's time for any: `long_period` picks the one on the bigger orbit.
+/// None if `tof` is too short to go round that many times.
+pub fn lambert_revolutions(
+    r1: DVec3,
+    r2: DVec3,
+    tof: TimeDelta,
+    mu: f64,
+    prograde: bool,
+    revolutions: u32,
+    long_period: bool,
+) -> Option<(DVec3, DVec3)> {
+    if revolutions == 0 { return lambert(r1, r2, tof, mu, prograde); }
+    let tof = tof.to_seconds();
+    if tof <= 0.0 { return None; }
+    let transfer = Transfer::new(r1, r2, mu, prograde)?;
+
+    // Time of flight runs down from infinity and back up across (2πN)² < ψ < (2π(N+1))²
+    let low = (std::f64::consts::TAU * revolutions as f64).powi(2);
+    let high = (std::f64::consts::TAU * (revolutions + 1) as f64).powi(2);
+    let margin = (high - low) * 1e-9;
+    let (mut a, mut b) = (low + margin, high - margin);
+    let golden = (5f64.sqrt() - 1.0) / 2.0;
+    for _ in 0..BISECTIONS {
+        let x1 = b - golden * (b - a);
+        let x2 = a + golden * (b - a);
+        if transfer.time_of_flight(x1) < transfer.time_of_flight(x2) { b = x2; } else { a = x1; }
+    }
+    let fastest = (a + b) / 2.0;
+    if transfer.time_of_flight(fastest) > tof { return None; }
+
+    let psi = if long_period {
+        bisect(&transfer, fastest, high - margin, tof, true)
+    } else {
+        bisect(&transfer, low + margin, fastest, tof, false)
+    };
+    transfer.velocities(psi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: DVec3, expected: DVec3, tolerance: f64) {
+        assert!(actual.distance(expected) < tolerance, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_curtis_example() {
+        // Curtis, Orbital Mechanics for Engineering Students, example 5.2, in km
+        let r1 = DVec3::new(5000.0, 10000.0, 2100.0);
+        let r2 = DVec3::new(-14600.0, 2500.0, 7000.0);
+        let (v1, v2) = lambert(r1, r2, TimeDelta::from_seconds(3600.0), 398600.0, true).unwrap();
+        assert_close(v1, DVec3::new(-5.9925, 1.9254, 3.2456), 1e-3);
+        assert_close(v2, DVec3::new(-3.3125, -4.1966, -0.38529), 1e-3);
+
+        // Going the other way round needs more speed
+        let (retrograde, _) = lambert(r1, r2, TimeDelta::from_seconds(3600.0), 398600.0, false).unwrap();
+        assert!(retrograde.length() > v1.length());
+    }
+
+    #[test]
+    fn test_earth_to_mars() {
+        let au = 1.495978707e11;
+        let sun_mu = 1.32712440018e20;
+        let earth = DVec3::new(au, 0.0, 0.0);
+        let earth_velocity = DVec3::new(0.0, (sun_mu / au).sqrt(), 0.0);
+        // Mars 135 degrees round on its orbit, 200 days later
+        let mars_radius = 1.5237 * au;
+        let angle = 135f64.to_radians();
+        let mars = mars_radius * DVec3::new(angle.cos(), angle.sin(), 0.0);
+
+        let (departure, _) = lambert(earth, mars, TimeDelta::from_seconds(200.0 * 86_400.0), sun_mu, true).unwrap();
+        let v_infinity = (departure - earth_velocity).length();
+        // No better than a Hohmann transfer's 2.9 km/s, and nowhere near escaping the sun
+        assert!((2.9e3..6.0e3).contains(&v_infinity), "{}", v_infinity);
+    }
+
+    #[test]
+    fn test_multiple_revolutions() {
+        let mu = 398600.0;
+        let r1 = DVec3::new(5000.0, 10000.0, 2100.0);
+        let r2 = DVec3::new(-14600.0, 2500.0, 7000.0);
+        let tof = 8.0 * 3600.0;
+        let semi_major_axis = |v: DVec3| 1.0 / (2.0 / r1.length() - v.length_squared() / mu);
+        let period = |v: DVec3| std::f64::consts::TAU * (semi_major_axis(v).powi(3) / mu).sqrt();
+
+        let (short, _) = lambert_revolutions(r1, r2, TimeDelta::from_seconds(tof), mu, true, 1, false).unwrap();
+        let (long, _) = lambert_revolutions(r1, r2, TimeDelta::from_seconds(tof), mu, true, 1, true).unwrap();
+        assert!(semi_major_axis(long) > semi_major_axis(short));
+        // Both go round once and then some
+        for v in [short, long] {
+            assert!((period(v)..2.0 * period(v)).contains(&tof), "{}", period(v));
+            assert!(r1.cross(v).dot(r1.cross(r2)) > 0.0);
+        }
+        // An hour isn't long enough to go round at all
+        assert!(lambert_revolutions(r1, r2, TimeDelta::from_seconds(3600.0), mu, true, 1, true).is_none());
+    }
+}
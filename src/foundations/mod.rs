@@ -1,4 +1,8 @@
 pub mod reference_frame;
 pub mod gravity;
 pub mod kepler;
+pub mod lambert;
 pub mod time;
+
+/// In a vacuum, in meters per second.
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
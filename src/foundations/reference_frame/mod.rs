@@ -1,6 +1,7 @@
 pub mod conversions;
 pub mod transformation;
 pub mod observation;
+pub mod plane;
 
 use bevy::math::{DMat4, DQuat, DVec3};
 use transformation::Transformation;
@@ -73,7 +74,13 @@ impl ReferenceFrame {
     /// When `other` is `IDENTITY`, this transforms local points to world coordinates.
     #[inline]
     pub fn transform_to(&self, other: Self) -> Transformation {
-        (other.mat.inverse() * self.mat).into()
+        (transformation::rigid_inverse(&other.mat) * self.mat).into()
+    }
+
+    /// The universal frame as seen from self: its origin and axes expressed in self's local coordinates.
+    #[inline]
+    pub fn inverse(&self) -> ReferenceFrame {
+        transformation::rigid_inverse(&self.mat).into()
     }
 
     #[inline]
@@ -293,3 +300,46 @@ impl ReferenceFrame {
         DMat4::from_rotation_translation(final_rotation, self.universal_origin()).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::{DMat3, EulerRot};
+
+    /// Translations are planetary distances, so allow for their rounding.
+    fn assert_identity(mat: DMat4) {
+        assert!(DMat3::from_mat4(mat).abs_diff_eq(DMat3::IDENTITY, 1e-12), "{:?} is not the identity", mat);
+        assert!(mat.w_axis.truncate().length() < 1e-3, "{:?} is not the identity", mat);
+    }
+
+    fn satellite() -> ReferenceFrame {
+        ReferenceFrame::from_position_yaw_pitch(DVec3::new(7.0e6, -2.0e5, 1.0e6), 0.7, -0.3)
+    }
+
+    fn planet() -> ReferenceFrame {
+        ReferenceFrame::new(DVec3::new(1.5e11, 2.0e10, -3.0e8), DQuat::from_euler(EulerRot::ZXZ, 1.2, 0.41, -2.0))
+    }
+
+    #[test]
+    fn test_round_trip_is_identity() {
+        let there = satellite().transform_to(planet());
+        let back = planet().transform_to(satellite());
+        assert_identity(there.then(&back).mat);
+        assert_identity(there.then(&there.inverse()).mat);
+    }
+
+    #[test]
+    fn test_chained_frames() {
+        let star = ReferenceFrame::from_position_yaw_pitch(DVec3::new(-4.0e12, 0.0, 1.0e9), 2.5, 0.1);
+        let chained = satellite().transform_to(planet()).then(&planet().transform_to(star.clone()));
+        let direct = satellite().transform_to(star.clone());
+        let point = DVec3::new(10.0, -20.0, 30.0);
+        assert!((chained.point(point) - direct.point(point)).length() < 1e-2);
+
+        // Frame composed with its own inverse lands back on the universal frame
+        let frame = planet();
+        assert_identity(frame.inverse().mat * frame.mat);
+        let rotation = DMat3::from_mat4(frame.inverse().mat);
+        assert!((rotation * rotation.transpose()).abs_diff_eq(DMat3::IDENTITY, 1e-12));
+    }
+}
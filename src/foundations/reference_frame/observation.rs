@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_2;
 use bevy::math::{DVec3, DQuat};
 use crate::foundations::reference_frame::{ReferenceFrame};
-use crate::foundations::time::Instant;
+use crate::foundations::SPEED_OF_LIGHT;
+use crate::foundations::time::{Instant, Span};
 
 /// An Observation represents standing at a location and seeing another location
 /// As such, it is an angle from a reference frame.
@@ -41,6 +43,175 @@ impl Observation {
     }
 }
 
+/// Distance from the observer to `target`, a point in universal coordinates.
+pub fn range(observer: &ReferenceFrame, target: DVec3) -> f64 {
+    observer.universal_origin().distance(target)
+}
+
+/// How fast `target` is moving away from the observer. Negative when approaching.
+/// Velocities are in universal coordinates.
+pub fn range_rate(observer: &ReferenceFrame, observer_velocity: DVec3, target: DVec3, target_velocity: DVec3) -> f64 {
+    let line_of_sight = (target - observer.universal_origin()).normalize_or_zero();
+    (target_velocity - observer_velocity).dot(line_of_sight)
+}
+
+/// Where `target` appears in the observer's local frame, as (azimuth, elevation) in radians.
+/// Same conventions as `Observation::from_azimuth_elevation`: azimuth 0 = local forward,
+/// π/2 = local right; elevation π/2 = local up.
+pub fn azimuth_elevation(observer: &ReferenceFrame, target: DVec3) -> (f64, f64) {
+    let local = ReferenceFrame::IDENTITY.transform_to(observer.clone()).point(target);
+    let azimuth = local.y.atan2(local.x);
+    let horizontal = (local.x * local.x + local.y * local.y).sqrt();
+    let elevation = local.z.atan2(horizontal);
+    (azimuth, elevation)
+}
+
+/// Angle, in radians, between two targets as seen by the observer.
+pub fn angular_separation(observer: &ReferenceFrame, a: DVec3, b: DVec3) -> f64 {
+    let origin = observer.universal_origin();
+    (a - origin).angle_between(b - origin)
+}
+
+/// Where a body appears to an observer at `time`: where it was when the light now arriving left it.
+/// `position_at` gives the body's true position at any instant.
+pub fn light_time_corrected(observer: DVec3, time: Instant, position_at: impl Fn(Instant) -> DVec3) -> DVec3 {
+    let now = time.to_j2000_seconds();
+    let mut position = position_at(time);
+    // Each pass takes the range error down by a factor of about v/c
+    for _ in 0..3 {
+        let light_time = observer.distance(position) / SPEED_OF_LIGHT;
+        position = position_at(Instant::from_seconds_since_j2000(now - light_time));
+    }
+    position
+}
+
+/// Angle at `body` between the star and the observer, in radians.
+/// 0 when the observer sees the lit side face on, π when they see only the night side.
+pub fn phase_angle(observer: DVec3, body: DVec3, star: DVec3) -> f64 {
+    (star - body).angle_between(observer - body)
+}
+
+/// Fraction of a sphere's disk that is lit, seen at this phase angle.
+pub fn illuminated_fraction(phase_angle: f64) -> f64 {
+    (1.0 + phase_angle.cos()) / 2.0
+}
+
+/// Apparent radius, in radians, of a sphere of `radius` centered on `target`.
+/// π/2 from inside it.
+pub fn angular_radius(observer: &ReferenceFrame, target: DVec3, radius: f64) -> f64 {
+    let range = range(observer, target);
+    if range <= radius { FRAC_PI_2 } else { (radius / range).asin() }
+}
+
+/// When two bodies' disks overlap as seen by the observer, so one transits or occults the other.
+pub struct Transit {
+    pub ingress: Instant,
+    pub egress: Instant,
+    /// Whether `a` was the nearer of the two, halfway through.
+    pub a_in_front: bool,
+}
+
+/// Where the observer, `a` and `b` are at a given time, in universal coordinates.
+pub type TransitPositions<'a> = dyn Fn(Instant) -> (DVec3, DVec3, DVec3) + 'a;
+
+/// Scans `span` every `step` seconds for times the disks of `a` and `b`, of radii `radius_a`
+/// and `radius_b`, overlap as seen from the observer. Contacts are found to well under a
+/// millisecond, but overlaps shorter than `step` can be missed. A transit already under way
+/// at either end of the span is cut off there.
+pub fn find_transits(span: &Span, step: f64, positions: &TransitPositions, radius_a: f64, radius_b: f64) -> Vec<Transit> {
+    assert!(step > 0.0, "Scan step must be positive, got {}", step);
+    // Negative while the disks overlap
+    let clearance = |t: f64| {
+        let (observer, a, b) = positions(Instant::from_seconds_since_j2000(t));
+        let observer = ReferenceFrame::new(observer, DQuat::IDENTITY);
+        angular_separation(&observer, a, b) - angular_radius(&observer, a, radius_a) - angular_radius(&observer, b, radius_b)
+    };
+    let contact = |mut before: f64, mut after: f64| {
+        let overlapping_after = clearance(after) < 0.0;
+        while after - before > 1e-4 {
+            let mid = 0.5 * (before + after);
+            if (clearance(mid) < 0.0) == overlapping_after { after = mid } else { before = mid }
+        }
+        0.5 * (before + after)
+    };
+
+    let start = span.start().to_j2000_seconds();
+    let end = span.end().to_j2000_seconds();
+    let mut transits = Vec::new();
+    let mut ingress = (clearance(start) < 0.0).then_some(start);
+    let mut t = start;
+    while t < end {
+        let next = (t + step).min(end);
+        let overlapping = clearance(next) < 0.0;
+        match (ingress, overlapping) {
+            (None, true) => ingress = Some(contact(t, next)),
+            (Some(began), false) => {
+                transits.push(transit(began, contact(t, next), positions));
+                ingress = None;
+            }
+            _ => {}
+        }
+        t = next;
+    }
+    if let Some(began) = ingress {
+        transits.push(transit(began, end, positions));
+    }
+    transits
+}
+
+fn transit(ingress: f64, egress: f64, positions: &TransitPositions) -> Transit {
+    let (observer, a, b) = positions(Instant::from_seconds_since_j2000(0.5 * (ingress + egress)));
+    Transit {
+        ingress: Instant::from_seconds_since_j2000(ingress),
+        egress: Instant::from_seconds_since_j2000(egress),
+        a_in_front: observer.distance(a) < observer.distance(b),
+    }
+}
+
+/// How far, in radians, the atmosphere lifts something on Earth's horizon. Bodies this far below
+/// the geometric horizon are just rising.
+pub const STANDARD_REFRACTION: f64 = 34.0 / 60.0 * std::f64::consts::PI / 180.0;
+
+/// The next time a target comes up over the horizon and goes down below it.
+pub struct RiseSet {
+    pub up_at_start: bool,
+    pub rise: Option<Instant>,
+    pub set: Option<Instant>,
+}
+
+/// Scans `span` every `step` seconds for the first times `elevation_at`, in radians, climbs above and
+/// drops below `horizon`. Crossings are found to well under a millisecond, but a target that is
+/// only up or down for less than `step` can be missed.
+pub fn next_rise_set(span: &Span, step: f64, elevation_at: &dyn Fn(Instant) -> f64, horizon: f64) -> RiseSet {
+    assert!(step > 0.0, "Scan step must be positive, got {}", step);
+    let up = |t: f64| elevation_at(Instant::from_seconds_since_j2000(t)) > horizon;
+    let crossing = |mut before: f64, mut after: f64| {
+        let up_after = up(after);
+        while after - before > 1e-4 {
+            let mid = 0.5 * (before + after);
+            if up(mid) == up_after { after = mid } else { before = mid }
+        }
+        Instant::from_seconds_since_j2000(0.5 * (before + after))
+    };
+
+    let start = span.start().to_j2000_seconds();
+    let end = span.end().to_j2000_seconds();
+    let up_at_start = up(start);
+    let mut found = RiseSet { up_at_start, rise: None, set: None };
+    let (mut t, mut was_up) = (start, up_at_start);
+    while t < end && (found.rise.is_none() || found.set.is_none()) {
+        let next = (t + step).min(end);
+        let is_up = up(next);
+        match (was_up, is_up) {
+            (false, true) if found.rise.is_none() => found.rise = Some(crossing(t, next)),
+            (true, false) if found.set.is_none() => found.set = Some(crossing(t, next)),
+            _ => {}
+        }
+        (t, was_up) = (next, is_up);
+    }
+    found
+}
+
 /// Zenith: 0 = up (+Z), π/2 = horizontal, π = down (-Z)
 /// Azimuth: 0 = +X direction, π/2 = +Y direction
 fn quat_from_azimuth_zenith(azimuth_rad: f64, zenith_rad: f64) -> DQuat {
@@ -56,3 +227,107 @@ fn quat_from_azimuth_elevation(azimuth_rad: f64, inclination_rad: f64) -> DQuat
     // Convert to zenith or rotate directly
     DQuat::from_rotation_z(azimuth_rad) * DQuat::from_rotation_y(PI / 2.0 - inclination_rad)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foundations::time::Includes;
+    use std::f64::consts::PI;
+
+    fn observer() -> ReferenceFrame {
+        ReferenceFrame::from_position_yaw_pitch(DVec3::new(1.0e7, -3.0e6, 5.0e5), 0.9, 0.25)
+    }
+
+    #[test]
+    fn test_target_forward_has_zero_azimuth() {
+        let observer = observer();
+        let target = observer.universal_origin() + observer.local_forward() * 4.0e8;
+        let (azimuth, elevation) = azimuth_elevation(&observer, target);
+        assert!(azimuth.abs() < 1e-9, "{}", azimuth);
+        assert!(elevation.abs() < 1e-9, "{}", elevation);
+        assert!((range(&observer, target) - 4.0e8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_target_up_has_full_elevation() {
+        let observer = observer();
+        let target = observer.universal_origin() + observer.local_up() * 2.0e3;
+        let (_, elevation) = azimuth_elevation(&observer, target);
+        assert!((elevation - FRAC_PI_2).abs() < 1e-9, "{}", elevation);
+
+        let right = observer.universal_origin() + observer.local_right() * 2.0e3;
+        let (azimuth, _) = azimuth_elevation(&observer, right);
+        assert!((azimuth - FRAC_PI_2).abs() < 1e-9, "{}", azimuth);
+        assert!((angular_separation(&observer, target, right) - FRAC_PI_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_range_rate() {
+        let observer = ReferenceFrame::IDENTITY;
+        let target = DVec3::new(3.0, 4.0, 0.0);
+        assert!((range_rate(&observer, DVec3::ZERO, target, DVec3::new(3.0, 4.0, 0.0)) - 5.0).abs() < 1e-12);
+        assert!((range_rate(&observer, DVec3::new(3.0, 4.0, 0.0), target, DVec3::ZERO) + 5.0).abs() < 1e-12);
+        // Moving across the line of sight doesn't change range
+        assert!(range_rate(&observer, DVec3::ZERO, target, DVec3::new(-4.0, 3.0, 7.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_light_time() {
+        // Crossing the line of sight an AU out, at 100 km/s
+        let (range, speed) = (1.495978707e11, 1.0e5);
+        let position_at = |time: Instant| DVec3::new(speed * time.to_j2000_seconds(), range, 0.0);
+        let time = Instant::from_seconds_since_j2000(0.0);
+
+        let apparent = light_time_corrected(DVec3::ZERO, time, position_at);
+        let expected_shift = speed * range / SPEED_OF_LIGHT;
+        assert!((apparent.x + expected_shift).abs() < 1e-6 * expected_shift, "{} vs {}", apparent.x, -expected_shift);
+        assert_eq!(apparent.y, range);
+    }
+
+    #[test]
+    fn test_phases() {
+        let star = DVec3::new(1.0e11, 0.0, 0.0);
+        let body = DVec3::ZERO;
+
+        // Between the body and the star: full
+        let full = phase_angle(DVec3::new(1.0e8, 0.0, 0.0), body, star);
+        assert!(full.abs() < 1e-9);
+        assert!((illuminated_fraction(full) - 1.0).abs() < 1e-12);
+
+        // Behind it: new
+        let new = phase_angle(DVec3::new(-1.0e8, 0.0, 0.0), body, star);
+        assert!((new - PI).abs() < 1e-9);
+        assert!(illuminated_fraction(new).abs() < 1e-12);
+
+        // Off to the side: half
+        let quarter = phase_angle(DVec3::new(0.0, 1.0e8, 0.0), body, star);
+        assert!((quarter - FRAC_PI_2).abs() < 1e-9);
+        assert!((illuminated_fraction(quarter) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_transit_window() {
+        // A circles the observer at a constant range, so its disk stays the same size,
+        // and lines up with B, far behind it, at t = 1000 s
+        let (range_a, radius_a, range_b, radius_b) = (1.0e5, 1.0e3, 1.0e6, 1.0e4);
+        let rate = 1.0e-4;
+        let positions = |time: Instant| {
+            let angle = rate * (time.to_j2000_seconds() - 1000.0);
+            (DVec3::ZERO, range_a * DVec3::new(angle.cos(), angle.sin(), 0.0), DVec3::new(range_b, 0.0, 0.0))
+        };
+        let half_width = ((radius_a / range_a).asin() + (radius_b / range_b).asin()) / rate;
+
+        let span = Span::new(Instant::from_seconds_since_j2000(0.0), Instant::from_seconds_since_j2000(3000.0), Includes::Both);
+        let transits = find_transits(&span, 60.0, &positions, radius_a, radius_b);
+        assert_eq!(transits.len(), 1);
+        let transit = &transits[0];
+        assert!((transit.ingress.to_j2000_seconds() - (1000.0 - half_width)).abs() < 1e-3, "{}", transit.ingress.to_j2000_seconds());
+        assert!((transit.egress.to_j2000_seconds() - (1000.0 + half_width)).abs() < 1e-3, "{}", transit.egress.to_j2000_seconds());
+        assert!(transit.a_in_front);
+
+        // Starting partway through cuts it off at the start
+        let span = Span::new(Instant::from_seconds_since_j2000(1000.0), Instant::from_seconds_since_j2000(3000.0), Includes::Both);
+        let transits = find_transits(&span, 60.0, &positions, radius_a, radius_b);
+        assert_eq!(transits[0].ingress.to_j2000_seconds(), 1000.0);
+    }
+}
@@ -0,0 +1,41 @@
+//! The planes angles can be measured from. Everything is simulated and stored against the
+//! J2000 ecliptic; the others are only for showing things in.
+
+use bevy::math::DMat3;
+use serde::{Deserialize, Serialize};
+
+/// Angle between the J2000 ecliptic and Earth's J2000 equator, in degrees.
+pub const J2000_OBLIQUITY: f64 = 23.4392911;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
+pub enum ReferencePlane {
+    /// The J2000 ecliptic, with +Z toward the ecliptic north pole.
+    #[default]
+    Ecliptic,
+    /// Earth's J2000 equator, with +Z toward the celestial north pole.
+    /// Both planes share +X, toward the vernal equinox.
+    Equatorial,
+}
+
+impl ReferencePlane {
+    pub const ALL: [ReferencePlane; 2] = [ReferencePlane::Ecliptic, ReferencePlane::Equatorial];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReferencePlane::Ecliptic => "Ecliptic",
+            ReferencePlane::Equatorial => "Equatorial",
+        }
+    }
+
+    /// Takes vectors in the simulation's ecliptic frame into this plane's frame.
+    pub fn from_ecliptic(&self) -> DMat3 {
+        match self {
+            ReferencePlane::Ecliptic => DMat3::IDENTITY,
+            ReferencePlane::Equatorial => DMat3::from_rotation_x(J2000_OBLIQUITY.to_radians()),
+        }
+    }
+
+    pub fn to_ecliptic(&self) -> DMat3 {
+        self.from_ecliptic().transpose()
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::math::{DMat4, DQuat, DVec3, Vec3};
+use bevy::math::{DMat3, DMat4, DQuat, DVec3, Vec3};
 use crate::foundations::reference_frame::ReferenceFrame;
 
 /// Represents a transformation from Reference Frame A to Reference Frame B
@@ -29,4 +29,27 @@ impl Transformation {
             mat: self.mat * pose.mat
         }
     }
+
+    /// A to B followed by `other`, B to C, giving A to C.
+    pub fn then(&self, other: &Transformation) -> Transformation {
+        (other.mat * self.mat).into()
+    }
+
+    /// B to A.
+    pub fn inverse(&self) -> Transformation {
+        rigid_inverse(&self.mat).into()
+    }
+}
+
+/// Inverts a rotation-plus-translation by transposing the rotation, rather than a general
+/// 4x4 inverse, so chains of frames stay orthonormal instead of drifting.
+pub(in crate::foundations::reference_frame) fn rigid_inverse(mat: &DMat4) -> DMat4 {
+    let rotation = DMat3::from_mat4(*mat).transpose();
+    let translation = -(rotation * mat.w_axis.truncate());
+    DMat4::from_cols(
+        rotation.x_axis.extend(0.0),
+        rotation.y_axis.extend(0.0),
+        rotation.z_axis.extend(0.0),
+        translation.extend(1.0),
+    )
 }
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone, Copy, PartialOrd, PartialEq, Default)]
 pub struct Instant(f64);
 
-// The instant of the J2000 epoch in Julian Days
+/// The instant of the J2000 epoch in Julian Days. The one definition; import it from here.
 pub(crate) const J2000_JD: f64 = 2451545.0;
 
 /// The number of seconds in a Julian Day
@@ -15,7 +15,7 @@ pub const JD_SECONDS_PER_JULIAN_DAY: f64 = 24.0 * 60.0 * 60.0;
 
 
 impl Instant {
-    pub const J2000: Self = Self(J2000_JD);
+    pub const J2000: Self = Self(0.0);
 
     #[inline(always)]
     pub fn from_julian_day(julian_day: f64) -> Self {
@@ -85,6 +85,7 @@ impl TimeLength {
     }
 }
 
+/// A stretch of time between two instants, in seconds since J2000.
 pub struct Span(f64, f64, Includes);
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
@@ -95,5 +96,37 @@ pub enum Includes {
 }
 
 impl Span {
+    pub fn new(start: Instant, end: Instant, includes: Includes) -> Self {
+        Self(start.to_j2000_seconds(), end.to_j2000_seconds(), includes)
+    }
+
+    pub fn start(&self) -> Instant {
+        Instant(self.0)
+    }
 
+    pub fn end(&self) -> Instant {
+        Instant(self.1)
+    }
+
+    pub fn includes(&self) -> Includes {
+        self.2
+    }
+
+    pub fn length(&self) -> TimeLength {
+        TimeLength(self.1 - self.0, self.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_j2000_constants() {
+        // The Unix epoch, 1970-01-01 00:00 UTC, which J2000 is 946,728,000 seconds after
+        assert_eq!(Instant::from_julian_day(2440587.5).to_j2000_seconds(), -946_728_000.0);
+        assert_eq!(Instant::from_seconds_since_j2000(-946_728_000.0).to_julian_day(), 2440587.5);
+        assert_eq!(Instant::from_julian_day(J2000_JD).to_j2000_seconds(), 0.0);
+        assert_eq!(Instant::from_seconds_since_j2000(JD_SECONDS_PER_JULIAN_DAY).to_julian_day(), J2000_JD + 1.0);
+    }
 }
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use bevy_egui::egui;
 use crate::util::format;
+use crate::util::units::Unit;
 
 pub fn despawn_entities_with<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
@@ -17,21 +18,96 @@ pub fn despawn_recursive_entities_with<T: Component>(
     }
 }
 
-pub fn stepper<S: AsRef<str>>(ui: &mut egui::Ui, label: S, mut value: &mut f64) {
+/// Returns the combined response of the buttons and drag value, so callers can tell when a drag ends.
+pub fn stepper<S: AsRef<str>>(ui: &mut egui::Ui, label: S, value: &mut f64) -> egui::Response {
     ui.horizontal(|ui| {
        ui.label(label.as_ref());
-        if ui.button("<<").clicked() { if *value > 0.0 { *value /= 10.0; } else { *value *= 10.0; } }
-        if ui.button("<").clicked() { *value = bump_decimal(*value, -1.0); }
-        ui.add(egui::DragValue::new(value)
+        let mut response = ui.button("<<");
+        if response.clicked() { if *value > 0.0 { *value /= 10.0; } else { *value *= 10.0; } }
+        let down = ui.button("<");
+        if down.clicked() { *value = bump_decimal(*value, -1.0); }
+        response = response.union(down);
+        response = response.union(ui.add(egui::DragValue::new(value)
             .speed(0.01)
             .range(f64::MIN..=f64::MAX)
             .fixed_decimals(1)
             .custom_formatter(|n, range| format::sci_not(n))
             .custom_parser(|s| format::sci_not_parser(s))
+        ));
+        response = response.union(sci_input(ui, value));
+        let up = ui.button(">");
+        if up.clicked() { *value = bump_decimal(*value, 1.0); }
+        response = response.union(up);
+        let big_up = ui.button(">>");
+        if big_up.clicked() { if *value > 0.0 { *value *= 10.0; } else { *value /= 10.0; } }
+        response.union(big_up)
+    }).inner
+}
+
+/// A stepper showing `value_si` in `unit`, with a dropdown to switch between units of the same quantity.
+/// `value_si` stays in SI; it's only written when the displayed value actually changes.
+pub fn unit_stepper<S: AsRef<str>>(ui: &mut egui::Ui, label: S, value_si: &mut f64, unit: &mut Unit) -> egui::Response {
+    ui.horizontal(|ui| {
+        let before = unit.from_si(*value_si);
+        let mut displayed = before;
+        let response = stepper(ui, label, &mut displayed);
+        if displayed != before {
+            *value_si = unit.to_si(displayed);
+        }
+
+        egui::ComboBox::from_id_salt(ui.id().with("unit"))
+            .selected_text(unit.symbol())
+            .width(50.0)
+            .show_ui(ui, |ui| {
+                for option in Unit::of_quantity(unit.quantity()) {
+                    ui.selectable_value(unit, *option, option.symbol());
+                }
+            });
+        response
+    }).inner
+}
+
+#[derive(Clone, Default)]
+struct SciInputState {
+    text: String,
+    error: bool,
+}
+
+/// A text box for typing numbers like "1.5 x 10 ^ 8" or "1.5e8".
+/// The value is only changed when the text is committed and parses; otherwise the box goes red.
+pub fn sci_input(ui: &mut egui::Ui, value: &mut f64) -> egui::Response {
+    ui.scope(|ui| {
+        let id = ui.id().with("sci_input");
+        let mut state = ui.data_mut(|data| data.get_temp::<SciInputState>(id)).unwrap_or_default();
+        let focused = ui.memory(|memory| memory.has_focus(id));
+        if !focused && !state.error {
+            state.text = format::sci_not(*value);
+        }
+
+        let mut response = ui.add(egui::TextEdit::singleline(&mut state.text)
+            .id(id)
+            .desired_width(110.0)
         );
-        if ui.button(">").clicked() { *value = bump_decimal(*value, 1.0); }
-        if ui.button(">>").clicked() { if *value > 0.0 { *value *= 10.0; } else { *value /= 10.0; } }
-    });
+        if response.changed() {
+            state.error = false;
+        }
+        if response.lost_focus() {
+            match format::sci_not_parser(&state.text) {
+                Some(parsed) => {
+                    *value = parsed;
+                    state.error = false;
+                    response.mark_changed();
+                }
+                None => state.error = true,
+            }
+        }
+        if state.error {
+            ui.painter().rect_stroke(response.rect, 2.0, egui::Stroke::new(1.5, egui::Color32::RED), egui::StrokeKind::Outside);
+        }
+
+        ui.data_mut(|data| data.insert_temp(id, state));
+        response
+    }).inner
 }
 
 fn bump_decimal(x: f64, direction: f64) -> f64 {
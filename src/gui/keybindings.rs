@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Everything that can be bound to a key.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    TogglePlay,
+    SpeedUp,
+    SlowDown,
+    Reverse,
+    StepForward,
+    FocusNext,
+    FocusPrevious,
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveAscend,
+    MoveDescend,
+    ToggleGrabCursor,
+    Screenshot,
+}
+
+impl Action {
+    pub const ALL: [Action; 15] = [
+        Action::TogglePlay,
+        Action::SpeedUp,
+        Action::SlowDown,
+        Action::Reverse,
+        Action::StepForward,
+        Action::FocusNext,
+        Action::FocusPrevious,
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveAscend,
+        Action::MoveDescend,
+        Action::ToggleGrabCursor,
+        Action::Screenshot,
+    ];
+
+    pub fn default_key(&self) -> KeyCode {
+        match self {
+            Action::TogglePlay => KeyCode::KeyP,
+            Action::SpeedUp => KeyCode::BracketRight,
+            Action::SlowDown => KeyCode::BracketLeft,
+            Action::Reverse => KeyCode::Backslash,
+            Action::StepForward => KeyCode::Period,
+            Action::FocusNext => KeyCode::Tab,
+            Action::FocusPrevious => KeyCode::Backspace,
+            Action::MoveForward => KeyCode::KeyW,
+            Action::MoveBackward => KeyCode::KeyS,
+            Action::MoveLeft => KeyCode::KeyA,
+            Action::MoveRight => KeyCode::KeyD,
+            Action::MoveAscend => KeyCode::Space,
+            Action::MoveDescend => KeyCode::ShiftLeft,
+            Action::ToggleGrabCursor => KeyCode::Backquote,
+            Action::Screenshot => KeyCode::F12,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::TogglePlay => "Play/Pause",
+            Action::SpeedUp => "Speed Up",
+            Action::SlowDown => "Slow Down",
+            Action::Reverse => "Reverse",
+            Action::StepForward => "Step Forward",
+            Action::FocusNext => "Focus Next Body",
+            Action::FocusPrevious => "Focus Previous Body",
+            Action::MoveForward => "Move Forward",
+            Action::MoveBackward => "Move Backward",
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::MoveAscend => "Move Up",
+            Action::MoveDescend => "Move Down",
+            Action::ToggleGrabCursor => "Grab Cursor",
+            Action::Screenshot => "Screenshot",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| format!("{:?}", action) == name)
+    }
+}
+
+/// Keys that can be bound, saved by their `KeyCode` variant name.
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+    KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+    KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+    KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Enter, KeyCode::Backspace, KeyCode::Escape,
+    KeyCode::Insert, KeyCode::Delete, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::BracketLeft, KeyCode::BracketRight, KeyCode::Backslash, KeyCode::Backquote,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Semicolon, KeyCode::Quote,
+    KeyCode::Minus, KeyCode::Equal,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::NumpadAdd, KeyCode::NumpadSubtract, KeyCode::NumpadMultiply, KeyCode::NumpadDivide,
+    KeyCode::NumpadEnter, KeyCode::NumpadDecimal,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|key| key_name(*key) == name)
+}
+
+pub fn is_bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+/// Saved as a table of action names to key names. Anything unrecognized keeps its default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct Keybindings {
+    keys: HashMap<Action, KeyCode>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL.into_iter().map(|action| (action, action.default_key())).collect(),
+        }
+    }
+}
+
+impl Keybindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys.get(&action).copied().unwrap_or(action.default_key())
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.keys.insert(action, key);
+    }
+
+    pub fn pressed(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.pressed(self.key(action))
+    }
+
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.key(action))
+    }
+}
+
+impl From<BTreeMap<String, String>> for Keybindings {
+    fn from(table: BTreeMap<String, String>) -> Self {
+        let mut bindings = Self::default();
+        for (action_name, key) in table {
+            let Some(action) = Action::from_name(&action_name) else {
+                warn!("Ignoring binding for unknown action {}", action_name);
+                continue;
+            };
+            match key_from_name(&key) {
+                Some(key) => bindings.bind(action, key),
+                None => warn!("Unknown key {} for {}, using {}", key, action_name, key_name(action.default_key())),
+            }
+        }
+        bindings
+    }
+}
+
+impl From<Keybindings> for BTreeMap<String, String> {
+    fn from(bindings: Keybindings) -> Self {
+        Action::ALL.into_iter()
+            .map(|action| (format!("{:?}", action), key_name(bindings.key(action))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::settings::Settings;
+
+    #[test]
+    fn test_custom_bindings_with_fallback() {
+        let settings: Settings = toml::from_str(r#"
+            [keybindings]
+            TogglePlay = "Space"
+            FocusNext = "KeyN"
+            SpeedUp = "NotAKey"
+            Teleport = "KeyT"
+        "#).unwrap();
+        let bindings = &settings.keybindings;
+        assert_eq!(bindings.key(Action::TogglePlay), KeyCode::Space);
+        assert_eq!(bindings.key(Action::FocusNext), KeyCode::KeyN);
+        assert_eq!(bindings.key(Action::SpeedUp), Action::SpeedUp.default_key());
+        assert_eq!(bindings.key(Action::MoveForward), KeyCode::KeyW);
+
+        let saved = toml::to_string_pretty(&settings).unwrap();
+        let reloaded: Settings = toml::from_str(&saved).unwrap();
+        assert_eq!(reloaded.keybindings, settings.keybindings);
+    }
+}
@@ -3,26 +3,38 @@ mod save_load;
 
 use std::fs;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy::window::{ClosingWindow, WindowCloseRequested};
 use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
 use serde::{Deserialize, Serialize};
 use crate::gui::app::AppState;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::planetarium::autosave;
+use crate::gui::settings::Settings;
+use crate::gui::util::ensure_folders;
 
 #[derive(Resource)]
 pub struct UiState {
     pub quit_requested: bool,
     pub current_save: Option<SaveFileMeta>,
+    /// A save that has a newer autosave, waiting on whether to recover it.
+    pub recover_prompt: Option<(SaveFileMeta, PathBuf)>,
+    /// Why the last save couldn't be opened.
+    pub load_error: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Resource, Debug)]
+#[derive(Serialize, Deserialize, Resource, Debug, Clone)]
 pub struct TagState {
     pub shown: bool,
     pub trajectory: bool,
     pub members: Vec<String>,
+    /// While any tag is soloed, only members of soloed tags are drawn. Not saved.
+    #[serde(skip)]
+    pub solo: bool,
+    /// Members of a muted tag are never drawn. Not saved.
+    #[serde(skip)]
+    pub muted: bool,
 }
 
 impl Default for TagState {
@@ -31,6 +43,8 @@ impl Default for TagState {
             shown: false,
             trajectory: false,
             members: Vec::new(),
+            solo: false,
+            muted: false,
         }
     }
 }
@@ -40,6 +54,8 @@ impl Default for UiState {
         Self {
             quit_requested: false,
             current_save: None,
+            recover_prompt: None,
+            load_error: None,
         }
     }
 }
@@ -81,10 +97,7 @@ pub fn main_menu(
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
     
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     egui::CentralPanel::default().show(ctx, |ui| {
         ui.vertical_centered(|ui| {
@@ -138,52 +151,55 @@ pub struct SaveFileMeta {
 }
 
 pub fn load_planetarium_files(mut files: ResMut<PlanetariumFiles>) {
-    files.templates.clear();
-    files.saves.clear();
-
-    let template_files = fs::read_dir("data/templates").unwrap();
-    let save_files = fs::read_dir("data/saves").unwrap();
-
-    for file in template_files {
-        let file = file.unwrap();
-        if file.path().is_file() {
-            let path = file.path();
-            let path2 = path.clone();
-            let name = path2.file_name().unwrap().to_str().unwrap();
-            files.templates.push(SaveFileMeta {
-                path,
-                file_name: name.to_string(),
-            })
+    *files = PlanetariumFiles::read(Path::new("data"));
+    // info!("{}, {}", files.templates.len(), files.saves.len());
+}
+
+impl PlanetariumFiles {
+    /// The templates and saves under `data`, making their folders if they aren't there yet.
+    pub fn read(data: &Path) -> Self {
+        let (templates, saves) = (data.join("templates"), data.join("saves"));
+        if let Err(e) = ensure_folders(&[&templates, &saves]) {
+            warn!("Couldn't make the save folders in {}: {}", data.display(), e);
         }
-    }
-    for file in save_files {
-        let file = file.unwrap();
-        if file.path().is_file() {
-            let path = file.path();
-            let path2 = path.clone();
-            let name = path2.file_name().unwrap().to_str().unwrap();
-            files.saves.push(SaveFileMeta {
-                path,
-                file_name: name.to_string(),
-            })
+        Self {
+            templates: list_files(&templates, |_| true),
+            saves: list_files(&saves, |name| !autosave::is_autosave(name)),
         }
     }
-    // info!("{}, {}", files.templates.len(), files.saves.len());
+}
+
+/// Files directly in `folder` whose names pass `keep`. A folder that can't be read has none.
+fn list_files(folder: &Path, keep: impl Fn(&str) -> bool) -> Vec<SaveFileMeta> {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Couldn't read {}: {}", folder.display(), e);
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_string_lossy().to_string();
+            keep(&file_name).then_some(SaveFileMeta { path, file_name })
+        })
+        .collect()
 }
 
 pub fn settings_menu(
     mut contexts: EguiContexts,
     mut settings: ResMut<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
     mut next_menu: ResMut<NextState<MenuState>>,
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
     
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     egui::CentralPanel::default().show(ctx, |ui| {
         if ui.button("Back").clicked() {
@@ -195,7 +211,7 @@ pub fn settings_menu(
 
             ui.separator();
 
-            settings::settings_panel(&mut settings, ui);
+            settings::settings_panel(&mut settings, &keys, ui);
         });
     });
 }
@@ -230,3 +246,21 @@ pub fn close_when_requested(
 
     let _ = fs::write("data/settings.toml", toml::to_string_pretty(settings.deref()).unwrap());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_run_makes_folders() {
+        let data = std::env::temp_dir().join(format!("first_run_{}", std::process::id())).join("data");
+        let files = PlanetariumFiles::read(&data);
+        assert!(files.templates.is_empty() && files.saves.is_empty());
+        assert!(data.join("templates").is_dir() && data.join("saves").is_dir());
+
+        fs::write(data.join("saves").join("mine.toml"), "").unwrap();
+        let files = PlanetariumFiles::read(&data);
+        assert_eq!(files.saves.iter().map(|save| save.file_name.as_str()).collect::<Vec<_>>(), vec!["mine.toml"]);
+        fs::remove_dir_all(data.parent().unwrap()).unwrap();
+    }
+}
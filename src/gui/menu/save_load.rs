@@ -4,7 +4,8 @@ use bevy_egui::egui::Ui;
 use crate::body::universe::Universe;
 use crate::gui::app::AppState;
 use crate::gui::menu::{MenuState, PlanetariumFiles, SaveFileMeta, UiState};
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::planetarium::autosave;
+use crate::gui::settings::Settings;
 
 pub fn planetarium_menu(
     mut contexts: EguiContexts,
@@ -19,10 +20,7 @@ pub fn planetarium_menu(
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     egui::CentralPanel::default().show(ctx, |ui| {
         // Top button bar
@@ -91,6 +89,59 @@ pub fn planetarium_menu(
                 });
         });
     });
+
+    recover_prompt(ctx, &mut ui_state, &mut next_app_state);
+    load_error(ctx, &mut ui_state);
+}
+
+fn load_error(ctx: &egui::Context, ui_state: &mut ResMut<UiState>) {
+    let Some(message) = ui_state.load_error.clone() else { return };
+    egui::Window::new("Couldn't Open")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(message);
+            if ui.button("OK").clicked() {
+                ui_state.load_error = None;
+            }
+        });
+}
+
+fn recover_prompt(
+    ctx: &egui::Context,
+    ui_state: &mut ResMut<UiState>,
+    next_app_state: &mut ResMut<NextState<AppState>>,
+) {
+    let Some((save, autosave)) = ui_state.recover_prompt.clone() else { return };
+    let mut choice = None;
+    egui::Window::new("Recover Autosave")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.label(format!("There's an autosave of {} newer than the file itself.", save.file_name));
+            ui.horizontal(|ui| {
+                if ui.button("Recover Autosave").clicked() {
+                    // Keeps the original name so later autosaves go to the same place
+                    choice = Some(Some(SaveFileMeta { path: autosave.clone(), file_name: save.file_name.clone() }));
+                }
+                if ui.button("Open Original").clicked() {
+                    choice = Some(Some(save.clone()));
+                }
+                if ui.button("Cancel").clicked() {
+                    choice = Some(None);
+                }
+            });
+        });
+
+    if let Some(choice) = choice {
+        ui_state.recover_prompt = None;
+        if let Some(save) = choice {
+            ui_state.current_save = Some(save);
+            next_app_state.set(AppState::PlanetariumLoading);
+        }
+    }
 }
 
 fn display_saves_list(
@@ -119,8 +170,13 @@ fn display_saves_list(
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if ui.add_sized([60.0, 24.0], egui::Button::new(load_label)).clicked() {
-                            ui_state.current_save = Some((*save).clone());
-                            next_app_state.set(AppState::PlanetariumLoading)
+                            match autosave::newer_autosave(save) {
+                                Some(autosave) => ui_state.recover_prompt = Some(((*save).clone(), autosave)),
+                                None => {
+                                    ui_state.current_save = Some((*save).clone());
+                                    next_app_state.set(AppState::PlanetariumLoading)
+                                }
+                            }
                         }
                     });
                 });
@@ -1,9 +1,12 @@
+use std::path::PathBuf;
 use bevy::prelude::*;
 use bevy_egui::egui;
 use bevy_egui::egui::Ui;
+use crate::foundations::reference_frame::plane::ReferencePlane;
+use crate::gui::keybindings::{self, Action};
 use crate::gui::settings::{DisplayGlow, DisplayQuality, Settings, UiTheme};
 
-pub fn settings_panel(mut settings: &mut ResMut<Settings>, ui: &mut Ui) {
+pub fn settings_panel(mut settings: &mut ResMut<Settings>, keys: &ButtonInput<KeyCode>, ui: &mut Ui) {
     ui.vertical(|ui| {
         ui.heading("Display");
         egui::ComboBox::from_label("Quality")
@@ -25,6 +28,13 @@ pub fn settings_panel(mut settings: &mut ResMut<Settings>, ui: &mut Ui) {
             .text("Trajectory Fade Min"));
         ui.add(egui::Slider::new(&mut settings.display.trajectory_fade_max, 0.0..=100.0)
             .text("Trajectory Fade Max"));
+        egui::ComboBox::from_label("Reference Plane")
+            .selected_text(settings.display.reference_plane.label())
+            .show_ui(ui, |ui| {
+                for plane in ReferencePlane::ALL {
+                    ui.selectable_value(&mut settings.display.reference_plane, plane, plane.label());
+                }
+            });
     });
 
     ui.separator();
@@ -44,15 +54,105 @@ pub fn settings_panel(mut settings: &mut ResMut<Settings>, ui: &mut Ui) {
             .show_ui(ui, |ui| {
                 ui.selectable_value(&mut settings.ui.theme, UiTheme::Light, "Light");
                 ui.selectable_value(&mut settings.ui.theme, UiTheme::Dark, "Dark");
+                ui.selectable_value(&mut settings.ui.theme, UiTheme::HighContrast, "High Contrast");
             });
+        ui.add(egui::Slider::new(&mut settings.ui.ui_scale, 0.5..=3.0)
+            .text("UI Scale")
+            .step_by(0.05));
+        ui.add(egui::Slider::new(&mut settings.ui.label_font_size, 8.0..=32.0)
+            .text("Label Font Size")
+            .step_by(1.0));
+        ui.checkbox(&mut settings.ui.show_compass, "Axis Compass");
+        ui.checkbox(&mut settings.ui.show_relative_velocity, "Relative Velocity")
+            .on_hover_text("While circling a body, how fast the camera is moving relative to it");
+    });
+
+    ui.separator();
+    ui.vertical(|ui| {
+        ui.heading("Autosave");
+        ui.checkbox(&mut settings.autosave.enabled, "Enabled");
+        ui.add(egui::Slider::new(&mut settings.autosave.interval_seconds, 10.0..=600.0)
+            .text("Interval (s)"));
+        ui.add(egui::Slider::new(&mut settings.autosave.backups, 0..=10)
+            .text("Backups Kept"));
+    });
+
+    ui.separator();
+    ui.vertical(|ui| {
+        ui.heading("Startup");
+        let mut default_scenario = settings.startup.default_scenario.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.label("Default Scenario");
+            if ui.text_edit_singleline(&mut default_scenario).changed() {
+                settings.startup.default_scenario = Some(default_scenario.clone()).filter(|path| !path.is_empty()).map(PathBuf::from);
+            }
+        });
+        ui.checkbox(&mut settings.startup.skip_menu, "Skip Menu")
+            .on_hover_text("Open the default scenario at launch instead of this menu");
+    });
+
+    ui.separator();
+    ui.vertical(|ui| {
+        ui.heading("Screenshots");
+        ui.checkbox(&mut settings.screenshot.hide_ui, "Hide UI");
+        ui.checkbox(&mut settings.screenshot.transparent_background, "Transparent Background");
     });
 
     ui.separator();
     ui.vertical(|ui| {
         ui.heading("Windows");
+        ui.checkbox(&mut settings.windows.controls, "Controls");
+        ui.checkbox(&mut settings.windows.settings, "Settings")
+            .on_hover_text("Once hidden, turn it back on from the main menu's settings");
         ui.checkbox(&mut settings.windows.spin, "Spin Gravity Calculator");
         ui.checkbox(&mut settings.windows.body_edit, "Body Edit");
         ui.checkbox(&mut settings.windows.body_info, "Body Info");
-        ui.checkbox(&mut settings.windows.camera, "Camera Settings")
+        ui.checkbox(&mut settings.windows.camera, "Camera Settings");
+        ui.checkbox(&mut settings.windows.diagnostics, "Frame Diagnostics");
+        ui.checkbox(&mut settings.windows.timelapse, "Time-Lapse");
+        ui.checkbox(&mut settings.windows.hohmann, "Hohmann Transfer Calculator");
+        ui.checkbox(&mut settings.windows.csv_import, "Import Bodies");
+        ui.checkbox(&mut settings.windows.save_diff, "Compare Save")
+    });
+
+    ui.separator();
+    ui.vertical(|ui| {
+        ui.heading("Keybindings");
+        keybindings_grid(settings, keys, ui);
     });
 }
+
+/// Clicking a binding waits for the next key press to replace it. Escape cancels.
+fn keybindings_grid(settings: &mut ResMut<Settings>, keys: &ButtonInput<KeyCode>, ui: &mut Ui) {
+    let capture_id = ui.id().with("rebinding");
+    let mut capturing: Option<Action> = ui.data(|data| data.get_temp(capture_id)).flatten();
+
+    if let Some(action) = capturing {
+        if keys.just_pressed(KeyCode::Escape) {
+            capturing = None;
+        } else if let Some(key) = keys.get_just_pressed().copied().find(|key| keybindings::is_bindable(*key)) {
+            settings.keybindings.bind(action, key);
+            capturing = None;
+        }
+    }
+
+    egui::Grid::new("keybindings").striped(true).show(ui, |ui| {
+        for action in Action::ALL {
+            ui.label(action.label());
+            let text = if capturing == Some(action) {
+                "Press a key...".to_string()
+            } else {
+                keybindings::key_name(settings.keybindings.key(action))
+            };
+            if ui.selectable_label(capturing == Some(action), text).clicked() {
+                capturing = Some(action);
+            }
+            if ui.small_button("Default").clicked() {
+                settings.keybindings.bind(action, action.default_key());
+            }
+            ui.end_row();
+        }
+    });
+
+    ui.data_mut(|data| data.insert_temp(capture_id, capturing));
+}
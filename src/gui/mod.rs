@@ -4,6 +4,7 @@ pub(crate) mod body;
 pub mod util;
 pub mod app;
 mod settings;
+mod keybindings;
 mod splash;
 pub mod common;
 pub mod horizons;
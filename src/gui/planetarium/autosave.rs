@@ -0,0 +1,161 @@
+//! Periodic saves of the open universe, so a crash doesn't lose edits.
+//!
+//! The latest goes to `<name>.autosave.em` in the saves folder, and the ones before it
+//! shift along to `<name>.autosave.1.em`, `<name>.autosave.2.em` and so on.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use crate::body::appearance::Appearance;
+use crate::body::motive::info::{BodyInfo, BodyProperties};
+use crate::body::motive::Motive;
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::deletion::DeleteBody;
+use crate::body::universe::edit::Edit;
+use crate::body::universe::save::{UniverseFileContents, UniversePhysics, ViewSettings};
+use crate::body::universe::save_sqlite;
+use crate::body::universe::scenario::Scenario;
+use crate::gui::planetarium::CameraBookmarks;
+use crate::gui::menu::{SaveFileMeta, UiState};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
+
+pub const SAVE_DIR: &str = "data/saves";
+
+#[derive(Resource, Default)]
+pub struct Autosave {
+    /// Something was edited since the last autosave.
+    dirty: bool,
+    since_last: f32,
+}
+
+/// `backup` 0 is the latest autosave, higher numbers are older.
+pub fn autosave_path(dir: &Path, name: &str, backup: usize) -> PathBuf {
+    if backup == 0 {
+        dir.join(format!("{}.autosave.em", name))
+    } else {
+        dir.join(format!("{}.autosave.{}.em", name, backup))
+    }
+}
+
+pub fn is_autosave(file_name: &str) -> bool {
+    file_name.contains(".autosave.")
+}
+
+/// Autosaves are named after the file the universe was loaded from.
+pub fn autosave_name(save: Option<&SaveFileMeta>) -> String {
+    save.and_then(|save| Path::new(&save.file_name).file_stem())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("untitled")
+        .to_string()
+}
+
+/// Which numbered backup of `name` this file is, if any.
+fn backup_number(file_name: &str, name: &str) -> Option<usize> {
+    file_name.strip_prefix(name)?
+        .strip_prefix(".autosave.")?
+        .strip_suffix(".em")?
+        .parse().ok()
+}
+
+/// Shifts every autosave of `name` one number older to make room for a new latest one,
+/// deleting any that would be older than `keep`.
+pub fn rotate_backups(dir: &Path, name: &str, keep: usize) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(number) = file_name.to_str().and_then(|file_name| backup_number(file_name, name)) else { continue };
+        if number >= keep {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    for number in (1..keep).rev() {
+        let from = autosave_path(dir, name, number);
+        if from.exists() {
+            fs::rename(from, autosave_path(dir, name, number + 1))?;
+        }
+    }
+    let latest = autosave_path(dir, name, 0);
+    if keep > 0 && latest.exists() {
+        fs::rename(latest, autosave_path(dir, name, 1))?;
+    }
+    Ok(())
+}
+
+/// The latest autosave for a save file, if it was written after the file itself.
+pub fn newer_autosave(save: &SaveFileMeta) -> Option<PathBuf> {
+    let autosave = autosave_path(Path::new(SAVE_DIR), &autosave_name(Some(save)), 0);
+    let autosaved = fs::metadata(&autosave).and_then(|meta| meta.modified()).ok()?;
+    let saved = fs::metadata(&save.path).and_then(|meta| meta.modified()).ok()?;
+    (autosaved > saved).then_some(autosave)
+}
+
+pub fn reset_autosave(mut commands: Commands) {
+    commands.insert_resource(Autosave::default());
+}
+
+pub fn autosave(
+    mut autosave: ResMut<Autosave>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    ui_state: Res<UiState>,
+    mut edits: MessageReader<Edit>,
+    mut deletions: MessageReader<DeleteBody>,
+    sim_time: Res<SimTime>,
+    view_settings: Res<ViewSettings>,
+    physics: Res<UniversePhysics>,
+    scenario: Res<Scenario>,
+    bookmarks: Res<CameraBookmarks>,
+    bodies: Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>,
+) {
+    if edits.read().count() + deletions.read().count() > 0 || (bookmarks.is_changed() && !bookmarks.is_added()) {
+        autosave.dirty = true;
+    }
+    if !settings.autosave.enabled { return; }
+
+    autosave.since_last += time.delta_secs();
+    if autosave.since_last < settings.autosave.interval_seconds { return; }
+    autosave.since_last = 0.0;
+    if !autosave.dirty { return; }
+
+    let dir = PathBuf::from(SAVE_DIR);
+    let name = autosave_name(ui_state.current_save.as_ref());
+    if let Err(e) = rotate_backups(&dir, &name, settings.autosave.backups) {
+        warn!("Couldn't rotate autosaves for {}: {}", name, e);
+    }
+    let contents = UniverseFileContents::snapshot(&bodies, &sim_time, &view_settings, &physics, &scenario, &bookmarks.0);
+    match save_sqlite::save_to_em(&autosave_path(&dir, &name, 0), &contents) {
+        Ok(()) => autosave.dirty = false,
+        Err(e) => warn!("Autosave failed: {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_and_prune() {
+        let dir = std::env::temp_dir().join(format!("autosave_rotation_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let read = |backup: usize| fs::read_to_string(autosave_path(&dir, "world", backup)).ok();
+
+        // Left over from when more were kept, and another universe's autosave
+        fs::write(autosave_path(&dir, "world", 5), "stale").unwrap();
+        fs::write(autosave_path(&dir, "other", 1), "other").unwrap();
+
+        for contents in ["a", "b", "c", "d"] {
+            rotate_backups(&dir, "world", 2).unwrap();
+            fs::write(autosave_path(&dir, "world", 0), contents).unwrap();
+        }
+        assert_eq!(read(0).as_deref(), Some("d"));
+        assert_eq!(read(1).as_deref(), Some("c"));
+        assert_eq!(read(2).as_deref(), Some("b"));
+        assert_eq!(read(3), None);
+        assert_eq!(read(5), None);
+        assert!(autosave_path(&dir, "other", 1).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
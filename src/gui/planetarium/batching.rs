@@ -0,0 +1,324 @@
+//! Draws crowds of minor debug balls, like an asteroid belt, in one draw per material instead
+//! of one entity draw each. Each batch's mesh holds a unit ball per member, tagged with the
+//! member's index, and only changes when the batch changes size. Where each ball goes is kept
+//! in an instance buffer the vertex shader reads, so moving them only uploads a `Vec4` each.
+//!
+//! Batched bodies keep their entity and `Transform`, so labels and everything else still
+//! work on them; only their `Mesh3d` and material move onto the shared batch.
+
+use std::collections::HashMap;
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::visibility::NoFrustumCulling;
+use bevy::light::NotShadowCaster;
+use bevy::mesh::{Indices, MeshVertexAttribute, MeshVertexBufferLayoutRef, PrimitiveTopology};
+use bevy::pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, RenderPipelineDescriptor, SpecializedMeshPipelineError, VertexFormat};
+use bevy::render::storage::ShaderStorageBuffer;
+use bevy::shader::ShaderRef;
+use crate::body::appearance::Appearance;
+use crate::body::universe::Minor;
+use crate::body::SimulationObject;
+
+/// Below this many minor debug balls, each is drawn on its own.
+pub const BATCHING_THRESHOLD: usize = 500;
+
+/// Subdivisions of each ball in a batch. They're small on screen when there are this many.
+const BATCH_SUBDIVISIONS: u32 = 1;
+
+const SHADER_PATH: &str = "shaders/ball_batch.wgsl";
+
+/// Which member of the batch a vertex belongs to, indexing its instance buffer.
+pub const ATTRIBUTE_BALL: MeshVertexAttribute = MeshVertexAttribute::new("Ball", 988_540_917, VertexFormat::Uint32);
+
+/// A debug ball's material, with its balls placed by the instance buffer.
+pub type BallBatchMaterial = ExtendedMaterial<StandardMaterial, BallInstances>;
+
+/// Where each ball in a batch is and how big, as `xyz` and `w`.
+#[derive(Asset, AsBindGroup, Reflect, Clone)]
+pub struct BallInstances {
+    #[storage(100, read_only)]
+    instances: Handle<ShaderStorageBuffer>,
+}
+
+impl MaterialExtension for BallInstances {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            ATTRIBUTE_BALL.at_shader_location(8),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// A body drawn as part of a `BallBatch`.
+#[derive(Component)]
+pub struct Batched;
+
+/// One mesh holding a ball for every member, all sharing a material.
+#[derive(Component)]
+pub struct BallBatch {
+    pub members: Vec<Entity>,
+    /// The material the members were drawn with on their own.
+    standard: Handle<StandardMaterial>,
+    instances: Handle<ShaderStorageBuffer>,
+}
+
+/// The shape every ball in a batch is copied from.
+pub struct BatchBase {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl BatchBase {
+    pub fn new() -> Self {
+        let sphere = Sphere::new(1.0).mesh().ico(BATCH_SUBDIVISIONS).unwrap();
+        let positions = sphere.attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|positions| positions.as_float3())
+            .unwrap()
+            .to_vec();
+        let normals = sphere.attribute(Mesh::ATTRIBUTE_NORMAL)
+            .and_then(|normals| normals.as_float3())
+            .unwrap()
+            .to_vec();
+        let indices = sphere.indices().unwrap().iter().map(|index| index as u32).collect();
+        Self { positions, normals, indices }
+    }
+
+    /// A mesh with `count` unit balls at the origin, each tagged with its index.
+    pub fn mesh(&self, count: usize) -> Mesh {
+        let vertices = self.positions.len() as u32;
+        let indices = (0..count as u32)
+            .flat_map(|ball| self.indices.iter().map(move |index| index + ball * vertices))
+            .collect();
+        let balls: Vec<u32> = (0..count as u32)
+            .flat_map(|ball| std::iter::repeat_n(ball, vertices as usize))
+            .collect();
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.repeat(count))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.repeat(count))
+            .with_inserted_attribute(ATTRIBUTE_BALL, balls)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+
+    /// Each member's position and radius, in the order of the balls in `mesh`.
+    /// Balls are only ever scaled evenly, so the normals stay as they are.
+    pub fn write_instances<'a>(instances: &mut Vec<Vec4>, transforms: impl Iterator<Item = &'a Transform>) {
+        instances.clear();
+        instances.extend(transforms.map(|transform| transform.translation.extend(transform.scale.x)));
+    }
+}
+
+impl Default for BatchBase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Once there are enough minor debug balls, moves them into batches by material.
+/// Bodies dressed again after being batched (such as by an edit) are taken back out.
+pub fn batch_minor_balls(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut batch_materials: ResMut<Assets<BallBatchMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    base: Local<BatchBase>,
+    unbatched: Query<(Entity, &Appearance, &MeshMaterial3d<StandardMaterial>), (With<Minor>, With<Mesh3d>, Without<Batched>)>,
+    redressed: Query<Entity, (With<Batched>, Added<Mesh3d>)>,
+    mut batches: Query<&mut BallBatch>,
+) {
+    for entity in redressed.iter() {
+        commands.entity(entity).remove::<Batched>();
+        for mut batch in batches.iter_mut() {
+            batch.members.retain(|member| *member != entity);
+        }
+    }
+
+    let balls: Vec<_> = unbatched.iter()
+        .filter(|(_, appearance, _)| matches!(appearance, Appearance::DebugBall(_)))
+        .collect();
+    let already_batched = batches.iter().map(|batch| batch.members.len()).sum::<usize>();
+    if balls.is_empty() || balls.len() + already_batched < BATCHING_THRESHOLD {
+        return;
+    }
+
+    let mut by_material: HashMap<Handle<StandardMaterial>, Vec<Entity>> = HashMap::new();
+    for (entity, _, material) in balls {
+        commands.entity(entity)
+            .remove::<(Mesh3d, MeshMaterial3d<StandardMaterial>)>()
+            .insert(Batched);
+        by_material.entry(material.0.clone()).or_default().push(entity);
+    }
+
+    for mut batch in batches.iter_mut() {
+        if let Some(entities) = by_material.remove(&batch.standard) {
+            batch.members.extend(entities);
+        }
+    }
+    for (standard, members) in by_material {
+        let count = members.len();
+        let instances = buffers.add(ShaderStorageBuffer::from(vec![Vec4::ZERO; count]));
+        let material = batch_materials.add(BallBatchMaterial {
+            base: standard_materials.get(&standard).cloned().unwrap_or_default(),
+            extension: BallInstances { instances: instances.clone() },
+        });
+        commands.spawn((
+            BallBatch { members, standard, instances },
+            Mesh3d(meshes.add(base.mesh(count))),
+            MeshMaterial3d(material),
+            Transform::default(),
+            NoFrustumCulling,
+            // Shadows are drawn with the stock vertex shader, which would leave every ball at the origin
+            NotShadowCaster,
+        ));
+    }
+}
+
+/// Uploads where each batch's members were just positioned.
+/// Batches where nothing moved, such as while paused, are left alone.
+pub fn update_batches(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut materials: ResMut<Assets<BallBatchMaterial>>,
+    base: Local<BatchBase>,
+    mut batches: Query<(Entity, &mut BallBatch, &Mesh3d, &MeshMaterial3d<BallBatchMaterial>)>,
+    members: Query<Ref<Transform>, (With<Batched>, With<SimulationObject>)>,
+) {
+    for (entity, mut batch, mesh, material) in batches.iter_mut() {
+        batch.members.retain(|member| members.contains(*member));
+        if batch.members.is_empty() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let count = batch.members.len();
+        let resized = meshes.get(&mesh.0).is_some_and(|mesh| mesh.count_vertices() != base.positions.len() * count);
+        let moved = batch.members.iter().any(|member| members.get(*member).is_ok_and(|transform| transform.is_changed()));
+        if !resized && !moved {
+            continue;
+        }
+        if resized {
+            if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                *mesh = base.mesh(count);
+            }
+            // Touched so its bind group is made again around the resized buffer
+            let _ = materials.get_mut(&material.0);
+        }
+        let Some(buffer) = buffers.get_mut(&batch.instances) else { continue };
+        let mut instances = Vec::with_capacity(count);
+        BatchBase::write_instances(&mut instances, batch.members.iter().filter_map(|member| members.get(*member).ok().map(Ref::into_inner)));
+        buffer.set_data(instances);
+    }
+}
+
+pub fn unload_batches(mut commands: Commands, batches: Query<Entity, With<BallBatch>>) {
+    for entity in batches.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant as StdInstant;
+    use crate::body::appearance::{AppearanceColor, DebugBall};
+
+    #[test]
+    fn test_batch_follows_members() {
+        let base = BatchBase::new();
+        let transforms = [
+            Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)),
+            Transform::from_xyz(0.0, -5.0, 1.0),
+        ];
+        let mesh = base.mesh(transforms.len());
+        assert_eq!(mesh.count_vertices(), base.positions.len() * 2);
+        assert_eq!(mesh.indices().unwrap().len(), base.indices.len() * 2);
+        // Every vertex of the second ball looks up the second instance
+        let Some(bevy::mesh::VertexAttributeValues::Uint32(balls)) = mesh.attribute(ATTRIBUTE_BALL) else { panic!("No ball indices") };
+        assert!(balls[base.positions.len()..].iter().all(|ball| *ball == 1));
+
+        let mut instances = Vec::new();
+        BatchBase::write_instances(&mut instances, transforms.iter());
+        assert_eq!(instances, vec![Vec4::new(10.0, 0.0, 0.0, 2.0), Vec4::new(0.0, -5.0, 1.0, 1.0)]);
+    }
+
+    /// A belt of `count` minor balls going round every frame, batched or each on its own.
+    fn belt_app(count: usize, batched: bool) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<Assets<BallBatchMaterial>>()
+            .init_resource::<Assets<ShaderStorageBuffer>>();
+        let revolve = |mut bodies: Query<&mut Transform, With<Minor>>| {
+            for mut transform in bodies.iter_mut() {
+                transform.translation = Quat::from_rotation_y(1e-3) * transform.translation;
+            }
+        };
+        if batched {
+            app.add_systems(Update, (revolve, batch_minor_balls, update_batches).chain());
+        } else {
+            app.add_systems(Update, revolve);
+        }
+
+        let mesh = app.world_mut().resource_mut::<Assets<Mesh>>().add(Sphere::new(1.0).mesh().ico(BATCH_SUBDIVISIONS).unwrap());
+        let material = app.world_mut().resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial::default());
+        let ball = Appearance::DebugBall(DebugBall { radius: 1.0, color: AppearanceColor { r: 128, g: 128, b: 128 } });
+        for i in 0..count {
+            let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+            app.world_mut().spawn((
+                SimulationObject,
+                Minor,
+                ball.clone(),
+                Transform::from_xyz(angle.cos() * 100.0, 0.0, angle.sin() * 100.0),
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+            ));
+        }
+        app
+    }
+
+    /// Milliseconds per frame once the belt has settled.
+    fn frame_time(app: &mut App) -> f64 {
+        for _ in 0..10 {
+            app.update();
+        }
+        const FRAMES: u32 = 200;
+        let start = StdInstant::now();
+        for _ in 0..FRAMES {
+            app.update();
+        }
+        start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64
+    }
+
+    /// Run with `cargo test --release bench_belt -- --ignored --nocapture`. There's no renderer
+    /// here, so this is the CPU side of a frame and what it leaves to upload, not the draw calls.
+    #[test]
+    #[ignore]
+    fn bench_belt_instanced_vs_individual() {
+        const COUNT: usize = 5000;
+        let individual = frame_time(&mut belt_app(COUNT, false));
+        let mut instanced_app = belt_app(COUNT, true);
+        let instanced = frame_time(&mut instanced_app);
+        let batches = instanced_app.world_mut().query::<&BallBatch>().iter(instanced_app.world()).count();
+        let base = BatchBase::new();
+        println!("{} asteroids: individual {:.3} ms/frame ({} draws), instanced {:.3} ms/frame ({} draw, {} bytes of instances a frame, vs {} bytes of vertices merged)",
+            COUNT, individual, COUNT, instanced, batches,
+            COUNT * size_of::<Vec4>(), COUNT * base.positions.len() * size_of::<[f32; 3]>());
+        assert_eq!(batches, 1);
+    }
+}
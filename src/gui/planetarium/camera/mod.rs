@@ -1,17 +1,28 @@
 use std::f64::consts::{PI, TAU};
 use bevy::app::App;
+use bevy::camera::Viewport;
+use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::input::mouse::MouseMotion;
 use bevy::math::{DMat3, DQuat, DVec3};
+use bevy::post_process::bloom::Bloom;
 use bevy::prelude::*;
+use bevy::render::view::{ColorGrading, Hdr};
 use bevy::window::{CursorGrabMode, CursorOptions, PrimaryWindow};
 use bevy_egui::EguiContexts;
 use num_traits::Float;
 use crate::body::appearance::Appearance;
-use crate::body::motive::info::BodyState;
+use crate::body::motive::info::{BodyInfo, BodyState};
 use crate::body::motive::{calculate_body_positions, newton_motive};
-use crate::body::universe::save::ViewSettings;
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::save::{CameraBookmark, ViewSettings};
+use crate::body::universe::Universe;
+use crate::body::SimulationObject;
+use crate::foundations::time::Instant;
 use crate::gui::app::AppState;
-use crate::gui::planetarium::position_bodies;
+use crate::gui::keybindings::Action;
+use crate::gui::planetarium::{position_bodies, SceneOrigin};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
 use crate::gui::util::freecam::{FreeCamPlugin, Freecam, MovementSettings};
 use crate::util::bevystuff::GlamVec;
 use crate::util::ease;
@@ -23,14 +34,24 @@ impl Plugin for PlanetariumCameraPlugin {
         app
             .add_plugins(FreeCamPlugin)
             .add_message::<GoTo>()
+            .add_message::<ViewFromSurface>()
+            .add_message::<CameraCommand>()
+            .init_resource::<CameraBookmarks>()
+            .init_resource::<SplitScreen>()
+            .add_systems(OnExit(AppState::Planetarium), close_split_screen)
             .add_systems(Update, (
+                arrange_viewports,
                 handle_gotos,
+                handle_surface_views,
+                handle_camera_commands.after(position_bodies),
                 run_goto,
                 // Camera position changes must happen *before* bodies are rendered
                 // to avoid jerking, because their rendered positions are relative to the camera,
                 // but after all bodies have moved in the sim if the camera is located relative
                 // to a simulated body.
                 revolve_around.before(position_bodies).after(calculate_body_positions),
+                surface_view.before(position_bodies).after(calculate_body_positions),
+                place_secondary_camera.after(revolve_around).after(run_goto),
                 ).run_if(in_state(AppState::Planetarium)))
         ;
     }
@@ -47,12 +68,59 @@ impl PlanetariumCamera {
             action: CameraAction::Free,
         }
     }
+
+    /// The body the camera is going to, circling, or standing on.
+    pub fn focused(&self) -> Option<Entity> {
+        match &self.action {
+            CameraAction::Goto(goto) => Some(goto.entity),
+            CameraAction::RevolveAround(revolve) => Some(revolve.entity),
+            CameraAction::SurfaceView { body, .. } => Some(*body),
+            CameraAction::Restore(restore) => restore.target,
+            CameraAction::Free => None,
+        }
+    }
+}
+
+/// The second planetarium camera, drawn beside the first in split screen.
+#[derive(Component)]
+pub struct SecondaryCamera;
+
+/// The first planetarium camera, which the UI is drawn over and everything is positioned around.
+pub type PrimaryCamera = (With<PlanetariumCamera>, Without<SecondaryCamera>);
+
+/// Which of the planetarium cameras something is meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraSlot {
+    #[default]
+    Primary,
+    Secondary,
+}
+
+impl CameraSlot {
+    pub fn of(secondary: bool) -> Self {
+        if secondary { CameraSlot::Secondary } else { CameraSlot::Primary }
+    }
 }
 
+/// Whether the window is split between two cameras, side by side.
+#[derive(Resource, Default)]
+pub struct SplitScreen {
+    pub enabled: bool,
+}
+
+/// Views saved with the universe, to jump back to.
+#[derive(Resource, Default)]
+pub struct CameraBookmarks(pub Vec<CameraBookmark>);
+
 pub enum CameraAction {
     Free,
     Goto(GoToInProgress),
     RevolveAround(RevolveAround),
+    /// Flying back to a bookmarked view.
+    Restore(RestoreInProgress),
+    /// Standing on a body, latitude and longitude in degrees and altitude in meters,
+    /// looking out along the local horizon at `azimuth` (0 = north, π/2 = east) and `elevation`.
+    SurfaceView { body: Entity, lat: f64, lon: f64, alt: f64, azimuth: f64, elevation: f64 },
 }
 
 impl PartialEq for CameraAction {
@@ -61,6 +129,8 @@ impl PartialEq for CameraAction {
             (CameraAction::Free, CameraAction::Free) => true,
             (CameraAction::Goto(_), CameraAction::Goto(_)) => true,
             (CameraAction::RevolveAround(_), CameraAction::RevolveAround(_)) => true,
+            (CameraAction::SurfaceView { .. }, CameraAction::SurfaceView { .. }) => true,
+            (CameraAction::Restore(_), CameraAction::Restore(_)) => true,
             (_, _) => false,
         }
     }
@@ -69,6 +139,105 @@ impl PartialEq for CameraAction {
 #[derive(Message)]
 pub struct GoTo {
     pub entity: Entity,
+    /// Which camera flies there. Goes nowhere if it's the secondary one and the screen isn't split.
+    pub camera: CameraSlot,
+}
+
+#[derive(Message)]
+pub struct ViewFromSurface {
+    pub entity: Entity,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+#[derive(Message, Clone)]
+pub enum CameraCommand {
+    /// Back to where the camera starts, looking at the system barycenter.
+    ResetView,
+    /// Back off until every visible body is in view.
+    FrameAll,
+    /// Fly back to a saved view.
+    Restore(CameraBookmark),
+}
+
+/// Where the camera starts, in Bevy units from what it's looking at.
+const DEFAULT_VIEW_OFFSET: DVec3 = DVec3::new(20.0, 2.0, 0.0);
+
+/// How far from the center of a sphere of `radius` a camera has to be to fit all of it in view.
+/// `fov` is vertical, and a window narrower than it is tall fits less across.
+pub fn fit_distance(radius: f64, fov: f64, aspect_ratio: f64) -> f64 {
+    let vertical = fov / 2.0;
+    let horizontal = (vertical.tan() * aspect_ratio).atan();
+    radius / vertical.min(horizontal).sin()
+}
+
+/// A sphere around balls at `(center, radius)`, centered on their centroid. Not the smallest
+/// one, but close enough to frame them.
+fn bounding_sphere(balls: &[(DVec3, f64)]) -> Option<(DVec3, f64)> {
+    if balls.is_empty() { return None; }
+    let center = balls.iter().map(|(position, _)| *position).sum::<DVec3>() / balls.len() as f64;
+    let radius = balls.iter()
+        .map(|(position, radius)| position.distance(center) + radius)
+        .fold(0.0, f64::max);
+    Some((center, radius))
+}
+
+fn handle_camera_commands(
+    mut commands: MessageReader<CameraCommand>,
+    mut camera: Single<(&mut Transform, &mut PlanetariumCamera, &mut Freecam, &Projection), Without<SecondaryCamera>>,
+    bodies: Query<(&Transform, &BodyInfo), (With<SimulationObject>, Without<PlanetariumCamera>)>,
+    states: Query<(&BodyInfo, &BodyState)>,
+    targets: Query<(Entity, &BodyInfo)>,
+    view_settings: Res<ViewSettings>,
+    origin: Res<SceneOrigin>,
+    time: Res<Time>,
+) {
+    let (cam_t, pcam, fcam, projection) = &mut *camera;
+    for command in commands.read() {
+        match command {
+            CameraCommand::ResetView => {
+                let majors: Vec<String> = states.iter()
+                    .filter(|(info, _)| info.major)
+                    .map(|(info, _)| info.id.clone())
+                    .collect();
+                let barycenter = Universe::barycenter(&majors, &states).unwrap_or(origin.position);
+                let target = origin.recenter(barycenter).as_bevy_scaled_dvec(view_settings.distance_factor());
+                fcam.bevy_pos = target + DEFAULT_VIEW_OFFSET;
+                cam_t.rotation = look_at(target, fcam.bevy_pos, DVec3::Y).as_quat();
+            }
+            CameraCommand::FrameAll => {
+                // Drawn positions are relative to the camera, and already scaled
+                let balls: Vec<(DVec3, f64)> = bodies.iter()
+                    .filter(|(_, info)| view_settings.body_in_scope(&info.id))
+                    .map(|(transform, _)| (transform.translation.as_dvec3() + fcam.bevy_pos, transform.scale.x as f64))
+                    .collect();
+                let Some((center, radius)) = bounding_sphere(&balls) else { continue };
+                let (fov, aspect_ratio) = match projection {
+                    Projection::Perspective(perspective) => (perspective.fov as f64, perspective.aspect_ratio as f64),
+                    _ => continue,
+                };
+                let forward = cam_t.forward().as_vec3().as_dvec3();
+                fcam.bevy_pos = center - forward * fit_distance(radius, fov, aspect_ratio);
+                cam_t.rotation = look_at(center, fcam.bevy_pos, DVec3::Y).as_quat();
+            }
+            CameraCommand::Restore(bookmark) => {
+                let target = bookmark.target_id.as_ref().and_then(|id| targets.iter()
+                    .find(|(_, info)| &info.id == id)
+                    .map(|(entity, _)| entity));
+                pcam.action = CameraAction::Restore(RestoreInProgress {
+                    start_pos: fcam.bevy_pos,
+                    start_rot: cam_t.rotation,
+                    start_time: time.elapsed().as_secs_f64(),
+                    end_pos: bookmark.position,
+                    end_rot: bookmark.rotation(),
+                    target,
+                });
+                continue;
+            }
+        }
+        pcam.action = CameraAction::Free;
+    }
 }
 
 pub struct GoToInProgress {
@@ -81,6 +250,15 @@ pub struct GoToInProgress {
     entity: Entity,
 }
 
+pub struct RestoreInProgress {
+    start_pos: DVec3,
+    start_rot: Quat,
+    start_time: f64,
+    end_pos: DVec3,
+    end_rot: Quat,
+    target: Option<Entity>,
+}
+
 pub struct RevolveAround {
     entity: Entity,
     bevy_distance: f64,
@@ -90,13 +268,15 @@ pub struct RevolveAround {
 
 fn handle_gotos (
     mut go_tos: MessageReader<GoTo>,
-    mut camera: Query<(&mut Transform, &mut PlanetariumCamera, &mut Freecam)>,
+    mut cameras: Query<(&Transform, &mut PlanetariumCamera, &Freecam, Has<SecondaryCamera>)>,
     bodies: Query<(Entity, &BodyState, &Appearance), Without<PlanetariumCamera>>,
     view_settings: Res<ViewSettings>,
+    origin: Res<SceneOrigin>,
     time: Res<Time>,
 ) {
-    if let Ok((mut cam_t, mut pcam, mut fcam)) = camera.single_mut() {
-        for event in go_tos.read() {
+    for event in go_tos.read() {
+        let camera = cameras.iter_mut().find(|(_, _, _, secondary)| CameraSlot::of(*secondary) == event.camera);
+        if let Some((cam_t, mut pcam, fcam, _)) = camera {
             let start_pos = fcam.bevy_pos;
             let start_rot = cam_t.rotation;
 
@@ -107,7 +287,7 @@ fn handle_gotos (
             // Calculate the direction from object to camera (opposite of look direction)
             // info!("Radius: {}", appearance.radius(), view_settings.body_scale_factor(appearance.radius()));
             let nearby_distance = 3f64 * view_settings.body_scale_factor(appearance.radius()) as f64;
-            let (altitude, azimuth) = alt_az_in_bevy(obj_pos.as_bevy_scaled_dvec(view_settings.distance_factor()), fcam.bevy_pos);
+            let (altitude, azimuth) = alt_az_in_bevy(origin.recenter(obj_pos).as_bevy_scaled_dvec(view_settings.distance_factor()), fcam.bevy_pos);
 
             pcam.action = CameraAction::Goto(GoToInProgress {
                 start_pos,
@@ -122,17 +302,70 @@ fn handle_gotos (
     }
 }
 
+fn handle_surface_views(
+    mut views: MessageReader<ViewFromSurface>,
+    mut camera: Query<&mut PlanetariumCamera, Without<SecondaryCamera>>,
+) {
+    if let Ok(mut pcam) = camera.single_mut() {
+        for view in views.read() {
+            pcam.action = CameraAction::SurfaceView {
+                body: view.entity,
+                lat: view.lat,
+                lon: view.lon,
+                alt: view.alt,
+                azimuth: 0.0,
+                elevation: 0.0,
+            };
+        }
+    }
+}
+
+/// Flies to the next or previous body by id, starting from whichever one the camera is on.
+pub fn focus_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut contexts: EguiContexts,
+    camera: Query<&PlanetariumCamera, Without<SecondaryCamera>>,
+    bodies: Query<(Entity, &BodyInfo), (With<BodyState>, With<Appearance>)>,
+    mut go_tos: MessageWriter<GoTo>,
+) {
+    if let Ok(ctx) = contexts.ctx_mut() {
+        if ctx.wants_keyboard_input() { return; }
+    }
+    let bindings = &settings.keybindings;
+    let direction: isize = if bindings.just_pressed(&keys, Action::FocusNext) {
+        1
+    } else if bindings.just_pressed(&keys, Action::FocusPrevious) {
+        -1
+    } else {
+        return;
+    };
+
+    let mut ordered: Vec<(Entity, &BodyInfo)> = bodies.iter().collect();
+    if ordered.is_empty() { return; }
+    ordered.sort_by(|a, b| a.1.id.cmp(&b.1.id));
+
+    let focused = camera.single().ok().and_then(|pcam| pcam.focused());
+    let next = match focused.and_then(|focused| ordered.iter().position(|(entity, _)| *entity == focused)) {
+        Some(index) => (index as isize + direction).rem_euclid(ordered.len() as isize) as usize,
+        None if direction > 0 => 0,
+        None => ordered.len() - 1,
+    };
+    go_tos.write(GoTo { entity: ordered[next].0, camera: CameraSlot::Primary });
+}
+
 fn run_goto (
     mut camera: Query<(&mut Transform, &mut PlanetariumCamera, &mut Freecam)>,
     bodies: Query<&BodyState, Without<PlanetariumCamera>>,
     time: Res<Time>,
     view_settings: Res<ViewSettings>,
+    origin: Res<SceneOrigin>,
 ) {
     let animation_time = 2.0;
     let now = time.elapsed().as_secs_f64();
-    let mut next_action = None;
 
-    if let Ok((mut cam_t, mut pcam, mut fcam)) = camera.single_mut() {
+    for (mut cam_t, mut pcam, mut fcam) in camera.iter_mut() {
+        let mut next_action = None;
         match &mut pcam.action {
             CameraAction::Goto(goto) => {
                 if let Ok(body_state) = bodies.get(goto.entity) {
@@ -141,7 +374,7 @@ fn run_goto (
                     let frac = ease::f64::circ(frac);
 
                     // get current position
-                    let body_pos_in_bevy = body_state.current_position.as_bevy_scaled_dvec(view_settings.distance_factor());
+                    let body_pos_in_bevy = origin.recenter(body_state.current_position).as_bevy_scaled_dvec(view_settings.distance_factor());
 
                     // Set new end position based on object's current location
                     let offset = local_to_object_in_bevy(goto.end_altitude, goto.end_azimuth, goto.end_distance);
@@ -169,6 +402,32 @@ fn run_goto (
                     next_action = Some(CameraAction::Free);
                 }
             }
+            CameraAction::Restore(restore) => {
+                let frac = f64::min(1.0, (now - restore.start_time) / animation_time);
+                if frac < 1.0 {
+                    let frac = ease::f64::circ(frac);
+                    fcam.bevy_pos = restore.start_pos.lerp(restore.end_pos, frac);
+                    cam_t.rotation = restore.start_rot.slerp(restore.end_rot, frac as f32);
+                } else {
+                    // Exactly as bookmarked, whatever the easing rounds to
+                    fcam.bevy_pos = restore.end_pos;
+                    cam_t.rotation = restore.end_rot;
+                    let target = restore.target.and_then(|entity| bodies.get(entity).ok().map(|state| (entity, state)));
+                    next_action = Some(match target {
+                        Some((entity, state)) => {
+                            let body_pos_in_bevy = origin.recenter(state.current_position).as_bevy_scaled_dvec(view_settings.distance_factor());
+                            let (altitude, azimuth) = alt_az_in_bevy(body_pos_in_bevy, restore.end_pos);
+                            CameraAction::RevolveAround(RevolveAround {
+                                entity,
+                                bevy_distance: body_pos_in_bevy.distance(restore.end_pos),
+                                altitude,
+                                azimuth,
+                            })
+                        }
+                        None => CameraAction::Free,
+                    });
+                }
+            }
             _ => {}
         }
 
@@ -180,16 +439,17 @@ fn run_goto (
 
 fn revolve_around(
     settings: Res<MovementSettings>,
-    mut camera: Query<(&mut Transform, &mut PlanetariumCamera, &mut Freecam)>,
+    mut camera: Query<(&mut Transform, &mut PlanetariumCamera, &mut Freecam, Has<SecondaryCamera>)>,
     mut mouse: MessageReader<MouseMotion>,
     mouse_buttons: Res<ButtonInput<MouseButton>>,
     mut primary_window: Query<(&mut Window, &mut CursorOptions), With<PrimaryWindow>>,
     view_settings: Res<ViewSettings>,
     entities: Query<(Entity, &BodyState, &Transform), Without<Freecam>>,
+    origin: Res<SceneOrigin>,
     mut egui_ctx: EguiContexts,
 ) {
     if let Ok((mut window, mut cursor_options)) = primary_window.single_mut() {
-        for (mut cam_t, mut pcam, mut fcam) in camera.iter_mut() {
+        for (mut cam_t, mut pcam, mut fcam, secondary) in camera.iter_mut() {
 
             match &mut pcam.action {
                 CameraAction::RevolveAround(revolve) => {
@@ -198,7 +458,9 @@ fn revolve_around(
                         Ok((entity, state, transform)) => {
                             let window_scale = window.height().min(window.width());
 
-                            if mouse_buttons.pressed(MouseButton::Left) {
+                            if secondary {
+                                // The mouse only turns the primary camera
+                            } else if mouse_buttons.pressed(MouseButton::Left) {
                                 if let Ok(ctx) = egui_ctx.ctx_mut() && ctx.wants_pointer_input() && ctx.wants_pointer_input() {
                                     // If hovering over an egui window, don't rotate around! It grabs the mouse :(
                                     cursor_options.grab_mode = CursorGrabMode::None;
@@ -219,7 +481,7 @@ fn revolve_around(
                                 cursor_options.visible = true;
                             }
 
-                            let body_pos_in_bevy = state.current_position.as_bevy_scaled_dvec(view_settings.distance_factor());
+                            let body_pos_in_bevy = origin.recenter(state.current_position).as_bevy_scaled_dvec(view_settings.distance_factor());
                             let offset = local_to_object_in_bevy(revolve.altitude, revolve.azimuth, revolve.bevy_distance);
                             let camera_pos_in_bevy = body_pos_in_bevy + offset;
 
@@ -240,6 +502,203 @@ fn revolve_around(
     }
 }
 
+fn surface_view(
+    settings: Res<MovementSettings>,
+    mut camera: Query<(&mut Transform, &mut PlanetariumCamera, &mut Freecam), Without<SecondaryCamera>>,
+    mut mouse: MessageReader<MouseMotion>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut primary_window: Query<(&Window, &mut CursorOptions), With<PrimaryWindow>>,
+    view_settings: Res<ViewSettings>,
+    sim_time: Res<SimTime>,
+    bodies: Query<(&BodyState, &Appearance, Option<&BodyRotation>), Without<Freecam>>,
+    origin: Res<SceneOrigin>,
+    mut egui_ctx: EguiContexts,
+) {
+    let Ok((window, mut cursor_options)) = primary_window.single_mut() else { return };
+    let Ok((mut cam_t, mut pcam, mut fcam)) = camera.single_mut() else { return };
+    let CameraAction::SurfaceView { body, lat, lon, alt, azimuth, elevation } = &mut pcam.action else { return };
+
+    let Ok((state, appearance, rotation)) = bodies.get(*body) else {
+        pcam.action = CameraAction::Free;
+        return;
+    };
+
+    let window_scale = window.height().min(window.width());
+    if mouse_buttons.pressed(MouseButton::Left) {
+        if let Ok(ctx) = egui_ctx.ctx_mut() && ctx.wants_pointer_input() {
+            cursor_options.grab_mode = CursorGrabMode::None;
+            cursor_options.visible = true;
+        } else {
+            cursor_options.grab_mode = CursorGrabMode::Confined;
+            cursor_options.visible = false;
+            for ev in mouse.read() {
+                *azimuth += (ev.delta.x.clamp(-1000.0, 1000.0) * window_scale * settings.sensitivity) as f64;
+                *azimuth = azimuth.rem_euclid(TAU);
+                *elevation -= (ev.delta.y.clamp(-1000.0, 1000.0) * window_scale * settings.sensitivity) as f64;
+                const ELEVATION_LIMIT: f64 = PI / 2.0 - 0.001;
+                *elevation = elevation.clamp(-ELEVATION_LIMIT, ELEVATION_LIMIT);
+            }
+        }
+    } else {
+        cursor_options.grab_mode = CursorGrabMode::None;
+        cursor_options.visible = true;
+    }
+
+    let horizon = SurfaceFrame::new(rotation, *lat, *lon, sim_time.time).rotated(origin.rotation);
+    let center_in_bevy = origin.recenter(state.current_position).as_bevy_scaled_dvec(view_settings.distance_factor());
+    // Matches how position_bodies sizes the body, so the camera sits on the drawn surface
+    let bevy_distance = view_settings.body_scale_factor(appearance.radius() + *alt) as f64;
+    let (position, rotation) = horizon.camera_pose(center_in_bevy, bevy_distance, *azimuth, *elevation);
+    if position.is_finite() && rotation.is_finite() {
+        fcam.bevy_pos = position;
+        cam_t.rotation = rotation.as_quat();
+    }
+}
+
+/// Positions and sizes, in physical pixels, of the left and right halves of a window `size` big.
+fn split_viewports(size: UVec2) -> [(UVec2, UVec2); 2] {
+    let left = size.x / 2;
+    [
+        (UVec2::ZERO, UVec2::new(left, size.y)),
+        (UVec2::new(left, 0), UVec2::new(size.x - left, size.y)),
+    ]
+}
+
+fn set_viewport(camera: &mut Camera, (physical_position, physical_size): (UVec2, UVec2)) {
+    let unchanged = camera.viewport.as_ref()
+        .is_some_and(|viewport| viewport.physical_position == physical_position && viewport.physical_size == physical_size);
+    if !unchanged {
+        camera.viewport = Some(Viewport { physical_position, physical_size, ..default() });
+    }
+}
+
+/// Spawns or despawns the secondary camera to match `SplitScreen`, and gives each camera half the window.
+/// The secondary camera starts out where the primary one is, and follows its tonemapping and exposure.
+fn arrange_viewports(
+    mut commands: Commands,
+    split: Res<SplitScreen>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut primary: Single<(&mut Camera, &Transform, &Freecam, &Projection, &Tonemapping, Ref<ColorGrading>), PrimaryCamera>,
+    mut secondary: Query<(Entity, &mut Camera, &mut Tonemapping, &mut ColorGrading), With<SecondaryCamera>>,
+) {
+    let (camera, transform, fcam, projection, tonemapping, color_grading) = &mut *primary;
+    if !split.enabled {
+        for (entity, ..) in &secondary {
+            commands.entity(entity).despawn();
+        }
+        if camera.viewport.is_some() {
+            camera.viewport = None;
+        }
+        return;
+    }
+
+    let size = window.physical_size();
+    // Nothing to split while minimized
+    if size.x < 2 || size.y == 0 { return; }
+    let [left, right] = split_viewports(size);
+    set_viewport(camera, left);
+    match secondary.iter_mut().next() {
+        Some((_, mut camera, mut secondary_tonemapping, mut secondary_grading)) => {
+            set_viewport(&mut camera, right);
+            if *secondary_tonemapping != **tonemapping {
+                *secondary_tonemapping = **tonemapping;
+            }
+            if color_grading.is_changed() {
+                *secondary_grading = (**color_grading).clone();
+            }
+        }
+        None => {
+            let mut camera = Camera { order: 1, ..default() };
+            set_viewport(&mut camera, right);
+            commands.spawn((
+                Camera3d::default(),
+                camera,
+                Hdr,
+                (*projection).clone(),
+                Transform::from_rotation(transform.rotation),
+                Freecam { bevy_pos: fcam.bevy_pos },
+                PlanetariumCamera::new(),
+                SecondaryCamera,
+                Bloom::NATURAL,
+                **tonemapping,
+                (**color_grading).clone(),
+            ));
+        }
+    }
+}
+
+/// Bodies are drawn around the primary camera, so the secondary one sits at its offset from it.
+fn place_secondary_camera(
+    primary: Single<&Freecam, PrimaryCamera>,
+    mut secondary: Query<(&mut Transform, &Freecam), With<SecondaryCamera>>,
+) {
+    for (mut transform, fcam) in secondary.iter_mut() {
+        transform.translation = (fcam.bevy_pos - primary.bevy_pos).as_vec3();
+    }
+}
+
+fn close_split_screen(
+    mut commands: Commands,
+    mut split: ResMut<SplitScreen>,
+    mut primary: Query<&mut Camera, PrimaryCamera>,
+    secondary: Query<Entity, With<SecondaryCamera>>,
+) {
+    split.enabled = false;
+    for entity in &secondary {
+        commands.entity(entity).despawn();
+    }
+    for mut camera in primary.iter_mut() {
+        camera.viewport = None;
+    }
+}
+
+/// The local horizon at a point on a body's surface, as unit vectors in sim coordinates.
+pub struct SurfaceFrame {
+    pub up: DVec3,
+    pub north: DVec3,
+    pub east: DVec3,
+}
+
+impl SurfaceFrame {
+    /// Bodies without rotation data are treated as fixed to the reference frame.
+    pub fn new(rotation: Option<&BodyRotation>, lat: f64, lon: f64, time: Instant) -> Self {
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+        let body_to_reference = rotation.map(|r| r.body_to_reference(time)).unwrap_or(DMat3::IDENTITY);
+        // Written out in the body-fixed frame so they're well-defined at the poles
+        let up = DVec3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin());
+        let north = DVec3::new(-lat.sin() * lon.cos(), -lat.sin() * lon.sin(), lat.cos());
+        let east = DVec3::new(-lon.sin(), lon.cos(), 0.0);
+        Self {
+            up: body_to_reference * up,
+            north: body_to_reference * north,
+            east: body_to_reference * east,
+        }
+    }
+
+    /// The same frame with each axis rotated, such as into the drawn scene's frame.
+    pub fn rotated(self, rotation: DMat3) -> Self {
+        Self {
+            up: rotation * self.up,
+            north: rotation * self.north,
+            east: rotation * self.east,
+        }
+    }
+
+    /// Direction of a point on the sky at `azimuth` (0 = north, π/2 = east) and `elevation`.
+    pub fn direction(&self, azimuth: f64, elevation: f64) -> DVec3 {
+        (self.north * azimuth.cos() + self.east * azimuth.sin()) * elevation.cos() + self.up * elevation.sin()
+    }
+
+    /// Camera position and rotation in bevy space, `bevy_distance` out from the body's center.
+    pub fn camera_pose(&self, center_in_bevy: DVec3, bevy_distance: f64, azimuth: f64, elevation: f64) -> (DVec3, DQuat) {
+        let position = center_in_bevy + self.up.as_bevy_scaled_dvec(bevy_distance);
+        let looking = self.direction(azimuth, elevation).as_bevy_scaled_dvec(1.0);
+        // look_at points the camera's +Z at `to`, and bevy cameras look down -Z
+        let rotation = look_at(position + looking, position, self.up.as_bevy_scaled_dvec(1.0));
+        (position, rotation)
+    }
+}
+
 fn local_to_object_in_bevy(altitude: f64, azimuth: f64, bevy_distance: f64) -> DVec3 {
     let cos_alt = altitude.cos();
     let x = bevy_distance * cos_alt * azimuth.sin();
@@ -265,3 +724,109 @@ fn look_at(from: DVec3, to: DVec3, up: DVec3) -> DQuat {
 
     DQuat::from_mat3(&rot_matrix)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::rotation::subpoint;
+
+    fn earth() -> BodyRotation {
+        BodyRotation {
+            period: 86_164.0905,
+            axial_tilt: 23.44,
+            node_longitude: 0.0,
+            prime_meridian_at_epoch: 280.46,
+            epoch: Instant::from_seconds_since_j2000(0.0),
+        }
+    }
+
+    #[test]
+    fn test_surface_view_position_at_epoch() {
+        let earth = earth();
+        let epoch = earth.epoch;
+        let center = DVec3::new(-2.6e10, 1.45e11, 1.0e6);
+        let distance_factor = 1e-9;
+        let center_in_bevy = center.as_bevy_scaled_dvec(distance_factor);
+
+        let horizon = SurfaceFrame::new(Some(&earth), 0.0, 0.0, epoch);
+        let (position, _) = horizon.camera_pose(center_in_bevy, 2.0, 0.0, 0.0);
+        let expected = center_in_bevy + (earth.body_to_reference(epoch) * DVec3::X).as_bevy_scaled_dvec(2.0);
+        assert!((position - expected).length() < 1e-12, "{:?} != {:?}", position, expected);
+
+        // Standing anywhere puts the camera above that point
+        let horizon = SurfaceFrame::new(Some(&earth), 35.0, -120.0, epoch);
+        let (lat, lon) = subpoint(center + horizon.up * 6.4e6, &earth, center, epoch);
+        assert!((lat - 35.0).abs() < 1e-9 && (lon + 120.0).abs() < 1e-9, "{} {}", lat, lon);
+    }
+
+    #[test]
+    fn test_fit_distance() {
+        // With a 90° view, a unit sphere just fits at √2
+        let distance = fit_distance(1.0, PI / 2.0, 1.0);
+        assert!((distance - 2f64.sqrt()).abs() < 1e-12, "{}", distance);
+        // Where the edge of the view just touches the sphere
+        let (radius, fov) = (3.5, 50f64.to_radians());
+        let distance = fit_distance(radius, fov, 16.0 / 9.0);
+        assert!((distance * (fov / 2.0).sin() - radius).abs() < 1e-12);
+        // A tall window has to back off further to fit it across
+        assert!(fit_distance(radius, fov, 0.5) > distance);
+
+        let (center, radius) = bounding_sphere(&[(DVec3::new(-4.0, 0.0, 0.0), 1.0), (DVec3::new(4.0, 0.0, 0.0), 2.0)]).unwrap();
+        assert_eq!(center, DVec3::ZERO);
+        assert_eq!(radius, 6.0);
+        assert!(bounding_sphere(&[]).is_none());
+    }
+
+    #[test]
+    fn test_cameras_keep_their_own_actions() {
+        let mut app = App::new();
+        app
+            .init_resource::<Time>()
+            .init_resource::<ViewSettings>()
+            .init_resource::<SceneOrigin>()
+            .add_message::<GoTo>()
+            .add_systems(Update, (handle_gotos, run_goto.after(handle_gotos)));
+        let mut spawn_camera = |position: DVec3| {
+            app.world_mut().spawn((Transform::default(), Freecam { bevy_pos: position }, PlanetariumCamera::new())).id()
+        };
+        let primary = spawn_camera(DVec3::new(20.0, 2.0, 0.0));
+        let secondary = spawn_camera(DVec3::new(-20.0, 2.0, 0.0));
+        app.world_mut().entity_mut(secondary).insert(SecondaryCamera);
+
+        let mut spawn_body = |position: DVec3| {
+            let state = BodyState { current_position: position, ..default() };
+            app.world_mut().spawn((state, Appearance::Empty)).id()
+        };
+        let earth = spawn_body(DVec3::new(1.5e11, 0.0, 0.0));
+        let mars = spawn_body(DVec3::new(0.0, 2.3e11, 0.0));
+        let focused = |app: &App, camera| app.world().get::<PlanetariumCamera>(camera).unwrap().focused();
+
+        app.world_mut().write_message(GoTo { entity: mars, camera: CameraSlot::Secondary });
+        app.update();
+        assert_eq!(focused(&app, secondary), Some(mars));
+        assert_eq!(focused(&app, primary), None);
+
+        app.world_mut().write_message(GoTo { entity: earth, camera: CameraSlot::Primary });
+        app.update();
+        assert_eq!(focused(&app, primary), Some(earth));
+        assert_eq!(focused(&app, secondary), Some(mars));
+
+        assert_eq!(split_viewports(UVec2::new(1281, 720)), [
+            (UVec2::ZERO, UVec2::new(640, 720)),
+            (UVec2::new(640, 0), UVec2::new(641, 720)),
+        ]);
+    }
+
+    #[test]
+    fn test_surface_view_looks_along_horizon() {
+        let horizon = SurfaceFrame::new(Some(&earth()), 35.0, -120.0, Instant::from_seconds_since_j2000(0.0));
+        let (_, rotation) = horizon.camera_pose(DVec3::ZERO, 1.0, 0.0, 0.0);
+        let forward = rotation * -DVec3::Z;
+        assert!((forward - horizon.north.as_bevy_scaled_dvec(1.0)).length() < 1e-9);
+        let up = rotation * DVec3::Y;
+        assert!((up - horizon.up.as_bevy_scaled_dvec(1.0)).length() < 1e-9);
+
+        assert!((horizon.direction(0.0, PI / 2.0) - horizon.up).length() < 1e-12);
+        assert!((horizon.direction(PI / 2.0, 0.0) - horizon.east).length() < 1e-12);
+    }
+}
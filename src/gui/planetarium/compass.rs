@@ -0,0 +1,90 @@
+//! The reference frame's axes in the corner of the screen, turned with the camera.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy_egui::{egui, EguiContexts};
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
+use crate::gui::settings::Settings;
+use crate::util::bevystuff::GlamVec;
+
+/// Width and height of the compass, in points.
+const COMPASS_SIZE: f32 = 80.0;
+
+/// +X points toward the vernal equinox in either reference plane.
+const AXES: [(&str, DVec3, egui::Color32); 3] = [
+    ("X ♈", DVec3::X, egui::Color32::from_rgb(230, 80, 80)),
+    ("Y", DVec3::Y, egui::Color32::from_rgb(80, 200, 80)),
+    ("Z", DVec3::Z, egui::Color32::from_rgb(90, 140, 255)),
+];
+
+/// Where a reference frame axis ends up on screen, as seen through a camera with
+/// `camera_rotation`. `scene_rotation` is `SceneOrigin::bevy_rotation`.
+/// Returns x right and y down, as egui draws, then how far toward the viewer it points.
+pub fn project_axis(axis: DVec3, camera_rotation: Quat, scene_rotation: Quat) -> (Vec2, f32) {
+    let in_view = camera_rotation.inverse() * (scene_rotation * axis.as_bevy());
+    (Vec2::new(in_view.x, -in_view.y), in_view.z)
+}
+
+pub fn axis_compass(
+    settings: Res<Settings>,
+    camera: Single<&Transform, PrimaryCamera>,
+    origin: Res<SceneOrigin>,
+    mut contexts: EguiContexts,
+) {
+    if !settings.ui.show_compass { return; }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let scene_rotation = origin.bevy_rotation();
+    let mut axes: Vec<_> = AXES.iter()
+        .map(|(label, axis, color)| (*label, *color, project_axis(*axis, camera.rotation, scene_rotation)))
+        .collect();
+    // Furthest first, so the nearer axes are drawn over them
+    axes.sort_by(|(_, _, (_, a)), (_, _, (_, b))| a.total_cmp(b));
+
+    egui::Area::new(egui::Id::new("axis_compass"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(COMPASS_SIZE, COMPASS_SIZE), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            let center = rect.center();
+            let length = COMPASS_SIZE * 0.35;
+            for (label, color, (direction, depth)) in axes {
+                // Axes pointing away are dimmed
+                let color = if depth < 0.0 { color.gamma_multiply(0.5) } else { color };
+                let end = center + egui::vec2(direction.x, direction.y) * length;
+                painter.line_segment([center, end], egui::Stroke::new(2.0, color));
+                painter.text(
+                    center + egui::vec2(direction.x, direction.y) * (length + 8.0),
+                    egui::Align2::CENTER_CENTER,
+                    label,
+                    egui::FontId::proportional(11.0),
+                    color,
+                );
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_axis() {
+        let near = |(a, depth): (Vec2, f32), (b, expected_depth): (Vec2, f32)| {
+            assert!(a.distance(b) < 1e-6 && (depth - expected_depth).abs() < 1e-6, "{:?} {:?}", (a, depth), (b, expected_depth));
+        };
+
+        // Cameras start out looking down Bevy's -Z, which is the frame's +Y, with +Z up
+        let level = Quat::IDENTITY;
+        near(project_axis(DVec3::X, level, Quat::IDENTITY), (Vec2::new(1.0, 0.0), 0.0));
+        near(project_axis(DVec3::Y, level, Quat::IDENTITY), (Vec2::ZERO, -1.0));
+        near(project_axis(DVec3::Z, level, Quat::IDENTITY), (Vec2::new(0.0, -1.0), 0.0));
+
+        // Turned to look down the frame's +X, +Y is off to the left
+        let facing_x = Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2);
+        near(project_axis(DVec3::X, facing_x, Quat::IDENTITY), (Vec2::ZERO, -1.0));
+        near(project_axis(DVec3::Y, facing_x, Quat::IDENTITY), (Vec2::new(-1.0, 0.0), 0.0));
+        near(project_axis(DVec3::Z, facing_x, Quat::IDENTITY), (Vec2::new(0.0, -1.0), 0.0));
+    }
+}
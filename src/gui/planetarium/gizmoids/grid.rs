@@ -0,0 +1,126 @@
+//! Distance rings and radial lines in the reference plane, around the scene's origin.
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+use bevy::prelude::*;
+use bevy::color::Srgba;
+use bevy::math::DVec3;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::universe::save::ViewSettings;
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
+use crate::gui::util::freecam::Freecam;
+use crate::util::bevystuff::GlamVec;
+use crate::util::format::si_prefixed;
+use crate::util::units::METERS_PER_AU;
+
+/// Rings drawn out from the origin, each one ring radius further than the last.
+pub const RING_COUNT: usize = 5;
+
+const RADIAL_LINES: usize = 12;
+
+/// Ring labels drawn this frame, in camera-relative Bevy space.
+#[derive(Resource, Default)]
+pub struct GridLabels(Vec<(String, Vec3)>);
+
+/// Spacing between rings, in meters, for a camera `distance` meters from the origin.
+/// The largest 1, 2 or 5 times a power of ten that still fits all `RING_COUNT` rings inside
+/// `distance`. Counted in AU from a tenth of one out.
+pub fn nice_ring_radius(distance: f64) -> f64 {
+    let unit = if distance / RING_COUNT as f64 >= 0.1 * METERS_PER_AU { METERS_PER_AU } else { 1.0 };
+    let span = (distance / RING_COUNT as f64 / unit).max(f64::MIN_POSITIVE);
+    let magnitude = 10f64.powi(span.log10().floor() as i32);
+    let step = [5.0, 2.0, 1.0].into_iter()
+        .find(|step| step * magnitude <= span)
+        .unwrap_or(1.0);
+    step * magnitude * unit
+}
+
+/// "3 AU" for ring radii counted in AU, SI-prefixed meters otherwise.
+pub fn ring_label(radius: f64) -> String {
+    if radius >= 0.1 * METERS_PER_AU {
+        format!("{} AU", (radius / METERS_PER_AU * 1000.0).round() / 1000.0)
+    } else {
+        si_prefixed(radius, "m")
+    }
+}
+
+/// Logarithmic distances have no even spacing to draw, so the grid only shows with linear ones.
+pub fn render_grid(
+    view_settings: Res<ViewSettings>,
+    fcam: Single<&Freecam, PrimaryCamera>,
+    origin: Res<SceneOrigin>,
+    mut labels: ResMut<GridLabels>,
+    mut gizmos: Gizmos,
+) {
+    labels.0.clear();
+    if !view_settings.show_grid || view_settings.logarithmic_distance_scale { return; }
+    let distance_scale = view_settings.distance_factor();
+
+    // Recentering already rotates into the reference plane, so it's the drawn XZ plane
+    let center = DVec3::ZERO.as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos);
+    let camera_distance = fcam.bevy_pos.length() / distance_scale;
+    let ring_radius = nice_ring_radius(camera_distance);
+    let flat = Isometry3d::new(center, Quat::from_rotation_x(FRAC_PI_2));
+    let color = Srgba::new(0.5, 0.5, 0.5, 0.3);
+
+    for ring in 1..=RING_COUNT {
+        let radius = ring_radius * ring as f64;
+        gizmos.circle(flat, (radius * distance_scale) as f32, color).resolution(64);
+        labels.0.push((ring_label(radius), center + Vec3::X * (radius * distance_scale) as f32));
+    }
+    let outer = (ring_radius * RING_COUNT as f64 * distance_scale) as f32;
+    for line in 0..RADIAL_LINES {
+        let angle = line as f32 / RADIAL_LINES as f32 * TAU;
+        gizmos.line(center, center + Vec3::new(angle.cos(), 0.0, -angle.sin()) * outer, color);
+    }
+}
+
+pub fn label_grid(
+    labels: Res<GridLabels>,
+    mut contexts: EguiContexts,
+    cameras: Query<(&Camera, &GlobalTransform), PrimaryCamera>,
+) {
+    if labels.0.is_empty() { return; }
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("grid_labels")));
+    let zoom = ctx.zoom_factor();
+
+    for (camera, camera_transform) in &cameras {
+        for (label, position) in &labels.0 {
+            if let Ok(pos) = camera.world_to_viewport(camera_transform, *position) {
+                painter.text(
+                    egui::pos2(pos.x / zoom, pos.y / zoom),
+                    egui::Align2::LEFT_TOP,
+                    label,
+                    egui::FontId::proportional(11.0),
+                    egui::Color32::GRAY,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nice_ring_radius() {
+        // Out at Jupiter, rings every AU
+        assert_eq!(nice_ring_radius(5.2 * METERS_PER_AU), METERS_PER_AU);
+        assert_eq!(ring_label(nice_ring_radius(5.2 * METERS_PER_AU) * 5.0), "5 AU");
+        // Further out, steps of 2 and 5
+        assert_eq!(nice_ring_radius(30.0 * METERS_PER_AU), 5.0 * METERS_PER_AU);
+        assert_eq!(nice_ring_radius(12.0 * METERS_PER_AU), 2.0 * METERS_PER_AU);
+        // Close in, round numbers of meters
+        assert_eq!(nice_ring_radius(3.844e8), 5e7);
+        assert_eq!(nice_ring_radius(1.2e7), 2e6);
+
+        for distance in [1e3, 4.2e7, 3.3e9, 6.1e12] {
+            let radius = nice_ring_radius(distance);
+            assert!(radius * RING_COUNT as f64 <= distance, "{} at {}", radius, distance);
+            assert!(radius * RING_COUNT as f64 * 2.5 > distance, "{} at {}", radius, distance);
+        }
+    }
+}
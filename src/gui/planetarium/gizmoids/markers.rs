@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy::color::Srgba;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::{Motive, MotiveSelection};
+use crate::body::universe::Universe;
+use crate::body::universe::save::ViewSettings;
+use crate::foundations::gravity;
+use crate::foundations::time::Instant;
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::planetarium::windows::body_info::BodyInfoState;
+use crate::gui::util::freecam::Freecam;
+use crate::util::bevystuff::GlamVec;
+
+/// Points of interest that aren't bodies. Not saved with the universe.
+#[derive(Resource, Default)]
+pub struct Markers {
+    pub barycenter: Option<BarycenterPreset>,
+    /// Lagrange points of the selected body and its primary.
+    pub lagrange: bool,
+    /// Camera-relative positions drawn this frame, for labelling.
+    placed: Vec<(&'static str, Vec3)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarycenterPreset {
+    /// All major bodies.
+    System,
+    /// The selected body and everything orbiting it, however indirectly.
+    SelectedSubsystem,
+}
+
+impl BarycenterPreset {
+    pub const ALL: [BarycenterPreset; 2] = [BarycenterPreset::System, BarycenterPreset::SelectedSubsystem];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BarycenterPreset::System => "System barycenter",
+            BarycenterPreset::SelectedSubsystem => "Selected body + children",
+        }
+    }
+}
+
+/// Marker size as a fraction of its distance from the camera, so it stays the same size on screen.
+const MARKER_SCREEN_SIZE: f32 = 0.01;
+
+const LAGRANGE_LABELS: [&str; 5] = ["L1", "L2", "L3", "L4", "L5"];
+
+pub fn render_markers(
+    mut markers: ResMut<Markers>,
+    bodies: Query<(&BodyInfo, &BodyState)>,
+    motives: Query<(&BodyInfo, &Motive)>,
+    body_info_state: Res<BodyInfoState>,
+    sim_time: Res<SimTime>,
+    view_settings: Res<ViewSettings>,
+    fcam: Single<&Freecam, PrimaryCamera>,
+    origin: Res<SceneOrigin>,
+    mut gizmos: Gizmos,
+) {
+    let distance_scale = view_settings.distance_factor();
+    let selected = body_info_state.current_body_id.as_deref();
+    let mut placed = Vec::new();
+
+    let barycenter_ids: Option<Vec<String>> = match (markers.barycenter, selected) {
+        (Some(BarycenterPreset::System), _) => Some(bodies.iter()
+            .filter(|(info, _)| info.major)
+            .map(|(info, _)| info.id.clone())
+            .collect()),
+        (Some(BarycenterPreset::SelectedSubsystem), Some(selected)) => Some(subsystem(selected, &motives, sim_time.time)),
+        _ => None,
+    };
+    if let Some(barycenter) = barycenter_ids.and_then(|ids| Universe::barycenter(&ids, &bodies)) {
+        let position = origin.recenter(barycenter).as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos);
+        cross(&mut gizmos, position, Srgba::new(1.0, 0.8, 0.0, 1.0));
+        placed.push(("Barycenter", position));
+    }
+
+    if let (true, Some(selected)) = (markers.lagrange, selected) {
+        let primary_id = motives.iter()
+            .find(|(info, _)| info.id == selected)
+            .and_then(|(_, motive)| motive.motive_at(sim_time.time).1.primary_id().map(str::to_string));
+        let find = |id: &str| bodies.iter().find(|(info, _)| info.id == id);
+        if let Some(((primary, primary_state), (secondary, secondary_state))) = primary_id
+            .and_then(|primary_id| find(primary_id.as_str()))
+            .zip(find(selected))
+        {
+            let points = gravity::lagrange_points(
+                primary.mass, primary_state.current_position,
+                secondary.mass, secondary_state.current_position,
+            );
+            for (label, point) in LAGRANGE_LABELS.into_iter().zip(points) {
+                let position = origin.recenter(point).as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos);
+                cross(&mut gizmos, position, Srgba::new(0.3, 0.8, 1.0, 1.0));
+                placed.push((label, position));
+            }
+        }
+    }
+
+    markers.placed = placed;
+}
+
+/// Wire sphere of the sphere of influence around each major body orbiting a primary.
+/// A radius has no size on a logarithmic distance scale, so like the grid they only show with linear ones.
+pub fn render_spheres_of_influence(
+    bodies: Query<(&BodyInfo, &BodyState, &Motive)>,
+    sim_time: Res<SimTime>,
+    view_settings: Res<ViewSettings>,
+    fcam: Single<&Freecam, PrimaryCamera>,
+    origin: Res<SceneOrigin>,
+    mut gizmos: Gizmos,
+) {
+    if !view_settings.show_soi || view_settings.logarithmic_distance_scale { return; }
+    let distance_scale = view_settings.distance_factor();
+
+    for (info, state, motive) in bodies.iter() {
+        if !info.major { continue; }
+        let (_, MotiveSelection::Keplerian(kepler)) = motive.motive_at(sim_time.time) else { continue; };
+        let Some((primary, _, _)) = bodies.iter().find(|(primary, _, _)| primary.id == kepler.primary_id) else { continue; };
+
+        let radius = gravity::soi_radius(kepler.semi_major_axis(), info.mass, primary.mass) * distance_scale;
+        let position = origin.recenter(state.current_position).as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos);
+        gizmos.sphere(Isometry3d::from_translation(position), radius as f32, Srgba::new(0.6, 0.6, 1.0, 0.25));
+    }
+}
+
+pub fn label_markers(
+    markers: Res<Markers>,
+    mut contexts: EguiContexts,
+    cameras: Query<(&Camera, &GlobalTransform), PrimaryCamera>,
+) {
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("marker_labels")));
+    let zoom = ctx.zoom_factor();
+
+    for (camera, camera_transform) in &cameras {
+        for (label, position) in &markers.placed {
+            if let Ok(pos) = camera.world_to_viewport(camera_transform, *position) {
+                painter.text(
+                    egui::pos2(pos.x / zoom, pos.y / zoom),
+                    egui::Align2::LEFT_BOTTOM,
+                    *label,
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::LIGHT_GRAY,
+                );
+            }
+        }
+    }
+}
+
+/// The body and all of its descendants at `time`.
+fn subsystem(root: &str, motives: &Query<(&BodyInfo, &Motive)>, time: Instant) -> Vec<String> {
+    let mut members = vec![root.to_string()];
+    let mut i = 0;
+    while i < members.len() {
+        for (info, motive) in motives.iter() {
+            let (_, selection) = motive.motive_at(time);
+            if selection.primary_id() == Some(members[i].as_str()) && !members.contains(&info.id) {
+                members.push(info.id.clone());
+            }
+        }
+        i += 1;
+    }
+    members
+}
+
+/// Three-axis cross centered on `position`, in camera-relative Bevy space.
+fn cross(gizmos: &mut Gizmos, position: Vec3, color: Srgba) {
+    let size = position.length() * MARKER_SCREEN_SIZE;
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        gizmos.line(position - axis * size, position + axis * size, color);
+    }
+}
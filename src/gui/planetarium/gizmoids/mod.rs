@@ -1 +1,4 @@
+pub mod grid;
+pub mod markers;
+pub mod starfield;
 pub mod trajectory;
@@ -0,0 +1,108 @@
+//! What's drawn behind the bodies: a solid color, or stars fixed to the reference frame.
+
+use std::f64::consts::TAU;
+use bevy::prelude::*;
+use bevy::color::Srgba;
+use bevy::math::DVec3;
+use crate::body::universe::save::{Background, ViewSettings};
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
+use crate::util::bevystuff::GlamVec;
+use crate::util::rng::SplitMix64;
+
+/// How far out stars are drawn, in Bevy units. Inside the camera's far plane.
+const STAR_DISTANCE: f32 = 9000.0;
+/// How long the dash drawn for each star is, as a fraction of `STAR_DISTANCE`.
+const STAR_SIZE: f32 = 2e-4;
+
+/// Stars go through their own gizmos so they can sit behind everything else.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct StarfieldGizmos;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Star {
+    /// Unit vector in the ecliptic frame.
+    pub direction: DVec3,
+    /// 0 to 1. Most stars are dim.
+    pub brightness: f32,
+}
+
+/// `count` stars spread evenly over the sky. The same seed always gives the same stars.
+pub fn star_directions(seed: u64, count: usize) -> Vec<Star> {
+    let mut rng = SplitMix64::new(seed);
+    (0..count).map(|_| {
+        // Drawn in a fixed order so a seed means the same sky from one version to the next
+        let z = rng.range(-1.0, 1.0);
+        let angle = rng.range(0.0, TAU);
+        let brightness = 0.15 + 0.85 * rng.next_f64().powi(3);
+        let r = (1.0 - z * z).sqrt();
+        Star { direction: DVec3::new(r * angle.cos(), r * angle.sin(), z), brightness: brightness as f32 }
+    }).collect()
+}
+
+/// The stars last drawn, and the seed and count they came from.
+#[derive(Default)]
+pub struct StarCache {
+    key: Option<(u64, usize)>,
+    stars: Vec<Star>,
+}
+
+pub fn apply_background(view_settings: Res<ViewSettings>, mut clear_color: ResMut<ClearColor>) {
+    let color = match view_settings.background {
+        Background::Solid([r, g, b]) => Color::srgb_u8(r, g, b),
+        Background::Starfield { .. } => Color::BLACK,
+    };
+    // Only written when it changes, so nothing downstream sees it change every frame
+    if clear_color.0 != color {
+        clear_color.0 = color;
+    }
+}
+
+/// Stars stay put as the camera moves, since they're drawn around it, but turn with it and
+/// with the reference plane.
+pub fn render_starfield(
+    view_settings: Res<ViewSettings>,
+    camera: Single<(&GlobalTransform, &Transform), PrimaryCamera>,
+    origin: Res<SceneOrigin>,
+    mut cache: Local<StarCache>,
+    mut gizmos: Gizmos<StarfieldGizmos>,
+) {
+    let Background::Starfield { seed, count } = view_settings.background else { return };
+    if cache.key != Some((seed, count)) {
+        cache.stars = star_directions(seed, count);
+        cache.key = Some((seed, count));
+    }
+
+    let (global_transform, transform) = *camera;
+    let center = global_transform.translation();
+    let up = transform.up() * (STAR_DISTANCE * STAR_SIZE);
+    let rotation = origin.bevy_rotation();
+    for star in &cache.stars {
+        let position = center + rotation * star.direction.as_bevy() * STAR_DISTANCE;
+        let color = Srgba::new(1.0, 1.0, 1.0, star.brightness);
+        gizmos.line(position, position + up, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_star_directions_stable() {
+        let stars = star_directions(42, 500);
+        assert_eq!(stars, star_directions(42, 500));
+        assert_ne!(stars, star_directions(43, 500));
+        // More stars from the same seed start with the same ones
+        assert_eq!(&star_directions(42, 1000)[..500], &stars[..]);
+
+        let first = stars[0];
+        let expected = DVec3::new(0.46955862477577337, 0.73898601832139, 0.4831297575436466);
+        assert!(first.direction.abs_diff_eq(expected, 1e-12), "{:?}", first);
+        assert!((first.brightness - 0.16838093).abs() < 1e-6, "{:?}", first);
+
+        for star in &stars {
+            assert!((star.direction.length() - 1.0).abs() < 1e-12, "{:?}", star);
+            assert!((0.15..=1.0).contains(&star.brightness), "{:?}", star);
+        }
+    }
+}
@@ -1,29 +1,144 @@
+//! Orbits drawn as one line strip mesh per body, baked when the trajectory is recalculated.
+//!
+//! The mesh is in meters relative to the body's primary, so following the primary and
+//! changing the distance scale (linear or logarithmic) only touches the line's `Transform`.
+
 use bevy::prelude::*;
-use bevy::color::Srgba;
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::visibility::NoFrustumCulling;
 use bevy::math::{DVec3, FloatExt};
+use bevy::mesh::PrimitiveTopology;
 use bevy::render::view::ColorGrading;
-use itertools::Itertools;
 use num_traits::Pow;
+use crate::body::appearance::AssetCache;
 use crate::body::motive::info::{BodyInfo, BodyState};
-use crate::body::motive::{Motive, MotiveSelection};
-use crate::body::universe::save::ViewSettings;
-use crate::gui::planetarium::PlanetariumCamera;
+use crate::body::universe::save::{TrajectoryFade, ViewSettings};
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
 use crate::gui::planetarium::time::SimTime;
 use crate::gui::settings::{DisplayGlow, Settings};
 use crate::gui::util::freecam::Freecam;
 use crate::util::bevystuff::GlamVec;
+use crate::util::time_map::TimeMap;
+
+/// The baked trajectory of `body`.
+#[derive(Component)]
+pub struct TrajectoryLine {
+    body: Entity,
+    /// `BodyState::trajectory_revision` the mesh was baked from.
+    revision: u32,
+    /// Segment the body was in when the line was last colored.
+    lit_segment: Option<usize>,
+    lit_brightness: (f32, f32),
+    lit_fade: TrajectoryFade,
+}
+
+/// One vertex per trajectory point, colored by `color_trajectory_mesh`.
+pub fn trajectory_mesh(trajectory: &TimeMap<DVec3>) -> Mesh {
+    let positions: Vec<Vec3> = trajectory.iter().map(|(_, displacement)| displacement.as_bevy()).collect();
+    let colors = vec![[0.0f32; 4]; positions.len()];
+    Mesh::new(PrimitiveTopology::LineStrip, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+}
+
+/// How bright the trajectory is at `vertex_frac` of the way round, with the body at `body_frac`.
+/// Dark ahead of the body, brightening behind it into a trail.
+pub fn vertex_brightness(vertex_frac: f32, body_frac: f32) -> f32 {
+    let forward_offset = (vertex_frac - body_frac + 1.0) % 1.0;
+    if forward_offset <= 0.5 {
+        0.0
+    } else {
+        (forward_offset - 0.5) * 2.0
+    }
+}
+
+/// `vertex_brightness` for each way a trajectory can fade. Ahead of the body is the trail
+/// run backwards.
+pub fn faded_brightness(fade: TrajectoryFade, vertex_frac: f32, body_frac: f32) -> f32 {
+    match fade {
+        TrajectoryFade::Uniform => 1.0,
+        TrajectoryFade::Trail => vertex_brightness(vertex_frac, body_frac),
+        TrajectoryFade::Ahead => vertex_brightness(body_frac, vertex_frac),
+        TrajectoryFade::Both => vertex_brightness(vertex_frac, body_frac).max(vertex_brightness(body_frac, vertex_frac)),
+    }
+}
+
+fn color_trajectory_mesh(mesh: &mut Mesh, body_frac: f32, (min_brightness, max_brightness): (f32, f32), fade: TrajectoryFade) {
+    let count = mesh.count_vertices();
+    let colors: Vec<[f32; 4]> = (0..count).map(|i| {
+        let brightness = min_brightness.lerp(max_brightness, faded_brightness(fade, i as f32 / count as f32, body_frac));
+        // Brighter than 1 lets the glow settings bloom
+        [0.0, brightness, 0.0, brightness.min(1.0)]
+    }).collect();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+}
+
+/// Spawns, rebakes and despawns trajectory lines to match the bodies' trajectories.
+pub fn bake_trajectories(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cache: ResMut<AssetCache>,
+    bodies: Query<(Entity, &BodyState)>,
+    mut lines: Query<(Entity, &mut TrajectoryLine, &Mesh3d)>,
+) {
+    let mut baked = Vec::with_capacity(lines.iter().len());
+    for (entity, mut line, mesh) in lines.iter_mut() {
+        let trajectory = bodies.get(line.body).ok().and_then(|(_, state)| state.trajectory.as_ref().map(|trajectory| (trajectory, state.trajectory_revision)));
+        let Some((trajectory, revision)) = trajectory else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        baked.push(line.body);
+        if line.revision == revision { continue; }
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = trajectory_mesh(trajectory);
+        }
+        line.revision = revision;
+        line.lit_segment = None;
+    }
 
+    for (body, state) in bodies.iter() {
+        let Some(trajectory) = &state.trajectory else { continue };
+        if baked.contains(&body) { continue; }
+        let material = cache.materials.entry("trajectory".into()).or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            })
+        }).clone();
+        commands.spawn((
+            TrajectoryLine { body, revision: state.trajectory_revision, lit_segment: None, lit_brightness: (0.0, 0.0), lit_fade: TrajectoryFade::default() },
+            Mesh3d(meshes.add(trajectory_mesh(trajectory))),
+            MeshMaterial3d(material),
+            Transform::default(),
+            Visibility::default(),
+            NoFrustumCulling,
+        ));
+    }
+}
+
+pub fn unload_trajectory_lines(mut commands: Commands, lines: Query<Entity, With<TrajectoryLine>>) {
+    for entity in lines.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Moves each line onto its primary, or whatever its trajectory is relative to, and recolors it as the body goes round.
 pub fn render_trajectories(
-    bodies: Query<(&BodyState, &BodyInfo, &Motive)>,
-    mut gizmos: Gizmos,
+    bodies: Query<(&BodyState, &BodyInfo)>,
+    mut lines: Query<(&mut TrajectoryLine, &Mesh3d, &mut Transform, &mut Visibility)>,
+    mut meshes: ResMut<Assets<Mesh>>,
     view_settings: Res<ViewSettings>,
     settings: Res<Settings>,
-    fcam: Single<&Freecam, With<PlanetariumCamera>>,
+    fcam: Single<&Freecam, PrimaryCamera>,
     sim_time: Res<SimTime>,
-    color_grading: Single<&ColorGrading>,
+    color_grading: Single<&ColorGrading, PrimaryCamera>,
+    origin: Res<SceneOrigin>,
 ) {
     let distance_scale = view_settings.distance_factor();
-    let current_time = sim_time.time;
 
     let exposure = color_grading.global.exposure;
 
@@ -34,87 +149,81 @@ pub fn render_trajectories(
         DisplayGlow::Defcon => { (0.2, 10.0) }
     };
     let exposure_adjust = 2f32.pow(-exposure);
-    let min_brightness = min_brightness * exposure_adjust;
-    let max_brightness = max_brightness * exposure_adjust;
+    let brightness = (min_brightness * exposure_adjust, max_brightness * exposure_adjust);
 
-    let mut color = Srgba::new(1.0, 0.0, 0.0, 1.0);
-    for (state, info, motive) in bodies.iter() {
-        if !(view_settings.show_trajectories || view_settings.body_in_any_trajectory_tag(&info.id)) {
+    for (mut line, mesh, mut transform, mut visibility) in lines.iter_mut() {
+        let Ok((state, info)) = bodies.get(line.body) else { continue };
+        let Some(trajectory) = &state.trajectory else { continue };
+        if !view_settings.trajectory_visible(&info.id) {
+            *visibility = Visibility::Hidden;
             continue;
         }
-        if let Some(trajectory) = &state.trajectory {
-            let len = trajectory.len();
-            let frac = match trajectory.periodicity() {
-                None => 0.0,
-                Some(periodicity) => {
-                    periodicity.cycle_fraction(sim_time.time.to_j2000_seconds())
-                }
-            };
-
-            // Get the primary_id if this is a Keplerian motive
-            let primary_id = match motive.motive_at(current_time) {
-                (_, MotiveSelection::Keplerian(k)) => Some(&k.primary_id),
-                _ => None,
-            };
-
-            // TODO: this doesn't track for the future.
-            let primary_d: Option<Vec<DVec3>> = primary_id
-                .and_then(|id| {
-                    bodies.iter().find(|(_, info, _)| { &info.id == id })
-                })
-                .and_then(|(primary_state, _, _)| {
-                    if primary_state.trajectory.is_none() { return None; }
-                    let _primary_trajectory = primary_state.trajectory.as_ref().unwrap();
-                    Some(trajectory.iter().map(|(_t, _)| {
-                        // primary_trajectory.get_lerp(t)
-                        primary_state.current_position
-                    }).collect())
-                });
-
-            for (idx, ((t1, d1), (t2, d2))) in trajectory.iter().tuple_windows().enumerate() {
-                let (d1, d2) = match &primary_d {
-                    None => (d1.clone(), d2.clone()),
-                    Some(primary_d) => (d1 + primary_d[idx], d2 + primary_d[idx + 1])
-                };
-
-                // Calculate the fractional position of this trajectory segment
-                let segment_frac = idx as f32 / len as f32;
-                let next_segment_frac = (idx + 1) as f32 / len as f32;
-                
-                // Check if planet is currently within this segment
-                let planet_in_segment = if next_segment_frac > segment_frac {
-                    frac as f32 >= segment_frac && (frac as f32) < next_segment_frac
-                } else {
-                    // Handle wraparound case
-                    frac as f32 >= segment_frac || (frac as f32) < next_segment_frac
-                };
-                
-                let brightness_factor = if planet_in_segment {
-                    // Smooth fade within current segment based on planet's position within it
-                    let progress_through_segment = if next_segment_frac > segment_frac {
-                        (frac as f32 - segment_frac) / (next_segment_frac - segment_frac)
-                    } else {
-                        // Handle wraparound
-                        if frac as f32 >= segment_frac {
-                            (frac as f32 - segment_frac) / (1.0 - segment_frac + next_segment_frac)
-                        } else {
-                            (frac as f32 + 1.0 - segment_frac) / (1.0 - segment_frac + next_segment_frac)
-                        }
-                    };
-                    progress_through_segment // Fade from 0.0 to 1.0 as planet moves through segment
-                } else {
-                    // Use sharp discontinuity for all other segments
-                    let forward_offset = (segment_frac - frac as f32 + 1.0) % 1.0;
-                    if forward_offset <= 0.5 {
-                        0.0  // Dark ahead of planet
-                    } else {
-                        (forward_offset - 0.5) * 2.0  // Brightens as we go behind planet (trail)
-                    }
-                };
-                
-                color = Srgba::new(0.0, 1.0, 0.0, min_brightness.lerp(max_brightness, brightness_factor));
-                gizmos.line(d1.as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos), d2.as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos), color);
-            }
+        *visibility = Visibility::Inherited;
+
+        // TODO: this doesn't track for the future.
+        let primary_position = match state.trajectory_origin {
+            Some(origin) => bodies.get(origin).map(|(origin, _)| origin.current_position).unwrap_or(DVec3::ZERO),
+            None => state.current_primary_position.unwrap_or(DVec3::ZERO),
+        };
+        transform.translation = origin.recenter(primary_position).as_bevy_scaled_cheated(distance_scale, fcam.bevy_pos);
+        transform.rotation = origin.bevy_rotation();
+        transform.scale = Vec3::splat(distance_scale as f32);
+
+        let frac = match trajectory.periodicity() {
+            None => 0.0,
+            Some(periodicity) => periodicity.cycle_fraction(sim_time.time.to_j2000_seconds()),
+        } as f32;
+        // Within a segment, the vertex colors blend the trail into the body
+        let segment = (frac * trajectory.len() as f32) as usize;
+        let fade = view_settings.trajectory_fade;
+        if line.lit_segment == Some(segment) && line.lit_brightness == brightness && line.lit_fade == fade { continue; }
+        let Some(mesh) = meshes.get_mut(&mesh.0) else { continue };
+        color_trajectory_mesh(mesh, frac, brightness, fade);
+        line.lit_segment = Some(segment);
+        line.lit_brightness = brightness;
+        line.lit_fade = fade;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mesh_has_vertex_per_point() {
+        let resolution = ViewSettings::default().trajectory_resolution;
+        let mut trajectory = TimeMap::new();
+        for i in 0..=resolution {
+            let angle = i as f64 / resolution as f64 * std::f64::consts::TAU;
+            trajectory.insert(angle, DVec3::new(angle.cos(), angle.sin(), 0.0) * 1.5e11);
+        }
+        let mut mesh = trajectory_mesh(&trajectory);
+        assert_eq!(mesh.count_vertices(), resolution + 1);
+        assert_eq!(mesh.primitive_topology(), PrimitiveTopology::LineStrip);
+
+        color_trajectory_mesh(&mut mesh, 0.25, (0.1, 1.0), TrajectoryFade::Trail);
+        let colors = mesh.attribute(Mesh::ATTRIBUTE_COLOR).unwrap();
+        assert_eq!(colors.len(), resolution + 1);
+        // Just behind the body is brightest, and the half ahead of it is dark
+        assert!((vertex_brightness(0.2499, 0.25) - 1.0).abs() < 1e-3);
+        assert_eq!(vertex_brightness(0.5, 0.25), 0.0);
+    }
+
+    #[test]
+    fn test_faded_brightness() {
+        let body = 0.25;
+        for vertex in [0.0, 0.1, 0.3, 0.7, 0.9] {
+            assert_eq!(faded_brightness(TrajectoryFade::Uniform, vertex, body), 1.0);
+            assert_eq!(faded_brightness(TrajectoryFade::Trail, vertex, body), vertex_brightness(vertex, body));
         }
+        // Just ahead of the body is brightest looking forward, and the half behind it is dark
+        assert!((faded_brightness(TrajectoryFade::Ahead, 0.2501, body) - 1.0).abs() < 1e-3);
+        assert_eq!(faded_brightness(TrajectoryFade::Ahead, 0.0, body), 0.0);
+        assert!(faded_brightness(TrajectoryFade::Ahead, 0.3, body) > faded_brightness(TrajectoryFade::Ahead, 0.6, body));
+
+        // Both ways is bright either side of the body, and dark opposite it
+        assert!((faded_brightness(TrajectoryFade::Both, 0.2499, body) - 1.0).abs() < 1e-3);
+        assert!((faded_brightness(TrajectoryFade::Both, 0.2501, body) - 1.0).abs() < 1e-3);
+        assert!(faded_brightness(TrajectoryFade::Both, 0.75, body) < 1e-6);
     }
 }
@@ -0,0 +1,103 @@
+//! Click two bodies to see how far apart they are.
+
+use bevy::prelude::*;
+use bevy::color::Srgba;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::SimulationObject;
+use crate::foundations::SPEED_OF_LIGHT;
+use crate::gui::planetarium::PrimaryCamera;
+use crate::gui::planetarium::picking::{self, PICK_THRESHOLD};
+use crate::util::format::{seconds_to_naive_date, si_prefixed};
+
+#[derive(Resource, Default)]
+pub struct Measurement {
+    /// Clicks pick bodies to measure between.
+    pub active: bool,
+    pub first: Option<Entity>,
+    pub second: Option<Entity>,
+}
+
+impl Measurement {
+    /// The first click starts a measurement, the second finishes it, and the next starts over.
+    fn pick(&mut self, entity: Entity) {
+        match (self.first, self.second) {
+            (Some(first), None) if first != entity => self.second = Some(entity),
+            (Some(_), None) => {}
+            _ => {
+                self.first = Some(entity);
+                self.second = None;
+            }
+        }
+    }
+}
+
+pub fn measure_clicks(
+    mut measurement: ResMut<Measurement>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), PrimaryCamera>,
+    bodies: Query<(Entity, &Transform), With<SimulationObject>>,
+    mut contexts: EguiContexts,
+) {
+    if !measurement.active || !mouse_buttons.just_pressed(MouseButton::Left) { return; }
+    if let Ok(ctx) = contexts.ctx_mut() {
+        if ctx.wants_pointer_input() { return; }
+    }
+    let Some(cursor) = window.cursor_position() else { return };
+
+    let (camera, camera_transform) = *camera;
+    let projected = picking::project_bodies(camera, camera_transform, bodies.iter());
+    if let Some(entity) = picking::nearest_within(cursor, &projected, PICK_THRESHOLD) {
+        measurement.pick(entity);
+    }
+}
+
+pub fn draw_measurement(
+    measurement: Res<Measurement>,
+    bodies: Query<&Transform, With<SimulationObject>>,
+    mut gizmos: Gizmos,
+) {
+    if !measurement.active { return; }
+    let (Some(first), Some(second)) = (measurement.first, measurement.second) else { return };
+    let (Ok(first), Ok(second)) = (bodies.get(first), bodies.get(second)) else { return };
+    gizmos.line(first.translation, second.translation, Srgba::new(1.0, 1.0, 0.3, 1.0));
+}
+
+pub fn measurement_overlay(
+    measurement: Res<Measurement>,
+    bodies: Query<(&BodyInfo, &BodyState)>,
+    mut contexts: EguiContexts,
+) {
+    if !measurement.active { return; }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let first = measurement.first.and_then(|entity| bodies.get(entity).ok());
+    let second = measurement.second.and_then(|entity| bodies.get(entity).ok());
+
+    egui::Area::new(egui::Id::new("measurement"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let (Some((first_info, first_state)), Some((second_info, second_state))) = (first, second) else {
+                    match first {
+                        None => ui.label("Click a body to measure from"),
+                        Some((info, _)) => ui.label(format!("From {}, click another body", info.display_name())),
+                    };
+                    return;
+                };
+                ui.label(format!("{} to {}", first_info.display_name(), second_info.display_name()));
+                let separation = first_state.current_position.distance(second_state.current_position);
+                ui.label(format!("Distance: {}", si_prefixed(separation, "m")));
+                let light_time = separation / SPEED_OF_LIGHT;
+                if light_time < 60.0 {
+                    ui.label(format!("Light time: {}", si_prefixed(light_time, "s")));
+                } else {
+                    ui.label(format!("Light time: {}", seconds_to_naive_date(light_time.round() as i64)));
+                }
+                if let (Some(first_velocity), Some(second_velocity)) = (first_state.current_velocity, second_state.current_velocity) {
+                    ui.label(format!("Relative speed: {}", si_prefixed(first_velocity.distance(second_velocity), "m/s")));
+                }
+            });
+        });
+}
@@ -1,31 +1,49 @@
 use std::collections::HashMap;
 use bevy::app::{App, Update};
-use bevy::math::DVec3;
+use bevy::math::{DMat3, DQuat, DVec3};
 use bevy::light::PointLight;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
-use gizmoids::trajectory;
-use crate::body::appearance::{Appearance, AssetCache};
-use crate::body::universe::save::{UniverseFile, UniversePhysics, ViewSettings};
-use crate::body::universe::{Major, Minor, Universe};
+use gizmoids::{grid, markers, starfield, trajectory};
+use crate::body::appearance::{self, Appearance, AssetCache};
+use crate::body::universe::save::{self, UniverseFile, UniversePhysics, UniverseReadError, ViewCenter, ViewSettings};
+use crate::body::universe::save_sqlite::{HeldEmFile, SqliteSaveError};
+use crate::body::universe::scenario::{self, Scenario};
+use crate::body::universe::{lint, Major, Minor, Universe};
+use crate::body::universe::collision;
+use crate::body::universe::deletion::{self, DeleteBody};
+use crate::body::universe::edit::{self, Edit, UndoStack};
 use crate::gui::app::AppState;
-use crate::gui::menu::{TagState, UiState};
+use crate::gui::menu::{MenuState, TagState, UiState};
 use crate::gui::planetarium::time::SimTime;
 use crate::body::{universe, unload_simulation_objects, SimulationObject};
 use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::rotation::BodyRotation;
+use crate::body::motive::{Motive, MotiveSelection};
 use crate::body::motive::calculate_body_positions::{self, PhysicsGraph, PositionCache, SimulationPerformanceMetrics};
-use crate::body::motive::kepler_motive;
-use crate::foundations::time::{Instant, J2000_JD, JD_SECONDS_PER_JULIAN_DAY};
-pub(crate) use crate::gui::planetarium::camera::{PlanetariumCamera, PlanetariumCameraPlugin};
+use crate::body::motive::kepler_motive::{self, TrajectoryDebounce};
+use crate::foundations::reference_frame::observation;
+use crate::foundations::time::Instant;
+pub(crate) use crate::gui::planetarium::camera::{CameraBookmarks, PlanetariumCamera, PlanetariumCameraPlugin, PrimaryCamera};
 use crate::gui::planetarium::windows::body_info::BodyInfoState;
+use crate::gui::settings::Settings;
 use crate::gui::util::freecam::{Freecam};
 use crate::util::bevystuff::GlamVec;
 use crate::util::mappings;
 
 pub mod time;
+pub mod autosave;
 mod windows;
 pub(crate) mod camera;
 mod gizmoids;
+mod batching;
+mod picking;
+pub mod measure;
+pub mod screenshot;
+pub mod timelapse;
+mod compass;
+mod rise_set;
+mod relative_velocity;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct PlanetariumUISet;
@@ -43,6 +61,40 @@ pub struct CalculateTrajectory {
     pub selection: BodySelection,
 }
 
+/// Simulation position drawn at the origin of the scene, per `ViewSettings::center`,
+/// and the rotation into the reference plane the scene is drawn in.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SceneOrigin {
+    pub position: DVec3,
+    /// From the simulation's ecliptic frame into the drawn frame.
+    pub rotation: DMat3,
+}
+
+impl SceneOrigin {
+    /// Where a body at `position` is drawn, before scaling.
+    pub fn recenter(&self, position: DVec3) -> DVec3 {
+        self.rotation * (position - self.position)
+    }
+
+    /// Undoes `recenter`.
+    pub fn simulation_position(&self, scene_position: DVec3) -> DVec3 {
+        self.position + self.rotation.transpose() * scene_position
+    }
+
+    /// `rotation` for things drawn in Bevy space, like trajectory meshes.
+    pub fn bevy_rotation(&self) -> Quat {
+        self.bevy_orientation(DMat3::IDENTITY)
+    }
+
+    /// How to turn something in Bevy space whose own axes are at `frame` in the simulation,
+    /// like a body's `BodyRotation::body_to_reference`.
+    pub fn bevy_orientation(&self, frame: DMat3) -> Quat {
+        // The same swap of axes `GlamVec::as_bevy` does
+        let to_bevy = DMat3::from_cols(DVec3::X, DVec3::NEG_Z, DVec3::Y);
+        DQuat::from_mat3(&(to_bevy * self.rotation * frame * to_bevy.transpose())).as_quat()
+    }
+}
+
 pub enum BodySelection {
     All,
     Tag(String),
@@ -56,37 +108,113 @@ impl Plugin for PlanetariumUI {
             .init_resource::<UniversePhysics>()
             .init_resource::<ViewSettings>()
             .init_resource::<AssetCache>()
+            .init_resource::<SceneOrigin>()
             .init_resource::<BodyInfoState>()
             .init_resource::<PhysicsGraph>()
             .init_resource::<PositionCache>()
             .init_resource::<SimulationPerformanceMetrics>()
+            .init_resource::<UndoStack>()
+            .init_resource::<TrajectoryDebounce>()
+            .init_resource::<markers::Markers>()
+            .init_resource::<grid::GridLabels>()
+            .init_resource::<measure::Measurement>()
+            .init_resource::<Scenario>()
+            .init_resource::<autosave::Autosave>()
+            .init_resource::<screenshot::Capture>()
+            .init_resource::<timelapse::TimeLapse>()
+            .init_resource::<HeldEmFile>()
             .add_message::<CalculateTrajectory>()
+            .add_message::<DeleteBody>()
+            .add_message::<Edit>()
+            .insert_gizmo_config(starfield::StarfieldGizmos, GizmoConfig {
+                // As far back as can be, so every body is drawn over the stars
+                depth_bias: 1.0,
+                ..default()
+            })
             .configure_sets(Update, (
                 PlanetariumUISet.run_if(in_state(AppState::Planetarium)),
                 PlanetariumSimulationSet.run_if(in_state(AppState::Planetarium)),
                 PlanetariumLoadingSet.run_if(in_state(AppState::PlanetariumLoading)),
             ))
             .add_plugins(PlanetariumCameraPlugin)
+            .add_plugins(MaterialPlugin::<batching::BallBatchMaterial>::default())
             .add_systems(EguiPrimaryContextPass, (
                 (
-                    windows::controls::control_window,
-                    windows::body_edit::body_edit_window,
-                    windows::body_info::body_info_window,
-                    windows::settings::settings_window,
-                    windows::spin::spin_window,
-                    windows::camera::camera_window,
+                    (
+                        windows::controls::control_window,
+                        windows::body_edit::body_edit_window,
+                        windows::body_info::body_info_window,
+                        windows::settings::settings_window,
+                        windows::spin::spin_window,
+                        windows::camera::camera_window,
+                        windows::diagnostics::diagnostics_window,
+                        windows::timelapse::timelapse_window,
+                        windows::hohmann::hohmann_window,
+                        windows::csv_import::csv_import_window,
+                        windows::save_diff::save_diff_window,
+                        rise_set::rise_set_window,
+                    ),
+                    edit::undo_shortcuts,
+                    time::time_shortcuts,
+                    time::throttle_notice,
+                    camera::focus_shortcuts,
 
                     label_bodies,
-                    ).run_if(in_state(AppState::Planetarium)),
+                    markers::label_markers,
+                    grid::label_grid,
+                    measure::measurement_overlay,
+                    picking::hover_tooltip,
+                    compass::axis_compass,
+                    relative_velocity::relative_velocity_overlay,
+                    windows::remember_window_positions,
+                    ).run_if(in_state(AppState::Planetarium)).run_if(screenshot::ui_shown),
                 ))
             .add_systems(Update, (
                 (
                     adjust_lights,
+                    edit::apply_edit
+                        .before(deletion::delete_bodies),
+                    deletion::delete_bodies
+                        .before(calculate_body_positions::calculate_body_positions),
                     calculate_body_positions::calculate_body_positions
                         .after(universe::advance_time),
-                    kepler_motive::calculate_trajectory,
-                    position_bodies.after(calculate_body_positions::calculate_body_positions),
-                    trajectory::render_trajectories,
+                    kepler_motive::flush_trajectory_debounce
+                        .before(kepler_motive::calculate_trajectory),
+                    kepler_motive::calculate_trajectory
+                        .after(deletion::delete_bodies),
+                    scenario::run_scenario
+                        .after(calculate_body_positions::calculate_body_positions),
+                    collision::resolve_collisions
+                        .after(calculate_body_positions::calculate_body_positions),
+                    dress_spawned_bodies,
+                    update_scene_origin.after(calculate_body_positions::calculate_body_positions),
+                    position_bodies.after(update_scene_origin),
+                    select_mesh_detail.after(position_bodies),
+                    batching::batch_minor_balls.after(dress_spawned_bodies),
+                    autosave::autosave,
+                ).in_set(PlanetariumUISet),
+                (
+                    batching::update_batches
+                        .after(position_bodies)
+                        .after(batching::batch_minor_balls),
+                    trajectory::bake_trajectories
+                        .after(kepler_motive::calculate_trajectory),
+                    trajectory::render_trajectories
+                        .after(trajectory::bake_trajectories)
+                        .after(position_bodies),
+                    markers::render_markers.after(update_scene_origin),
+                    markers::render_spheres_of_influence.after(update_scene_origin),
+                    grid::render_grid.after(update_scene_origin),
+                    starfield::apply_background,
+                    starfield::render_starfield.after(update_scene_origin),
+                    measure::measure_clicks.after(position_bodies),
+                    picking::click_to_select.after(position_bodies),
+                    measure::draw_measurement.after(position_bodies),
+                    screenshot::screenshot_shortcut,
+                    // Before playback can queue anything this frame
+                    timelapse::run_timelapse
+                        .before(universe::advance_time)
+                        .before(screenshot::screenshot_shortcut),
                 ).in_set(PlanetariumUISet),
                 (
                     universe::advance_time,
@@ -94,7 +222,7 @@ impl Plugin for PlanetariumUI {
                 (load_assets).in_set(PlanetariumLoadingSet),
             ))
             .add_systems(OnExit(AppState::PlanetariumLoading), initial_trajectories)
-            .add_systems(OnExit(AppState::Planetarium), unload_simulation_objects)
+            .add_systems(OnExit(AppState::Planetarium), (unload_simulation_objects, batching::unload_batches, trajectory::unload_trajectory_lines, edit::clear_undo_stack, autosave::reset_autosave, release_held_file))
         ;
 
 
@@ -103,6 +231,11 @@ impl Plugin for PlanetariumUI {
 
 // The calculate_body_positions system is imported from motive/calculate_body_positions.rs
 
+/// Lets go of the open .em file, so other copies of the program can have it.
+fn release_held_file(mut held: ResMut<HeldEmFile>) {
+    held.release();
+}
+
 fn initial_trajectories(mut calcs: MessageWriter<CalculateTrajectory>) {
     calcs.write(CalculateTrajectory { selection: BodySelection::All });
 }
@@ -174,26 +307,60 @@ fn scale_distant_objects(
     }
 }
 
+fn update_scene_origin(
+    view_settings: Res<ViewSettings>,
+    settings: Res<Settings>,
+    bodies: Query<(&BodyInfo, &BodyState)>,
+    mut origin: ResMut<SceneOrigin>,
+) {
+    origin.rotation = settings.display.reference_plane.from_ecliptic();
+    origin.position = match &view_settings.center {
+        ViewCenter::Origin => DVec3::ZERO,
+        ViewCenter::Body(id) => bodies.iter()
+            .find(|(info, _)| &info.id == id)
+            .map(|(_, state)| state.current_position)
+            .unwrap_or(DVec3::ZERO),
+    };
+}
+
 fn position_bodies(
-    mut bodies: Query<(&SimulationObject, &mut Transform, &BodyInfo, &BodyState, &Appearance)>,
-    camera: Query<&Freecam, With<PlanetariumCamera>>,
+    mut bodies: Query<(&SimulationObject, &mut Transform, &BodyInfo, &BodyState, &Appearance, Option<&BodyRotation>)>,
+    camera: Query<&Freecam, PrimaryCamera>,
     view_settings: Res<ViewSettings>,
+    motives: Query<(&BodyInfo, &BodyState, &Motive)>,
+    sim_time: Res<SimTime>,
+    physics: Res<UniversePhysics>,
+    origin: Res<SceneOrigin>,
 ) {
-    // TODO: Move the origin to the main camera.
+    // Positions are relative to the camera, which stays at the Bevy origin, to keep f32 precision
     let distance_scale = view_settings.distance_factor();
 
-    let freecam = camera.single().unwrap();
+    // Nothing to be relative to until a camera's been spawned
+    let Ok(freecam) = camera.single() else { return };
 
-    for (_, mut transform, _, state, appearance) in bodies.iter_mut() {
+    let light_time = view_settings.apply_light_time && !view_settings.logarithmic_distance_scale;
+    let retarded: HashMap<&str, (&Motive, &BodyState, f64)> = if light_time {
+        motives.iter().map(|(info, state, motive)| (info.id.as_str(), (motive, state, info.mass))).collect()
+    } else {
+        HashMap::new()
+    };
+    let camera_position = origin.simulation_position(freecam.bevy_pos.as_regular() / distance_scale);
+
+    for (_, mut transform, info, state, appearance, rotation) in bodies.iter_mut() {
         // TODO: I doubt any of this works for moonmoons.
-        let global_position: DVec3 = if !view_settings.logarithmic_distance_scale || state.current_local_position.is_none() || state.current_primary_position.is_none() {
+        let global_position: DVec3 = if light_time {
+            observation::light_time_corrected(camera_position, sim_time.time, |time| {
+                position_at(&info.id, time, sim_time.time, &retarded, physics.gravitational_constant, 0)
+                    .unwrap_or(state.current_position)
+            })
+        } else if !view_settings.logarithmic_distance_scale || state.current_local_position.is_none() || state.current_primary_position.is_none() {
             state.current_position  // Use calculated position *unless* we are doing logarithmic distance scale and current object has a primary.
         } else {
             let local_position = state.current_local_position.unwrap();
             let primary_position = state.current_primary_position.unwrap();
             primary_position + local_position
         };
-        transform.translation = global_position.as_bevy_scaled_cheated(distance_scale, freecam.bevy_pos);
+        transform.translation = origin.recenter(global_position).as_bevy_scaled_cheated(distance_scale, freecam.bevy_pos);
 
         //let body_scale = view_settings.body_scale_factor(appearance.radius());
         //transform.scale = Vec3::splat(body_scale);
@@ -203,11 +370,68 @@ fn position_bodies(
             appearance.radius() * view_settings.body_scale
         } as f32;
         transform.scale = Vec3::splat(body_scale);
+
+        // Meshes have their poles along Bevy +Y, which is simulation +Z
+        transform.rotation = match rotation {
+            Some(rotation) => origin.bevy_orientation(rotation.body_to_reference(sim_time.time)),
+            None => Quat::IDENTITY,
+        };
+    }
+}
+
+/// Where a body was at `time`, a little before `now`. Keplerian and fixed bodies are exact, but
+/// Newtonian ones are extrapolated back along their current velocity.
+pub(crate) fn position_at(id: &str, time: Instant, now: Instant, bodies: &HashMap<&str, (&Motive, &BodyState, f64)>, gravitational_constant: f64, depth: usize) -> Option<DVec3> {
+    // Deeper than any real hierarchy, so probably a cycle
+    if depth > 16 { return None; }
+    let (motive, state, _) = bodies.get(id)?;
+    let primary = |primary_id: &str| {
+        let mass = bodies.get(primary_id)?.2;
+        let position = position_at(primary_id, time, now, bodies, gravitational_constant, depth + 1)?;
+        Some((position, mass))
+    };
+    match &motive.motive_at(time).1 {
+        MotiveSelection::Fixed { primary_id: None, position } => Some(*position),
+        MotiveSelection::Fixed { primary_id: Some(primary_id), position } => primary(primary_id).map(|(primary, _)| primary + *position),
+        MotiveSelection::Keplerian(kepler) => {
+            let (primary, mass) = primary(&kepler.primary_id)?;
+            Some(primary + kepler.displacement(time, gravitational_constant * mass)?)
+        }
+        MotiveSelection::Newtonian { .. } => {
+            let velocity = state.current_velocity.unwrap_or(DVec3::ZERO);
+            Some(state.current_position - velocity * (now - time).to_seconds())
+        }
+    }
+}
+
+/// Draws balls that are only a few pixels across with fewer triangles.
+fn select_mesh_detail(
+    cameras: Query<(&Camera, &GlobalTransform), With<PlanetariumCamera>>,
+    mut bodies: Query<(&Transform, &mut Mesh3d), With<SimulationObject>>,
+    mut cache: ResMut<AssetCache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (transform, mut mesh) in bodies.iter_mut() {
+        // Detailed enough for whichever camera it's closest to, where it's drawn largest
+        let nearest = cameras.iter()
+            .min_by(|(_, a), (_, b)| a.translation().distance_squared(transform.translation)
+                .total_cmp(&b.translation().distance_squared(transform.translation)));
+        let Some((camera, camera_transform)) = nearest else { return };
+        let right = camera_transform.right().as_vec3();
+        let center = camera.world_to_viewport(camera_transform, transform.translation);
+        let edge = camera.world_to_viewport(camera_transform, transform.translation + right * transform.scale.x);
+        let (Ok(center), Ok(edge)) = (center, edge) else { continue };
+
+        let handle = cache.sphere(&mut meshes, appearance::lod_subdivisions(center.distance(edge)));
+        if mesh.0 != handle {
+            mesh.0 = handle;
+        }
     }
 }
 
 fn label_bodies(
     view_settings: Res<ViewSettings>,
+    settings: Res<Settings>,
     mut contexts: EguiContexts,
     cameras: Query<(&Camera, &Camera3d, &PlanetariumCamera, &GlobalTransform)>,
     bodies: Query<(&SimulationObject, &mut Transform, &BodyInfo)>,
@@ -215,23 +439,29 @@ fn label_bodies(
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
-    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("body_labels")));
+    let layer_painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("body_labels")));
+    // Viewport positions are in logical pixels, which egui divides into larger points when scaled up
+    let zoom = ctx.zoom_factor();
 
     for (camera, _, _, camera_transform) in &cameras {
+        // Each camera's labels are kept to its own part of the window
+        let Some(viewport) = camera.logical_viewport_rect() else { continue };
+        let painter = layer_painter.with_clip_rect(to_points(viewport, zoom));
         for (_, transform, body_info) in bodies.iter() {
-            if !view_settings.show_labels && !view_settings.body_in_any_visible_tag(&body_info.id) {
+            if !view_settings.label_visible(&body_info.id) {
                 continue;
             }
 
             let position = transform.translation;
+            // Already in window space, wherever in the window this camera's viewport is
             let view_pos = camera.world_to_viewport(camera_transform, position);
             match view_pos {
                 Ok(pos) => {
                     painter.text(
-                        egui::pos2(pos.x, pos.y),
+                        egui::pos2(pos.x / zoom, pos.y / zoom),
                         egui::Align2::CENTER_BOTTOM,
                         body_info.display_name(),
-                        egui::FontId::proportional(14.0),
+                        egui::FontId::proportional(settings.ui.label_font_size),
                         egui::Color32::WHITE,
                     );
                 }
@@ -241,11 +471,37 @@ fn label_bodies(
     }
 }
 
+/// A rect in logical pixels as egui points.
+fn to_points(rect: Rect, zoom: f32) -> egui::Rect {
+    egui::Rect::from_min_max(egui::pos2(rect.min.x / zoom, rect.min.y / zoom), egui::pos2(rect.max.x / zoom, rect.max.y / zoom))
+}
+
+/// Gives bodies spawned mid-simulation, such as by a scenario, something to draw.
+fn dress_spawned_bodies(
+    mut commands: Commands,
+    mut cache: ResMut<AssetCache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut calcs: MessageWriter<CalculateTrajectory>,
+    bodies: Query<(Entity, &BodyInfo, &Appearance), (Added<Appearance>, Without<Mesh3d>)>,
+) {
+    let mut ids = Vec::new();
+    for (entity, info, appearance) in bodies.iter() {
+        save::insert_render_components(&mut commands.entity(entity), appearance, &mut cache, &mut meshes, &mut materials, &mut images);
+        ids.push(info.id.clone());
+    }
+    if !ids.is_empty() {
+        calcs.write(CalculateTrajectory { selection: BodySelection::IDs(ids) });
+    }
+}
+
 fn load_assets(
     mut commands: Commands,
     mut ui_state: ResMut<UiState>,
     mut view_settings: ResMut<ViewSettings>,
     mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
     mut cache: ResMut<AssetCache>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -254,6 +510,8 @@ fn load_assets(
     mut physics: ResMut<UniversePhysics>,
     mut sim_time: ResMut<SimTime>,
 ) {
+    commands.insert_resource(Scenario::default());
+    commands.insert_resource(HeldEmFile::default());
     if ui_state.current_save.is_none() {
         next_app_state.set(AppState::Planetarium);
         return;
@@ -262,22 +520,45 @@ fn load_assets(
     let save = (ui_state.current_save.clone()).unwrap();
     let path = save.path;
 
-    let universe_file: Option<UniverseFile> = UniverseFile::load_from_path(&path);
+    let universe_file = match UniverseFile::try_load_and_hold(&path) {
+        Ok((universe_file, held)) => {
+            commands.insert_resource(HeldEmFile::new(held));
+            Some(universe_file)
+        }
+        Err(e) => {
+            ui_state.load_error = Some(match e {
+                UniverseReadError::Sqlite(SqliteSaveError::Locked(_)) =>
+                    format!("{} is in use by another copy of the program.", save.file_name),
+                e => format!("Couldn't load {}: {:?}", save.file_name, e),
+            });
+            ui_state.current_save = None;
+            next_app_state.set(AppState::MainMenu);
+            next_menu_state.set(MenuState::Planetarium);
+            return;
+        }
+    };
     if let Some(universe_file) = universe_file {
-        let (new_universe, mut sim_time) = Universe::from_file(&universe_file);
+        for lint in lint::lint_universe(&universe_file.contents) {
+            warn!("Suspicious data in {}: {}", save.file_name, lint);
+        }
+        let (new_universe, loaded_time) = Universe::from_file(&universe_file);
         universe.path = new_universe.path.clone();
         universe.clear_all();
         let version = universe_file.contents.version; // TODO: Support multiple file format versions?
 
-        let time = (universe_file.contents.time.time_julian_days - J2000_JD) * JD_SECONDS_PER_JULIAN_DAY; // Convert Julian Days to seconds
-        sim_time.time = Instant::from_seconds_since_j2000(time);
+        *sim_time = loaded_time;
         sim_time.playing = false;
 
         physics.gravitational_constant = universe_file.contents.physics.gravitational_constant;
         view_settings.tags = HashMap::<String, TagState>::new();
 
         let bodies = universe_file.contents.bodies;
-        for body in bodies {
+        for mut body in bodies {
+            if universe.get_by_id(body.id()).is_some() {
+                let id = universe.generate_id(&body.id());
+                warn!("More than one body has the id {}, renaming one to {}", body.id(), id);
+                body.info_mut().id = id;
+            }
             let id = body.id();
             let name = body.name();
             for tag in body.tags() {
@@ -287,7 +568,156 @@ fn load_assets(
             universe.insert(name, id);
             body.spawn(&mut commands, &mut cache, &mut meshes, &mut materials, &mut images);
         }
+        let scenario = Scenario::new(universe_file.contents.scenario, sim_time.time, physics.gravitational_constant);
+        physics.gravitational_constant = scenario.gravitational_constant();
+        commands.insert_resource(scenario);
+        commands.insert_resource(CameraBookmarks(universe_file.contents.camera_bookmarks));
     }
 
     next_app_state.set(AppState::Planetarium);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::appearance::DebugBall;
+    use crate::foundations::reference_frame::plane::ReferencePlane;
+    use std::path::PathBuf;
+    use crate::body::universe::save_sqlite;
+    use crate::gui::menu::SaveFileMeta;
+    use crate::foundations::time::J2000_JD;
+
+    #[test]
+    fn test_centered_on_primary() {
+        let mut app = App::new();
+        app
+            .insert_resource(ViewSettings { center: ViewCenter::Body("earth".into()), distance_scale: 1e-6, ..default() })
+            .init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<SceneOrigin>()
+            .init_resource::<Settings>()
+            .add_systems(Update, (update_scene_origin, position_bodies.after(update_scene_origin)));
+        app.world_mut().spawn((Freecam { bevy_pos: DVec3::ZERO }, PlanetariumCamera::new()));
+
+        let earth_position = DVec3::new(1.5e11, 2.0e10, 0.0);
+        let local = DVec3::new(3.0e8, -2.0e8, 1.0e7);
+        let mut spawn = |id: &str, position: DVec3| {
+            let info = BodyInfo { id: id.into(), ..default() };
+            let state = BodyState { current_position: position, ..default() };
+            let appearance = Appearance::DebugBall(DebugBall { radius: 1.0, color: default() });
+            app.world_mut().spawn((SimulationObject, Transform::default(), info, state, appearance)).id()
+        };
+        let earth = spawn("earth", earth_position);
+        let moon = spawn("luna", earth_position + local);
+        app.update();
+
+        let translation = |entity| app.world().get::<Transform>(entity).unwrap().translation;
+        assert_eq!(translation(earth), Vec3::ZERO);
+        let expected = local.as_bevy_scaled(1e-6);
+        assert!(translation(moon).distance(expected) < 1e-3, "{} {}", translation(moon), expected);
+    }
+
+    /// Just what `load_assets` needs, to load `path` on the first update.
+    fn loading_app(path: &PathBuf, sim_time: SimTime) -> App {
+        let mut app = App::new();
+        app
+            .insert_resource(UiState { current_save: Some(SaveFileMeta { path: path.clone(), file_name: "test".into() }), ..default() })
+            .insert_resource(sim_time)
+            .init_resource::<ViewSettings>()
+            .init_resource::<NextState<AppState>>()
+            .init_resource::<NextState<MenuState>>()
+            .init_resource::<AssetCache>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<Universe>()
+            .init_resource::<UniversePhysics>()
+            .add_systems(Update, load_assets);
+        app
+    }
+
+    #[test]
+    fn test_load_assets_sets_file_time() {
+        let path = std::env::temp_dir().join(format!("load_time_{}.toml", std::process::id()));
+        let mut contents = universe::solar_system::solar_system().contents;
+        contents.time.time_julian_days = 2460000.5;
+        std::fs::write(&path, toml::to_string_pretty(&contents).unwrap()).unwrap();
+
+        let mut app = loading_app(&path, SimTime { time: Instant::from_julian_day(J2000_JD), playing: true, ..default() });
+        app.update();
+        std::fs::remove_file(&path).unwrap();
+
+        let sim_time = app.world().resource::<SimTime>();
+        assert!((sim_time.time.to_julian_day() - 2460000.5).abs() < 1e-9, "{}", sim_time.time.to_julian_day());
+        assert!(!sim_time.playing);
+    }
+
+    #[test]
+    fn test_open_em_file_stays_locked() {
+        let path = std::env::temp_dir().join(format!("held_save_{}.em", std::process::id()));
+        save_sqlite::save_to_em(&path, &universe::solar_system::solar_system().contents).unwrap();
+
+        let mut app = loading_app(&path, SimTime::default());
+        app.update();
+        assert!(app.world().resource::<HeldEmFile>().is_held());
+        // Another copy of the program can't open it while this one has it
+        assert!(matches!(save_sqlite::open_em_file(&path), Err(SqliteSaveError::Locked(_))));
+        // Though this one can still read it
+        assert!(app.world().resource::<HeldEmFile>().read().unwrap().is_ok());
+
+        app.world_mut().run_system_cached(release_held_file).unwrap();
+        assert!(save_sqlite::open_em_file(&path).is_ok());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_labels_clip_to_offset_viewport() {
+        // The right half of an 800 by 600 window, with egui zoomed in 2x
+        let clip = to_points(Rect::new(400.0, 0.0, 800.0, 600.0), 2.0);
+        assert_eq!(clip, egui::Rect::from_min_max(egui::pos2(200.0, 0.0), egui::pos2(400.0, 300.0)));
+        // world_to_viewport gives the middle of that viewport as (600, 300), already in window space
+        assert!(clip.contains(egui::pos2(600.0 / 2.0, 300.0 / 2.0)));
+        assert!(!clip.contains(egui::pos2((600.0 + 400.0) / 2.0, 300.0 / 2.0)));
+    }
+
+    #[test]
+    fn test_no_camera_yet() {
+        let mut app = App::new();
+        app
+            .init_resource::<ViewSettings>()
+            .init_resource::<SimTime>()
+            .init_resource::<UniversePhysics>()
+            .init_resource::<SceneOrigin>()
+            .init_resource::<Settings>()
+            .add_systems(Update, (update_scene_origin, position_bodies.after(update_scene_origin)));
+
+        let state = BodyState { current_position: DVec3::new(1.5e11, 0.0, 0.0), ..default() };
+        let appearance = Appearance::DebugBall(DebugBall { radius: 1.0, color: default() });
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0);
+        let body = app.world_mut().spawn((SimulationObject, transform, BodyInfo::default(), state, appearance)).id();
+        app.update();
+        // Left where it was, rather than panicking
+        assert_eq!(*app.world().get::<Transform>(body).unwrap(), transform);
+
+        // And positioned once there's a camera to be relative to
+        app.world_mut().spawn((Freecam { bevy_pos: DVec3::ZERO }, PlanetariumCamera::new()));
+        app.update();
+        assert_ne!(*app.world().get::<Transform>(body).unwrap(), transform);
+    }
+
+    #[test]
+    fn test_rotated_scene_origin() {
+        let origin = SceneOrigin {
+            position: DVec3::new(1.0e8, 0.0, 0.0),
+            rotation: ReferencePlane::Equatorial.from_ecliptic(),
+        };
+        let position = DVec3::new(3.0e8, -2.0e8, 1.0e7);
+        assert!((origin.simulation_position(origin.recenter(position)) - position).length() < 1e-6);
+
+        // Meshes rotated by `bevy_rotation` land where recentered points do
+        let offset = position - origin.position;
+        let rotated_mesh = origin.bevy_rotation() * offset.as_bevy_scaled(1e-6);
+        let recentered = origin.recenter(position).as_bevy_scaled(1e-6);
+        assert!(rotated_mesh.distance(recentered) < 1e-3, "{} {}", rotated_mesh, recentered);
+    }
+}
@@ -0,0 +1,267 @@
+//! Finding which body is under the mouse, from where bodies are drawn on screen.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::universe::save::ViewSettings;
+use crate::body::SimulationObject;
+use crate::gui::planetarium::{PrimaryCamera, SceneOrigin};
+use crate::gui::planetarium::camera::{CameraSlot, GoTo};
+use crate::gui::planetarium::measure::Measurement;
+use crate::gui::planetarium::windows::body_info::BodyInfoState;
+use crate::gui::util::freecam::Freecam;
+use crate::util::bevystuff::GlamVec;
+use crate::util::format::{sci_not, si_prefixed};
+
+/// A body as drawn on screen, in logical pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenBody {
+    pub entity: Entity,
+    pub position: Vec2,
+    /// Radius of the drawn ball.
+    pub radius: f32,
+    /// Distance from the camera, in Bevy units.
+    pub depth: f32,
+}
+
+/// How far from a body, in logical pixels, a click still counts as on it.
+pub const PICK_THRESHOLD: f32 = 12.0;
+
+/// Pixels around a drawn ball that still count as on it, so tiny ones can be clicked.
+pub const PICK_MARGIN: f32 = 4.0;
+
+/// A press that moves further than this, in logical pixels, is a drag rather than a click.
+const CLICK_SLOP: f32 = 4.0;
+
+/// Seconds between two clicks on a body for them to count as a double click.
+const DOUBLE_CLICK_TIME: f64 = 0.4;
+
+/// Seconds the mouse has to stay on a body before its tooltip shows, so it doesn't flicker
+/// while the mouse passes over things.
+const HOVER_DELAY: f64 = 0.25;
+
+impl ScreenBody {
+    /// Whether `cursor` is on the ball as drawn, give or take `margin` pixels.
+    pub fn covers(&self, cursor: Vec2, margin: f32) -> bool {
+        self.position.distance(cursor) <= self.radius + margin
+    }
+}
+
+/// Projects bodies drawn at these transforms, skipping any behind the camera.
+pub fn project_bodies<'a>(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    bodies: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Vec<ScreenBody> {
+    let right = camera_transform.right().as_vec3();
+    bodies.filter_map(|(entity, transform)| {
+        let position = camera.world_to_viewport(camera_transform, transform.translation).ok()?;
+        let edge = camera.world_to_viewport(camera_transform, transform.translation + right * transform.scale.x).ok()?;
+        Some(ScreenBody {
+            entity,
+            position,
+            radius: position.distance(edge),
+            depth: camera_transform.translation().distance(transform.translation),
+        })
+    }).collect()
+}
+
+/// The body drawn nearest `cursor`, if any is within `threshold` pixels of it.
+pub fn nearest_within(cursor: Vec2, bodies: &[ScreenBody], threshold: f32) -> Option<Entity> {
+    bodies.iter()
+        .map(|body| (body, body.position.distance(cursor)))
+        .filter(|(_, distance)| *distance <= threshold)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(body, _)| body.entity)
+}
+
+/// The body under `cursor`. Where several overlap, the one nearest the camera is in front.
+pub fn pick(cursor: Vec2, bodies: &[ScreenBody], margin: f32) -> Option<Entity> {
+    bodies.iter()
+        .filter(|body| body.covers(cursor, margin))
+        .min_by(|a, b| a.depth.total_cmp(&b.depth))
+        .map(|body| body.entity)
+}
+
+#[derive(Default)]
+pub struct ClickState {
+    pressed_at: Option<Vec2>,
+    /// The last body clicked, and when.
+    last_click: Option<(Entity, f64)>,
+}
+
+/// Clicking a body selects it, and double clicking flies the camera to it.
+/// Bodies hidden by their tags can't be clicked.
+pub fn click_to_select(
+    mut click: Local<ClickState>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), PrimaryCamera>,
+    bodies: Query<(Entity, &Transform, &BodyInfo), With<SimulationObject>>,
+    view_settings: Res<ViewSettings>,
+    measurement: Res<Measurement>,
+    mut body_info_state: ResMut<BodyInfoState>,
+    mut go_tos: MessageWriter<GoTo>,
+    time: Res<Time<Real>>,
+    mut contexts: EguiContexts,
+) {
+    let Some(cursor) = window.cursor_position() else { return };
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        let over_ui = contexts.ctx_mut().is_ok_and(|ctx| ctx.wants_pointer_input());
+        click.pressed_at = (!over_ui && !measurement.active).then_some(cursor);
+        return;
+    }
+    if !mouse_buttons.just_released(MouseButton::Left) { return; }
+    let Some(pressed_at) = click.pressed_at.take() else { return };
+    if pressed_at.distance(cursor) > CLICK_SLOP { return; }
+
+    let (camera, camera_transform) = *camera;
+    let visible = bodies.iter()
+        .filter(|(_, _, info)| view_settings.body_in_scope(&info.id))
+        .map(|(entity, transform, _)| (entity, transform));
+    let projected = project_bodies(camera, camera_transform, visible);
+    let Some(entity) = pick(cursor, &projected, PICK_MARGIN) else { return };
+    let Ok((_, _, info)) = bodies.get(entity) else { return };
+
+    body_info_state.current_body_id = Some(info.id.clone());
+    let now = time.elapsed_secs_f64();
+    if let Some((last_entity, last_time)) = click.last_click {
+        if last_entity == entity && now - last_time <= DOUBLE_CLICK_TIME {
+            go_tos.write(GoTo { entity, camera: CameraSlot::Primary });
+            click.last_click = None;
+            return;
+        }
+    }
+    click.last_click = Some((entity, now));
+}
+
+#[derive(Default)]
+pub struct HoverState {
+    /// The body under the mouse, and since when.
+    body: Option<(Entity, f64)>,
+}
+
+/// Name, type, mass and distance of the body under the mouse.
+pub fn hover_tooltip(
+    mut hover: Local<HoverState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform, &Freecam), PrimaryCamera>,
+    bodies: Query<(Entity, &Transform, &BodyInfo, &BodyState), With<SimulationObject>>,
+    view_settings: Res<ViewSettings>,
+    origin: Res<SceneOrigin>,
+    time: Res<Time<Real>>,
+    mut contexts: EguiContexts,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let cursor = window.cursor_position();
+    let over_ui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+    let Some(cursor) = cursor.filter(|_| !over_ui) else {
+        hover.body = None;
+        return;
+    };
+
+    let (camera, camera_transform, freecam) = *camera;
+    let visible = bodies.iter()
+        .filter(|(_, _, info, _)| view_settings.body_in_scope(&info.id))
+        .map(|(entity, transform, _, _)| (entity, transform));
+    let projected = project_bodies(camera, camera_transform, visible);
+    let now = time.elapsed_secs_f64();
+    let Some(entity) = pick(cursor, &projected, PICK_MARGIN) else {
+        hover.body = None;
+        return;
+    };
+    let since = match hover.body {
+        Some((hovered, since)) if hovered == entity => since,
+        _ => now,
+    };
+    hover.body = Some((entity, since));
+    if now - since < HOVER_DELAY { return; }
+    let Ok((_, _, info, state)) = bodies.get(entity) else { return };
+
+    let camera_position = origin.simulation_position(freecam.bevy_pos.as_regular() / view_settings.distance_factor());
+    let kind = if !info.tags.is_empty() {
+        info.tags.join(", ")
+    } else if info.major {
+        "Major body".to_string()
+    } else {
+        "Minor body".to_string()
+    };
+    // Viewport positions are in logical pixels, which egui divides into larger points when scaled up
+    let zoom = ctx.zoom_factor();
+    egui::Area::new(egui::Id::new("body_tooltip"))
+        .order(egui::Order::Tooltip)
+        .fixed_pos(egui::pos2(cursor.x / zoom + 16.0, cursor.y / zoom + 16.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.strong(info.display_name());
+                ui.label(kind);
+                ui.label(format!("Mass: {} kg", sci_not(info.mass)));
+                ui.label(format!("Distance: {}", si_prefixed(state.current_position.distance(camera_position), "m")));
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_within() {
+        let mut world = World::new();
+        let mut body = |x: f32, y: f32| ScreenBody {
+            entity: world.spawn_empty().id(),
+            position: Vec2::new(x, y),
+            radius: 1.0,
+            depth: 1.0,
+        };
+        let bodies = [body(100.0, 100.0), body(108.0, 100.0), body(300.0, 40.0)];
+
+        assert_eq!(nearest_within(Vec2::new(103.0, 101.0), &bodies, PICK_THRESHOLD), Some(bodies[0].entity));
+        assert_eq!(nearest_within(Vec2::new(106.0, 99.0), &bodies, PICK_THRESHOLD), Some(bodies[1].entity));
+        assert_eq!(nearest_within(Vec2::new(300.0, 51.0), &bodies, PICK_THRESHOLD), Some(bodies[2].entity));
+        assert_eq!(nearest_within(Vec2::new(200.0, 200.0), &bodies, PICK_THRESHOLD), None);
+        assert_eq!(nearest_within(Vec2::new(100.0, 100.0), &[], PICK_THRESHOLD), None);
+    }
+
+    #[test]
+    fn test_pick_nearest_of_overlapping() {
+        let mut world = World::new();
+        let mut body = |x: f32, radius: f32, depth: f32| ScreenBody {
+            entity: world.spawn_empty().id(),
+            position: Vec2::new(x, 100.0),
+            radius,
+            depth,
+        };
+        // A big distant planet with a small near moon drawn over its edge
+        let planet = body(100.0, 50.0, 100.0);
+        let moon = body(130.0, 10.0, 20.0);
+        let far_moon = body(125.0, 10.0, 500.0);
+        let bodies = [planet, moon, far_moon];
+
+        assert_eq!(pick(Vec2::new(130.0, 105.0), &bodies, PICK_MARGIN), Some(moon.entity));
+        // Only the planet is there
+        assert_eq!(pick(Vec2::new(80.0, 100.0), &bodies, PICK_MARGIN), Some(planet.entity));
+        // Just outside the moon, but the margin still catches it
+        assert_eq!(pick(Vec2::new(142.0, 100.0), &[moon], 0.0), None);
+        assert_eq!(pick(Vec2::new(142.0, 100.0), &[moon, far_moon], PICK_MARGIN), Some(moon.entity));
+        assert_eq!(pick(Vec2::new(400.0, 100.0), &bodies, PICK_MARGIN), None);
+    }
+
+    #[test]
+    fn test_covers() {
+        let mut world = World::new();
+        let ball = ScreenBody { entity: world.spawn_empty().id(), position: Vec2::new(200.0, 150.0), radius: 20.0, depth: 1.0 };
+        let dot = ScreenBody { radius: 0.5, ..ball };
+
+        assert!(ball.covers(Vec2::new(200.0, 150.0), 0.0));
+        assert!(ball.covers(Vec2::new(212.0, 166.0), 0.0));
+        assert!(!ball.covers(Vec2::new(215.0, 166.0), 0.0));
+        assert!(ball.covers(Vec2::new(215.0, 166.0), PICK_MARGIN));
+        assert!(!ball.covers(Vec2::new(200.0, 175.0), PICK_MARGIN));
+        // A ball smaller than a pixel is still easy to point at
+        assert!(dot.covers(Vec2::new(203.0, 151.0), PICK_MARGIN));
+        assert!(!dot.covers(Vec2::new(206.0, 150.0), PICK_MARGIN));
+    }
+}
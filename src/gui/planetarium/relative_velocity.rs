@@ -0,0 +1,125 @@
+//! How fast the camera is moving relative to the body it's circling. The camera moves with
+//! whatever's at the center of the scene.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::{Motive, MotiveSelection};
+use crate::body::universe::save::{UniversePhysics, ViewCenter, ViewSettings};
+use crate::foundations::time::Instant;
+use crate::gui::planetarium::camera::CameraAction;
+use crate::gui::planetarium::{PlanetariumCamera, PrimaryCamera, SceneOrigin};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
+use crate::gui::util::freecam::Freecam;
+use crate::util::bevystuff::GlamVec;
+use crate::util::format::si_prefixed;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeMotion {
+    /// Of the camera, in m/s, as seen from the body.
+    pub velocity: DVec3,
+    /// How fast the distance between them is changing, in m/s. Negative while closing.
+    pub range_rate: f64,
+}
+
+/// The camera's motion relative to a body, given where the camera is relative to it and
+/// how fast each is going.
+pub fn relative_motion(offset: DVec3, camera_velocity: DVec3, body_velocity: DVec3) -> RelativeMotion {
+    let velocity = camera_velocity - body_velocity;
+    RelativeMotion {
+        velocity,
+        range_rate: velocity.dot(offset.normalize_or_zero()),
+    }
+}
+
+/// A body's velocity, from its state where it's integrated and from its orbit where it isn't.
+fn velocity_of(id: &str, bodies: &Query<(&BodyInfo, &BodyState, &Motive)>, time: Instant, gravitational_constant: f64, depth: usize) -> Option<DVec3> {
+    // Deeper than any real hierarchy, so probably a cycle
+    if depth > 16 { return None; }
+    let (_, state, motive) = bodies.iter().find(|(info, ..)| info.id == id)?;
+    if let Some(velocity) = state.current_velocity { return Some(velocity); }
+    let primary = |primary_id: &str| velocity_of(primary_id, bodies, time, gravitational_constant, depth + 1);
+    match &motive.motive_at(time).1 {
+        MotiveSelection::Fixed { primary_id: None, .. } => Some(DVec3::ZERO),
+        MotiveSelection::Fixed { primary_id: Some(primary_id), .. } => primary(primary_id),
+        MotiveSelection::Keplerian(kepler) => {
+            let (primary_info, ..) = bodies.iter().find(|(info, ..)| info.id == kepler.primary_id)?;
+            Some(primary(&kepler.primary_id)? + kepler.velocity(time, gravitational_constant * primary_info.mass))
+        }
+        // Not integrated yet, so still at its starting velocity
+        MotiveSelection::Newtonian { velocity, .. } => Some(*velocity),
+    }
+}
+
+pub fn relative_velocity_overlay(
+    settings: Res<Settings>,
+    camera: Single<(&PlanetariumCamera, &Freecam), PrimaryCamera>,
+    bodies: Query<(&BodyInfo, &BodyState, &Motive)>,
+    view_settings: Res<ViewSettings>,
+    origin: Res<SceneOrigin>,
+    sim_time: Res<SimTime>,
+    physics: Res<UniversePhysics>,
+    mut contexts: EguiContexts,
+) {
+    let (pcam, fcam) = *camera;
+    let focused = match &pcam.action {
+        CameraAction::RevolveAround(_) => pcam.focused(),
+        _ => None,
+    };
+    let Some((info, state, _)) = focused.and_then(|entity| bodies.get(entity).ok()) else { return };
+    if !settings.ui.show_relative_velocity { return; }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let camera_position = origin.simulation_position(fcam.bevy_pos.as_regular() / view_settings.distance_factor());
+    let offset = camera_position - state.current_position;
+    let velocity = |id: &str| velocity_of(id, &bodies, sim_time.time, physics.gravitational_constant, 0);
+    let camera_velocity = match &view_settings.center {
+        ViewCenter::Origin => Some(DVec3::ZERO),
+        ViewCenter::Body(id) => velocity(id),
+    };
+    let motion = camera_velocity.zip(velocity(&info.id))
+        .map(|(camera_velocity, body_velocity)| relative_motion(offset, camera_velocity, body_velocity));
+
+    egui::Area::new(egui::Id::new("relative_velocity"))
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -8.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(format!("Relative to {}", info.display_name()));
+                ui.label(format!("Distance: {}", si_prefixed(offset.length(), "m")));
+                match motion {
+                    None => { ui.label("No velocity to compare"); }
+                    Some(motion) => {
+                        ui.label(format!("Relative speed: {}", si_prefixed(motion.velocity.length(), "m/s")));
+                        let direction = if motion.range_rate < 0.0 { "closing" } else { "opening" };
+                        ui.label(format!("Range rate: {} ({})", si_prefixed(motion.range_rate, "m/s"), direction));
+                    }
+                }
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_rate() {
+        // Straight in from 1000 m out, the body coming at 10 m/s
+        let motion = relative_motion(DVec3::X * 1000.0, DVec3::ZERO, DVec3::X * 10.0);
+        assert_eq!(motion.range_rate, -10.0);
+        assert_eq!(motion.velocity, DVec3::X * -10.0);
+
+        // Moving together doesn't open or close
+        let motion = relative_motion(DVec3::X * 1000.0, DVec3::Y * 3e4, DVec3::Y * 3e4);
+        assert_eq!(motion.velocity, DVec3::ZERO);
+        assert_eq!(motion.range_rate, 0.0);
+
+        // Only the part along the line between them counts toward the range rate
+        let motion = relative_motion(DVec3::X * 5.0, DVec3::new(3.0, 4.0, 0.0), DVec3::ZERO);
+        assert_eq!(motion.range_rate, 3.0);
+        assert_eq!(motion.velocity.length(), 5.0);
+    }
+}
@@ -0,0 +1,158 @@
+//! When the body picked in Body Info next rises and sets, seen from where the camera is standing.
+
+use std::collections::HashMap;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::appearance::Appearance;
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::Motive;
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::save::UniversePhysics;
+use crate::foundations::reference_frame::observation::{self, RiseSet};
+use crate::foundations::time::{Includes, Instant, Span};
+use crate::gui::planetarium::camera::{CameraAction, PlanetariumCamera, PrimaryCamera, SurfaceFrame};
+use crate::gui::planetarium::position_at;
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::planetarium::windows::body_info::BodyInfoState;
+use crate::util::format::seconds_to_naive_date;
+
+/// Steps to scan the window in. The window is two of the observer's days, so each is two
+/// minutes of Earth's rotation.
+const SCAN_STEPS: f64 = 1440.0;
+/// Real seconds between working it out again.
+const REFRESH_SECONDS: f64 = 0.5;
+
+/// Elevation of `target`, in radians, from `height` meters out from the center of a body
+/// at `body_position`, above latitude `lat` and longitude `lon` in degrees.
+pub fn elevation_from_surface(rotation: Option<&BodyRotation>, lat: f64, lon: f64, height: f64, body_position: DVec3, target: DVec3, time: Instant) -> f64 {
+    let up = SurfaceFrame::new(rotation, lat, lon, time).up;
+    let observer = body_position + up * height;
+    let direction = (target - observer).normalize_or_zero();
+    up.dot(direction).clamp(-1.0, 1.0).asin()
+}
+
+#[derive(Default)]
+pub struct RiseSetPanel {
+    refraction: bool,
+    refreshed_at: f64,
+    found: Option<(String, RiseSet)>,
+}
+
+pub fn rise_set_window(
+    camera: Single<&PlanetariumCamera, PrimaryCamera>,
+    body_info_state: Res<BodyInfoState>,
+    sim_time: Res<SimTime>,
+    real_time: Res<Time<Real>>,
+    physics: Res<UniversePhysics>,
+    bodies: Query<(&BodyInfo, &BodyState, &Motive, &Appearance, Option<&BodyRotation>)>,
+    mut contexts: EguiContexts,
+    mut panel: Local<RiseSetPanel>,
+) {
+    let CameraAction::SurfaceView { body, lat, lon, alt, .. } = camera.action else { return };
+    let Some(target_id) = body_info_state.current_body_id.as_ref() else { return };
+    let Ok((observer_info, observer_state, _, appearance, rotation)) = bodies.get(body) else { return };
+    if &observer_info.id == target_id { return; }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+
+    let panel = &mut *panel;
+    let now = real_time.elapsed_secs_f64();
+    let stale = panel.found.as_ref().is_none_or(|(id, _)| id != target_id) || now - panel.refreshed_at > REFRESH_SECONDS;
+    if stale {
+        let snapshot: HashMap<&str, (&Motive, &BodyState, f64)> = bodies.iter()
+            .map(|(info, state, motive, _, _)| (info.id.as_str(), (motive, state, info.mass)))
+            .collect();
+        let g = physics.gravitational_constant;
+        let time = sim_time.time;
+        let position = |id: &str, at: Instant| position_at(id, at, time, &snapshot, g, 0);
+        let day = rotation.map(|rotation| rotation.period.abs()).filter(|period| *period > 0.0).unwrap_or(86_400.0);
+        let span = Span::new(time, Instant::from_seconds_since_j2000(time.to_j2000_seconds() + 2.0 * day), Includes::Both);
+        let height = appearance.radius() + alt;
+        let elevation = |at: Instant| {
+            let body_position = position(&observer_info.id, at).unwrap_or(observer_state.current_position);
+            let Some(target) = position(target_id, at) else { return f64::NEG_INFINITY };
+            elevation_from_surface(rotation, lat, lon, height, body_position, target, at)
+        };
+        let horizon = if panel.refraction { -observation::STANDARD_REFRACTION } else { 0.0 };
+        panel.found = Some((target_id.clone(), observation::next_rise_set(&span, 2.0 * day / SCAN_STEPS, &elevation, horizon)));
+        panel.refreshed_at = now;
+    }
+
+    let target_name = bodies.iter().find(|(info, ..)| &info.id == target_id).map(|(info, ..)| info.display_name()).unwrap_or_default();
+    egui::Window::new("Rise and Set")
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.label(format!("{} from {}", target_name, observer_info.display_name()));
+            ui.checkbox(&mut panel.refraction, "Atmospheric refraction");
+            let Some((_, found)) = &panel.found else { return };
+            let until = |instant: Instant| seconds_to_naive_date((instant - sim_time.time).to_seconds().round() as i64);
+            match (found.rise, found.set) {
+                (None, None) if found.up_at_start => { ui.label("Always up."); }
+                (None, None) => { ui.label("Never rises."); }
+                (rise, set) => {
+                    for (label, crossing) in [("Rises in:", rise), ("Sets in:", set)] {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            ui.label(crossing.map(until).unwrap_or("Not for two days".into()));
+                        });
+                    }
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDEREAL_DAY: f64 = 86_164.0905;
+    const EARTH_RADIUS: f64 = 6.371e6;
+
+    fn earth() -> BodyRotation {
+        BodyRotation {
+            period: SIDEREAL_DAY,
+            axial_tilt: 0.0,
+            node_longitude: 0.0,
+            prime_meridian_at_epoch: 0.0,
+            epoch: Instant::from_seconds_since_j2000(0.0),
+        }
+    }
+
+    fn two_days() -> Span {
+        Span::new(Instant::from_seconds_since_j2000(0.0), Instant::from_seconds_since_j2000(2.0 * SIDEREAL_DAY), Includes::Both)
+    }
+
+    #[test]
+    fn test_geostationary_never_sets() {
+        let earth = &earth();
+        // Hanging over the equator at longitude 40°, turning with the earth
+        let satellite = |at: Instant| earth.body_to_reference(at) * (SurfaceFrame::new(None, 0.0, 40.0, at).up * 4.2164e7);
+        let elevation = |lon: f64| move |at: Instant| elevation_from_surface(Some(earth), 10.0, lon, EARTH_RADIUS, DVec3::ZERO, satellite(at), at);
+
+        let beneath = observation::next_rise_set(&two_days(), 120.0, &elevation(40.0), 0.0);
+        assert!(beneath.up_at_start && beneath.rise.is_none() && beneath.set.is_none());
+        // From the far side of the earth it never comes up
+        let opposite = observation::next_rise_set(&two_days(), 120.0, &elevation(-140.0), 0.0);
+        assert!(!opposite.up_at_start && opposite.rise.is_none() && opposite.set.is_none());
+    }
+
+    #[test]
+    fn test_polar_target() {
+        let earth = &earth();
+        let over_north_pole = DVec3::Z * 1e12;
+        let elevation = |lat: f64| move |at: Instant| elevation_from_surface(Some(earth), lat, 0.0, EARTH_RADIUS, DVec3::ZERO, over_north_pole, at);
+        let north = observation::next_rise_set(&two_days(), 120.0, &elevation(45.0), 0.0);
+        assert!(north.up_at_start && north.rise.is_none() && north.set.is_none());
+        let south = observation::next_rise_set(&two_days(), 120.0, &elevation(-45.0), 0.0);
+        assert!(!south.up_at_start && south.rise.is_none() && south.set.is_none());
+
+        // Something on the celestial equator rises and sets once a day from the equator
+        let equatorial = DVec3::X * 1e12;
+        let from_equator = |at: Instant| elevation_from_surface(Some(earth), 0.0, 0.0, EARTH_RADIUS, DVec3::ZERO, equatorial, at);
+        let found = observation::next_rise_set(&two_days(), 120.0, &from_equator, 0.0);
+        let (rise, set) = (found.rise.unwrap().to_j2000_seconds(), found.set.unwrap().to_j2000_seconds());
+        // Overhead at the start, so it sets a quarter turn later and rises a quarter turn before the next transit
+        assert!((set - SIDEREAL_DAY / 4.0).abs() < 1.0, "{}", set);
+        assert!((rise - 3.0 * SIDEREAL_DAY / 4.0).abs() < 1.0, "{}", rise);
+    }
+}
@@ -0,0 +1,128 @@
+//! Saving what's on screen to PNGs.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy_egui::EguiContexts;
+use crate::gui::keybindings::Action;
+use crate::gui::planetarium::PrimaryCamera;
+use crate::gui::settings::{ScreenshotSettings, Settings};
+use crate::gui::util::ensure_folder;
+use crate::util::format::file_timestamp;
+
+pub const SCREENSHOT_DIR: &str = "data/screenshots";
+
+/// A frame being captured.
+#[derive(Resource, Default)]
+pub struct Capture {
+    pending: bool,
+    /// The planetarium's windows and labels are left out until it's saved.
+    hide_ui: bool,
+    /// Put back on the camera afterwards, when the background was cleared to transparent.
+    clear_color: Option<ClearColorConfig>,
+}
+
+impl Capture {
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+}
+
+/// Named for when it was taken, to the millisecond.
+pub fn screenshot_path(dir: &Path, since_epoch: Duration) -> PathBuf {
+    dir.join(format!("{}.png", file_timestamp(since_epoch)))
+}
+
+/// Run condition for the planetarium's egui systems.
+pub fn ui_shown(capture: Res<Capture>) -> bool {
+    !capture.hide_ui
+}
+
+pub fn screenshot_shortcut(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut capture: ResMut<Capture>,
+    mut camera: Single<&mut Camera, PrimaryCamera>,
+) {
+    if let Ok(ctx) = contexts.ctx_mut() {
+        if ctx.wants_keyboard_input() { return; }
+    }
+    if capture.pending || !settings.keybindings.just_pressed(&keys, Action::Screenshot) { return; }
+
+    let dir = PathBuf::from(SCREENSHOT_DIR);
+    if let Err(e) = ensure_folder(&dir) {
+        warn!("Couldn't make {}: {}", dir.display(), e);
+        return;
+    }
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    start_capture(&mut commands, &mut capture, &mut camera, screenshot_path(&dir, since_epoch), settings.screenshot);
+}
+
+/// Saves the frame being drawn now to `path`.
+pub fn start_capture(commands: &mut Commands, capture: &mut Capture, camera: &mut Camera, path: PathBuf, options: ScreenshotSettings) {
+    capture.pending = true;
+    capture.hide_ui = options.hide_ui;
+    if options.transparent_background {
+        capture.clear_color = Some(camera.clear_color.clone());
+        camera.clear_color = ClearColorConfig::Custom(Color::NONE);
+    }
+
+    let keep_alpha = options.transparent_background;
+    commands.spawn(Screenshot::primary_window()).observe(move |
+        captured: On<ScreenshotCaptured>,
+        mut capture: ResMut<Capture>,
+        mut camera: Single<&mut Camera, PrimaryCamera>,
+    | {
+        save_capture(&captured.image, &path, keep_alpha);
+        capture.pending = false;
+        capture.hide_ui = false;
+        if let Some(clear_color) = capture.clear_color.take() {
+            camera.clear_color = clear_color;
+        }
+    });
+}
+
+fn save_capture(image: &Image, path: &Path, keep_alpha: bool) {
+    let image = match image.clone().try_into_dynamic() {
+        Ok(image) => image,
+        Err(e) => {
+            warn!("Couldn't convert screenshot for {}: {:?}", path.display(), e);
+            return;
+        }
+    };
+    // Without a transparent background the alpha channel is meaningless, so it's dropped
+    let saved = if keep_alpha {
+        image.to_rgba8().save(path)
+    } else {
+        image.to_rgb8().save(path)
+    };
+    match saved {
+        Ok(()) => info!("Saved screenshot to {}", path.display()),
+        Err(e) => warn!("Couldn't save screenshot to {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screenshot_path() {
+        let root = std::env::temp_dir().join(format!("screenshots_{}", std::process::id()));
+        let dir = root.join(SCREENSHOT_DIR);
+        ensure_folder(&dir).unwrap();
+        assert!(dir.is_dir());
+        // Already existing is fine
+        ensure_folder(&dir).unwrap();
+
+        let path = screenshot_path(&dir, Duration::from_millis(1_760_448_245_123));
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert_eq!(path.file_name().unwrap(), "2025-10-14T13-24-05.123.png");
+        // Shots a moment apart don't overwrite each other
+        assert_ne!(path, screenshot_path(&dir, Duration::from_millis(1_760_448_245_124)));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,14 +1,21 @@
-use std::time::Instant as StdInstant;
+use std::time::{Duration, Instant as StdInstant};
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
 use crate::foundations::time::Instant;
+use crate::gui::keybindings::Action;
+use crate::gui::settings::Settings;
+use crate::util::format;
 
 /// Represents a queue of simulation times to be processed.
 /// Instead of storing each time value, we store the start time and count,
 /// computing values on-the-fly to avoid unbounded memory usage.
 #[derive(Clone, Debug, Default)]
 pub struct PreviousTimes {
-    /// The first time in the queue
+    /// Time the step indices count from
     start_time: f64,
+    /// Index of the first step in the queue. Draining bumps this rather than summing
+    /// into start_time, so a time comes out the same however the queue was drained.
+    first: u64,
     /// Number of steps remaining to process
     count: usize,
     /// Time step between each value
@@ -20,6 +27,7 @@ impl PreviousTimes {
     pub fn new() -> Self {
         Self {
             start_time: 0.0,
+            first: 0,
             count: 0,
             step: 1.0,
         }
@@ -27,7 +35,7 @@ impl PreviousTimes {
     
     /// Create a queue with the given parameters
     pub fn with_values(start_time: f64, count: usize, step: f64) -> Self {
-        Self { start_time, count, step }
+        Self { start_time, first: 0, count, step }
     }
     
     /// Returns true if there are no times to process
@@ -42,35 +50,36 @@ impl PreviousTimes {
     
     /// Returns the last time in the queue (or None if empty)
     pub fn last(&self) -> Option<f64> {
-        if self.count == 0 {
-            None
-        } else {
-            Some(self.start_time + self.step * (self.count - 1) as f64)
-        }
+        self.count.checked_sub(1).and_then(|i| self.get(i))
+    }
+    
+    /// Time between queued values. Negative when playing in reverse.
+    pub fn step(&self) -> f64 {
+        self.step
     }
     
     /// Returns the first time in the queue (or None if empty)
     pub fn first(&self) -> Option<f64> {
-        if self.count == 0 {
-            None
-        } else {
-            Some(self.start_time)
-        }
+        self.get(0)
     }
     
     /// Get the time at index i (0-based)
     pub fn get(&self, i: usize) -> Option<f64> {
         if i < self.count {
-            Some(self.start_time + self.step * i as f64)
+            Some(self.time_of(self.first + i as u64))
         } else {
             None
         }
     }
     
-    /// Drain n items from the front, advancing start_time
+    fn time_of(&self, index: u64) -> f64 {
+        self.start_time + self.step * index as f64
+    }
+    
+    /// Drain n items from the front
     pub fn drain_front(&mut self, n: usize) {
         let to_drain = n.min(self.count);
-        self.start_time += self.step * to_drain as f64;
+        self.first += to_drain as u64;
         self.count -= to_drain;
     }
     
@@ -92,6 +101,7 @@ impl PreviousTimes {
         if self.count == 0 {
             // Queue is empty - set fresh values
             self.start_time = new_start;
+            self.first = 0;
             self.count = additional_count;
             self.step = step;
         } else {
@@ -105,14 +115,29 @@ impl PreviousTimes {
     /// Set the queue to have exactly this many steps starting from start_time
     pub fn set(&mut self, start_time: f64, count: usize, step: f64) {
         self.start_time = start_time;
+        self.first = 0;
         self.count = count;
         self.step = step;
     }
     
+    /// Queue steps `first..first + count` counted from `origin`.
+    /// Anything already queued must be counted from the same origin and end at `first`.
+    pub fn queue_steps(&mut self, origin: f64, first: u64, count: usize, step: f64) {
+        if self.count == 0 {
+            self.start_time = origin;
+            self.first = first;
+            self.count = count;
+            self.step = step;
+        } else {
+            self.count += count;
+        }
+    }
+    
     /// Create an iterator that yields times without modifying the queue
     pub fn iter(&self) -> PreviousTimesIter {
         PreviousTimesIter {
-            current: self.start_time,
+            start_time: self.start_time,
+            next: self.first,
             remaining: self.count,
             step: self.step,
         }
@@ -121,7 +146,8 @@ impl PreviousTimes {
 
 /// Iterator over PreviousTimes that yields each time value
 pub struct PreviousTimesIter {
-    current: f64,
+    start_time: f64,
+    next: u64,
     remaining: usize,
     step: f64,
 }
@@ -133,8 +159,8 @@ impl Iterator for PreviousTimesIter {
         if self.remaining == 0 {
             None
         } else {
-            let value = self.current;
-            self.current += self.step;
+            let value = self.start_time + self.step * self.next as f64;
+            self.next += 1;
             self.remaining -= 1;
             Some(value)
         }
@@ -147,6 +173,24 @@ impl Iterator for PreviousTimesIter {
 
 impl ExactSizeIterator for PreviousTimesIter {}
 
+/// Step counting for deterministic playback.
+/// Real time is kept in whole nanoseconds and steps are counted from a fixed origin,
+/// so the same total real time always queues the same steps, however it was split into frames.
+#[derive(Clone, Debug)]
+pub struct DeterministicClock {
+    origin: f64,
+    step: f64,
+    speed: f64,
+    real_nanos: u128,
+    issued: u64,
+}
+
+impl DeterministicClock {
+    fn time_of(&self, index: u64) -> f64 {
+        self.origin + self.step * index as f64
+    }
+}
+
 #[derive(Resource)]
 pub struct SimTime {
     /// Current simulation time
@@ -155,10 +199,14 @@ pub struct SimTime {
     pub previous_times: PreviousTimes,
     /// Physics time step in simulation seconds
     pub step: f64,
-    /// GUI speed multiplier (sim seconds per real second)
+    /// GUI speed multiplier (sim seconds per real second). Negative plays in reverse.
     pub gui_speed: f64,
     /// Whether the simulation is currently playing
     pub playing: bool,
+    /// Something else is queuing the steps, like a time-lapse, so real time doesn't while playing.
+    pub held: bool,
+    /// Playing only for the one step `step_forward` queued, then pausing again.
+    pub stepping: bool,
     /// Display mode - seconds only vs formatted time
     pub seconds_only: bool,
     
@@ -174,7 +222,16 @@ pub struct SimTime {
     /// Accumulated simulation time that hasn't been queued yet.
     /// Used when gui_speed * delta_time is less than one step - we accumulate
     /// partial time until we have enough for a full step, preventing overshoot.
+    /// While throttled it also holds the time owed past what the throttle lets through.
     pub accumulated_time: f64,
+    /// The `gui_speed` that `accumulated_time` was owed at. Changing speed forgives the debt.
+    pub owed_speed: f64,
+    
+    /// Queue an exact number of fixed steps per real second, so runs are reproducible
+    /// regardless of frame rate. Steps that can't be simulated in time are deferred, never dropped.
+    pub deterministic: bool,
+    /// Counting state for deterministic mode. Reset whenever speed, step or time change underneath it.
+    pub clock: Option<DeterministicClock>,
     
     // === Performance tracking ===
     
@@ -186,8 +243,22 @@ pub struct SimTime {
     pub steps_completed: usize,
     /// Number of physics steps requested this frame
     pub steps_requested: usize,
+    /// Slows how fast owed steps are queued while the simulation can't keep up. 1.0 is full speed.
+    /// Time keeps being owed at `gui_speed`, so nothing is lost, just caught up on later.
+    pub throttle: f64,
+    /// Most steps queued at once. Owed steps past this wait rather than piling up.
+    pub max_backlog: usize,
+    /// Steps owed at full speed the last time real time was advanced.
+    pub steps_owed: usize,
+    /// How many of `steps_owed` the throttle and the backlog ceiling let be queued.
+    pub steps_issued: usize,
 }
 
+/// Slowest the throttle will take the simulation, as a fraction of `gui_speed`.
+pub const MIN_THROTTLE: f64 = 1e-3;
+/// How much faster the throttle lets the simulation go each frame it keeps up.
+const THROTTLE_RECOVERY: f64 = 1.02;
+
 impl Default for SimTime {
     fn default() -> Self {
         Self {
@@ -196,19 +267,163 @@ impl Default for SimTime {
             step: 0.1,
             gui_speed: 1.0,
             playing: false,
+            held: false,
+            stepping: false,
             seconds_only: false,
             // Performance defaults
             max_frame_time: 1.0 / 50.0,
             accumulated_time: 0.0,
+            owed_speed: 1.0,
+            deterministic: false,
+            clock: None,
             sim_time_fraction: 1.0,
             frame_start: None,
             steps_completed: 0,
             steps_requested: 0,
+            throttle: 1.0,
+            max_backlog: 10_000,
+            steps_owed: 0,
+            steps_issued: 0,
         }
     }
 }
 
 impl SimTime {
+    /// The current time as "2000-01-01 12:00:00 UTC (JD 2451545.0)".
+    pub fn format_calendar(&self) -> String {
+        format::calendar_date(self.time.to_julian_day())
+    }
+
+    /// A span of sim time as "1 y 34 d 5 h". Negative when it runs backwards.
+    pub fn format_elapsed(seconds: f64) -> String {
+        format::elapsed(seconds)
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.gui_speed < 0.0
+    }
+
+    /// Sim seconds per real second after throttling.
+    pub fn effective_speed(&self) -> f64 {
+        self.gui_speed * self.throttle
+    }
+
+    /// Most steps to queue for `real_delta` of real time. No limit at full speed, so owed
+    /// time can be caught up on; while throttled, what the throttled speed needs.
+    fn issue_limit(&self, real_delta: f64) -> usize {
+        if self.throttle >= 1.0 { return usize::MAX; }
+        (self.effective_speed().abs() * real_delta / self.step).ceil().max(1.0) as usize
+    }
+
+    /// Fewer steps were queued than were owed, so sim time is being put off to later frames.
+    pub fn falling_behind(&self) -> bool {
+        self.playing && !self.held && self.steps_owed > self.steps_issued
+    }
+
+    /// The physics step with the sign of the playback direction.
+    pub fn signed_step(&self) -> f64 {
+        if self.is_reversed() { -self.step } else { self.step }
+    }
+
+    /// Queue up the steps owed for `real_delta` of real time at the current speed.
+    pub fn advance(&mut self, real_delta: Duration) {
+        if self.deterministic {
+            self.advance_deterministic(real_delta);
+            return;
+        }
+        self.clock = None;
+
+        let real_delta = real_delta.as_secs_f64();
+        let step = self.signed_step();
+        
+        // Direction changed: anything queued or accumulated was heading the other way.
+        let queue_reversed = !self.previous_times.is_empty() && self.previous_times.step() * step < 0.0;
+        if queue_reversed || self.accumulated_time * step < 0.0 {
+            self.previous_times.clear();
+            self.accumulated_time = 0.0;
+        }
+        
+        // Speed changed: what was owed at the old speed isn't owed at this one.
+        if self.owed_speed != self.gui_speed {
+            self.owed_speed = self.gui_speed;
+            self.accumulated_time = 0.0;
+            // Keeps the front (earliest steps) since those must be simulated in order.
+            let this_frame = (self.gui_speed * real_delta / step).floor().max(0.0) as usize;
+            self.previous_times.truncate(this_frame);
+        }
+        
+        // Time is owed at the full speed, whatever the throttle
+        self.accumulated_time += self.gui_speed * real_delta;
+        let owed = (self.accumulated_time / step).floor() as usize;
+        
+        // The throttle and the backlog ceiling only hold back how many are queued now
+        let room = self.max_backlog.saturating_sub(self.previous_times.len());
+        let count = owed.min(self.issue_limit(real_delta)).min(room);
+        self.steps_owed = owed;
+        self.steps_issued = count;
+        if count == 0 {
+            return;
+        }
+        self.accumulated_time -= count as f64 * step;
+        
+        // Append new steps after the last queued time (or current time if queue is empty)
+        let last_queued_time = self.previous_times.last()
+            .unwrap_or(self.time.to_j2000_seconds());
+        self.previous_times.expand(last_queued_time + step, count, step);
+        
+        // NOTE: We do NOT update time here.
+        // time is updated by calculate_body_positions to reflect
+        // what was actually processed, not what we're trying to reach.
+    }
+
+    fn advance_deterministic(&mut self, real_delta: Duration) {
+        let step = self.signed_step();
+        let stale = match &self.clock {
+            None => true,
+            Some(clock) => clock.step != step || clock.speed != self.gui_speed
+                // Time was set from elsewhere
+                || (self.previous_times.is_empty() && self.time.to_j2000_seconds() != clock.time_of(clock.issued)),
+        };
+        if stale {
+            // Count from where the simulation actually is
+            self.previous_times.clear();
+            self.accumulated_time = 0.0;
+            self.clock = Some(DeterministicClock {
+                origin: self.time.to_j2000_seconds(),
+                step,
+                speed: self.gui_speed,
+                real_nanos: 0,
+                issued: 0,
+            });
+        }
+
+        let limit = self.issue_limit(real_delta.as_secs_f64());
+        let clock = self.clock.as_mut().unwrap();
+        clock.real_nanos += real_delta.as_nanos();
+        let owed = (clock.real_nanos as f64 * 1e-9 * clock.speed.abs() / clock.step.abs()).floor() as u64;
+        let room = self.max_backlog.saturating_sub(self.previous_times.len()).min(limit);
+        self.steps_owed = owed.saturating_sub(clock.issued) as usize;
+        self.steps_issued = 0;
+        if owed > clock.issued && room > 0 {
+            // Past the ceiling or the throttle, steps stay owed until there's room.
+            // They're the same steps either way, so throttling doesn't change the run.
+            let count = ((owed - clock.issued) as usize).min(room);
+            self.previous_times.queue_steps(clock.origin, clock.issued + 1, count, clock.step);
+            clock.issued += count as u64;
+            self.steps_issued = count;
+        }
+    }
+
+    /// While paused, queues a single step in the playback direction and plays just that.
+    pub fn step_forward(&mut self) {
+        if self.playing { return; }
+        let step = self.signed_step();
+        self.previous_times.set(self.time.to_j2000_seconds() + step, 1, step);
+        self.playing = true;
+        self.held = true;
+        self.stepping = true;
+    }
+
     /// Start timing a new frame of physics calculations
     pub fn begin_frame(&mut self) {
         self.frame_start = Some(StdInstant::now());
@@ -233,6 +448,23 @@ impl SimTime {
             self.sim_time_fraction = 1.0;
         }
         self.frame_start = None;
+        self.update_throttle();
+        if self.stepping && self.previous_times.is_empty() {
+            self.playing = false;
+            self.held = false;
+            self.stepping = false;
+        }
+    }
+    
+    /// Slows down toward the fraction of steps that got simulated while some are left over
+    /// at the end of a frame, and eases back up once the backlog clears.
+    fn update_throttle(&mut self) {
+        if !self.playing { return; }
+        if self.steps_completed < self.steps_requested {
+            self.throttle = (self.throttle * self.sim_time_fraction.max(0.8)).max(MIN_THROTTLE);
+        } else if self.previous_times.is_empty() {
+            self.throttle = (self.throttle * THROTTLE_RECOVERY).min(1.0);
+        }
     }
     
     /// Record that a physics step was completed
@@ -240,3 +472,177 @@ impl SimTime {
         self.steps_completed += 1;
     }
 }
+
+/// Play/pause, speed, direction and single step keys.
+pub fn time_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut contexts: EguiContexts,
+    mut sim_time: ResMut<SimTime>,
+) {
+    if let Ok(ctx) = contexts.ctx_mut() {
+        if ctx.wants_keyboard_input() { return; }
+    }
+    let bindings = &settings.keybindings;
+
+    if bindings.just_pressed(&keys, Action::TogglePlay) {
+        sim_time.playing = !sim_time.playing;
+    }
+    if bindings.just_pressed(&keys, Action::SpeedUp) {
+        sim_time.gui_speed *= 10.0;
+    }
+    if bindings.just_pressed(&keys, Action::SlowDown) {
+        sim_time.gui_speed /= 10.0;
+    }
+    if bindings.just_pressed(&keys, Action::Reverse) {
+        sim_time.gui_speed = -sim_time.gui_speed;
+    }
+    if bindings.just_pressed(&keys, Action::StepForward) {
+        sim_time.step_forward();
+    }
+}
+
+/// A note in the corner while the throttle is holding the simulation back.
+pub fn throttle_notice(mut contexts: EguiContexts, sim_time: Res<SimTime>) {
+    if sim_time.throttle >= 1.0 || !sim_time.playing { return; }
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    egui::Area::new(egui::Id::new("throttle_notice"))
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 8.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.colored_label(egui::Color32::YELLOW, format!(
+                "Can't keep up, running at {:.1}% of the set speed",
+                sim_time.throttle * 100.0,
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::DVec3;
+    use crate::foundations::gravity;
+    use crate::foundations::time::J2000_JD;
+
+    /// Steps through the queue the way calculate_body_positions does, integrating a body around
+    /// a fixed primary, with the draining cut short every so often like a blown frame budget.
+    fn run(frames: &[Duration], budget: usize) -> (Instant, DVec3, DVec3) {
+        let mut sim_time = SimTime { deterministic: true, playing: true, gui_speed: 3600.0, step: 7.0, ..default() };
+        let mu = 3.986e14;
+        let (mut position, mut velocity) = (DVec3::new(7.0e6, 0.0, 0.0), DVec3::new(0.0, 7.5e3, 1.0e2));
+
+        let mut simulate = |sim_time: &mut SimTime, budget: usize| {
+            let mut processed = 0;
+            for step_time in sim_time.previous_times.iter().take(budget) {
+                let dt = sim_time.signed_step();
+                velocity += gravity::one_body_acceleration(mu, position) * dt;
+                position += velocity * dt;
+                sim_time.time = Instant::from_seconds_since_j2000(step_time);
+                processed += 1;
+            }
+            sim_time.previous_times.drain_front(processed);
+        };
+        for frame in frames {
+            sim_time.advance(*frame);
+            simulate(&mut sim_time, budget);
+        }
+        // Catch up on anything deferred
+        simulate(&mut sim_time, usize::MAX);
+        (sim_time.time, position, velocity)
+    }
+
+    #[test]
+    fn test_deterministic_regardless_of_frame_rate() {
+        let one_frame = run(&[Duration::from_secs(10)], usize::MAX);
+        let ten_frames = run(&[Duration::from_secs(1); 10], usize::MAX);
+        let uneven = run(&[Duration::from_millis(16); 625], 100);
+
+        assert_eq!(one_frame.0.to_j2000_seconds(), 3600.0 * 10.0 - (3600.0 * 10.0) % 7.0);
+        for other in [ten_frames, uneven] {
+            assert_eq!(one_frame.0.to_j2000_seconds().to_bits(), other.0.to_j2000_seconds().to_bits());
+            assert_eq!(one_frame.1, other.1);
+            assert_eq!(one_frame.2, other.2);
+        }
+    }
+
+    #[test]
+    fn test_throttle_bounds_backlog() {
+        let mut sim_time = SimTime { deterministic: true, playing: true, gui_speed: 1000.0, step: 1.0, max_backlog: 500, ..default() };
+        let frame = Duration::from_millis(20);
+        // Each frame only has time for 5 of the 20 steps it owes
+        let mut run_frame = |sim_time: &mut SimTime, per_frame: usize| {
+            sim_time.advance(frame);
+            sim_time.begin_frame();
+            let processed = per_frame.min(sim_time.previous_times.len());
+            for _ in 0..processed {
+                sim_time.step_completed();
+            }
+            if let Some(time) = sim_time.previous_times.get(processed.saturating_sub(1)) {
+                sim_time.time = Instant::from_seconds_since_j2000(time);
+            }
+            sim_time.previous_times.drain_front(processed);
+            sim_time.end_frame();
+        };
+
+        for _ in 0..300 {
+            run_frame(&mut sim_time, 5);
+            assert!(sim_time.previous_times.len() <= sim_time.max_backlog);
+        }
+        assert!(sim_time.throttle < 0.5, "{}", sim_time.throttle);
+        assert!(sim_time.falling_behind());
+        // It hovers around the 25% that's sustainable
+        assert!(sim_time.effective_speed() > 1000.0 * MIN_THROTTLE);
+        // Throttling doesn't start the count over
+        assert_eq!(sim_time.clock.as_ref().unwrap().origin, 0.0);
+
+        // Once steps are cheap again it comes back up to full speed
+        for _ in 0..1000 {
+            run_frame(&mut sim_time, usize::MAX);
+        }
+        assert_eq!(sim_time.throttle, 1.0);
+        assert!(!sim_time.falling_behind());
+        // And catches up on everything owed in the 26 s of frames
+        assert!((sim_time.time.to_j2000_seconds() - 26_000.0).abs() <= 1.0, "{}", sim_time.time.to_j2000_seconds());
+    }
+
+    #[test]
+    fn test_throttle_defers_owed_time() {
+        let mut sim_time = SimTime { playing: true, gui_speed: 100.0, step: 1.0, ..default() };
+        sim_time.throttle = 0.25;
+        sim_time.advance(Duration::from_secs(1));
+        assert_eq!(sim_time.previous_times.len(), 25);
+        assert_eq!(sim_time.accumulated_time, 75.0);
+        assert!(sim_time.falling_behind());
+
+        // Back at full speed, the rest comes through along with this frame's
+        sim_time.throttle = 1.0;
+        sim_time.advance(Duration::from_secs(1));
+        assert_eq!(sim_time.previous_times.len(), 200);
+        assert_eq!(sim_time.previous_times.last(), Some(200.0));
+        assert!(!sim_time.falling_behind());
+    }
+
+    #[test]
+    fn test_step_forward_takes_one_step() {
+        let mut sim_time = SimTime { step: 60.0, gui_speed: -1000.0, ..default() };
+        sim_time.step_forward();
+        assert!(sim_time.playing && sim_time.held);
+        assert_eq!((sim_time.previous_times.len(), sim_time.previous_times.first()), (1, Some(-60.0)));
+
+        // Real time queues nothing more, and it pauses once the step is taken
+        sim_time.begin_frame();
+        sim_time.previous_times.drain_front(1);
+        sim_time.end_frame();
+        assert!(!sim_time.playing && !sim_time.held && !sim_time.stepping);
+    }
+
+    #[test]
+    fn test_format_calendar() {
+        let mut sim_time = SimTime::default();
+        sim_time.time = Instant::from_julian_day(J2000_JD);
+        assert_eq!(sim_time.format_calendar(), "2000-01-01 12:00:00 UTC (JD 2451545.0)");
+        sim_time.time = Instant::from_seconds_since_j2000(-86400.0 * 366.0);
+        assert_eq!(sim_time.format_calendar(), "1998-12-31 12:00:00 UTC (JD 2451179.0)");
+        assert_eq!(SimTime::format_elapsed(-2.0 * 86400.0 - 3600.0), "-2 d 1 h");
+    }
+}
@@ -0,0 +1,223 @@
+//! Recording a numbered PNG sequence with sim time stepped a fixed amount between frames,
+//! to make into a video with something else.
+
+use std::path::PathBuf;
+use bevy::prelude::*;
+use crate::gui::planetarium::PrimaryCamera;
+use crate::gui::planetarium::screenshot::{self, Capture};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
+use crate::gui::util::ensure_folder;
+
+#[derive(Resource)]
+pub struct TimeLapse {
+    pub dir: String,
+    pub frames: usize,
+    /// Sim seconds between frames. Rounded to whole physics steps.
+    pub increment: f64,
+    pub recording: Option<Recording>,
+}
+
+impl Default for TimeLapse {
+    fn default() -> Self {
+        Self {
+            dir: "data/timelapse".into(),
+            frames: 120,
+            increment: 3600.0,
+            recording: None,
+        }
+    }
+}
+
+impl TimeLapse {
+    /// Starts recording from where the simulation is now.
+    /// It plays while recording, so everything moves, but only by the steps the recording queues.
+    pub fn start(&mut self, sim_time: &mut SimTime) -> Result<(), std::io::Error> {
+        let dir = PathBuf::from(&self.dir);
+        ensure_folder(&dir)?;
+        // Steps owed to real time would throw the frame times off
+        sim_time.previous_times.clear();
+        sim_time.accumulated_time = 0.0;
+        sim_time.clock = None;
+        self.recording = Some(Recording::new(dir, self.frames, self.increment, sim_time));
+        sim_time.playing = true;
+        sim_time.held = true;
+        Ok(())
+    }
+
+    /// Stops recording, playing or pausing as it was before.
+    pub fn stop(&mut self, sim_time: &mut SimTime) {
+        let Some(recording) = self.recording.take() else { return };
+        sim_time.playing = recording.was_playing;
+        sim_time.held = false;
+    }
+}
+
+/// What a recording needs done next.
+#[derive(Debug, PartialEq)]
+pub enum TimeLapseStep {
+    /// Steps or a capture are still in flight.
+    Wait,
+    Capture(PathBuf),
+    /// Queue steps `first..first + count` counted from the recording's start.
+    Queue { first: u64, count: usize },
+    Done,
+}
+
+pub struct Recording {
+    dir: PathBuf,
+    frames: usize,
+    steps_per_frame: u64,
+    origin: f64,
+    step: f64,
+    /// Next frame to capture.
+    next: usize,
+    /// The steps to `next` are queued.
+    queued: bool,
+    was_playing: bool,
+}
+
+impl Recording {
+    pub fn new(dir: PathBuf, frames: usize, increment: f64, sim_time: &SimTime) -> Self {
+        Self {
+            dir,
+            frames,
+            steps_per_frame: (increment.abs() / sim_time.step).round().max(1.0) as u64,
+            origin: sim_time.time.to_j2000_seconds(),
+            step: sim_time.signed_step(),
+            next: 0,
+            queued: false,
+            was_playing: sim_time.playing,
+        }
+    }
+
+    /// Frames captured so far, and how many there will be.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.frames)
+    }
+
+    /// Sim time of a frame, in seconds since J2000. Counted in steps from the start so that
+    /// it's exactly where the queued steps end up.
+    pub fn frame_time(&self, frame: usize) -> f64 {
+        self.origin + self.step * (frame as u64 * self.steps_per_frame) as f64
+    }
+
+    pub fn frame_path(&self, frame: usize) -> PathBuf {
+        self.dir.join(format!("frame_{:05}.png", frame))
+    }
+
+    pub fn next_step(&mut self, steps_pending: bool, capture_pending: bool) -> TimeLapseStep {
+        if capture_pending || (self.queued && steps_pending) {
+            return TimeLapseStep::Wait;
+        }
+        if self.next >= self.frames {
+            return TimeLapseStep::Done;
+        }
+        // The first frame is wherever the simulation was
+        if self.next == 0 || self.queued {
+            let path = self.frame_path(self.next);
+            self.next += 1;
+            self.queued = false;
+            return TimeLapseStep::Capture(path);
+        }
+        self.queued = true;
+        TimeLapseStep::Queue {
+            first: (self.next as u64 - 1) * self.steps_per_frame + 1,
+            count: self.steps_per_frame as usize,
+        }
+    }
+}
+
+/// Drives the simulation while recording, holding real-time playback off until it's done.
+/// Pausing isn't possible meanwhile, since what isn't playing doesn't step.
+pub fn run_timelapse(
+    mut commands: Commands,
+    mut timelapse: ResMut<TimeLapse>,
+    mut sim_time: ResMut<SimTime>,
+    mut capture: ResMut<Capture>,
+    mut camera: Single<&mut Camera, PrimaryCamera>,
+    settings: Res<Settings>,
+) {
+    let Some(recording) = timelapse.recording.as_mut() else { return };
+    sim_time.playing = true;
+
+    let steps_pending = !sim_time.previous_times.is_empty();
+    match recording.next_step(steps_pending, capture.pending()) {
+        TimeLapseStep::Wait => {}
+        TimeLapseStep::Capture(path) => {
+            screenshot::start_capture(&mut commands, &mut capture, &mut camera, path, settings.screenshot);
+        }
+        TimeLapseStep::Queue { first, count } => {
+            sim_time.previous_times.queue_steps(recording.origin, first, count, recording.step);
+        }
+        TimeLapseStep::Done => {
+            info!("Recorded {} frames to {}", recording.frames, recording.dir.display());
+            timelapse.stop(&mut sim_time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foundations::time::Instant;
+    use crate::gui::planetarium::time::PreviousTimes;
+
+    #[test]
+    fn test_frames_advance_by_whole_steps() {
+        let sim_time = SimTime { time: Instant::from_seconds_since_j2000(1000.0), step: 10.0, ..default() };
+        // 25 s rounds to 3 steps
+        let mut recording = Recording::new(PathBuf::from("out"), 3, 25.0, &sim_time);
+        assert_eq!(recording.frame_time(2), 1060.0);
+
+        let mut queue = PreviousTimes::new();
+        let mut captured = Vec::new();
+        loop {
+            match recording.next_step(!queue.is_empty(), false) {
+                TimeLapseStep::Capture(path) => captured.push(path),
+                TimeLapseStep::Queue { first, count } => {
+                    queue.queue_steps(recording.origin, first, count, recording.step);
+                    // Still stepping
+                    assert_eq!(recording.next_step(true, false), TimeLapseStep::Wait);
+                    // Each frame lands exactly on its step, however many frames it takes to get there
+                    assert_eq!(queue.last(), Some(recording.frame_time(recording.next)));
+                    assert_eq!(queue.first(), Some(recording.frame_time(recording.next - 1) + 10.0));
+                    queue.drain_front(1);
+                    queue.drain_front(count);
+                }
+                TimeLapseStep::Wait => panic!("nothing to wait for"),
+                TimeLapseStep::Done => break,
+            }
+            // Nothing moves on while a capture is being saved
+            if !captured.is_empty() && recording.next < 3 {
+                assert_eq!(recording.next_step(false, true), TimeLapseStep::Wait);
+            }
+        }
+        assert_eq!(captured, vec![
+            PathBuf::from("out/frame_00000.png"),
+            PathBuf::from("out/frame_00001.png"),
+            PathBuf::from("out/frame_00002.png"),
+        ]);
+        assert_eq!(recording.progress(), (3, 3));
+    }
+
+    #[test]
+    fn test_recording_plays_and_stop_restores() {
+        let dir = std::env::temp_dir().join(format!("timelapse_{}", std::process::id()));
+        let mut timelapse = TimeLapse { dir: dir.display().to_string(), ..default() };
+        let mut sim_time = SimTime::default();
+        timelapse.start(&mut sim_time).unwrap();
+        // Playing, so Newtonian and mutual gravity bodies step, but not on real time
+        assert!(sim_time.playing && sim_time.held);
+
+        timelapse.stop(&mut sim_time);
+        assert!(timelapse.recording.is_none());
+        assert!(!sim_time.playing && !sim_time.held);
+
+        sim_time.playing = true;
+        timelapse.start(&mut sim_time).unwrap();
+        timelapse.stop(&mut sim_time);
+        assert!(sim_time.playing && !sim_time.held);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
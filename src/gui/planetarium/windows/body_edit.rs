@@ -1,63 +1,160 @@
+use bevy::math::DVec3;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_egui::egui::Ui;
-use crate::body::motive::fixed_motive::FixedMotive;
-use crate::body::motive::info::{BodyInfo, BodyState};
-use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEulerAngles, KeplerMotive, KeplerRotation, KeplerShape};
-use crate::body::motive::newton_motive::NewtonMotive;
+use crate::body::appearance::{Appearance, AppearanceColor, DebugBall};
+use crate::body::motive::info::{BodyInfo, BodyProperties};
+use crate::body::motive::kepler_motive::{KeplerEpoch, KeplerEpochKind, KeplerMotive, KeplerRotation, KeplerShape, MeanAnomalyAtEpoch, TrueAnomalyAtEpoch};
+use crate::body::motive::{Motive, MotiveSelection};
+use crate::body::rotation::{self as body_rotation, BodyRotation};
 use crate::body::universe::Universe;
+use crate::body::universe::deletion::OrphanPolicy;
+use crate::body::universe::edit::{self, Edit, EditCommand, KeplerElement, UndoStack};
+use crate::body::universe::save::{CompoundMotiveEntry, UniversePhysics};
+use crate::foundations::time::Instant;
 use crate::gui::common;
-use crate::gui::menu::UiState;
-use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
+use crate::gui::planetarium::time::SimTime;
 use crate::gui::planetarium::windows::body_info::BodyInfoState;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::settings::Settings;
+use crate::util::belt::{self, BeltParams};
+use crate::util::circular;
+use crate::util::format::{seconds_to_naive_date, si_prefixed};
+use crate::util::units::Unit;
+
 pub fn body_edit_window(
-    mut settings: ResMut<Settings>,
-    mut ui_state: ResMut<UiState>,
+    settings: Res<Settings>,
     universe: Res<Universe>,
     mut contexts: EguiContexts,
     mut body_info_state: ResMut<BodyInfoState>,
-    mut bodies: Query<(Entity, &mut BodyInfo, &BodyState, Option<&mut FixedMotive>, Option<&mut KeplerMotive>, Option<&mut NewtonMotive>)>,
-    mut calc: MessageWriter<CalculateTrajectory>,
+    sim_time: Res<SimTime>,
+    undo_stack: Res<UndoStack>,
+    bodies: Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>,
+    mut edits: MessageWriter<Edit>,
+    mut orphan_policy: Local<OrphanPolicy>,
+    mut units: Local<EditUnits>,
+    mut new_satellite: Local<NewSatellite>,
+    mut new_belt: Local<BeltParams>,
+    physics: Res<UniversePhysics>,
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
-    
+    settings.ui.apply(ctx);
+
     if settings.windows.body_edit {
-        egui::Window::new("Body Edit")
+        settings.window_positions.window("Body Edit")
             .vscroll(true)
             .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(undo_stack.can_undo(), egui::Button::new("Undo")).clicked() {
+                        edits.write(Edit::Undo);
+                    }
+                    if ui.add_enabled(undo_stack.can_redo(), egui::Button::new("Redo")).clicked() {
+                        edits.write(Edit::Redo);
+                    }
+                });
+
                 let mut body_options: Vec<(String, String)> = universe.id_to_name_iter()
                     .map(|(id, name)| (name.clone(), id.clone()))
                     .collect();
                 body_options.sort_by(|a, b| a.0.cmp(&b.0));
-                crate::gui::planetarium::windows::body_info::body_select_dropdown(universe, &mut body_info_state, ui, body_options);
+                crate::gui::planetarium::windows::body_info::body_select_dropdown(Res::clone(&universe), &mut body_info_state, ui, body_options);
 
-                let mut selected_body = bodies.iter_mut().filter(|(e, info, state, fixed_motive, kepler_motive, newton_motive)| {
-                    if body_info_state.current_body_id.is_none() { return false; }
-                    <std::string::String as AsRef<str>>::as_ref(&info.id) == body_info_state.current_body_id.as_ref().unwrap()
-                }).collect::<Vec<_>>();
+                let selected_body = bodies.iter().find(|(info, _, _, _, _)| {
+                    Some(&info.id) == body_info_state.current_body_id.as_ref()
+                });
 
-                let selected_body = selected_body.get_mut(0);
                 match selected_body {
                     None => { ui.label("No body Selected"); },
-                    Some((entity, info, state, fixed_motive, kepler_motive, newton_motive)) => {
-                        calc.write(CalculateTrajectory { selection: BodySelection::IDs(vec![info.id.clone()]) });
-                        body_info_section(ui, info);
-                        if let Some(fixed_motive) = fixed_motive.as_mut() {
-                            fixed_motive_section(ui, fixed_motive.as_mut())
+                    Some((info, motive, appearance, rotation, properties)) => {
+                        body_info_section(ui, info, &mut units, &mut edits);
+
+                        let event_time = motive.event_time_at(sim_time.time);
+                        match &motive.motive_at(event_time).1 {
+                            MotiveSelection::Fixed { primary_id, position } => {
+                                fixed_motive_section(ui, &info.id, event_time, primary_id.as_deref(), *position, &mut edits)
+                            }
+                            MotiveSelection::Newtonian { position, velocity } => {
+                                newton_motive_section(ui, &info.id, event_time, *position, *velocity, &mut edits)
+                            }
+                            MotiveSelection::Keplerian(kepler_motive) => {
+                                let mu = bodies.iter()
+                                    .find(|(primary, ..)| primary.id == kepler_motive.primary_id)
+                                    .map(|(primary, ..)| physics.gravitational_constant * primary.mass);
+                                kepler_motive_section(ui, &info.id, event_time, kepler_motive, mu, &mut units, &mut edits)
+                            }
                         }
-                        if let Some(kepler_motive) = kepler_motive.as_mut() {
-                            kepler_motive_section(ui, kepler_motive.as_mut())
+
+                        ui.separator();
+                        let orbit = match &motive.motive_at(event_time).1 {
+                            MotiveSelection::Keplerian(kepler) => bodies.iter()
+                                .find(|(primary, ..)| primary.id == kepler.primary_id)
+                                .map(|(primary, _, primary_appearance, _, _)| (
+                                    kepler.period(physics.gravitational_constant * primary.mass).to_seconds(),
+                                    matches!(primary_appearance, Appearance::Star(_)),
+                                )),
+                            _ => None,
+                        };
+                        rotation_section(ui, &info.id, rotation, orbit, &mut edits);
+
+                        ui.separator();
+                        let largest_radius = bodies.iter().map(|(_, _, appearance, _, _)| appearance.radius()).fold(0.0, f64::max);
+                        appearance_section(ui, &info.id, appearance, largest_radius, &mut edits);
+
+                        let entry = || CompoundMotiveEntry {
+                            info: info.clone(),
+                            motive: motive.clone(),
+                            appearance: appearance.clone(),
+                            rotation: rotation.cloned(),
+                            properties: properties.cloned().unwrap_or_default(),
+                        };
+
+                        ui.separator();
+                        if ui.button("Duplicate").on_hover_text("Add a copy just ahead of this body").clicked() {
+                            let copy = edit::duplicate(&entry(), &universe, sim_time.time);
+                            body_info_state.current_body_id = Some(copy.info.id.clone());
+                            edits.write(Edit::Apply {
+                                command: EditCommand::AddBody { body: Box::new(copy), satellites: Vec::new() },
+                                coalesce: false,
+                            });
                         }
-                        if let Some(newton_motive) = newton_motive.as_mut() {
-                            newton_motive_section(ui, newton_motive.as_mut())
+
+                        ui.separator();
+                        let mu = physics.gravitational_constant * info.mass;
+                        if circular_orbit_section(ui, mu, &mut new_satellite, &mut units) {
+                            let satellite = circular_satellite(info, appearance, &new_satellite, &universe);
+                            body_info_state.current_body_id = Some(satellite.info.id.clone());
+                            edits.write(Edit::Apply {
+                                command: EditCommand::AddBody { body: Box::new(satellite), satellites: Vec::new() },
+                                coalesce: false,
+                            });
+                        }
+
+                        ui.separator();
+                        if belt_section(ui, &mut new_belt, &mut units) {
+                            // The whole belt is one undo step
+                            let added = belt::generate_belt(&info.id, &new_belt).into_iter().map(|body| {
+                                let (info, appearance, motive, rotation, properties) = body.into_parts();
+                                EditCommand::AddBody {
+                                    body: Box::new(CompoundMotiveEntry { info, motive, appearance, rotation, properties }),
+                                    satellites: Vec::new(),
+                                }
+                            }).collect();
+                            edits.write(Edit::Apply { command: EditCommand::Batch(added), coalesce: false });
+                        }
+
+                        ui.separator();
+                        if delete_section(ui, &mut orphan_policy) {
+                            edits.write(Edit::Apply {
+                                command: EditCommand::RemoveBody {
+                                    body: Box::new(entry()),
+                                    policy: *orphan_policy,
+                                    satellites: Vec::new(),
+                                },
+                                coalesce: false,
+                            });
+                            body_info_state.current_body_id = None;
                         }
                     }
                 }
@@ -65,167 +162,477 @@ pub fn body_edit_window(
     }
 }
 
-fn body_info_section(ui: &mut egui::Ui, info: &mut BodyInfo) {
-    ui.horizontal(|ui| {
-        ui.label("Name:");
-        ui.label(info.display_name());
-    });
-
-    let mass = &mut info.mass;
-    ui.horizontal(|ui| {
-        ui.label("Mass:");
-        common::stepper(ui, "", mass);
-        ui.label("kg");
-    });
+/// The units the editor shows values in. Stored values are always SI.
+pub struct EditUnits {
+    distance: Unit,
+    mass: Unit,
 }
 
-fn fixed_motive_section(ui: &mut egui::Ui, motive: &mut FixedMotive) {
-    ui.heading("Fixed Position");
-    ui.vertical(|ui| {
-        let x = &mut motive.position.x;
-        ui.horizontal(|ui| {
-            common::stepper(ui, "x", x);
-            ui.label("m");
-        });
-        let y = &mut motive.position.y;
-        ui.horizontal(|ui| {
-            common::stepper(ui, "y", y);
-            ui.label("m");
-        });
-        let z = &mut motive.position.z;
-        ui.horizontal(|ui| {
-            common::stepper(ui, "z", z);
-            ui.label("m");
-        });
-    });
+impl Default for EditUnits {
+    fn default() -> Self {
+        Self {
+            distance: Unit::Meter,
+            mass: Unit::Kilogram,
+        }
+    }
 }
 
-fn kepler_motive_section(ui: &mut egui::Ui, motive: &mut KeplerMotive) {
-    ui.heading("Keplerian Body");
+/// Where "Add circular orbit" puts the next satellite.
+pub struct NewSatellite {
+    radius: f64,
+    /// Degrees.
+    inclination: f64,
+}
 
-    ui.vertical(|ui| {
-        ui.heading("Shape");
-        match &mut motive.shape {
-            KeplerShape::EccentricitySMA(sma) => kepler_motive_shape_sma_section(ui, sma),
-            KeplerShape::Apsides(apsides) => {}
+impl Default for NewSatellite {
+    fn default() -> Self {
+        Self {
+            radius: 1e7,
+            inclination: 0.0,
         }
+    }
+}
+
+/// Returns true if a satellite should be added around this body.
+fn circular_orbit_section(ui: &mut Ui, mu: f64, new_satellite: &mut NewSatellite, units: &mut EditUnits) -> bool {
+    ui.heading("Add Satellite");
+    common::unit_stepper(ui, "Radius", &mut new_satellite.radius, &mut units.distance);
+    ui.horizontal(|ui| {
+        ui.label("Inclination");
+        ui.add(egui::DragValue::new(&mut new_satellite.inclination)
+            .speed(0.1)
+            .range(0.0..=180.0)
+            .fixed_decimals(1)
+        );
     });
-    ui.separator();
+    if new_satellite.radius > 0.0 && mu > 0.0 {
+        ui.label(format!("Speed: {}", si_prefixed(circular::velocity(mu, new_satellite.radius), "m/s")));
+        ui.label(format!("Period: {}", si_prefixed(circular::period(mu, new_satellite.radius), "s")));
+    }
+    ui.add_enabled(new_satellite.radius > 0.0, egui::Button::new("Add circular orbit")).clicked()
+}
 
-    ui.vertical(|ui| {
-        ui.heading("Rotation");
-        match &mut motive.rotation {
-            KeplerRotation::EulerAngles(ea) => kepler_motive_rotation_ea_section(ui, ea),
-            KeplerRotation::FlatAngles(fa) => {}
-            KeplerRotation::PrecessingEulerAngles(pea) => {}
+/// Sidereal period and axial tilt. `orbit` is the body's orbital period in seconds
+/// and whether it's going round a star, for working out the length of its day.
+fn rotation_section(ui: &mut Ui, id: &str, rotation: Option<&BodyRotation>, orbit: Option<(f64, bool)>, edits: &mut MessageWriter<Edit>) {
+    ui.heading("Rotation");
+    let set_rotation = |after: Option<BodyRotation>| EditCommand::SetRotation { id: id.to_string(), before: rotation.cloned(), after };
+    let Some(rotation) = rotation else {
+        if ui.button("Add rotation").clicked() {
+            let rotation = BodyRotation {
+                period: 86_400.0,
+                axial_tilt: 0.0,
+                node_longitude: 0.0,
+                prime_meridian_at_epoch: 0.0,
+                epoch: Instant::from_seconds_since_j2000(0.0),
+            };
+            edits.write(Edit::Apply { command: set_rotation(Some(rotation)), coalesce: false });
         }
-    });
-    ui.separator();
+        return;
+    };
 
-    ui.vertical(|ui| {
-        ui.heading("Epoch");
+    edit_stepper(ui, "Sidereal period:", "h", rotation.period / 3600.0, edits, |hours| {
+        set_rotation(Some(BodyRotation { period: hours * 3600.0, ..rotation.clone() }))
+    });
+    edit_stepper(ui, "Axial tilt:", "°", rotation.axial_tilt, edits, |axial_tilt| {
+        set_rotation(Some(BodyRotation { axial_tilt, ..rotation.clone() }))
     });
+
+    if let Some((orbital_period, around_star)) = orbit.filter(|_| rotation.period != 0.0) {
+        if body_rotation::tidally_locked(rotation.period, orbital_period) {
+            ui.colored_label(egui::Color32::YELLOW, "Tidally locked: the same side always faces the primary.");
+        } else if let Some(solar_day) = body_rotation::solar_day(rotation.period, orbital_period).filter(|_| around_star) {
+            let direction = if solar_day < 0.0 { " (sun rises in the west)" } else { "" };
+            ui.label(format!("Solar day: {}{}", seconds_to_naive_date(solar_day.abs().round() as i64), direction));
+        }
+    }
+
+    if ui.button("Remove rotation").clicked() {
+        edits.write(Edit::Apply { command: set_rotation(None), coalesce: false });
+    }
 }
 
-fn kepler_motive_shape_sma_section(ui: &mut egui::Ui, sma: &mut EccentricitySMA) {
+/// Width and height of the appearance preview, in points.
+const PREVIEW_SIZE: f32 = 64.0;
+
+/// How much of the preview a body of `radius` fills, next to the largest body at 1.
+/// Flattened a lot, so moons still show up next to stars.
+fn preview_fraction(radius: f64, largest_radius: f64) -> f32 {
+    if largest_radius <= 0.0 { return 1.0; }
+    ((radius / largest_radius).powf(0.25) as f32).clamp(0.1, 1.0)
+}
+
+/// A disc in the body's colors, sized against the largest body in the universe, and their pickers.
+fn appearance_section(ui: &mut Ui, id: &str, appearance: &Appearance, largest_radius: f64, edits: &mut MessageWriter<Edit>) {
+    ui.heading("Appearance");
     ui.horizontal(|ui| {
-        common::stepper(ui, "Semi-Major Axis", &mut sma.semi_major_axis);
-        ui.label("m");
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(PREVIEW_SIZE, PREVIEW_SIZE), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, egui::Color32::BLACK);
+        let center = rect.center();
+        let radius = PREVIEW_SIZE * 0.35 * preview_fraction(appearance.radius(), largest_radius);
+        match appearance {
+            Appearance::Empty => {
+                painter.circle_stroke(center, radius, egui::Stroke::new(1.0, egui::Color32::GRAY));
+            }
+            Appearance::DebugBall(ball) => {
+                painter.circle_filled(center, radius, ball.color.to_egui());
+            }
+            Appearance::Star(star) => {
+                // The light it gives off, as a halo
+                painter.circle_filled(center, radius * 1.4, star.light.to_egui().gamma_multiply(0.4));
+                painter.circle_filled(center, radius, star.color.to_egui());
+            }
+        }
+
+        ui.vertical(|ui| {
+            ui.label(match appearance {
+                Appearance::Empty => "Not drawn",
+                Appearance::DebugBall(_) => "Ball",
+                Appearance::Star(_) => "Star",
+            });
+            ui.label(format!("Radius: {}", si_prefixed(appearance.radius(), "m")));
+            if let Appearance::Star(star) = appearance {
+                ui.label(format!("Absolute magnitude: {:.2}", star.absolute_magnitude));
+            }
+        });
     });
 
+    let mut after = appearance.clone();
+    match &mut after {
+        Appearance::Empty => {}
+        Appearance::DebugBall(ball) => {
+            color_picker(ui, "Color", &mut ball.color);
+        }
+        Appearance::Star(star) => {
+            color_picker(ui, "Color", &mut star.color);
+            light_picker(ui, &mut star.light);
+        }
+    }
+    if after != *appearance {
+        // Held down while picking, so a drag across the picker is one undo step
+        let coalesce = ui.input(|input| input.pointer.primary_down());
+        edits.write(Edit::Apply {
+            command: EditCommand::SetAppearance { id: id.to_string(), before: appearance.clone(), after },
+            coalesce,
+        });
+    }
+    if ui.input(|input| input.pointer.primary_released()) {
+        edits.write(Edit::Seal);
+    }
+}
+
+fn color_picker(ui: &mut Ui, label: &str, color: &mut AppearanceColor) {
     ui.horizontal(|ui| {
-        ui.label("Eccentricity");
-        ui.add(egui::DragValue::new(&mut sma.eccentricity)
-            .speed(0.05)
-            .range(0.0..=2.0)
-            .clamp_existing_to_range(false)
-            .fixed_decimals(1)
-        );
+        ui.label(label);
+        // Anything past 255 is shown as 255, and only written back if the color's changed
+        let mut picked = color.to_egui();
+        if ui.color_edit_button_srgba(&mut picked).changed() {
+            *color = AppearanceColor::from_egui(picked);
+        }
     });
+}
 
+/// A light's color, with how much brighter than 255 it goes as a separate intensity.
+fn light_picker(ui: &mut Ui, light: &mut AppearanceColor) {
+    let (mut color, mut intensity) = light.split_intensity();
     ui.horizontal(|ui| {
-       if ui.button("Circular").clicked() {
-           sma.eccentricity = 0.0;
-       }
-        if ui.button("Escape").clicked() {
-            sma.eccentricity = 1.0;
+        ui.label("Light");
+        let picked = ui.color_edit_button_srgba(&mut color).changed();
+        let scaled = ui.add(egui::Slider::new(&mut intensity, 1.0..=64.0).logarithmic(true).text("×")).changed();
+        if picked || scaled {
+            *light = AppearanceColor::with_intensity(color, intensity);
         }
     });
 }
 
-fn kepler_motive_rotation_ea_section(ui: &mut Ui, kea: &mut KeplerEulerAngles) {
+/// Returns true if a belt should be generated around this body.
+fn belt_section(ui: &mut Ui, params: &mut BeltParams, units: &mut EditUnits) -> bool {
+    ui.heading("Add Asteroid Belt");
     ui.horizontal(|ui| {
-        ui.label("Inclination");
-        let mut inclination = kea.inclination;
-        let before = inclination;
-        ui.add(egui::DragValue::new(&mut inclination)
-            .speed(0.1)
-            .range(0.0..=360.0)
-            .clamp_existing_to_range(false)
-            .fixed_decimals(1)
-        );
-        if inclination != before {
-            kea.inclination = inclination;
-        }
+        ui.label("Count");
+        ui.add(egui::DragValue::new(&mut params.count).range(1..=10_000));
     });
+    common::unit_stepper(ui, "Inner", &mut params.inner, &mut units.distance);
+    common::unit_stepper(ui, "Outer", &mut params.outer, &mut units.distance);
     ui.horizontal(|ui| {
-        ui.label("Longitude of Ascending Node");
-        let mut longitude_of_ascending_node = kea.longitude_of_ascending_node;
-        let before = longitude_of_ascending_node;
-        ui.add(egui::DragValue::new(&mut longitude_of_ascending_node)
-            .speed(0.1)
-            .range(0.0..=360.0)
-            .clamp_existing_to_range(false)
-            .fixed_decimals(1)
-        );
-        if longitude_of_ascending_node != before {
-            kea.longitude_of_ascending_node = longitude_of_ascending_node;
-        }
+        ui.label("Max eccentricity");
+        ui.add(egui::DragValue::new(&mut params.max_eccentricity).speed(0.01).range(0.0..=0.99).fixed_decimals(2));
     });
     ui.horizontal(|ui| {
-        ui.label("Argument of Periapsis");
-        let mut argument_of_periapsis = kea.argument_of_periapsis;
-        let before = argument_of_periapsis;
-        ui.add(egui::DragValue::new(&mut argument_of_periapsis)
+        ui.label("Max inclination");
+        ui.add(egui::DragValue::new(&mut params.max_inclination).speed(0.1).range(0.0..=180.0).fixed_decimals(1));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Seed");
+        ui.add(egui::DragValue::new(&mut params.seed));
+    });
+    let valid = params.inner > 0.0 && params.outer > params.inner;
+    ui.add_enabled(valid, egui::Button::new("Generate belt")).clicked()
+}
+
+/// A small body on a circular orbit around `primary`, named so it doesn't clash.
+fn circular_satellite(primary: &BodyInfo, primary_appearance: &Appearance, new_satellite: &NewSatellite, universe: &Universe) -> CompoundMotiveEntry {
+    let base_name = format!("Satellite of {}", primary.display_name());
+    let mut name = base_name.clone();
+    let mut n = 2;
+    while universe.get_by_name(&name).is_some() {
+        name = format!("{} ({})", base_name, n);
+        n += 1;
+    }
+
+    let orbit = circular::circular_orbit(primary.id.clone(), new_satellite.radius, new_satellite.inclination);
+    CompoundMotiveEntry {
+        info: BodyInfo {
+            id: universe.generate_id(&name),
+            name: Some(name),
+            mass: 1000.0,
+            major: false,
+            designation: None,
+            tags: Vec::new(),
+        },
+        motive: Motive::keplerian(orbit.primary_id, orbit.shape, orbit.rotation, orbit.epoch),
+        appearance: Appearance::DebugBall(DebugBall {
+            radius: primary_appearance.radius() * 0.1,
+            color: AppearanceColor { r: 200, g: 200, b: 200 },
+        }),
+        rotation: None,
+        properties: Default::default(),
+    }
+}
+
+/// Sends an edit if the value changed. Changes made while dragging are coalesced into one undo step.
+fn send_edit(edits: &mut MessageWriter<Edit>, response: &egui::Response, before: f64, after: f64, command: impl FnOnce(f64) -> EditCommand) {
+    if after != before {
+        edits.write(Edit::Apply { command: command(after), coalesce: response.dragged() });
+    }
+    if response.drag_stopped() {
+        edits.write(Edit::Seal);
+    }
+}
+
+pub(crate) fn edit_stepper(ui: &mut Ui, label: &str, unit: &str, before: f64, edits: &mut MessageWriter<Edit>, command: impl FnOnce(f64) -> EditCommand) {
+    let mut after = before;
+    let response = ui.horizontal(|ui| {
+        let response = common::stepper(ui, label, &mut after);
+        ui.label(unit);
+        response
+    }).inner;
+    send_edit(edits, &response, before, after, command);
+}
+
+fn edit_unit_stepper(ui: &mut Ui, label: &str, before: f64, unit: &mut Unit, edits: &mut MessageWriter<Edit>, command: impl FnOnce(f64) -> EditCommand) {
+    let mut after = before;
+    let response = common::unit_stepper(ui, label, &mut after, unit);
+    send_edit(edits, &response, before, after, command);
+}
+
+pub(crate) fn edit_angle(ui: &mut Ui, label: &str, before: f64, edits: &mut MessageWriter<Edit>, command: impl FnOnce(f64) -> EditCommand) {
+    let mut after = before;
+    let response = ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut after)
             .speed(0.1)
             .range(0.0..=360.0)
             .clamp_existing_to_range(false)
             .fixed_decimals(1)
-        );
-        if argument_of_periapsis != before {
-            kea.argument_of_periapsis = argument_of_periapsis;
-        }
+        )
+    }).inner;
+    send_edit(edits, &response, before, after, command);
+}
+
+fn edit_vector(ui: &mut Ui, unit: &str, before: DVec3, edits: &mut MessageWriter<Edit>, command: impl Fn(DVec3) -> EditCommand) {
+    ui.vertical(|ui| {
+        edit_stepper(ui, "x", unit, before.x, edits, |x| command(before.with_x(x)));
+        edit_stepper(ui, "y", unit, before.y, edits, |y| command(before.with_y(y)));
+        edit_stepper(ui, "z", unit, before.z, edits, |z| command(before.with_z(z)));
     });
 }
 
-fn newton_motive_section(ui: &mut egui::Ui, motive: &mut NewtonMotive) {
-    ui.heading("Newtonian Body");
+fn kepler_edit(id: &str, event_time: Instant, element: KeplerElement, before: f64) -> impl FnOnce(f64) -> EditCommand {
+    let id = id.to_string();
+    move |after| EditCommand::SetKeplerElement { id, event_time, element, before, after }
+}
 
-    ui.heading("Position");
+fn body_info_section(ui: &mut egui::Ui, info: &BodyInfo, units: &mut EditUnits, edits: &mut MessageWriter<Edit>) {
     ui.horizontal(|ui| {
-        common::stepper(ui, "x", &mut motive.position.x);
-        ui.label("m");
+        ui.label("Name:");
+        ui.label(info.display_name());
     });
-    ui.horizontal(|ui| {
-        common::stepper(ui, "y", &mut motive.position.y);
-        ui.label("m");
+
+    let before = info.mass;
+    edit_unit_stepper(ui, "Mass:", before, &mut units.mass, edits, |after| EditCommand::SetMass { id: info.id.clone(), before, after });
+}
+
+fn fixed_motive_section(ui: &mut egui::Ui, id: &str, event_time: Instant, primary_id: Option<&str>, position: DVec3, edits: &mut MessageWriter<Edit>) {
+    ui.heading("Fixed Position");
+    ui.label(format!("Relative to: {}", primary_id.unwrap_or("origin")));
+    edit_vector(ui, "m", position, edits, |after| EditCommand::SetPosition { id: id.to_string(), event_time, before: position, after });
+}
+
+/// `mu` is the primary's gravitational parameter, if it's there to be found.
+fn kepler_motive_section(ui: &mut egui::Ui, id: &str, event_time: Instant, motive: &KeplerMotive, mu: Option<f64>, units: &mut EditUnits, edits: &mut MessageWriter<Edit>) {
+    ui.heading("Keplerian Body");
+    ui.label(format!("Orbiting: {}", motive.primary_id));
+
+    ui.vertical(|ui| {
+        ui.heading("Shape");
+        kepler_shape_toggle(ui, id, event_time, &motive.shape, edits);
+        match &motive.shape {
+            KeplerShape::EccentricitySMA(esma) => {
+                edit_unit_stepper(ui, "Semi-Major Axis", esma.semi_major_axis, &mut units.distance, edits,
+                             kepler_edit(id, event_time, KeplerElement::SemiMajorAxis, esma.semi_major_axis));
+
+                let before = esma.eccentricity;
+                let mut after = before;
+                let response = ui.horizontal(|ui| {
+                    ui.label("Eccentricity");
+                    ui.add(egui::DragValue::new(&mut after)
+                        .speed(0.05)
+                        .range(0.0..=2.0)
+                        .clamp_existing_to_range(false)
+                        .fixed_decimals(1)
+                    )
+                }).inner;
+                send_edit(edits, &response, before, after, kepler_edit(id, event_time, KeplerElement::Eccentricity, before));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Circular").clicked() {
+                        let command = kepler_edit(id, event_time, KeplerElement::Eccentricity, before)(0.0);
+                        edits.write(Edit::Apply { command, coalesce: false });
+                    }
+                    if ui.button("Escape").clicked() {
+                        let command = kepler_edit(id, event_time, KeplerElement::Eccentricity, before)(1.0);
+                        edits.write(Edit::Apply { command, coalesce: false });
+                    }
+                });
+            }
+            KeplerShape::Apsides(apsides) => {
+                edit_unit_stepper(ui, "Periapsis", apsides.periapsis, &mut units.distance, edits,
+                             kepler_edit(id, event_time, KeplerElement::Periapsis, apsides.periapsis));
+                edit_unit_stepper(ui, "Apoapsis", apsides.apoapsis, &mut units.distance, edits,
+                             kepler_edit(id, event_time, KeplerElement::Apoapsis, apsides.apoapsis));
+            }
+        }
     });
-    ui.horizontal(|ui| {
-        common::stepper(ui, "z", &mut motive.position.z);
-        ui.label("m");
+    ui.separator();
+
+    ui.vertical(|ui| {
+        ui.heading("Rotation");
+        let angles = match &motive.rotation {
+            KeplerRotation::EulerAngles(ea) => Some((ea.inclination, ea.longitude_of_ascending_node, ea.argument_of_periapsis)),
+            KeplerRotation::PrecessingEulerAngles(pea) => Some((pea.inclination, pea.longitude_of_ascending_node, pea.argument_of_periapsis)),
+            KeplerRotation::FlatAngles(flat) => {
+                edit_angle(ui, "Longitude of Periapsis", flat.longitude_of_periapsis, edits,
+                           kepler_edit(id, event_time, KeplerElement::LongitudeOfPeriapsis, flat.longitude_of_periapsis));
+                None
+            }
+        };
+        if let Some((inclination, longitude_of_ascending_node, argument_of_periapsis)) = angles {
+            edit_angle(ui, "Inclination", inclination, edits,
+                       kepler_edit(id, event_time, KeplerElement::Inclination, inclination));
+            edit_angle(ui, "Longitude of Ascending Node", longitude_of_ascending_node, edits,
+                       kepler_edit(id, event_time, KeplerElement::LongitudeOfAscendingNode, longitude_of_ascending_node));
+            edit_angle(ui, "Argument of Periapsis", argument_of_periapsis, edits,
+                       kepler_edit(id, event_time, KeplerElement::ArgumentOfPeriapsis, argument_of_periapsis));
+        }
     });
+    ui.separator();
 
-    ui.heading("Velocity");
-    ui.horizontal(|ui| {
-        common::stepper(ui, "x", &mut motive.velocity.x);
-        ui.label("m/s");
+    ui.vertical(|ui| {
+        ui.heading("Epoch");
+        kepler_epoch_select(ui, id, event_time, motive, mu, edits);
+        let set_epoch = |after: KeplerEpoch| EditCommand::SetKeplerEpoch { id: id.to_string(), event_time, before: motive.epoch.clone(), after };
+        match &motive.epoch {
+            KeplerEpoch::MeanAnomaly(maae) => {
+                edit_stepper(ui, "Mean Anomaly", "rad", maae.mean_anomaly, edits,
+                             kepler_edit(id, event_time, KeplerElement::MeanAnomaly, maae.mean_anomaly));
+                edit_stepper(ui, "At", "JD", maae.epoch.to_julian_day(), edits, |julian_day| set_epoch(KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch {
+                    epoch: Instant::from_julian_day(julian_day),
+                    mean_anomaly: maae.mean_anomaly,
+                })));
+            }
+            KeplerEpoch::TrueAnomaly(taae) => {
+                edit_stepper(ui, "True Anomaly", "rad", taae.true_anomaly, edits,
+                             kepler_edit(id, event_time, KeplerElement::TrueAnomaly, taae.true_anomaly));
+                edit_stepper(ui, "At", "JD", taae.epoch.to_julian_day(), edits, |julian_day| set_epoch(KeplerEpoch::TrueAnomaly(TrueAnomalyAtEpoch {
+                    epoch: Instant::from_julian_day(julian_day),
+                    true_anomaly: taae.true_anomaly,
+                })));
+            }
+            KeplerEpoch::TimeAtPeriapsisPassage(passage) => {
+                edit_stepper(ui, "Periapsis Passage", "JD", passage.to_julian_day(), edits,
+                             |julian_day| set_epoch(KeplerEpoch::TimeAtPeriapsisPassage(Instant::from_julian_day(julian_day))));
+            }
+            KeplerEpoch::J2000(j2000) => {
+                edit_stepper(ui, "Mean Anomaly at J2000", "rad", j2000.mean_anomaly, edits,
+                             kepler_edit(id, event_time, KeplerElement::MeanAnomaly, j2000.mean_anomaly));
+            }
+        }
     });
+}
+
+/// Switches between describing the orbit by e/a and by its apsides, keeping the same orbit.
+fn kepler_shape_toggle(ui: &mut egui::Ui, id: &str, event_time: Instant, shape: &KeplerShape, edits: &mut MessageWriter<Edit>) {
+    let is_apsides = matches!(shape, KeplerShape::Apsides(_));
+    let apsides = shape.to_apsides();
+    let mut switch_to = None;
     ui.horizontal(|ui| {
-        common::stepper(ui, "y", &mut motive.velocity.y);
-        ui.label("m/s");
+        if ui.selectable_label(!is_apsides, "e / a").clicked() && is_apsides {
+            switch_to = Some(shape.to_eccentricity_sma());
+        }
+        ui.add_enabled_ui(apsides.is_some(), |ui| {
+            if ui.selectable_label(is_apsides, "Apsides").on_disabled_hover_text("Open orbits have no apoapsis").clicked() && !is_apsides {
+                switch_to = apsides.clone();
+            }
+        });
     });
+    if let Some(after) = switch_to {
+        let command = EditCommand::SetKeplerShape { id: id.to_string(), event_time, before: shape.clone(), after };
+        edits.write(Edit::Apply { command, coalesce: false });
+    }
+}
+
+/// Switches how the epoch is given, converting it so the body stays where it is.
+/// Ways that need a period can't be picked without the primary or for open orbits.
+fn kepler_epoch_select(ui: &mut egui::Ui, id: &str, event_time: Instant, motive: &KeplerMotive, mu: Option<f64>, edits: &mut MessageWriter<Edit>) {
+    let current = motive.epoch.kind();
+    let mut selected = current;
+    egui::ComboBox::from_label("Given as")
+        .selected_text(current.label())
+        .show_ui(ui, |ui| {
+            for kind in KeplerEpochKind::ALL {
+                let available = kind == current || mu.and_then(|mu| motive.epoch_as(kind, mu)).is_some();
+                ui.add_enabled_ui(available, |ui| {
+                    ui.selectable_value(&mut selected, kind, kind.label());
+                });
+            }
+        });
+    if selected == current { return; }
+    if let Some(after) = mu.and_then(|mu| motive.epoch_as(selected, mu)) {
+        let command = EditCommand::SetKeplerEpoch { id: id.to_string(), event_time, before: motive.epoch.clone(), after };
+        edits.write(Edit::Apply { command, coalesce: false });
+    }
+}
+
+fn newton_motive_section(ui: &mut egui::Ui, id: &str, event_time: Instant, position: DVec3, velocity: DVec3, edits: &mut MessageWriter<Edit>) {
+    ui.heading("Newtonian Body");
+
+    ui.heading("Position");
+    edit_vector(ui, "m", position, edits, |after| EditCommand::SetPosition { id: id.to_string(), event_time, before: position, after });
+
+    ui.heading("Velocity");
+    edit_vector(ui, "m/s", velocity, edits, |after| EditCommand::SetVelocity { id: id.to_string(), event_time, before: velocity, after });
+}
+
+/// Returns true if the body should be deleted.
+fn delete_section(ui: &mut Ui, policy: &mut OrphanPolicy) -> bool {
+    ui.heading("Delete");
     ui.horizontal(|ui| {
-        common::stepper(ui, "z", &mut motive.velocity.z);
-        ui.label("m/s");
+        ui.label("Satellites:");
+        ui.radio_value(policy, OrphanPolicy::Reparent, OrphanPolicy::Reparent.label());
+        ui.radio_value(policy, OrphanPolicy::Release, OrphanPolicy::Release.label());
     });
+
+    ui.button("Delete Body").clicked()
 }
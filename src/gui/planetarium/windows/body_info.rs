@@ -1,26 +1,57 @@
+use std::collections::HashMap;
+use bevy::math::DVec3;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_egui::egui::Ui;
 use crate::body::appearance::Appearance;
 use crate::body::motive::fixed_motive::FixedMotive;
-use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::info::{BodyInfo, BodyProperties, BodyState};
+use crate::body::motive::compound_motive::{Motive, MotiveSelection};
 use crate::body::motive::kepler_motive::KeplerMotive;
 use crate::body::motive::newton_motive::NewtonMotive;
+use crate::body::rotation::{self, BodyRotation};
+use crate::body::universe::edit::{Edit, EditCommand};
+use crate::body::universe::save::UniversePhysics;
 use crate::body::universe::Universe;
+use crate::foundations::kepler::speed;
+use crate::foundations::reference_frame::observation::{self, Transit};
+use crate::foundations::reference_frame::plane::ReferencePlane;
+use crate::foundations::time::{Includes, Instant, Span};
 use crate::gui::menu::UiState;
-use crate::gui::planetarium::camera::GoTo;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::planetarium::camera::{CameraSlot, GoTo, ViewFromSurface};
+use crate::gui::planetarium::position_at;
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
 use crate::util::bevystuff::GlamVec;
+use crate::util::format::{seconds_to_naive_date, si_prefixed};
+
+/// Seconds between looks when finding transits. Shorter ones can be missed.
+const TRANSIT_SCAN_STEP: f64 = 600.0;
 
 #[derive(Resource)]
 pub struct BodyInfoState {
     pub current_body_id: Option<String>,
+    /// How far out to list nearby bodies, in meters.
+    pub nearby_radius: f64,
+    /// Who to work out phases as seen by. The body's primary when unset.
+    pub phase_observer_id: Option<String>,
+    /// A property being typed in, before it's added.
+    pub new_property: (String, String),
+    /// How many days ahead to look for transits.
+    pub transit_days: f64,
+    /// The transits last found, and the id of the body they were found for.
+    pub transits: Option<(String, Vec<Transit>)>,
 }
 
 impl Default for BodyInfoState {
     fn default() -> Self {
         Self {
             current_body_id: None,
+            nearby_radius: 1e9,
+            phase_observer_id: None,
+            new_property: (String::new(), String::new()),
+            transit_days: 30.0,
+            transits: None,
         }
     }
 }
@@ -33,18 +64,24 @@ pub fn body_info_window(
     mut contexts: EguiContexts,
     mut body_info_state: ResMut<BodyInfoState>,
     mut go_to: MessageWriter<GoTo>,
+    motives: Query<(&BodyInfo, &BodyState, &Motive)>,
+    sim_time: Res<SimTime>,
+    physics: Res<UniversePhysics>,
+    rotating: Query<(&BodyInfo, &BodyState, &BodyRotation)>,
+    mut surface_views: MessageWriter<ViewFromSurface>,
+    mut surface_point: Local<SurfacePoint>,
+    positions: Query<(&BodyInfo, &BodyState)>,
+    appearances: Query<(&BodyInfo, &BodyState, &Appearance)>,
+    (properties, mut edits): (Query<&BodyProperties>, MessageWriter<Edit>),
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     if settings.windows.body_info {
-        egui::Window::new("Body Info")
+        settings.window_positions.window("Body Info")
             .vscroll(true)
             .show(ctx, |ui| {
                 // Create a sorted list of body names and their IDs
@@ -53,7 +90,7 @@ pub fn body_info_window(
                     .collect();
                 body_options.sort_by(|a, b| a.0.cmp(&b.0));
 
-                body_select_dropdown(universe, &mut body_info_state, ui, body_options);
+                body_select_dropdown(Res::clone(&universe), &mut body_info_state, ui, body_options);
                 
                 // Get the body using the BodyInfo.id from bodies query
                 let selected_body = bodies.iter().filter(|(e, info, state, fixed_motive, kepler_motive, newton_motive)| {
@@ -67,10 +104,103 @@ pub fn body_info_window(
                         if ui.button("Go to").clicked() {
                             go_to.write(GoTo {
                                 entity: e.entity(),
+                                camera: CameraSlot::Primary,
+                            });
+                        }
+                        if surface_view_section(ui, &mut surface_point) {
+                            surface_views.write(ViewFromSurface {
+                                entity: e.entity(),
+                                lat: surface_point.lat,
+                                lon: surface_point.lon,
+                                alt: surface_point.alt,
                             });
                         }
 
-                        display_body_info(ui, info, state, *fixed_motive, *kepler_motive, *newton_motive)
+                        display_body_info(ui, info, state, *fixed_motive, *kepler_motive, *newton_motive, sim_time.time, settings.display.reference_plane);
+
+                        ui.separator();
+                        let mut radius = body_info_state.nearby_radius;
+                        let nearby = Universe::bodies_near_body(&positions, &info.id, radius);
+                        if let Some(id) = nearby_section(ui, &universe, &mut radius, state, &nearby) {
+                            if let Some((entity, ..)) = bodies.iter().find(|(_, info, ..)| info.id == id) {
+                                go_to.write(GoTo { entity, camera: CameraSlot::Primary });
+                            }
+                        }
+                        body_info_state.nearby_radius = radius;
+
+                        let current = motives.get(*e).ok().map(|(_, _, motive)| &motive.motive_at(sim_time.time).1);
+                        let primary_id = match current {
+                            Some(MotiveSelection::Keplerian(kepler)) => Some(kepler.primary_id.as_str()),
+                            Some(MotiveSelection::Fixed { primary_id, .. }) => primary_id.as_deref(),
+                            _ => None,
+                        };
+                        ui.separator();
+                        let primary = primary_id.and_then(|id| appearances.iter().find(|(primary, _, _)| primary.id == id));
+                        flight_section(ui, current, state, primary, sim_time.time, physics.gravitational_constant);
+
+                        let rotating_primary = primary_id.and_then(|id| rotating.iter().find(|(primary, _, _)| primary.id == id));
+                        if let Some((primary, primary_state, primary_rotation)) = rotating_primary {
+                            ui.separator();
+                            subpoint_section(ui, primary, rotation::subpoint(state.current_position, primary_rotation, primary_state.current_position, sim_time.time));
+                        }
+
+                        let star = dominant_star(&appearances, state.current_position);
+                        if let Some((star, star_position)) = star.filter(|(star, _)| star.id != info.id) {
+                            let observer_id = body_info_state.phase_observer_id.as_deref().or(primary_id);
+                            let observer = observer_id.and_then(|id| positions.iter().find(|(observer, _)| observer.id == id))
+                                .filter(|(observer, _)| observer.id != info.id);
+                            ui.separator();
+                            let mut observer_choice = body_info_state.phase_observer_id.clone();
+                            phase_section(ui, &universe, &mut observer_choice, star, observer.map(|(observer, observer_state)| {
+                                (observer, observation::phase_angle(observer_state.current_position, state.current_position, star_position))
+                            }));
+                            body_info_state.phase_observer_id = observer_choice;
+
+                            if let Some((observer, observer_state)) = observer.filter(|(observer, _)| observer.id != star.id) {
+                                ui.separator();
+                                let found = body_info_state.transits.as_ref()
+                                    .filter(|(id, _)| *id == info.id)
+                                    .map(|(_, transits)| transits.as_slice());
+                                let mut days = body_info_state.transit_days;
+                                let search = transits_section(ui, &mut days, star, observer, found, &sim_time);
+                                body_info_state.transit_days = days;
+                                if search {
+                                    let snapshot: HashMap<&str, (&Motive, &BodyState, f64)> = motives.iter()
+                                        .map(|(body, body_state, motive)| (body.id.as_str(), (motive, body_state, body.mass)))
+                                        .collect();
+                                    let g = physics.gravitational_constant;
+                                    let now = sim_time.time;
+                                    let at = |id: &str, time: Instant, fallback: DVec3| position_at(id, time, now, &snapshot, g, 0).unwrap_or(fallback);
+                                    let positions = |time: Instant| (
+                                        at(&observer.id, time, observer_state.current_position),
+                                        at(&info.id, time, state.current_position),
+                                        at(&star.id, time, star_position),
+                                    );
+                                    let radius = |id: &str| appearances.iter()
+                                        .find(|(body, ..)| body.id == id)
+                                        .map(|(.., appearance)| appearance.radius())
+                                        .unwrap_or(0.0);
+                                    let span = Span::new(now, Instant::from_seconds_since_j2000(now.to_j2000_seconds() + days * 86_400.0), Includes::Both);
+                                    let transits = observation::find_transits(&span, TRANSIT_SCAN_STEP, &positions, radius(&info.id), radius(&star.id));
+                                    body_info_state.transits = Some((info.id.clone(), transits));
+                                }
+                            }
+                        }
+
+                        if let Some(MotiveSelection::Keplerian(kepler)) = current {
+                            let primary_mass = motives.iter()
+                                .find(|(primary, ..)| primary.id == kepler.primary_id)
+                                .map(|(primary, ..)| primary.mass);
+                            if let Some(primary_mass) = primary_mass {
+                                ui.separator();
+                                orbit_events_section(ui, kepler, physics.gravitational_constant * primary_mass, &sim_time);
+                            }
+                        }
+
+                        if let Ok(properties) = properties.get(*e) {
+                            ui.separator();
+                            properties_section(ui, &info.id, properties, &mut body_info_state.new_property, &mut edits);
+                        }
                     }
                     None => {
                         ui.label("No body selected.");
@@ -80,13 +210,147 @@ pub fn body_info_window(
     }
 }
 
+/// Free-form notes on the body. Typing into a value is one undo step until the field loses focus.
+fn properties_section(ui: &mut Ui, id: &str, properties: &BodyProperties, (new_key, new_value): &mut (String, String), edits: &mut MessageWriter<Edit>) {
+    ui.label("Properties");
+    let mut after = properties.clone();
+    let mut typing = false;
+    for (key, value) in properties.0.iter() {
+        ui.horizontal(|ui| {
+            ui.label(key);
+            let mut text = value.clone();
+            let response = ui.text_edit_singleline(&mut text);
+            if response.changed() {
+                after.0.insert(key.clone(), text);
+                typing = true;
+            }
+            if response.lost_focus() {
+                edits.write(Edit::Seal);
+            }
+            if ui.small_button("✖").clicked() {
+                after.0.remove(key);
+            }
+        });
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(new_key).hint_text("Key").desired_width(80.0));
+        ui.add(egui::TextEdit::singleline(new_value).hint_text("Value").desired_width(120.0));
+        let key = new_key.trim();
+        if ui.add_enabled(!key.is_empty(), egui::Button::new("Add")).clicked() {
+            after.0.insert(key.to_string(), std::mem::take(new_value));
+            new_key.clear();
+        }
+    });
+
+    if after != *properties {
+        edits.write(Edit::Apply {
+            command: EditCommand::SetProperties { id: id.to_string(), before: properties.clone(), after },
+            coalesce: typing,
+        });
+    }
+}
+
+/// The star lighting `position` most brightly.
+fn dominant_star<'a>(appearances: &'a Query<(&BodyInfo, &BodyState, &Appearance)>, position: DVec3) -> Option<(&'a BodyInfo, DVec3)> {
+    appearances.iter()
+        .filter_map(|(info, state, appearance)| match appearance {
+            Appearance::Star(star) => {
+                let flux = star.intensity() as f64 / state.current_position.distance_squared(position).max(f64::MIN_POSITIVE);
+                Some((info, state.current_position, flux))
+            }
+            _ => None,
+        })
+        .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(info, position, _)| (info, position))
+}
+
+fn phase_section(ui: &mut Ui, universe: &Universe, observer_id: &mut Option<String>, star: &BodyInfo, phase: Option<(&BodyInfo, f64)>) {
+    ui.label(format!("Phase, lit by {}", star.display_name()));
+    egui::ComboBox::from_label("Seen from")
+        .selected_text(observer_id.as_ref().and_then(|id| universe.get_by_id(id)).map(|name| name.as_str()).unwrap_or("Primary"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(observer_id, None, "Primary");
+            let mut options: Vec<_> = universe.id_to_name_iter().collect();
+            options.sort_by(|a, b| a.1.cmp(b.1));
+            for (id, name) in options {
+                ui.selectable_value(observer_id, Some(id.clone()), name);
+            }
+        });
+    match phase {
+        Some((observer, phase_angle)) => {
+            ui.horizontal(|ui| {
+                ui.label("Phase angle:");
+                ui.label(format!("{:.2}°", phase_angle.to_degrees()));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Illuminated:");
+                ui.label(format!("{:.1}% from {}", observation::illuminated_fraction(phase_angle) * 100.0, observer.display_name()));
+            });
+        }
+        None => { ui.label("No observer."); }
+    }
+}
+
+/// Returns the id of a body to go to, if one was clicked.
+fn nearby_section(ui: &mut Ui, universe: &Universe, radius: &mut f64, state: &BodyState, nearby: &[(String, DVec3)]) -> Option<String> {
+    let mut go_to = None;
+    ui.collapsing(format!("Nearby ({})", nearby.len()), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Within");
+            // Floored so a radius dragged down to zero can still be dragged back up
+            let speed = (*radius * 0.01).max(1000.0);
+            ui.add(egui::DragValue::new(radius).range(0.0..=f64::MAX).speed(speed).suffix(" m"));
+        });
+        for (id, position) in nearby {
+            ui.horizontal(|ui| {
+                ui.label(universe.get_by_id(id).unwrap_or(id));
+                ui.label(format!("{} m", crate::util::format::sci_not(position.distance(state.current_position))));
+                if ui.small_button("Go to").clicked() {
+                    go_to = Some(id.clone());
+                }
+            });
+        }
+    });
+    go_to
+}
+
+/// Where to stand for "View from surface". Degrees, and meters above the surface.
+#[derive(Default)]
+pub struct SurfacePoint {
+    lat: f64,
+    lon: f64,
+    alt: f64,
+}
+
+/// Returns true when the view was requested.
+fn surface_view_section(ui: &mut Ui, point: &mut SurfacePoint) -> bool {
+    let mut requested = false;
+    ui.collapsing("View from surface", |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Lat");
+            ui.add(egui::DragValue::new(&mut point.lat).range(-90.0..=90.0).speed(0.1).suffix("°"));
+            ui.label("Lon");
+            ui.add(egui::DragValue::new(&mut point.lon).range(-180.0..=180.0).speed(0.1).suffix("°"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Altitude");
+            ui.add(egui::DragValue::new(&mut point.alt).range(0.0..=f64::MAX).speed(10.0).suffix(" m"));
+        });
+        requested = ui.button("Stand here").clicked();
+    });
+    requested
+}
+
 fn display_body_info (
     ui: &mut Ui, 
     info: &BodyInfo, 
     state: &BodyState, 
     fixed_motive: Option<&FixedMotive>, 
     kepler_motive: Option<&KeplerMotive>, 
-    newton_motive: Option<&NewtonMotive>
+    newton_motive: Option<&NewtonMotive>,
+    time: Instant,
+    plane: ReferencePlane,
 ) {
     body_info_section(ui, info);
     ui.separator();
@@ -97,7 +361,7 @@ fn display_body_info (
     }
     if let Some(kepler_motive) = kepler_motive {
         ui.separator();
-        kepler_motive_section(ui, kepler_motive);
+        kepler_motive_section(ui, kepler_motive, time, plane);
     }
     if let Some(newton_motive) = newton_motive {
         ui.separator();
@@ -143,6 +407,9 @@ fn body_info_section(ui: &mut Ui, info: &BodyInfo) {
 
 fn body_state_section(ui: &mut Ui, state: &BodyState) {
     ui.label("Current State");
+    if state.inaccurate {
+        ui.colored_label(egui::Color32::YELLOW, format!("Last step hit the substep limit ({}), so this trajectory may be inaccurate.", state.substeps));
+    }
 }
 
 fn fixed_motive_section(ui: &mut Ui, motive: &FixedMotive) {
@@ -150,9 +417,136 @@ fn fixed_motive_section(ui: &mut Ui, motive: &FixedMotive) {
     motive.display(ui);
 }
 
-fn kepler_motive_section(ui: &mut Ui, motive: &KeplerMotive) {
+fn kepler_motive_section(ui: &mut Ui, motive: &KeplerMotive, time: Instant, plane: ReferencePlane) {
     ui.label("Keplerian Body");
     motive.display(ui);
+
+    let (inclination, longitude_of_ascending_node, argument_of_periapsis) = motive.angles_in(plane, time);
+    ui.label(format!("Against the {} plane", plane.label().to_lowercase()));
+    for (label, angle) in [
+        ("Inclination", inclination),
+        ("Longitude of Ascending Node", longitude_of_ascending_node),
+        ("Argument of Periapsis", argument_of_periapsis),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(format!("{}:", label));
+            ui.label(format!("{:.3}°", angle));
+        });
+    }
+}
+
+/// Times the body crosses the star, or goes behind it, as seen by the observer.
+/// Returns whether to look for them again.
+fn transits_section(ui: &mut Ui, days: &mut f64, star: &BodyInfo, observer: &BodyInfo, transits: Option<&[Transit]>, sim_time: &SimTime) -> bool {
+    let mut search = false;
+    ui.label(format!("Transits of {} from {}", star.display_name(), observer.display_name()));
+    ui.horizontal(|ui| {
+        ui.label("Next");
+        ui.add(egui::DragValue::new(days).range(1.0..=365.0).speed(1.0).suffix(" days"));
+        search = ui.button("Find").clicked();
+    });
+    let format_duration = |seconds: f64| if sim_time.seconds_only {
+        format!("{:.1}s", seconds)
+    } else {
+        seconds_to_naive_date(seconds.round() as i64)
+    };
+    match transits {
+        None => {}
+        Some([]) => { ui.label("None found."); }
+        Some(transits) => for transit in transits {
+            let kind = if transit.a_in_front { "Transit" } else { "Occultation" };
+            ui.label(format!("{} in {}, lasting {}", kind,
+                format_duration((transit.ingress - sim_time.time).to_seconds()),
+                format_duration((transit.egress - transit.ingress).to_seconds())));
+        }
+    }
+    search
+}
+
+/// Countdowns to the next apsides and node crossings, in sim time and at the current playback speed.
+fn orbit_events_section(ui: &mut Ui, motive: &KeplerMotive, gravitational_parameter: f64, sim_time: &SimTime) {
+    ui.label("Upcoming Events");
+
+    let time = sim_time.time;
+    let events = [
+        ("Periapsis", motive.time_until_periapsis(time, gravitational_parameter)),
+        ("Apoapsis", motive.time_until_apoapsis(time, gravitational_parameter)),
+        ("Ascending node", motive.time_until_ascending_node(time, gravitational_parameter)),
+        ("Descending node", motive.time_until_descending_node(time, gravitational_parameter)),
+    ];
+    let format_duration = |seconds: f64| if sim_time.seconds_only {
+        format!("{:.1}s", seconds)
+    } else {
+        seconds_to_naive_date(seconds.round() as i64)
+    };
+
+    for (label, until) in events {
+        let Some(until) = until else { continue };
+        ui.horizontal(|ui| {
+            ui.label(format!("{}:", label));
+            ui.label(format!("in {}", format_duration(until)));
+            if sim_time.gui_speed > 0.0 {
+                ui.label(format!("({} real)", format_duration(until / sim_time.gui_speed)));
+            }
+        });
+    }
+}
+
+/// Speed, and for bodies with a primary, their altitude above its surface and how their
+/// velocity splits between climbing and going round.
+fn flight_section(
+    ui: &mut Ui,
+    selection: Option<&MotiveSelection>,
+    state: &BodyState,
+    primary: Option<(&BodyInfo, &BodyState, &Appearance)>,
+    time: Instant,
+    gravitational_constant: f64,
+) {
+    ui.label("Flight");
+    let row = |ui: &mut Ui, label: &str, value: String| {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.label(value);
+        });
+    };
+
+    let Some((primary, primary_state, appearance)) = primary else {
+        // Only a speed through space for bodies with nothing to measure against
+        match state.current_velocity {
+            Some(velocity) => row(ui, "Speed:", si_prefixed(velocity.length(), "m/s")),
+            None => { ui.label("No primary."); }
+        }
+        return;
+    };
+
+    let local_position = state.current_position - primary_state.current_position;
+    let (speed, local_velocity) = match selection {
+        Some(MotiveSelection::Keplerian(kepler)) => {
+            let mu = gravitational_constant * primary.mass;
+            (speed::vis_viva(mu, local_position.length(), kepler.semi_major_axis()), kepler.velocity(time, mu))
+        }
+        // Fixed to its primary
+        _ => (0.0, DVec3::ZERO),
+    };
+    let up = local_position.normalize_or_zero();
+    let vertical = local_velocity.dot(up);
+
+    row(ui, "Speed:", si_prefixed(speed, "m/s"));
+    row(ui, "Altitude:", format!("{} above {}", si_prefixed(local_position.length() - appearance.radius(), "m"), primary.display_name()));
+    row(ui, "Vertical speed:", si_prefixed(vertical, "m/s"));
+    row(ui, "Horizontal speed:", si_prefixed((local_velocity - up * vertical).length(), "m/s"));
+}
+
+fn subpoint_section(ui: &mut Ui, primary: &BodyInfo, (latitude, longitude): (f64, f64)) {
+    ui.label(format!("Over {}", primary.display_name()));
+    ui.horizontal(|ui| {
+        ui.label("Latitude:");
+        ui.label(format!("{:.3}° {}", latitude.abs(), if latitude < 0.0 { "S" } else { "N" }));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Longitude:");
+        ui.label(format!("{:.3}° {}", longitude.abs(), if longitude < 0.0 { "W" } else { "E" }));
+    });
 }
 
 fn newton_motive_section(ui: &mut Ui, motive: &NewtonMotive) {
@@ -2,35 +2,60 @@ use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::prelude::*;
 use bevy::render::view::ColorGrading;
 use bevy_egui::{egui, EguiContexts};
-use bevy_egui::egui::Context;
-use crate::gui::planetarium::PlanetariumCamera;
-use crate::gui::settings::{Settings, UiTheme};
+use bevy_egui::egui::{Context, Ui};
+use crate::body::motive::info::BodyInfo;
+use crate::body::universe::save::CameraBookmark;
+use crate::gui::planetarium::{CameraBookmarks, PlanetariumCamera, PrimaryCamera};
+use crate::gui::planetarium::camera::{CameraCommand, CameraSlot, GoTo, SplitScreen};
+use crate::gui::planetarium::windows::body_info::BodyInfoState;
+use crate::gui::settings::Settings;
+use crate::gui::util::freecam::Freecam;
 
 pub fn camera_window(
     mut settings: ResMut<Settings>,
     mut contexts: EguiContexts,
-    mut tonemapping: Single<&mut Tonemapping>,
-    mut color_grading: Single<&mut ColorGrading>,
-    mut camera: Single<&mut Projection, With<PlanetariumCamera>>,
+    mut tonemapping: Single<&mut Tonemapping, PrimaryCamera>,
+    mut color_grading: Single<&mut ColorGrading, PrimaryCamera>,
+    mut camera: Single<&mut Projection, PrimaryCamera>,
+    mut camera_commands: MessageWriter<CameraCommand>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut bookmark_name: Local<String>,
+    pose: Single<(&Transform, &Freecam, &PlanetariumCamera), PrimaryCamera>,
+    bodies: Query<(Entity, &BodyInfo)>,
+    mut split_screen: ResMut<SplitScreen>,
+    body_info_state: Res<BodyInfoState>,
+    mut go_tos: MessageWriter<GoTo>,
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     if settings.windows.camera {
-        camera_settings_window(ctx, camera, tonemapping, color_grading);
+        let (transform, freecam, pcam) = *pose;
+        let target_id = pcam.focused().and_then(|entity| bodies.get(entity).ok()).map(|(_, info)| info.id.clone());
+        let current = CameraBookmark::new(String::new(), freecam.bevy_pos, transform.rotation, target_id);
+        let selected = bodies.iter()
+            .find(|(_, info)| Some(&info.id) == body_info_state.current_body_id.as_ref())
+            .map(|(entity, info)| (entity, info.display_name()));
+        camera_settings_window(ctx, &settings, camera, tonemapping, color_grading, &mut camera_commands, &mut bookmarks, &mut bookmark_name, &current, &mut split_screen, selected, &mut go_tos);
     }
 }
 
-fn camera_settings_window(ctx: &mut Context, mut camera: Single<&mut Projection, With<PlanetariumCamera>>, tonemapping: Single<&mut Tonemapping>, mut color_grading: Single<&mut ColorGrading>) {
-    egui::Window::new("Camera Settings")
+fn camera_settings_window(ctx: &mut Context, settings: &Settings, mut camera: Single<&mut Projection, PrimaryCamera>, tonemapping: Single<&mut Tonemapping, PrimaryCamera>, mut color_grading: Single<&mut ColorGrading, PrimaryCamera>, camera_commands: &mut MessageWriter<CameraCommand>, bookmarks: &mut ResMut<CameraBookmarks>, bookmark_name: &mut String, current: &CameraBookmark, split_screen: &mut SplitScreen, selected: Option<(Entity, String)>, go_tos: &mut MessageWriter<GoTo>) {
+    settings.window_positions.window("Camera Settings")
         .vscroll(true)
         .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Reset View").clicked() {
+                    camera_commands.write(CameraCommand::ResetView);
+                }
+                if ui.button("Frame All").clicked() {
+                    camera_commands.write(CameraCommand::FrameAll);
+                }
+            });
+
             ui.heading("Exposure");
             ui.add(egui::Slider::new(&mut color_grading.global.exposure, -20.0..=10.0).text("Exposure"));
 
@@ -40,5 +65,57 @@ fn camera_settings_window(ctx: &mut Context, mut camera: Single<&mut Projection,
                 ui.add(egui::Slider::new(&mut fov_deg, 0.5..=190.0).text("FOV"));
                 perspective.fov = fov_deg.to_radians();
             }
+
+            ui.heading("Bookmarks");
+            bookmark_controls(ui, camera_commands, bookmarks, bookmark_name, current);
+
+            ui.heading("Split Screen");
+            split_screen_controls(ui, split_screen, selected, go_tos);
         });
+}
+
+/// `selected` is the body chosen in Body Info, for the second view to fly to.
+fn split_screen_controls(ui: &mut Ui, split_screen: &mut SplitScreen, selected: Option<(Entity, String)>, go_tos: &mut MessageWriter<GoTo>) {
+    ui.checkbox(&mut split_screen.enabled, "Second view")
+        .on_hover_text("Split the window between two cameras. The mouse and keys only move the left one.");
+    if !split_screen.enabled { return; }
+    match selected {
+        Some((entity, name)) => {
+            if ui.button(format!("Second view to {}", name)).clicked() {
+                go_tos.write(GoTo { entity, camera: CameraSlot::Secondary });
+            }
+        }
+        None => { ui.label("Choose a body in Body Info to send the second view to it."); }
+    }
+}
+
+fn bookmark_controls(ui: &mut Ui, camera_commands: &mut MessageWriter<CameraCommand>, bookmarks: &mut ResMut<CameraBookmarks>, bookmark_name: &mut String, current: &CameraBookmark) {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(bookmark_name);
+        let name = bookmark_name.trim();
+        if ui.add_enabled(!name.is_empty(), egui::Button::new("Save View")).clicked() {
+            let bookmark = CameraBookmark { name: name.to_string(), ..current.clone() };
+            // Saving under a name that's taken replaces it
+            match bookmarks.0.iter_mut().find(|existing| existing.name == bookmark.name) {
+                Some(existing) => *existing = bookmark,
+                None => bookmarks.0.push(bookmark),
+            }
+            bookmark_name.clear();
+        }
+    });
+
+    let mut removed = None;
+    for (i, bookmark) in bookmarks.0.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.button(&bookmark.name).clicked() {
+                camera_commands.write(CameraCommand::Restore(bookmark.clone()));
+            }
+            if ui.small_button("Delete").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        bookmarks.0.remove(i);
+    }
 }
\ No newline at end of file
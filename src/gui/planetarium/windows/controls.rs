@@ -1,17 +1,37 @@
+use std::path::PathBuf;
 use bevy_egui::{egui, EguiContexts};
 use bevy::prelude::*;
 use bevy_egui::egui::Ui;
 use num_traits::Pow;
 use crate::body::motive::calculate_body_positions::SimulationPerformanceMetrics;
-use crate::body::universe::save::ViewSettings;
-use crate::foundations::time::JD_SECONDS_PER_JULIAN_DAY;
+use crate::body::motive::PhysicsGraph;
+use crate::body::motive::Motive;
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::integrator::IntegratorKind;
+use crate::body::universe::collision::CollisionBehavior;
+use crate::body::universe::save::{Background, TrajectoryFade, UniversePhysics, ViewCenter, ViewSettings};
+use crate::foundations::gravity;
+use crate::foundations::time::{Instant, JD_SECONDS_PER_JULIAN_DAY};
 use crate::gui::app::AppState;
 use crate::gui::common;
 use crate::gui::menu::{MenuState, UiState};
 use crate::gui::planetarium::time::SimTime;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::planetarium::{BodySelection, CalculateTrajectory};
+use crate::gui::planetarium::gizmoids::markers::{BarycenterPreset, Markers};
+use crate::gui::planetarium::measure::Measurement;
+use crate::gui::settings::Settings;
+use crate::interop::elements;
 use crate::util::format;
-use crate::util::format::seconds_to_naive_date;
+use crate::util::units::Unit;
+
+/// Sim seconds per real second.
+const SPEED_PRESETS: [(&str, f64); 5] = [
+    ("1×", 1.0),
+    ("60×", 60.0),
+    ("3600×", 3600.0),
+    ("1 day/s", JD_SECONDS_PER_JULIAN_DAY),
+    ("1 year/s", JD_SECONDS_PER_JULIAN_DAY * 365.2425), // https://www.grc.nasa.gov/www/k-12/Numbers/Math/Mathematical_Thinking/calendar_calculations.htm
+];
 
 pub fn control_window(
     mut contexts: EguiContexts,
@@ -22,23 +42,210 @@ pub fn control_window(
     mut time: ResMut<SimTime>,
     view_settings: ResMut<ViewSettings>,
     perf_metrics: Res<SimulationPerformanceMetrics>,
+    mut physics: ResMut<UniversePhysics>,
+    mut graph: ResMut<PhysicsGraph>,
+    mut calcs: MessageWriter<CalculateTrajectory>,
+    mut gravity_unit: Local<Option<Unit>>,
+    mut markers: ResMut<Markers>,
+    bodies: Query<(&BodyInfo, &Motive, &BodyState)>,
+    mut measurement: ResMut<Measurement>,
+    mut export: Local<ElementExport>,
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
     
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
+    if !settings.windows.controls { return; }
 
-    egui::Window::new("Controls")
+    settings.window_positions.window("Controls")
         .vscroll(true)
         .show(ctx, |ui| {
-            planetarium_controls(next_app_state, next_menu_state, &mut time, ui, &mut ui_state, view_settings, &perf_metrics);
+            let mut centers: Vec<(String, String)> = bodies.iter()
+                .filter(|(info, _, _)| info.major)
+                .map(|(info, _, _)| (info.id.clone(), info.display_name()))
+                .collect();
+            centers.sort_by(|a, b| a.1.cmp(&b.1));
+            planetarium_controls(next_app_state, next_menu_state, &mut time, ui, &mut ui_state, view_settings, &perf_metrics, &centers);
+
+            ui.separator();
+            let unit = gravity_unit.get_or_insert(Unit::GravitySi);
+            if physics_controls(ui, &mut physics.gravitational_constant, unit) {
+                // Periods and Newtonian accelerations all scale with G
+                graph.needs_rebuild = true;
+                calcs.write(CalculateTrajectory { selection: BodySelection::All });
+            }
+            if ui.checkbox(&mut physics.mutual_gravity, "Mutual gravity")
+                .on_hover_text("Major bodies pull on each other, starting from where their orbits put them")
+                .changed()
+            {
+                graph.needs_rebuild = true;
+            }
+            collision_controls(ui, &mut physics.collision_behavior, &mut physics.restitution);
+            let physics = &mut *physics;
+            integrator_controls(ui, &mut physics.integrator, &mut physics.max_substeps, &bodies);
+
+            ui.separator();
+            marker_controls(ui, &mut markers);
+            ui.toggle_value(&mut measurement.active, "Measure between bodies")
+                .on_hover_text("Click two bodies to see how far apart they are");
+
+            ui.separator();
+            export_controls(ui, &mut export, &bodies, time.time, physics.gravitational_constant);
     });
 }
 
+/// Where to write the elements table, and how the last export went.
+pub struct ElementExport {
+    path: String,
+    result: Option<Result<String, String>>,
+}
+
+impl Default for ElementExport {
+    fn default() -> Self {
+        Self { path: elements::ELEMENTS_PATH.to_string(), result: None }
+    }
+}
+
+fn export_controls(ui: &mut Ui, export: &mut ElementExport, bodies: &Query<(&BodyInfo, &Motive, &BodyState)>, time: Instant, gravitational_constant: f64) {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut export.path);
+        if ui.button("Export Elements").on_hover_text("Write every body's current orbital elements to a CSV file").clicked() {
+            let path = PathBuf::from(&export.path);
+            let mut rows = elements::osculating_elements(bodies, time, gravitational_constant);
+            let written = path.parent()
+                .map(std::fs::create_dir_all)
+                .unwrap_or(Ok(()))
+                .and_then(|_| std::fs::File::create(&path))
+                .and_then(|file| elements::write_elements_csv(&mut rows, time, std::io::BufWriter::new(file)));
+            export.result = Some(match written {
+                Ok(()) => Ok(format!("Wrote {} bodies to {}", rows.len(), path.display())),
+                Err(e) => Err(format!("Couldn't write {}: {}", path.display(), e)),
+            });
+        }
+    });
+    match &export.result {
+        Some(Ok(message)) => { ui.label(message); }
+        Some(Err(message)) => { ui.colored_label(egui::Color32::RED, message); }
+        None => {}
+    }
+}
+
+/// Returns true if G changed.
+fn physics_controls(ui: &mut Ui, gravitational_constant: &mut f64, unit: &mut Unit) -> bool {
+    let before = *gravitational_constant;
+    ui.label("Physics");
+    common::unit_stepper(ui, "G", gravitational_constant, unit);
+    if ui.add_enabled(before != gravity::G, egui::Button::new("Reset to real G")).clicked() {
+        *gravitational_constant = gravity::G;
+    }
+    *gravitational_constant != before
+}
+
+fn collision_controls(ui: &mut Ui, behavior: &mut CollisionBehavior, restitution: &mut f64) {
+    egui::ComboBox::from_label("On collision")
+        .selected_text(behavior.label())
+        .show_ui(ui, |ui| {
+            for option in CollisionBehavior::ALL {
+                ui.selectable_value(behavior, option, option.label());
+            }
+        });
+    if *behavior == CollisionBehavior::Bounce {
+        ui.add(egui::Slider::new(restitution, 0.0..=1.0).text("Restitution"))
+            .on_hover_text("Share of their approach speed bodies keep as they bounce apart");
+    }
+}
+
+fn integrator_controls(ui: &mut Ui, integrator: &mut IntegratorKind, max_substeps: &mut usize, bodies: &Query<(&BodyInfo, &Motive, &BodyState)>) {
+    egui::ComboBox::from_label("Integrator")
+        .selected_text(integrator.label())
+        .show_ui(ui, |ui| {
+            for option in IntegratorKind::ALL {
+                ui.selectable_value(integrator, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("How Newtonian bodies are stepped. Higher orders drift less off their orbits, for more work per step");
+    if *integrator != IntegratorKind::Rk45 { return; }
+
+    ui.horizontal(|ui| {
+        ui.label("Max substeps");
+        ui.add(egui::DragValue::new(max_substeps).range(1..=1_000_000).speed(10.0));
+    }).response.on_hover_text("Most pieces one step is split into. A close pass can ask for thousands, and stall everything else");
+    let mut clamped: Vec<_> = bodies.iter()
+        .filter(|(_, _, state)| state.inaccurate)
+        .map(|(info, _, _)| info.display_name())
+        .collect();
+    if !clamped.is_empty() {
+        clamped.sort();
+        ui.colored_label(egui::Color32::YELLOW, format!("Hit the substep limit, may be inaccurate: {}", clamped.join(", ")));
+    }
+}
+
+/// `centers` are the ids and names of the bodies the view can be centered on.
+fn center_controls(ui: &mut Ui, center: &mut ViewCenter, centers: &[(String, String)]) {
+    let selected = match center {
+        ViewCenter::Origin => "Origin".to_string(),
+        ViewCenter::Body(id) => centers.iter()
+            .find(|(center_id, _)| center_id == id)
+            .map(|(_, name)| name.clone())
+            .unwrap_or(id.clone()),
+    };
+    egui::ComboBox::from_label("Centered on")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(center, ViewCenter::Origin, "Origin");
+            for (id, name) in centers {
+                ui.selectable_value(center, ViewCenter::Body(id.clone()), name);
+            }
+        })
+        .response
+        .on_hover_text("Draw everything relative to this body, taking out its own motion");
+}
+
+fn background_controls(ui: &mut Ui, background: &mut Background) {
+    ui.horizontal(|ui| {
+        ui.label("Background");
+        if ui.selectable_label(matches!(background, Background::Solid(_)), "Solid").clicked() {
+            *background = Background::default();
+        }
+        if ui.selectable_label(matches!(background, Background::Starfield { .. }), "Stars").clicked() {
+            *background = Background::Starfield { seed: 0, count: 2000 };
+        }
+    });
+    match background {
+        Background::Solid(color) => {
+            ui.horizontal(|ui| {
+                ui.label("Color");
+                ui.color_edit_button_srgb(color);
+            });
+        }
+        Background::Starfield { seed, count } => {
+            ui.add(egui::Slider::new(count, 0..=20_000).text("Stars"));
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(seed));
+            }).response.on_hover_text("The same seed always gives the same sky");
+        }
+    }
+}
+
+fn marker_controls(ui: &mut Ui, markers: &mut Markers) {
+    ui.label("Markers");
+    ui.horizontal(|ui| {
+        ui.label("Barycenter");
+        if ui.selectable_label(markers.barycenter.is_none(), "None").clicked() {
+            markers.barycenter = None;
+        }
+        for preset in BarycenterPreset::ALL {
+            if ui.selectable_label(markers.barycenter == Some(preset), preset.label()).clicked() {
+                markers.barycenter = Some(preset);
+            }
+        }
+    });
+    ui.checkbox(&mut markers.lagrange, "Lagrange points of selected body");
+}
+
 pub fn planetarium_controls(
     mut next_app_state: ResMut<NextState<AppState>>,
     mut next_menu_state: ResMut<NextState<MenuState>>,
@@ -47,6 +254,7 @@ pub fn planetarium_controls(
     ui_state: &mut ResMut<UiState>,
     mut view_settings: ResMut<ViewSettings>,
     perf_metrics: &SimulationPerformanceMetrics,
+    centers: &[(String, String)],
 ) {
     if ui.button("Quit to Main Menu").clicked() {
         // TODO: Some kind of save nag
@@ -77,7 +285,7 @@ pub fn planetarium_controls(
         if time.seconds_only {
             ui.label(format!("Time: {:.1}s", time.time.to_j2000_seconds()));
         } else {
-            ui.label(format!("Time: {}", seconds_to_naive_date(time.time.to_j2000_seconds().round() as i64)));
+            ui.label(format!("Time: {}", time.format_calendar()));
         }
     });
     let gui_speed_current = time.gui_speed;
@@ -92,7 +300,7 @@ pub fn planetarium_controls(
         if time.seconds_only {
             ui.label(format!("Simulation speed: {:.1}s / s", gui_speed_current));
         } else {
-            ui.label(format!("Simulation speed: {} / s", seconds_to_naive_date(gui_speed_current.round() as i64)));
+            ui.label(format!("Simulation speed: {} / s", SimTime::format_elapsed(gui_speed_current)));
         }
     });
     common::stepper(ui, "", &mut time.gui_speed);
@@ -100,12 +308,19 @@ pub fn planetarium_controls(
         ui.checkbox(&mut time.seconds_only, "Display as seconds");
     });
     ui.horizontal(|ui| {
-        if ui.button("1 year").clicked() { time.gui_speed = JD_SECONDS_PER_JULIAN_DAY * 365.2425; } // https://www.grc.nasa.gov/www/k-12/Numbers/Math/Mathematical_Thinking/calendar_calculations.htm
-        if ui.button("1 day").clicked() { time.gui_speed = JD_SECONDS_PER_JULIAN_DAY; }
-        if ui.button("1 hour").clicked() { time.gui_speed = 60.0 * 60.0; }
-        if ui.button("1 minute").clicked() { time.gui_speed = 60.0; }
-        if ui.button("1 second").clicked() { time.gui_speed = 1.0; }
+        // Presets keep the current direction
+        let direction = if time.is_reversed() { -1.0 } else { 1.0 };
+        for (label, speed) in SPEED_PRESETS {
+            let selected = time.gui_speed == speed * direction;
+            if ui.selectable_label(selected, label).clicked() { time.gui_speed = speed * direction; }
+        }
     });
+    ui.horizontal(|ui| {
+        let mut reversed = time.is_reversed();
+        if ui.checkbox(&mut reversed, "Reverse").changed() { time.gui_speed = -time.gui_speed; }
+    });
+    ui.checkbox(&mut time.deterministic, "Deterministic")
+        .on_hover_text("Step exactly with real time, independent of frame rate. Falls behind rather than skipping steps.");
 
     ui.separator();
 
@@ -148,15 +363,38 @@ pub fn planetarium_controls(
         ui.checkbox(&mut view_settings.show_labels, "");
         ui.checkbox(&mut view_settings.show_trajectories, "");
     });
+    egui::ComboBox::from_label("Orbit fade")
+        .selected_text(view_settings.trajectory_fade.label())
+        .show_ui(ui, |ui| {
+            for option in TrajectoryFade::ALL {
+                ui.selectable_value(&mut view_settings.trajectory_fade, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("Which way orbit lines fade from their bodies, to show which way they're going");
+    ui.checkbox(&mut view_settings.show_soi, "Spheres of influence")
+        .on_hover_text("Not shown with a logarithmic distance scale.");
+    ui.checkbox(&mut view_settings.show_grid, "Reference plane grid");
+    ui.checkbox(&mut view_settings.apply_light_time, "Light-time correction")
+        .on_hover_text("Show bodies where they were when the light now reaching the camera left them. Not applied with a logarithmic distance scale.");
+    center_controls(ui, &mut view_settings.center, centers);
+    background_controls(ui, &mut view_settings.background);
 
+    let mut solo_clicked = None;
     for (tag_name, tag_state) in &mut view_settings.tags {
         ui.horizontal(|ui| {
             ui.label(tag_name);
             ui.checkbox(&mut tag_state.shown, "");
             ui.checkbox(&mut tag_state.trajectory, "");
-
+            if ui.selectable_label(tag_state.solo, "S").on_hover_text("Show only this tag").clicked() {
+                solo_clicked = Some(tag_name.clone());
+            }
+            ui.toggle_value(&mut tag_state.muted, "M").on_hover_text("Hide this tag");
         });
     }
+    if let Some(tag_name) = solo_clicked {
+        view_settings.toggle_solo(tag_name);
+    }
 
     // Simulation performance
     ui.separator();
@@ -208,19 +446,7 @@ pub fn planetarium_controls(
                 Ok(content) => {
                     let timestamp = std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
-                        .map(|d| {
-                            let secs = d.as_secs();
-                            let millis = d.subsec_millis();
-                            // Break epoch seconds into date/time components
-                            let days = secs / 86400;
-                            let day_secs = secs % 86400;
-                            let hours = day_secs / 3600;
-                            let mins = (day_secs % 3600) / 60;
-                            let s = day_secs % 60;
-                            // Days since 1970-01-01 to Y-M-D (simplified)
-                            let (y, m, d) = epoch_days_to_ymd(days as i64);
-                            format!("{y:04}-{m:02}-{d:02}T{hours:02}-{mins:02}-{s:02}.{millis:03}")
-                        })
+                        .map(format::file_timestamp)
                         .unwrap_or_else(|_| "unknown".to_string());
                     let dir = std::path::Path::new("logs/performance");
                     if let Err(e) = std::fs::create_dir_all(dir) {
@@ -234,19 +460,3 @@ pub fn planetarium_controls(
         }
     });
 }
-
-/// Convert days since Unix epoch to (year, month, day).
-fn epoch_days_to_ymd(mut days: i64) -> (i64, u32, u32) {
-    // Shift to March-based year to simplify leap year handling
-    days += 719468; // days from 0000-03-01 to 1970-01-01
-    let era = days.div_euclid(146097);
-    let doe = days.rem_euclid(146097) as u32;
-    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
-    let y = yoe as i64 + era * 400;
-    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    let mp = (5 * doy + 2) / 153;
-    let d = doy - (153 * mp + 2) / 5 + 1;
-    let m = if mp < 10 { mp + 3 } else { mp - 9 };
-    let y = if m <= 2 { y + 1 } else { y };
-    (y, m, d)
-}
@@ -0,0 +1,82 @@
+//! Pasting in a table of bodies around one primary. See `interop::bodies_csv`.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::info::BodyInfo;
+use crate::body::universe::Universe;
+use crate::body::universe::edit::{Edit, EditCommand};
+use crate::body::universe::save::{CompoundMotiveEntry, UniversePhysics};
+use crate::gui::settings::Settings;
+use crate::interop::bodies_csv::{self, CsvImportError};
+
+#[derive(Default)]
+pub struct CsvImport {
+    primary_id: Option<String>,
+    text: String,
+    /// What happened the last time Import was pressed.
+    result: Option<String>,
+}
+
+pub fn csv_import_window(
+    settings: Res<Settings>,
+    universe: Res<Universe>,
+    physics: Res<UniversePhysics>,
+    bodies: Query<&BodyInfo>,
+    mut contexts: EguiContexts,
+    mut edits: MessageWriter<Edit>,
+    mut import: Local<CsvImport>,
+) {
+    if !settings.windows.csv_import { return; }
+
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+
+    settings.window_positions.window("Import Bodies")
+        .vscroll(true)
+        .show(ctx, |ui| {
+            let import = &mut *import;
+            egui::ComboBox::from_label("Primary")
+                .selected_text(import.primary_id.as_ref().and_then(|id| universe.get_by_id(id)).map(|name| name.as_str()).unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    let mut options: Vec<_> = universe.id_to_name_iter().collect();
+                    options.sort_by(|a, b| a.1.cmp(b.1));
+                    for (id, name) in options {
+                        ui.selectable_value(&mut import.primary_id, Some(id.clone()), name);
+                    }
+                });
+
+            ui.label(format!("Columns: {}", bodies_csv::COLUMNS.join(", ")));
+            ui.add(egui::TextEdit::multiline(&mut import.text)
+                .code_editor()
+                .desired_rows(8)
+                .hint_text("Paste a CSV here, header first"));
+
+            let primary = import.primary_id.as_ref().and_then(|id| bodies.iter().find(|info| &info.id == id));
+            if ui.add_enabled(primary.is_some(), egui::Button::new("Import")).clicked() {
+                let primary = primary.unwrap();
+                let mu = physics.gravitational_constant * primary.mass;
+                import.result = Some(match bodies_csv::import_bodies_csv(&import.text, &primary.id, mu) {
+                    Ok(imported) => {
+                        let count = imported.len();
+                        // The whole import is one undo step
+                        let added = imported.into_iter().map(|body| {
+                            let (info, appearance, motive, rotation, properties) = body.into_parts();
+                            EditCommand::AddBody {
+                                body: Box::new(CompoundMotiveEntry { info, motive, appearance, rotation, properties }),
+                                satellites: Vec::new(),
+                            }
+                        }).collect();
+                        edits.write(Edit::Apply { command: EditCommand::Batch(added), coalesce: false });
+                        format!("Added {} bodies around {}.", count, primary.display_name())
+                    }
+                    Err(CsvImportError::Empty) => "Nothing to import.".into(),
+                    Err(CsvImportError::Header(header)) => format!("The first line should be the column names, not \"{}\".", header),
+                    Err(CsvImportError::Row { line, message }) => format!("Line {}: {}", line, message),
+                });
+            }
+            if let Some(result) = &import.result {
+                ui.label(result);
+            }
+        });
+}
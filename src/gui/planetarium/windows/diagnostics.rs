@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::motive::calculate_body_positions::SimulationPerformanceMetrics;
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
+
+pub fn diagnostics_window(
+    settings: Res<Settings>,
+    mut contexts: EguiContexts,
+    sim_time: Res<SimTime>,
+    metrics: Res<SimulationPerformanceMetrics>,
+    time: Res<Time>,
+) {
+    if !settings.windows.diagnostics { return; }
+
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+
+    settings.window_positions.window("Frame Diagnostics")
+        .vscroll(true)
+        .show(ctx, |ui| {
+            ui.label(format!("Steps this frame: {} / {}", sim_time.steps_completed, sim_time.steps_requested));
+            ui.label(format!("Steps queued: {} of {} owed", sim_time.steps_issued, sim_time.steps_owed));
+
+            let physics_ms = metrics.avg_time_per_step_ms * metrics.steps_completed as f64;
+            let budget_ms = sim_time.max_frame_time * 1000.0;
+            ui.label(format!("Physics budget: {:.2} / {:.2} ms", physics_ms, budget_ms));
+            ui.add(egui::ProgressBar::new((physics_ms / budget_ms).clamp(0.0, 1.0) as f32));
+
+            let backlog = sim_time.previous_times.len();
+            ui.label(format!("Backlog: {} steps ({:.2} s sim time)", backlog, backlog as f64 * sim_time.step));
+
+            ui.separator();
+            if sim_time.falling_behind() {
+                ui.colored_label(egui::Color32::RED, "Falling behind at this speed");
+                let real_delta = time.delta_secs_f64();
+                if real_delta > 0.0 {
+                    let sustainable = sim_time.steps_completed as f64 * sim_time.step / real_delta;
+                    ui.label(format!("Sustainable speed: about {:.1}x", sustainable));
+                }
+            } else {
+                ui.label("Keeping up");
+            }
+        });
+}
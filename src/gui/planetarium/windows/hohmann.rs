@@ -0,0 +1,155 @@
+//! Hohmann transfers between two circular orbits around the same primary.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_egui::egui::Ui;
+use crate::body::universe::save::UniversePhysics;
+use crate::foundations::kepler::{eccentricity, semi_major_axis, speed};
+use crate::gui::common;
+use crate::gui::settings::Settings;
+use crate::util::circular;
+use crate::util::format::{seconds_to_naive_date, si_prefixed};
+use crate::util::units::Unit;
+
+/// Half an ellipse from one circular orbit out (or in) to another.
+#[derive(Debug, Clone, Copy)]
+pub struct HohmannTransfer {
+    /// Change of speed leaving the first orbit, in m/s. Negative is retrograde.
+    pub departure_burn: f64,
+    /// Change of speed joining the second orbit, in m/s. Negative is retrograde.
+    pub arrival_burn: f64,
+    /// Seconds from one burn to the other.
+    pub transfer_time: f64,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub periapsis: f64,
+    pub apoapsis: f64,
+}
+
+impl HohmannTransfer {
+    pub fn total_delta_v(&self) -> f64 {
+        self.departure_burn.abs() + self.arrival_burn.abs()
+    }
+}
+
+/// The transfer from a circular orbit `from` meters out to one `to` meters out.
+/// None for radii that aren't both positive.
+pub fn hohmann(mu: f64, from: f64, to: f64) -> Option<HohmannTransfer> {
+    if from <= 0.0 || to <= 0.0 || mu <= 0.0 { return None; }
+    let (periapsis, apoapsis) = (from.min(to), from.max(to));
+    let semi_major_axis = semi_major_axis::radii(periapsis, apoapsis);
+    Some(HohmannTransfer {
+        departure_burn: speed::vis_viva(mu, from, semi_major_axis) - circular::velocity(mu, from),
+        arrival_burn: circular::velocity(mu, to) - speed::vis_viva(mu, to, semi_major_axis),
+        transfer_time: circular::period(mu, semi_major_axis) / 2.0,
+        semi_major_axis,
+        eccentricity: eccentricity::radii(periapsis, apoapsis),
+        periapsis,
+        apoapsis,
+    })
+}
+
+/// What's typed into the calculator. Starts out at low Earth orbit to geostationary.
+pub struct HohmannInputs {
+    primary_mass: f64,
+    from: f64,
+    to: f64,
+    mass_unit: Unit,
+    radius_unit: Unit,
+}
+
+impl Default for HohmannInputs {
+    fn default() -> Self {
+        Self {
+            primary_mass: 5.972e24,
+            from: 6.678e6,
+            to: 4.2164e7,
+            mass_unit: Unit::EarthMass,
+            radius_unit: Unit::Kilometer,
+        }
+    }
+}
+
+pub fn hohmann_window(
+    settings: Res<Settings>,
+    physics: Res<UniversePhysics>,
+    mut contexts: EguiContexts,
+    mut inputs: Local<HohmannInputs>,
+) {
+    if !settings.windows.hohmann { return; }
+
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+
+    settings.window_positions.window("Hohmann Transfer")
+        .vscroll(true)
+        .show(ctx, |ui| {
+            let inputs = &mut *inputs;
+            common::unit_stepper(ui, "Primary mass", &mut inputs.primary_mass, &mut inputs.mass_unit);
+            let mut radius_unit = inputs.radius_unit;
+            common::unit_stepper(ui, "From radius", &mut inputs.from, &mut radius_unit);
+            common::unit_stepper(ui, "To radius", &mut inputs.to, &mut radius_unit);
+            inputs.radius_unit = radius_unit;
+
+            ui.separator();
+            let mu = physics.gravitational_constant * inputs.primary_mass;
+            match hohmann(mu, inputs.from, inputs.to) {
+                Some(transfer) => transfer_section(ui, &transfer),
+                None => { ui.label("Radii and mass must be positive."); }
+            }
+        });
+}
+
+fn transfer_section(ui: &mut Ui, transfer: &HohmannTransfer) {
+    let burn = |delta_v: f64| format!("{} {}", si_prefixed(delta_v.abs(), "m/s"), if delta_v < 0.0 { "retrograde" } else { "prograde" });
+    for (label, value) in [
+        ("Departure burn:", burn(transfer.departure_burn)),
+        ("Arrival burn:", burn(transfer.arrival_burn)),
+        ("Total Δv:", si_prefixed(transfer.total_delta_v(), "m/s")),
+        ("Transfer time:", seconds_to_naive_date(transfer.transfer_time.round() as i64)),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.label(value);
+        });
+    }
+
+    ui.separator();
+    ui.label("Transfer Orbit");
+    for (label, value) in [
+        ("Semi-major axis:", si_prefixed(transfer.semi_major_axis, "m")),
+        ("Eccentricity:", format!("{:.6}", transfer.eccentricity)),
+        ("Periapsis:", si_prefixed(transfer.periapsis, "m")),
+        ("Apoapsis:", si_prefixed(transfer.apoapsis, "m")),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.label(value);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EARTH_MU: f64 = 3.986004418e14;
+
+    #[test]
+    fn test_leo_to_geo() {
+        // 300 km up to geostationary: about 2.43 and 1.47 km/s, five and a quarter hours apart
+        let transfer = hohmann(EARTH_MU, 6.678e6, 4.2164e7).unwrap();
+        assert!((transfer.departure_burn - 2425.8).abs() < 1.0, "{:?}", transfer);
+        assert!((transfer.arrival_burn - 1466.8).abs() < 1.0, "{:?}", transfer);
+        assert!((transfer.total_delta_v() - 3892.6).abs() < 1.0, "{:?}", transfer);
+        assert!((transfer.transfer_time / 3600.0 - 5.275).abs() < 1e-3, "{:?}", transfer);
+        assert!((transfer.eccentricity - 0.72655).abs() < 1e-5, "{:?}", transfer);
+
+        // Coming back down takes the same burns, the other way
+        let back = hohmann(EARTH_MU, 4.2164e7, 6.678e6).unwrap();
+        assert!((back.departure_burn + transfer.arrival_burn).abs() < 1e-9);
+        assert!((back.arrival_burn + transfer.departure_burn).abs() < 1e-9);
+        assert!(hohmann(EARTH_MU, 0.0, 4.2164e7).is_none());
+    }
+}
@@ -1,6 +1,44 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::gui::settings::Settings;
+
 pub mod body_edit;
 pub mod settings;
 pub mod spin;
 pub mod controls;
 pub mod body_info;
 pub mod camera;
+pub mod diagnostics;
+pub mod timelapse;
+pub mod hohmann;
+pub mod csv_import;
+pub mod save_diff;
+
+/// Windows whose positions are kept in `Settings::window_positions` between sessions.
+const REMEMBERED: [&str; 12] = [
+    "Controls",
+    "Settings",
+    "Body Edit",
+    "Body Info",
+    "Camera Settings",
+    "Spin Gravity Calculator",
+    "Body Spin",
+    "Frame Diagnostics",
+    "Time-Lapse",
+    "Hohmann Transfer",
+    "Import Bodies",
+    "Compare Save",
+];
+
+/// Copies where each window is into the settings, to be saved with them.
+pub fn remember_window_positions(mut settings: ResMut<Settings>, mut contexts: EguiContexts) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    for title in REMEMBERED {
+        let Some(rect) = ctx.memory(|memory| memory.area_rect(egui::Id::new(title))) else { continue };
+        let position = [rect.left(), rect.top()];
+        // Only touched when a window's moved, so the settings aren't changed every frame
+        if settings.window_positions.get(title) != Some(position) {
+            settings.window_positions.set(title, position);
+        }
+    }
+}
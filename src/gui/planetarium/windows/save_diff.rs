@@ -0,0 +1,103 @@
+//! What's changed in the universe since a save file on disk. See `universe::diff`.
+
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::body::appearance::Appearance;
+use crate::body::motive::info::{BodyInfo, BodyProperties};
+use crate::body::motive::Motive;
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::Universe;
+use crate::body::universe::diff::{self, UniverseDiff};
+use crate::body::universe::save::{UniverseFile, UniverseFileContents, UniversePhysics, UniverseReadError, ViewSettings};
+use crate::body::universe::save_sqlite::HeldEmFile;
+use crate::body::universe::scenario::Scenario;
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::settings::Settings;
+
+#[derive(Default)]
+pub struct SaveDiff {
+    path: Option<String>,
+    /// The differences from the file to now, or why it couldn't be read.
+    result: Option<Result<Vec<UniverseDiff>, String>>,
+}
+
+pub fn save_diff_window(
+    settings: Res<Settings>,
+    universe: Res<Universe>,
+    sim_time: Res<SimTime>,
+    view_settings: Res<ViewSettings>,
+    physics: Res<UniversePhysics>,
+    scenario: Res<Scenario>,
+    held: Res<HeldEmFile>,
+    bodies: Query<(&BodyInfo, &Motive, &Appearance, Option<&BodyRotation>, Option<&BodyProperties>)>,
+    mut contexts: EguiContexts,
+    mut save_diff: Local<SaveDiff>,
+) {
+    if !settings.windows.save_diff { return; }
+
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+
+    settings.window_positions.window("Compare Save")
+        .vscroll(true)
+        .show(ctx, |ui| {
+            let save_diff = &mut *save_diff;
+            // Starts out as the file this universe was loaded from
+            let path = save_diff.path.get_or_insert_with(|| universe.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default());
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(path);
+                if ui.button("Compare").on_hover_text("List what's changed between this file and the universe now").clicked() {
+                    let file_path = PathBuf::from(&*path);
+                    // The open .em file is locked, even against this program, so it's read through the lock
+                    let open_file = (universe.path.as_ref() == Some(&file_path)).then(|| held.read()).flatten();
+                    let loaded = match open_file {
+                        Some(contents) => contents.map_err(UniverseReadError::from),
+                        None => UniverseFile::try_load_from_path(&file_path).map(|file| file.contents),
+                    };
+                    save_diff.result = Some(match loaded {
+                        Ok(contents) => {
+                            let now = UniverseFileContents::snapshot(&bodies, &sim_time, &view_settings, &physics, &scenario, &[]);
+                            Ok(diff::diff_universes(&contents, &now))
+                        }
+                        Err(e) => Err(format!("Couldn't read {}: {:?}", path, e)),
+                    });
+                }
+            });
+
+            match &save_diff.result {
+                None => {}
+                Some(Err(message)) => { ui.colored_label(egui::Color32::RED, message); }
+                Some(Ok(diffs)) if diffs.is_empty() => { ui.label("No bodies have changed."); }
+                Some(Ok(diffs)) => {
+                    let name = |id: &str| universe.get_by_id(id).cloned().unwrap_or(id.to_string());
+                    egui::Grid::new("save_diff").striped(true).show(ui, |ui| {
+                        for diff in diffs {
+                            match diff {
+                                UniverseDiff::Added(id) => {
+                                    ui.colored_label(egui::Color32::GREEN, "Added");
+                                    ui.label(name(id));
+                                    ui.label("");
+                                    ui.label("");
+                                }
+                                UniverseDiff::Removed(id) => {
+                                    ui.colored_label(egui::Color32::RED, "Removed");
+                                    ui.label(id);
+                                    ui.label("");
+                                    ui.label("");
+                                }
+                                UniverseDiff::Changed { id, field, old, new } => {
+                                    ui.colored_label(egui::Color32::YELLOW, "Changed");
+                                    ui.label(format!("{} {}", name(id), field));
+                                    ui.label(old).on_hover_text("In the file");
+                                    ui.label(new).on_hover_text("Now");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                }
+            }
+        });
+}
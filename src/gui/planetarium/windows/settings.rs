@@ -1,11 +1,12 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use crate::gui::menu::UiState;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::gui::settings::Settings;
 
 pub fn settings_window(
 
     mut settings: ResMut<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
     mut ui_state: ResMut<UiState>,
     mut contexts: EguiContexts,
 ) {
@@ -13,15 +14,13 @@ pub fn settings_window(
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
-    
+    settings.ui.apply(ctx);
+    if !settings.windows.settings { return; }
+
     // Start collapsed: https://github.com/emilk/egui/pull/5661
-    egui::Window::new("Settings")
+    settings.window_positions.window("Settings")
         .vscroll(true)
         .show(ctx, |ui| {
-            crate::gui::menu::settings::settings_panel(&mut settings, ui);
+            crate::gui::menu::settings::settings_panel(&mut settings, &keys, ui);
         });
 }
\ No newline at end of file
@@ -2,29 +2,80 @@ use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_egui::egui::Context;
 use num_traits::FloatConst;
-use crate::gui::settings::{Settings, UiTheme};
+use crate::body::motive::info::BodyInfo;
+use crate::body::rotation::BodyRotation;
+use crate::body::universe::Universe;
+use crate::body::universe::edit::{Edit, EditCommand};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::planetarium::windows::body_edit::{edit_angle, edit_stepper};
+use crate::gui::planetarium::windows::body_info::{self, BodyInfoState};
+use crate::gui::settings::Settings;
 
 pub fn spin_window(
-
     mut settings: ResMut<Settings>,
     mut contexts: EguiContexts,
+    universe: Res<Universe>,
+    mut body_info_state: ResMut<BodyInfoState>,
+    sim_time: Res<SimTime>,
+    bodies: Query<(&BodyInfo, Option<&BodyRotation>)>,
+    mut edits: MessageWriter<Edit>,
 ) {
     let ctx = contexts.ctx_mut();
     if ctx.is_err() { return; }
     let ctx = ctx.unwrap();
 
-    match settings.ui.theme {
-        UiTheme::Light => ctx.set_visuals(egui::Visuals::light()),
-        UiTheme::Dark => ctx.set_visuals(egui::Visuals::dark()),
-    }
+    settings.ui.apply(ctx);
 
     if settings.windows.spin {
         spin_gravity_calculator(&mut settings, ctx);
+        settings.window_positions.window("Body Spin")
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let mut body_options: Vec<(String, String)> = universe.id_to_name_iter()
+                    .map(|(id, name)| (name.clone(), id.clone()))
+                    .collect();
+                body_options.sort_by(|a, b| a.0.cmp(&b.0));
+                body_info::body_select_dropdown(Res::clone(&universe), &mut body_info_state, ui, body_options);
+
+                let selected = bodies.iter().find(|(info, _)| Some(&info.id) == body_info_state.current_body_id.as_ref());
+                match selected {
+                    None => { ui.label("No body Selected"); }
+                    Some((_, None)) => { ui.label("This body doesn't rotate. Add a rotation in Body Edit."); }
+                    Some((info, Some(rotation))) => body_spin(ui, &info.id, rotation, &sim_time, &mut edits),
+                }
+            });
+    }
+}
+
+/// Period, tilt and pole of the body's rotation, and where its prime meridian is now.
+fn body_spin(ui: &mut egui::Ui, id: &str, rotation: &BodyRotation, sim_time: &SimTime, edits: &mut MessageWriter<Edit>) {
+    let set_rotation = |after: BodyRotation| EditCommand::SetRotation { id: id.to_string(), before: Some(rotation.clone()), after: Some(after) };
+
+    edit_stepper(ui, "Sidereal period:", "h", rotation.period.abs() / 3600.0, edits, |hours| {
+        set_rotation(BodyRotation { period: hours.abs().max(1e-3) * 3600.0 * rotation.period.signum(), ..rotation.clone() })
+    });
+    let mut retrograde = rotation.period < 0.0;
+    if ui.checkbox(&mut retrograde, "Retrograde").on_hover_text("Turns clockwise seen from above its north pole").changed() {
+        edits.write(Edit::Apply { command: set_rotation(BodyRotation { period: -rotation.period, ..rotation.clone() }), coalesce: false });
     }
+    edit_stepper(ui, "Axial tilt:", "°", rotation.axial_tilt, edits, |axial_tilt| {
+        set_rotation(BodyRotation { axial_tilt, ..rotation.clone() })
+    });
+    edit_angle(ui, "Pole longitude:", rotation.node_longitude, edits, |node_longitude| {
+        set_rotation(BodyRotation { node_longitude, ..rotation.clone() })
+    });
+    edit_angle(ui, "Prime meridian at epoch:", rotation.prime_meridian_at_epoch, edits, |prime_meridian_at_epoch| {
+        set_rotation(BodyRotation { prime_meridian_at_epoch, ..rotation.clone() })
+    });
+
+    ui.separator();
+    let pole = rotation.pole();
+    ui.label(format!("Prime meridian: {:.2}°", rotation.prime_meridian_angle(sim_time.time)));
+    ui.label(format!("Pole: ({:.3}, {:.3}, {:.3})", pole.x, pole.y, pole.z));
 }
 
 pub fn spin_gravity_calculator(mut settings: &mut ResMut<Settings>, ctx: &mut Context) {
-    egui::Window::new("Spin Gravity Calculator")
+    settings.window_positions.window("Spin Gravity Calculator")
         .vscroll(true)
         .show(ctx, |ui| {
             ui.add(egui::Slider::new(&mut settings.windows.spin_data.radius, 0.1..=250.0)
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use crate::gui::planetarium::time::SimTime;
+use crate::gui::planetarium::timelapse::TimeLapse;
+use crate::gui::settings::Settings;
+
+pub fn timelapse_window(
+    settings: Res<Settings>,
+    mut contexts: EguiContexts,
+    mut timelapse: ResMut<TimeLapse>,
+    mut sim_time: ResMut<SimTime>,
+    mut error: Local<Option<String>>,
+) {
+    if !settings.windows.timelapse { return; }
+
+    let ctx = contexts.ctx_mut();
+    if ctx.is_err() { return; }
+    let ctx = ctx.unwrap();
+
+    settings.window_positions.window("Time-Lapse")
+        .vscroll(true)
+        .show(ctx, |ui| {
+            if let Some(recording) = &timelapse.recording {
+                let (done, frames) = recording.progress();
+                ui.label(format!("Recording frame {} / {}", done, frames));
+                ui.add(egui::ProgressBar::new(done as f32 / frames.max(1) as f32));
+                if ui.button("Stop").clicked() {
+                    timelapse.stop(&mut sim_time);
+                }
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Folder");
+                ui.text_edit_singleline(&mut timelapse.dir);
+            });
+            ui.add(egui::DragValue::new(&mut timelapse.frames).range(1..=100_000).prefix("Frames: "));
+            ui.add(egui::DragValue::new(&mut timelapse.increment).range(0.0..=f64::MAX).prefix("Sim time per frame: ").suffix(" s"));
+            ui.label(format!("{} physics steps per frame", (timelapse.increment / sim_time.step).round().max(1.0)));
+
+            if ui.button("Record").clicked() {
+                *error = timelapse.start(&mut sim_time).err().map(|e| format!("Couldn't make {}: {}", timelapse.dir, e));
+            }
+            if let Some(error) = error.as_ref() {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+}
@@ -1,6 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use bevy::prelude::*;
+use bevy_egui::egui;
 use serde::{Deserialize, Serialize};
+use crate::foundations::reference_frame::plane::ReferencePlane;
+use crate::gui::keybindings::Keybindings;
+use crate::gui::menu::SaveFileMeta;
 use crate::gui::util::ensure_toml;
 
 #[derive(Serialize, Deserialize, Debug, Resource)]
@@ -13,6 +18,16 @@ pub struct Settings {
     pub ui: UiSettings,
     #[serde(default)]
     pub windows: WindowSelections,
+    #[serde(default)]
+    pub window_positions: WindowPositions,
+    #[serde(default)]
+    pub keybindings: Keybindings,
+    #[serde(default)]
+    pub autosave: AutosaveSettings,
+    #[serde(default)]
+    pub screenshot: ScreenshotSettings,
+    #[serde(default)]
+    pub startup: StartupSettings,
 }
 
 impl Default for Settings {
@@ -22,6 +37,11 @@ impl Default for Settings {
             sound: SoundSettings::default(),
             ui: UiSettings::default(),
             windows: WindowSelections::default(),
+            window_positions: WindowPositions::default(),
+            keybindings: Keybindings::default(),
+            autosave: AutosaveSettings::default(),
+            screenshot: ScreenshotSettings::default(),
+            startup: StartupSettings::default(),
         }
     }
 }
@@ -41,6 +61,9 @@ pub struct DisplaySettings {
     /// Set both to 0.0 to disable fading.
     #[serde(default = "default_trajectory_fade_max")]
     pub trajectory_fade_max: f32,
+    /// Plane the scene is drawn in and orbital angles are shown against.
+    #[serde(default)]
+    pub reference_plane: ReferencePlane,
 }
 
 fn default_trajectory_fade_min() -> f32 {
@@ -58,6 +81,7 @@ impl Default for DisplaySettings {
             glow: DisplayGlow::default(),
             trajectory_fade_min: default_trajectory_fade_min(),
             trajectory_fade_max: default_trajectory_fade_max(),
+            reference_plane: ReferencePlane::default(),
         }
     }
 }
@@ -112,10 +136,22 @@ pub fn load() -> Settings {
         })
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct UiSettings {
     #[serde(default = "default_theme")]
     pub theme: UiTheme,
+    /// Multiplies the screen's own scale for all egui windows.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+    /// Size of the body name labels in the planetarium, in points.
+    #[serde(default = "default_label_font_size")]
+    pub label_font_size: f32,
+    /// The axis compass in the corner of the planetarium.
+    #[serde(default = "default_true")]
+    pub show_compass: bool,
+    /// The camera's speed relative to the body it's circling.
+    #[serde(default = "default_true")]
+    pub show_relative_velocity: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
@@ -123,22 +159,141 @@ pub enum UiTheme {
     #[default]
     Light,
     Dark,
+    HighContrast,
+}
+
+impl UiTheme {
+    pub fn visuals(&self) -> egui::Visuals {
+        match self {
+            UiTheme::Light => egui::Visuals::light(),
+            UiTheme::Dark => egui::Visuals::dark(),
+            UiTheme::HighContrast => {
+                let mut visuals = egui::Visuals::dark();
+                let outline = egui::Stroke::new(1.0, egui::Color32::WHITE);
+                visuals.override_text_color = Some(egui::Color32::WHITE);
+                visuals.panel_fill = egui::Color32::BLACK;
+                visuals.window_fill = egui::Color32::BLACK;
+                visuals.extreme_bg_color = egui::Color32::BLACK;
+                visuals.window_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+                visuals.widgets.noninteractive.bg_stroke = outline;
+                visuals.widgets.inactive.bg_stroke = outline;
+                visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+                visuals.selection.bg_fill = egui::Color32::from_rgb(255, 200, 0);
+                visuals.selection.stroke = egui::Stroke::new(1.0, egui::Color32::BLACK);
+                visuals.hyperlink_color = egui::Color32::YELLOW;
+                visuals
+            }
+        }
+    }
 }
 
 fn default_theme() -> UiTheme {
     UiTheme::Dark
 }
 
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_label_font_size() -> f32 {
+    14.0
+}
+
 impl Default for UiSettings {
     fn default() -> Self {
         Self {
             theme: default_theme(),
+            ui_scale: default_ui_scale(),
+            label_font_size: default_label_font_size(),
+            show_compass: default_true(),
+            show_relative_velocity: default_true(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+impl UiSettings {
+    /// Theme and scale, for every system that draws egui.
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_visuals(self.theme.visuals());
+        let native = ctx.native_pixels_per_point().unwrap_or(1.0);
+        let pixels_per_point = native * self.ui_scale.max(0.25);
+        if ctx.pixels_per_point() != pixels_per_point {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct AutosaveSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Real seconds between autosaves.
+    #[serde(default = "default_autosave_interval")]
+    pub interval_seconds: f32,
+    /// How many older autosaves to keep besides the latest.
+    #[serde(default = "default_autosave_backups")]
+    pub backups: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_autosave_interval() -> f32 {
+    120.0
+}
+
+fn default_autosave_backups() -> usize {
+    3
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            interval_seconds: default_autosave_interval(),
+            backups: default_autosave_backups(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
+pub struct ScreenshotSettings {
+    /// Leave the windows and labels out of screenshots.
+    #[serde(default)]
+    pub hide_ui: bool,
+    /// Clear to transparent instead of black behind the bodies.
+    #[serde(default)]
+    pub transparent_background: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StartupSettings {
+    /// Save or template to open at launch.
+    #[serde(default)]
+    pub default_scenario: Option<PathBuf>,
+    /// Go straight from the splash screen into `default_scenario`, without the menu.
+    #[serde(default)]
+    pub skip_menu: bool,
+}
+
+impl StartupSettings {
+    /// What to open instead of showing the menu. None to show the menu, including when the
+    /// default is missing. A default that's there but won't load goes back to the menu from loading.
+    pub fn save_to_open(&self) -> Option<SaveFileMeta> {
+        if !self.skip_menu { return None; }
+        let path = self.default_scenario.as_ref().filter(|path| path.is_file())?;
+        let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        Some(SaveFileMeta { path: path.clone(), file_name })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct WindowSelections {
+    #[serde(default = "default_true")]
+    pub controls: bool,
+    #[serde(default = "default_true")]
+    pub settings: bool,
     #[serde(default = "default_false")]
     pub spin: bool,
     #[serde(skip)]
@@ -151,22 +306,62 @@ pub struct WindowSelections {
     pub grid: bool,
     #[serde(default = "default_false")]
     pub camera: bool,
+    #[serde(default = "default_false")]
+    pub diagnostics: bool,
+    #[serde(default = "default_false")]
+    pub timelapse: bool,
+    #[serde(default = "default_false")]
+    pub hohmann: bool,
+    #[serde(default = "default_false")]
+    pub csv_import: bool,
+    #[serde(default = "default_false")]
+    pub save_diff: bool,
 }
 
 impl Default for WindowSelections {
     fn default() -> Self {
         Self {
+            controls: default_true(),
+            settings: default_true(),
             spin: default_false(),
             spin_data: SpinData::default(),
             body_edit: default_false(),
             body_info: default_false(),
             grid: default_false(),
             camera: default_false(),
+            diagnostics: default_false(),
+            timelapse: default_false(),
+            hohmann: default_false(),
+            csv_import: default_false(),
+            save_diff: default_false(),
+        }
+    }
+}
+
+/// Where each window was last left, by title, so it opens there next time.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct WindowPositions(BTreeMap<String, [f32; 2]>);
+
+impl WindowPositions {
+    pub fn get(&self, title: &str) -> Option<[f32; 2]> {
+        self.0.get(title).copied()
+    }
+
+    pub fn set(&mut self, title: &str, position: [f32; 2]) {
+        self.0.insert(title.to_string(), position);
+    }
+
+    /// A window titled `title`, starting where it was left if it's been moved before.
+    pub fn window(&self, title: &'static str) -> egui::Window<'static> {
+        let window = egui::Window::new(title);
+        match self.get(title) {
+            Some([x, y]) => window.default_pos(egui::pos2(x, y)),
+            None => window,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct SpinData {
     pub radius: f64,
     pub rpm: f64,
@@ -176,3 +371,63 @@ pub struct SpinData {
 fn default_false() -> bool {
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ui_settings_round_trip() {
+        let mut settings = Settings::default();
+        settings.ui = UiSettings { theme: UiTheme::HighContrast, ui_scale: 1.5, label_font_size: 20.0, show_compass: false, show_relative_velocity: false };
+        let saved = toml::to_string_pretty(&settings).unwrap();
+        let loaded: Settings = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded.ui, settings.ui);
+
+        // Older files without the new fields get the defaults
+        let old: Settings = toml::from_str("[ui]\ntheme = \"Light\"\n").unwrap();
+        assert_eq!(old.ui.ui_scale, 1.0);
+        assert_eq!(old.ui.label_font_size, 14.0);
+    }
+
+    #[test]
+    fn test_windows_round_trip() {
+        let mut settings = Settings::default();
+        settings.windows.controls = false;
+        settings.windows.body_info = true;
+        settings.windows.spin = true;
+        settings.window_positions.set("Body Info", [120.0, 48.5]);
+        settings.window_positions.set("Controls", [0.0, 0.0]);
+        let loaded: Settings = toml::from_str(&toml::to_string_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(loaded.windows, settings.windows);
+        assert_eq!(loaded.window_positions, settings.window_positions);
+        assert_eq!(loaded.window_positions.get("Body Info"), Some([120.0, 48.5]));
+
+        // Older files without the new flags keep Controls and Settings open
+        let old: Settings = toml::from_str("[windows]\nspin = true\n").unwrap();
+        assert!(old.windows.controls && old.windows.settings && old.windows.spin);
+        assert!(old.window_positions.get("Controls").is_none());
+    }
+
+    #[test]
+    fn test_startup_skips_menu_only_for_present_default() {
+        let present = std::env::temp_dir().join(format!("default_scenario_{}.toml", std::process::id()));
+        std::fs::write(&present, "").unwrap();
+        let missing = present.with_extension("missing");
+
+        let startup = |default_scenario: Option<&PathBuf>, skip_menu: bool| StartupSettings { default_scenario: default_scenario.cloned(), skip_menu };
+        let save = startup(Some(&present), true).save_to_open().unwrap();
+        assert_eq!(save.path, present);
+        assert_eq!(save.file_name, present.file_name().unwrap().to_string_lossy());
+        assert!(startup(Some(&missing), true).save_to_open().is_none());
+        assert!(startup(None, true).save_to_open().is_none());
+        assert!(startup(Some(&present), false).save_to_open().is_none());
+
+        // Kept in settings.toml
+        let mut settings = Settings::default();
+        settings.startup = startup(Some(&present), true);
+        let loaded: Settings = toml::from_str(&toml::to_string_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(loaded.startup, settings.startup);
+        std::fs::remove_file(&present).unwrap();
+    }
+}
@@ -4,6 +4,8 @@ use bevy::time::TimerMode;
 use bevy::ui::{AlignContent, AlignSelf, JustifyContent, JustifySelf};
 use crate::gui::app::AppState;
 use crate::gui::common::despawn_entities_with;
+use crate::gui::menu::UiState;
+use crate::gui::settings::Settings;
 
 #[derive(Component)]
 struct SplashScreen;
@@ -53,8 +55,16 @@ fn countdown(
     mut game_state: ResMut<NextState<AppState>>,
     time: Res<Time>,
     mut timer: ResMut<SplashTimer>,
+    settings: Res<Settings>,
+    mut ui_state: ResMut<UiState>,
 ) {
     if timer.tick(time.delta()).finished() {
-        game_state.set(AppState::MainMenu);
+        match settings.startup.save_to_open() {
+            Some(save) => {
+                ui_state.current_save = Some(save);
+                game_state.set(AppState::PlanetariumLoading);
+            }
+            None => game_state.set(AppState::MainMenu),
+        }
     }
 }
@@ -3,8 +3,10 @@ use bevy::math::DVec3;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, CursorOptions, PrimaryWindow};
 use crate::gui::app::AppState;
-use crate::gui::planetarium::camera::CameraAction;
+use crate::gui::keybindings::Action;
+use crate::gui::planetarium::camera::{CameraAction, SecondaryCamera};
 use crate::gui::planetarium::PlanetariumCamera;
+use crate::gui::settings::Settings;
 
 /// Mouse sensitivity and movement speed
 #[derive(Resource)]
@@ -22,32 +24,6 @@ impl Default for MovementSettings {
     }
 }
 
-/// Key configuration
-#[derive(Resource)]
-pub struct KeyBindings {
-    pub move_forward: KeyCode,
-    pub move_backward: KeyCode,
-    pub move_left: KeyCode,
-    pub move_right: KeyCode,
-    pub move_ascend: KeyCode,
-    pub move_descend: KeyCode,
-    pub toggle_grab_cursor: KeyCode,
-}
-
-impl Default for KeyBindings {
-    fn default() -> Self {
-        Self {
-            move_forward: KeyCode::KeyW,
-            move_backward: KeyCode::KeyS,
-            move_left: KeyCode::KeyA,
-            move_right: KeyCode::KeyD,
-            move_ascend: KeyCode::Space,
-            move_descend: KeyCode::ShiftLeft,
-            toggle_grab_cursor: KeyCode::Backquote,
-        }
-    }
-}
-
 /// Used in queries when you want flycams and not other cameras
 /// A marker component used in queries when you want flycams and not other cameras
 #[derive(Component)]
@@ -81,8 +57,8 @@ fn player_move(
     time: Res<Time>,
     cursor_options: Query<&CursorOptions, With<PrimaryWindow>>,
     settings: Res<MovementSettings>,
-    key_bindings: Res<KeyBindings>,
-    mut query: Query<(&mut Freecam, &Transform, &PlanetariumCamera)>, //    mut query: Query<&mut Transform, With<FlyCam>>,
+    app_settings: Res<Settings>,
+    mut query: Query<(&mut Freecam, &Transform, &PlanetariumCamera), Without<SecondaryCamera>>, //    mut query: Query<&mut Transform, With<FlyCam>>,
 ) {
     if let Ok(cursor_options) = cursor_options.single() {
         for (mut freecam, transform, pcam) in query.iter_mut() {
@@ -91,23 +67,24 @@ fn player_move(
                 let local_z = transform.local_z().as_vec3().as_dvec3();
                 let forward = -DVec3::new(local_z.x, 0., local_z.z);
                 let right = DVec3::new(local_z.z, 0., -local_z.x);
+                let key_bindings = &app_settings.keybindings;
 
                 for key in keys.get_pressed() {
                     match cursor_options.grab_mode {
                         CursorGrabMode::None => (),
                         _ => {
                             let key = *key;
-                            if key == key_bindings.move_forward {
+                            if key == key_bindings.key(Action::MoveForward) {
                                 velocity += forward;
-                            } else if key == key_bindings.move_backward {
+                            } else if key == key_bindings.key(Action::MoveBackward) {
                                 velocity -= forward;
-                            } else if key == key_bindings.move_left {
+                            } else if key == key_bindings.key(Action::MoveLeft) {
                                 velocity -= right;
-                            } else if key == key_bindings.move_right {
+                            } else if key == key_bindings.key(Action::MoveRight) {
                                 velocity += right;
-                            } else if key == key_bindings.move_ascend {
+                            } else if key == key_bindings.key(Action::MoveAscend) {
                                 velocity += DVec3::Y;
-                            } else if key == key_bindings.move_descend {
+                            } else if key == key_bindings.key(Action::MoveDescend) {
                                 velocity -= DVec3::Y;
                             }
                         }
@@ -129,7 +106,7 @@ fn player_look(
     settings: Res<MovementSettings>,
     primary_window: Query<(&Window, &CursorOptions), With<PrimaryWindow>>,
     mut state: MessageReader<MouseMotion>,
-    mut query: Query<(&mut Transform, &PlanetariumCamera, &Projection), With<Freecam>>,
+    mut query: Query<(&mut Transform, &PlanetariumCamera, &Projection), (With<Freecam>, Without<SecondaryCamera>)>,
 ) {
     if let Ok((window, cursor_options)) = primary_window.single() {
         for (mut transform, pcam, projection) in query.iter_mut() {
@@ -166,12 +143,12 @@ fn player_look(
 
 fn cursor_grab(
     keys: Res<ButtonInput<KeyCode>>,
-    key_bindings: Res<KeyBindings>,
+    settings: Res<Settings>,
     mut primary_window_cursor: Query<&mut CursorOptions, With<PrimaryWindow>>,
     state: Res<State<AppState>>,
 ) {
     if let Ok(mut cursor_options) = primary_window_cursor.single_mut() {
-        if keys.just_pressed(key_bindings.toggle_grab_cursor) {
+        if settings.keybindings.just_pressed(&keys, Action::ToggleGrabCursor) {
             toggle_grab_cursor(&mut cursor_options, state);
         }
     } else {
@@ -182,7 +159,7 @@ fn cursor_grab(
 // Grab cursor when an entity with FlyCam is added
 fn initial_grab_on_flycam_spawn(
     mut cursor_options: Query<&mut CursorOptions, With<PrimaryWindow>>,
-    query_added: Query<Entity, Added<Freecam>>,
+    query_added: Query<Entity, (Added<Freecam>, Without<SecondaryCamera>)>,
     state: Res<State<AppState>>,
 ) {
     if query_added.is_empty() {
@@ -201,7 +178,6 @@ pub struct FreeCamPlugin;
 impl Plugin for FreeCamPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MovementSettings>()
-            .init_resource::<KeyBindings>()
             .add_systems(Startup, initial_grab_on_flycam_spawn)
             .add_systems(Update, player_move)
             .add_systems(Update, player_look)
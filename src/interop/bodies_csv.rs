@@ -0,0 +1,160 @@
+//! Tables of orbital elements pasted in as new bodies, for building scenarios in bulk.
+
+use std::collections::HashSet;
+use crate::body::appearance::{Appearance, AppearanceColor, DebugBall};
+use crate::body::motive::info::BodyInfo;
+use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerMotive, KeplerRotation, KeplerShape, MeanAnomalyAtJ2000};
+use crate::body::universe::save::{KeplerEntry, SomeBody};
+use crate::body::universe;
+use crate::foundations::time::Instant;
+
+pub const COLUMNS: [&str; 9] = ["name", "a_m", "e", "i_deg", "raan_deg", "argp_deg", "M_deg", "mass_kg", "radius_m"];
+
+#[derive(Debug, PartialEq)]
+pub enum CsvImportError {
+    /// Nothing but blank lines.
+    Empty,
+    /// The first line isn't `COLUMNS`.
+    Header(String),
+    /// `line` counts from 1, blank lines included.
+    Row { line: usize, message: String },
+}
+
+/// Splits a line on commas, except inside double quotes. `""` in quotes is a literal quote.
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+fn parse_row(fields: &[String], primary_id: &str, mu: f64) -> Result<(String, KeplerMotive, f64, f64), String> {
+    if fields.len() != COLUMNS.len() {
+        return Err(format!("expected {} columns, found {}", COLUMNS.len(), fields.len()));
+    }
+    let name = fields[0].clone();
+    if name.is_empty() {
+        return Err("no name".into());
+    }
+    let mut numbers = [0.0; 8];
+    for (number, (field, column)) in numbers.iter_mut().zip(fields[1..].iter().zip(&COLUMNS[1..])) {
+        *number = field.parse().map_err(|_| format!("{} isn't a number: \"{}\"", column, field))?;
+    }
+    let [semi_major_axis, eccentricity, inclination, longitude_of_ascending_node, argument_of_periapsis, mean_anomaly, mass, radius] = numbers;
+    if semi_major_axis <= 0.0 || !(0.0..1.0).contains(&eccentricity) {
+        return Err(format!("not an ellipse: a_m {}, e {}", semi_major_axis, eccentricity));
+    }
+
+    let kepler = KeplerMotive {
+        primary_id: primary_id.to_string(),
+        shape: KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity, semi_major_axis }),
+        rotation: KeplerRotation::EulerAngles(KeplerEulerAngles { inclination, longitude_of_ascending_node, argument_of_periapsis }),
+        epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 { mean_anomaly: mean_anomaly.to_radians() }),
+    };
+    if kepler.displacement(Instant::from_seconds_since_j2000(0.0), mu).is_none_or(|position| !position.is_finite()) {
+        return Err("elements don't give a position".into());
+    }
+    Ok((name, kepler, mass, radius))
+}
+
+/// Bodies orbiting `primary_id`, one per row of `text` after the header. Mean anomalies are at J2000.
+/// Ids come from the names, and are only unique among the imported bodies.
+pub fn import_bodies_csv(text: &str, primary_id: &str, mu: f64) -> Result<Vec<SomeBody>, CsvImportError> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (_, header) = lines.next().ok_or(CsvImportError::Empty)?;
+    if split_fields(header) != COLUMNS {
+        return Err(CsvImportError::Header(header.to_string()));
+    }
+
+    let mut ids = HashSet::new();
+    let mut bodies = Vec::new();
+    for (index, line) in lines {
+        let (name, params, mass, radius) = parse_row(&split_fields(line), primary_id, mu)
+            .map_err(|message| CsvImportError::Row { line: index + 1, message })?;
+        let slug = universe::slugify(&name);
+        let stem = if slug.is_empty() { "body" } else { slug.as_str() };
+        let mut id = stem.to_string();
+        let mut n = 2;
+        while !ids.insert(id.clone()) {
+            id = format!("{}-{}", stem, n);
+            n += 1;
+        }
+        bodies.push(SomeBody::KeplerEntry(KeplerEntry {
+            info: BodyInfo {
+                id,
+                name: Some(name),
+                mass,
+                major: false,
+                designation: None,
+                tags: Vec::new(),
+            },
+            params,
+            appearance: Appearance::DebugBall(DebugBall {
+                radius,
+                color: AppearanceColor { r: 200, g: 200, b: 200 },
+            }),
+        }));
+    }
+    Ok(bodies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN_MU: f64 = 1.32712440018e20;
+
+    #[test]
+    fn test_import_two_rows() {
+        let text = "name, a_m, e, i_deg, raan_deg, argp_deg, M_deg, mass_kg, radius_m\n\
+            Ceres,4.14e11,0.0785,10.59,80.3,73.6,95.99,9.38e20,4.7e5\n\
+            \n\
+            \"Vesta, 4\",3.53e11,0.0887,7.14,103.8,151.2,205.5,2.59e20,2.6e5\n";
+        let bodies = import_bodies_csv(text, "sol", SUN_MU).unwrap();
+        assert_eq!(bodies.len(), 2);
+
+        let SomeBody::KeplerEntry(ceres) = &bodies[0] else { panic!("Ceres isn't Keplerian") };
+        assert_eq!(ceres.info.id, "ceres");
+        assert_eq!(ceres.info.mass, 9.38e20);
+        assert_eq!(ceres.params.primary_id, "sol");
+        assert_eq!(ceres.params.semi_major_axis(), 4.14e11);
+        assert_eq!(ceres.params.eccentricity(), 0.0785);
+        assert_eq!(ceres.params.inclination(), 10.59);
+        assert_eq!(ceres.params.epoch.mean_anomaly_at_epoch(ceres.params.eccentricity()), 95.99f64.to_radians());
+        assert_eq!(ceres.appearance.radius(), 4.7e5);
+
+        let SomeBody::KeplerEntry(vesta) = &bodies[1] else { panic!("Vesta isn't Keplerian") };
+        assert_eq!(vesta.info.name.as_deref(), Some("Vesta, 4"));
+        assert_eq!(vesta.info.id, "vesta-4");
+    }
+
+    #[test]
+    fn test_bad_row_reports_line() {
+        let text = "name,a_m,e,i_deg,raan_deg,argp_deg,M_deg,mass_kg,radius_m\n\
+            Ceres,4.14e11,0.0785,10.59,80.3,73.6,95.99,9.38e20,4.7e5\n\
+            \n\
+            Pallas,4.14e11,lots,34.8,173.1,310.0,78.2,2.04e20,2.56e5\n";
+        match import_bodies_csv(text, "sol", SUN_MU) {
+            Err(CsvImportError::Row { line, message }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("e isn't a number"), "{}", message);
+            }
+            other => panic!("{:?}", other.map(|bodies| bodies.len())),
+        }
+        assert_eq!(import_bodies_csv("\n\n", "sol", SUN_MU).map(|bodies| bodies.len()), Err(CsvImportError::Empty));
+        assert!(matches!(import_bodies_csv("name,a,e\n", "sol", SUN_MU), Err(CsvImportError::Header(_))));
+    }
+}
@@ -0,0 +1,166 @@
+//! CSV tables of every body's current osculating elements, for writing up.
+
+use std::io::{self, Write};
+use bevy::prelude::*;
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::body::motive::kepler_motive::KeplerMotive;
+use crate::body::motive::{Motive, MotiveSelection};
+use crate::body::universe::deletion;
+use crate::foundations::reference_frame::plane::ReferencePlane;
+use crate::foundations::time::Instant;
+
+pub const ELEMENTS_PATH: &str = "data/elements.csv";
+
+const COLUMNS: [&str; 10] = [
+    "id",
+    "name",
+    "primary",
+    "semi_major_axis_m",
+    "eccentricity",
+    "inclination_deg",
+    "longitude_of_ascending_node_deg",
+    "argument_of_periapsis_deg",
+    "true_anomaly_deg",
+    "period_s",
+];
+
+/// The primary pulling hardest on `info`, among the major bodies.
+fn dominant_primary<'a>(
+    info: &BodyInfo,
+    state: &BodyState,
+    bodies: &'a Query<(&BodyInfo, &Motive, &BodyState)>,
+) -> Option<(&'a BodyInfo, &'a BodyState)> {
+    bodies.iter()
+        .filter(|(primary, _, _)| primary.major && primary.id != info.id)
+        .map(|(primary, _, primary_state)| {
+            let pull = primary.mass / primary_state.current_position.distance_squared(state.current_position).max(f64::MIN_POSITIVE);
+            (primary, primary_state, pull)
+        })
+        .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(primary, primary_state, _)| (primary, primary_state))
+}
+
+/// Each body with its elements and the primary's gravitational parameter, in no particular order.
+/// Keplerian bodies give their own elements. Newtonian ones are fitted to the major body
+/// pulling hardest on them, and have none when they aren't bound to it. Fixed bodies have none.
+pub fn osculating_elements(
+    bodies: &Query<(&BodyInfo, &Motive, &BodyState)>,
+    time: Instant,
+    gravitational_constant: f64,
+) -> Vec<(BodyInfo, Option<(KeplerMotive, f64)>)> {
+    let mass_of = |id: &str| bodies.iter().find(|(info, _, _)| info.id == id).map(|(info, _, _)| info.mass);
+    let mut velocities = None;
+
+    bodies.iter().map(|(info, motive, state)| {
+        let elements = match &motive.motive_at(time).1 {
+            MotiveSelection::Keplerian(kepler) => mass_of(&kepler.primary_id)
+                .map(|mass| (kepler.clone(), gravitational_constant * mass)),
+            MotiveSelection::Newtonian { .. } => dominant_primary(info, state, bodies).and_then(|(primary, primary_state)| {
+                let velocities = velocities.get_or_insert_with(|| deletion::world_velocities(bodies.iter(), time, gravitational_constant));
+                let velocity = velocities.get(&info.id).copied().unwrap_or_default();
+                let primary_velocity = velocities.get(&primary.id).copied().unwrap_or_default();
+                let mu = gravitational_constant * primary.mass;
+                KeplerMotive::from_state_vectors(
+                    primary.id.clone(),
+                    state.current_position - primary_state.current_position,
+                    velocity - primary_velocity,
+                    mu,
+                    time,
+                ).map(|kepler| (kepler, mu))
+            }),
+            MotiveSelection::Fixed { .. } => None,
+        };
+        (info.clone(), elements)
+    }).collect()
+}
+
+/// Quoted when it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One row per body, sorted by id. Angles are against the ecliptic, and bodies
+/// without elements leave those columns empty.
+pub fn write_elements_csv(
+    rows: &mut [(BodyInfo, Option<(KeplerMotive, f64)>)],
+    time: Instant,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    rows.sort_by(|(a, _), (b, _)| a.id.cmp(&b.id));
+    writeln!(writer, "{}", COLUMNS.join(","))?;
+    for (info, elements) in rows.iter() {
+        let mut fields = vec![csv_field(&info.id), csv_field(&info.display_name())];
+        match elements {
+            Some((kepler, mu)) => {
+                let (inclination, longitude_of_ascending_node, argument_of_periapsis) = kepler.angles_in(ReferencePlane::Ecliptic, time);
+                fields.extend([
+                    csv_field(&kepler.primary_id),
+                    kepler.semi_major_axis().to_string(),
+                    kepler.eccentricity().to_string(),
+                    inclination.to_string(),
+                    longitude_of_ascending_node.to_string(),
+                    argument_of_periapsis.to_string(),
+                    kepler.true_anomaly(time, *mu).to_degrees().to_string(),
+                    kepler.period(*mu).to_seconds().to_string(),
+                ]);
+            }
+            None => fields.resize(COLUMNS.len(), String::new()),
+        }
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use bevy::math::DVec3;
+    use crate::util::circular;
+
+    #[test]
+    fn test_one_row_per_body() {
+        let g = 6.6743e-11;
+        let mut world = World::new();
+        let sun = BodyInfo { id: "sol".into(), mass: 2e30, major: true, ..Default::default() };
+        world.spawn((sun, Motive::fixed(DVec3::ZERO), BodyState::default()));
+
+        let orbit = circular::circular_orbit("sol".into(), 1.5e11, 0.0);
+        let earth = BodyInfo { id: "earth".into(), name: Some("Earth, Mostly Harmless".into()), mass: 6e24, major: true, ..Default::default() };
+        let position = orbit.displacement(Instant::from_seconds_since_j2000(0.0), g * 2e30).unwrap();
+        world.spawn((earth, Motive::keplerian(orbit.primary_id, orbit.shape, orbit.rotation, orbit.epoch), BodyState { current_position: position, ..Default::default() }));
+
+        // Out past the earth and slower than a circular orbit there, so at aphelion
+        let probe_position = DVec3::new(3.0e11, 0.0, 0.0);
+        let probe_velocity = DVec3::new(0.0, 0.9 * (g * 2e30 / 3.0e11).sqrt(), 0.0);
+        let probe = BodyInfo { id: "probe".into(), mass: 1000.0, ..Default::default() };
+        world.spawn((probe, Motive::newtonian(probe_position, probe_velocity), BodyState {
+            current_position: probe_position,
+            current_velocity: Some(probe_velocity),
+            ..Default::default()
+        }));
+
+        let mut state: SystemState<Query<(&BodyInfo, &Motive, &BodyState)>> = SystemState::new(&mut world);
+        let time = Instant::from_seconds_since_j2000(0.0);
+        let mut rows = osculating_elements(&state.get(&world), time, g);
+        let mut csv = Vec::new();
+        write_elements_csv(&mut rows, time, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("earth,\"Earth, Mostly Harmless\",sol,150000000000,0,"), "{}", lines[1]);
+        assert!(lines[2].starts_with("probe,probe,sol,"), "{}", lines[2]);
+        assert_eq!(lines[3], "sol,sol,,,,,,,,");
+
+        let (_, probe_elements) = rows.iter().find(|(info, _)| info.id == "probe").unwrap();
+        let (kepler, _) = probe_elements.as_ref().unwrap();
+        assert!(kepler.semi_major_axis() < 3.0e11);
+        assert!((kepler.apoapsis().unwrap() - 3.0e11).abs() < 1.0);
+    }
+}
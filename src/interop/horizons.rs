@@ -1,4 +1,10 @@
+use bevy::math::{DQuat, DVec3};
 use url::form_urlencoded;
+use crate::body::appearance::Appearance;
+use crate::body::motive::info::BodyInfo;
+use crate::body::universe::save::NewtonEntry;
+use crate::foundations::time::{Instant, JD_SECONDS_PER_JULIAN_DAY};
+use crate::util::units::METERS_PER_AU;
 
 const BASE_URL: &str = "https://ssd.jpl.nasa.gov/api/horizons.api";
 
@@ -1144,6 +1150,140 @@ fn safe_slice(s: &str, start: usize, end: usize) -> &str {
     &s[start..end]
 }
 
+// ---------------------------------------------------------------------------
+// Vector table parsing
+// ---------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum HorizonsError {
+    /// No `$$SOE`...`$$EOE` block, or nothing in it.
+    MissingTable,
+    MissingValue(&'static str),
+    InvalidNumber { label: &'static str, text: String },
+    UnsupportedUnits(String),
+    /// Only ecliptic and ICRF frames can be brought into the simulation's frame.
+    UnsupportedFrame(String),
+}
+
+/// IAU 1976 obliquity of the ecliptic, which Horizons uses between the ICRF and ecliptic planes.
+const J2000_OBLIQUITY_ARCSECONDS: f64 = 84381.448;
+
+/// A body at the first epoch of a Horizons vector table, with position and velocity in meters
+/// and meters per second in the simulation's frame, where Z is the ecliptic north pole.
+/// Start the universe at the entry's epoch, since its motive begins there.
+pub fn newton_entry_from_horizons(text: &str, info: BodyInfo) -> Result<NewtonEntry, HorizonsError> {
+    let (meters, seconds) = output_units(text)?;
+    let to_ecliptic = match reference_plane(text)? {
+        RefPlane::Ecliptic => DQuat::IDENTITY,
+        RefPlane::Frame => DQuat::from_rotation_x(-(J2000_OBLIQUITY_ARCSECONDS / 3600.0).to_radians()),
+        RefPlane::BodyEquator => return Err(HorizonsError::UnsupportedFrame("body equator".into())),
+    };
+
+    let record = first_record(text)?;
+    let number = |label: &'static str, value: Option<&str>| -> Result<f64, HorizonsError> {
+        let value = value.ok_or(HorizonsError::MissingValue(label))?;
+        value.parse().map_err(|_| HorizonsError::InvalidNumber { label, text: value.to_string() })
+    };
+
+    let (julian_day, position, velocity) = if record.contains(',') {
+        // CSV_FORMAT=YES: JDTDB, Calendar Date, X, Y, Z, VX, VY, VZ, ...
+        let fields: Vec<&str> = record.split(',').map(str::trim).collect();
+        let field = |i: usize| fields.get(i).copied();
+        (
+            number("JDTDB", field(0))?,
+            DVec3::new(number("X", field(2))?, number("Y", field(3))?, number("Z", field(4))?),
+            DVec3::new(number("VX", field(5))?, number("VY", field(6))?, number("VZ", field(7))?),
+        )
+    } else {
+        // "2451545.000000000 = A.D. 2000-Jan-01 12:00:00.0000 TDB" then "X =-2.6E+07 Y = ..." lines
+        let spaced = record.replace('=', " = ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let labelled = |label: &'static str| tokens.windows(3)
+            .find(|window| window[0] == label && window[1] == "=")
+            .map(|window| window[2]);
+        (
+            number("JDTDB", tokens.first().copied())?,
+            DVec3::new(number("X", labelled("X"))?, number("Y", labelled("Y"))?, number("Z", labelled("Z"))?),
+            DVec3::new(number("VX", labelled("VX"))?, number("VY", labelled("VY"))?, number("VZ", labelled("VZ"))?),
+        )
+    };
+
+    Ok(NewtonEntry {
+        info,
+        position: to_ecliptic * (position * meters),
+        velocity: to_ecliptic * (velocity * meters / seconds),
+        appearance: Appearance::default(),
+        epoch: Some(Instant::from_julian_day(julian_day)),
+    })
+}
+
+/// Meters per distance unit and seconds per time unit, from the `Output units` header line.
+/// Tables without a header are taken to be in Horizons' default of kilometers and seconds.
+fn output_units(text: &str) -> Result<(f64, f64), HorizonsError> {
+    let Some(units) = header_value(text, "Output units") else { return Ok((1000.0, 1.0)) };
+    let units = units.split_whitespace().next().unwrap_or_default().to_ascii_uppercase();
+    match units.as_str() {
+        "KM-S" => Ok((1000.0, 1.0)),
+        "KM-D" => Ok((1000.0, JD_SECONDS_PER_JULIAN_DAY)),
+        "AU-D" => Ok((METERS_PER_AU, JD_SECONDS_PER_JULIAN_DAY)),
+        _ => Err(HorizonsError::UnsupportedUnits(units)),
+    }
+}
+
+/// The X-Y plane, from the first line of the coordinate system description. Ecliptic if there isn't one.
+fn reference_plane(text: &str) -> Result<RefPlane, HorizonsError> {
+    let Some(description) = text.lines()
+        .skip_while(|line| !line.trim_start().starts_with("Coordinate system description"))
+        .skip(1)
+        .find(|line| !line.trim().is_empty())
+    else {
+        return Ok(RefPlane::Ecliptic);
+    };
+
+    let description = description.trim();
+    if description.starts_with("Ecliptic") {
+        Ok(RefPlane::Ecliptic)
+    } else if description.contains("ICRF") || description.contains("International Celestial Reference Frame") {
+        Ok(RefPlane::Frame)
+    } else if description.starts_with("Body") {
+        Ok(RefPlane::BodyEquator)
+    } else {
+        Err(HorizonsError::UnsupportedFrame(description.to_string()))
+    }
+}
+
+fn header_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines()
+        .take_while(|line| line.trim() != "$$SOE")
+        .find(|line| line.trim_start().starts_with(key))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, value)| value.trim())
+}
+
+/// Lines of the first record in the `$$SOE`...`$$EOE` block, joined with spaces.
+/// Each record starts with a line beginning with its Julian day.
+fn first_record(text: &str) -> Result<String, HorizonsError> {
+    let mut lines = text.lines()
+        .skip_while(|line| line.trim() != "$$SOE")
+        .skip(1)
+        .take_while(|line| line.trim() != "$$EOE")
+        .filter(|line| !line.trim().is_empty());
+
+    let first = lines.next().ok_or(HorizonsError::MissingTable)?;
+    let starts_record = |line: &str| line.trim_start().split(|c: char| c.is_whitespace() || c == ',' || c == '=')
+        .next()
+        .is_some_and(|token| token.parse::<f64>().is_ok());
+
+    let mut record = first.trim().to_string();
+    if !record.contains(',') {
+        for line in lines.take_while(|line| !starts_record(line)) {
+            record.push(' ');
+            record.push_str(line.trim());
+        }
+    }
+    Ok(record)
+}
+
 // ---------------------------------------------------------------------------
 // URL encoding helpers
 // ---------------------------------------------------------------------------
@@ -1165,3 +1305,63 @@ fn encode_url(params: &[(&str, String)]) -> String {
         .collect();
     format!("{}?{}", BASE_URL, pairs.join("&"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+Center body name: Sun (10)                        {source: DE441}
+Output units    : KM-S
+Output type     : GEOMETRIC cartesian states
+*******************************************************************************
+Coordinate system description:
+
+  Ecliptic at the standard reference epoch
+
+    Reference epoch: J2000.0
+*******************************************************************************
+$$SOE
+2451545.000000000 = A.D. 2000-Jan-01 12:00:00.0000 TDB
+ X =-2.649903367743050E+07 Y = 1.327574173383451E+08 Z =-1.000000000000000E+00
+ VX=-2.979426007043741E+01 VY=-5.018052308799903E+00 VZ= 2.000000000000000E-03
+ LT= 4.461178909714163E+02 RG= 1.337763487493377E+08 RR=-0.000000000000000E+00
+2451546.000000000 = A.D. 2000-Jan-02 12:00:00.0000 TDB
+ X =-2.907240975076342E+07 Y = 1.322899097178726E+08 Z = 0.000000000000000E+00
+ VX=-2.976178596514738E+01 VY=-5.794528554405538E+00 VZ= 0.000000000000000E+00
+$$EOE
+"#;
+
+    fn earth() -> BodyInfo {
+        BodyInfo { id: "earth".into(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_vector_table_units_and_epoch() {
+        let entry = newton_entry_from_horizons(SAMPLE, earth()).unwrap();
+        assert_eq!(entry.info.id, "earth");
+        assert!((entry.position - DVec3::new(-2.649903367743050e10, 1.327574173383451e11, -1.0e3)).length() < 1e-3);
+        assert!((entry.velocity - DVec3::new(-2.979426007043741e4, -5.018052308799903e3, 2.0)).length() < 1e-9);
+        assert!(entry.epoch.unwrap().to_j2000_seconds().abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_equatorial_table_rotated_to_ecliptic() {
+        let obliquity = (J2000_OBLIQUITY_ARCSECONDS / 3600.0).to_radians();
+        // The ecliptic north pole and the equinox, written in equatorial coordinates
+        let table = format!(
+            "Output units    : AU-D\nCoordinate system description:\n\n  International Celestial Reference Frame (ICRF)\n$$SOE\n\
+             2451545.5, A.D. 2000-Jan-02 00:00:00.0000, 0.0, {}, {}, 1.0, 0.0, 0.0,\n$$EOE\n",
+            -obliquity.sin(), obliquity.cos(),
+        );
+        let entry = newton_entry_from_horizons(&table, earth()).unwrap();
+        assert!((entry.position / METERS_PER_AU - DVec3::Z).length() < 1e-12, "{}", entry.position);
+        assert!((entry.velocity * JD_SECONDS_PER_JULIAN_DAY / METERS_PER_AU - DVec3::X).length() < 1e-12);
+        assert!((entry.epoch.unwrap().to_j2000_seconds() - 43_200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_missing_table() {
+        assert!(matches!(newton_entry_from_horizons("no ephemeris here", earth()), Err(HorizonsError::MissingTable)));
+    }
+}
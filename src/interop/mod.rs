@@ -1 +1,4 @@
 pub mod horizons;
+pub mod obj;
+pub mod elements;
+pub mod bodies_csv;
@@ -0,0 +1,80 @@
+//! Wavefront OBJ snapshots of the universe, for rendering in other programs.
+
+use std::io::{self, Write};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use crate::body::appearance::{self, Appearance};
+use crate::body::motive::info::{BodyInfo, BodyState};
+use crate::util::bevystuff::GlamVec;
+
+/// Writes each visible body as an icosphere at its current position, in a group named for its id.
+/// Positions and radii are multiplied by `scale`, like `ViewSettings::distance_factor`,
+/// and written Y-up, as Bevy and most modelling programs expect.
+pub fn export_scene_obj(bodies: &Query<(&BodyState, &Appearance, &BodyInfo)>, scale: f64, mut writer: impl Write) -> io::Result<()> {
+    let (vertices, faces) = unit_sphere();
+    writeln!(writer, "# Exotic Matters snapshot")?;
+
+    // OBJ indices count from 1 across the whole file
+    let mut first_index = 1;
+    for (state, appearance, info) in bodies.iter() {
+        if matches!(appearance, Appearance::Empty) { continue; }
+        let center = state.current_position.as_bevy_scaled_dvec(scale);
+        let radius = appearance.radius() * scale;
+
+        writeln!(writer, "g {}", info.id.split_whitespace().collect::<Vec<_>>().join("_"))?;
+        for vertex in &vertices {
+            let position = center + *vertex * radius;
+            writeln!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+        }
+        for face in &faces {
+            writeln!(writer, "f {} {} {}", first_index + face[0], first_index + face[1], first_index + face[2])?;
+        }
+        first_index += vertices.len();
+    }
+    writer.flush()
+}
+
+/// Vertices and triangles of the same sphere the renderer uses.
+fn unit_sphere() -> (Vec<DVec3>, Vec<[usize; 3]>) {
+    let mesh = appearance::unit_sphere_mesh();
+    let vertices = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|positions| positions.as_float3())
+        .map(|positions| positions.iter().map(|p| Vec3::from_array(*p).as_dvec3()).collect())
+        .unwrap_or_default();
+    let indices: Vec<usize> = mesh.indices().map(|indices| indices.iter().collect()).unwrap_or_default();
+    let faces = indices.chunks_exact(3).map(|face| [face[0], face[1], face[2]]).collect();
+    (vertices, faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use crate::body::appearance::{AppearanceColor, DebugBall, SPHERE_SUBDIVISIONS};
+
+    #[test]
+    fn test_one_group_per_body() {
+        let mut world = World::new();
+        for (i, id) in ["sol", "earth", "luna"].into_iter().enumerate() {
+            world.spawn((
+                BodyState { current_position: DVec3::X * i as f64, ..Default::default() },
+                Appearance::DebugBall(DebugBall { radius: 0.1, color: AppearanceColor::default() }),
+                BodyInfo { id: id.into(), ..Default::default() },
+            ));
+        }
+        world.spawn((BodyState::default(), Appearance::Empty, BodyInfo { id: "nothing".into(), ..Default::default() }));
+
+        let mut state: SystemState<Query<(&BodyState, &Appearance, &BodyInfo)>> = SystemState::new(&mut world);
+        let mut obj = Vec::new();
+        export_scene_obj(&state.get(&world), 2.0, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+
+        let per_edge = SPHERE_SUBDIVISIONS as usize + 1;
+        assert_eq!(count("g "), 3);
+        assert_eq!(count("v "), 3 * (10 * per_edge * per_edge + 2));
+        assert_eq!(count("f "), 3 * 20 * per_edge * per_edge);
+        assert!(obj.contains("g luna"));
+        assert!(!obj.contains("g nothing"));
+    }
+}
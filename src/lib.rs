@@ -2,4 +2,4 @@ pub mod util;
 pub mod gui;
 pub mod body;
 pub mod interop;
-mod foundations;
+pub mod foundations;
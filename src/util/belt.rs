@@ -0,0 +1,99 @@
+//! Procedurally generated belts of small bodies around one primary.
+
+use crate::body::appearance::{Appearance, AppearanceColor, DebugBall};
+use crate::body::motive::info::BodyInfo;
+use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerMotive, KeplerRotation, KeplerShape, MeanAnomalyAtJ2000};
+use crate::body::universe::save::{KeplerEntry, SomeBody};
+use crate::util::rng::SplitMix64;
+
+pub const ASTEROID_TAG: &str = "Asteroid";
+
+#[derive(Debug, Clone)]
+pub struct BeltParams {
+    pub count: usize,
+    /// Semi-major axes are uniform between these, in meters.
+    pub inner: f64,
+    pub outer: f64,
+    pub max_eccentricity: f64,
+    /// Degrees.
+    pub max_inclination: f64,
+    /// Of each body, in kg.
+    pub mass: f64,
+    /// Of each body, in meters.
+    pub radius: f64,
+    /// The same seed always gives the same belt.
+    pub seed: u64,
+}
+
+impl Default for BeltParams {
+    fn default() -> Self {
+        // Roughly the main belt
+        Self {
+            count: 200,
+            inner: 3.3e11,
+            outer: 4.9e11,
+            max_eccentricity: 0.2,
+            max_inclination: 10.0,
+            mass: 1e18,
+            radius: 5e4,
+            seed: 0,
+        }
+    }
+}
+
+/// `params.count` bodies tagged `ASTEROID_TAG` orbiting `primary_id`, with ids `{primary_id}-asteroid-{n}`.
+pub fn generate_belt(primary_id: &str, params: &BeltParams) -> Vec<SomeBody> {
+    let mut rng = SplitMix64::new(params.seed);
+    (1..=params.count).map(|n| {
+        // Drawn in a fixed order so a seed means the same belt from one version to the next
+        let semi_major_axis = rng.range(params.inner, params.outer);
+        let eccentricity = rng.range(0.0, params.max_eccentricity);
+        let inclination = rng.range(0.0, params.max_inclination);
+        let longitude_of_ascending_node = rng.range(0.0, 360.0);
+        let argument_of_periapsis = rng.range(0.0, 360.0);
+        let mean_anomaly = rng.range(0.0, std::f64::consts::TAU);
+
+        SomeBody::KeplerEntry(KeplerEntry {
+            info: BodyInfo {
+                id: format!("{}-asteroid-{}", primary_id, n),
+                name: Some(format!("Asteroid {}", n)),
+                mass: params.mass,
+                major: false,
+                designation: None,
+                tags: vec![ASTEROID_TAG.into()],
+            },
+            params: KeplerMotive {
+                primary_id: primary_id.to_string(),
+                shape: KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity, semi_major_axis }),
+                rotation: KeplerRotation::EulerAngles(KeplerEulerAngles { inclination, longitude_of_ascending_node, argument_of_periapsis }),
+                epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 { mean_anomaly }),
+            },
+            appearance: Appearance::DebugBall(DebugBall {
+                radius: params.radius,
+                color: AppearanceColor { r: 140, g: 130, b: 120 },
+            }),
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_belt() {
+        let params = BeltParams { count: 50, seed: 0xC0FFEE, ..Default::default() };
+        let belt = serde_json::to_vec(&generate_belt("sol", &params)).unwrap();
+        assert_eq!(belt, serde_json::to_vec(&generate_belt("sol", &params)).unwrap());
+
+        let other = serde_json::to_vec(&generate_belt("sol", &BeltParams { seed: 0xC0FFEF, ..params.clone() })).unwrap();
+        assert_ne!(belt, other);
+
+        for body in generate_belt("sol", &params) {
+            let SomeBody::KeplerEntry(entry) = body else { panic!("Asteroids should be Keplerian") };
+            assert!((params.inner..params.outer).contains(&entry.params.semi_major_axis()));
+            assert!(entry.params.eccentricity() < params.max_eccentricity);
+            assert_eq!(entry.info.tags, [ASTEROID_TAG]);
+        }
+    }
+}
@@ -25,8 +25,9 @@ impl GlamVec for DVec3 {
         Vec3::new(v.x, v.z, -v.y)
     }
 
+    /// Undoes `as_bevy`: from y-axis-up back to z-axis-up.
     fn as_regular(&self) -> DVec3 {
-        DVec3::new(self.y, -self.z, self.x)
+        DVec3::new(self.x, -self.z, self.y)
     }
 
     fn as_bevy_scaled(&self, scale: f64) -> Vec3 {
@@ -37,10 +38,42 @@ impl GlamVec for DVec3 {
         DVec3::new(self.x, self.z, -self.y) * scale
     }
 
+    /// Relative to the camera at `cheat`, in scaled Bevy space. Everything stays f64 until the
+    /// offset from the camera is small, so far from the origin only the offset loses precision.
     fn as_bevy_scaled_cheated(&self, scale: f64, cheat: DVec3) -> Vec3 {
-        let bevyed = DVec3::new(self.x, self.z, -self.y);
-        let scaled = bevyed * scale;
-        let cheated = scaled - cheat;
-        cheated.as_vec3()
+        let origin = cheat.as_regular() / scale;
+        let relative = (*self - origin) * scale;
+        DVec3::new(relative.x, relative.z, -relative.y).as_vec3()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_relative_precision() {
+        let scale = 1e-9;
+        // A kilometer from the camera, near the origin and then twenty AU out
+        for distance in [0.0, 3.0e12] {
+            let camera = DVec3::new(distance, 0.0, 0.0);
+            let body = camera + DVec3::new(1000.0, 0.0, 0.0);
+            let cheat = camera.as_bevy_scaled_dvec(scale);
+            let expected = Vec3::new(1.0e-6, 0.0, 0.0);
+
+            // To within a centimeter
+            let relative = body.as_bevy_scaled_cheated(scale, cheat);
+            assert!((relative - expected).length() < 1e-11, "{} m out: {}", distance, relative);
+
+            // Downcasting first loses the offset entirely once the positions are large
+            let absolute = body.as_bevy_scaled(scale) - camera.as_bevy_scaled(scale);
+            let absolute_error = (absolute - expected).length();
+            if distance == 0.0 {
+                assert!(absolute_error < 1e-12, "{}", absolute_error);
+            } else {
+                assert!(absolute_error > 1e-7, "{}", absolute_error);
+            }
+        }
+        assert_eq!(DVec3::new(1.0, 2.0, 3.0).as_bevy_scaled_dvec(1.0).as_regular(), DVec3::new(1.0, 2.0, 3.0));
     }
 }
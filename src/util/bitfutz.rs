@@ -1,13 +1,74 @@
 // Surely nothing can go wrong with a little bit nonsense.
 
 pub mod f64 {
+    const SIGN: u64 = 1 << 63;
+
+    /// Order-preserving key for a float: `a < b` exactly when `to_u64(a) < to_u64(b)`.
+    /// Positives get the sign bit set, negatives are inverted so larger magnitudes sort lower.
+    /// -0.0 and +0.0 share a key, since they compare equal.
+    /// Panics on NaN, which has no place in an ordering.
     pub fn to_u64(value: f64) -> u64 {
-        f64::to_bits(value)
+        assert!(!value.is_nan(), "NaN can't be used as a key");
+        let value = if value == 0.0 { 0.0 } else { value };
+        let bits = f64::to_bits(value);
+        if bits & SIGN == 0 { bits | SIGN } else { !bits }
+    }
+
+    /// Exact inverse of `to_u64`.
+    pub fn from_u64(key: u64) -> f64 {
+        let bits = if key & SIGN != 0 { key & !SIGN } else { !key };
+        f64::from_bits(bits)
     }
 }
 
 pub mod u64 {
     pub fn to_f64(value: u64) -> f64 {
-        f64::from_bits(value)
+        super::f64::from_u64(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Not a glob import, which would shadow the primitive types
+    use super::f64::{from_u64, to_u64};
+    use super::u64::to_f64;
+
+    #[test]
+    fn test_round_trip() {
+        let values = [
+            0.0, 1.0, -1.0, 0.1, -0.1, 1e300, -1e300,
+            f64::MIN_POSITIVE, -f64::MIN_POSITIVE,
+            f64::MIN_POSITIVE / 2.0, -f64::MIN_POSITIVE / 2.0, // subnormal
+            f64::from_bits(1), -f64::from_bits(1), // smallest subnormal
+            f64::MAX, f64::MIN, f64::INFINITY, f64::NEG_INFINITY,
+        ];
+        for value in values {
+            let back = from_u64(to_u64(value));
+            assert_eq!(back.to_bits(), value.to_bits(), "{:e}", value);
+            assert_eq!(to_f64(to_u64(value)).to_bits(), value.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_zeros_share_a_key() {
+        assert_eq!(to_u64(-0.0), to_u64(0.0));
+        assert_eq!(from_u64(to_u64(-0.0)).to_bits(), 0.0f64.to_bits());
+    }
+
+    #[test]
+    fn test_order_preserving() {
+        let ascending = [
+            f64::NEG_INFINITY, f64::MIN, -1.0, -f64::MIN_POSITIVE, -f64::from_bits(1),
+            0.0, f64::from_bits(1), f64::MIN_POSITIVE, 1.0, f64::MAX, f64::INFINITY,
+        ];
+        for pair in ascending.windows(2) {
+            assert!(to_u64(pair[0]) < to_u64(pair[1]), "{:e} !< {:e}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_nan_rejected() {
+        to_u64(f64::NAN);
     }
 }
@@ -0,0 +1,65 @@
+//! Circular orbits, for placing satellites without working out a full set of elements.
+
+use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerMotive, KeplerRotation, KeplerShape, MeanAnomalyAtJ2000};
+use crate::foundations::kepler::period::third_law;
+
+/// Speed of a circular orbit `radius` meters out, in m/s.
+pub fn velocity(mu: f64, radius: f64) -> f64 {
+    (mu / radius).sqrt()
+}
+
+/// Seconds to go once around a circular orbit `radius` meters out.
+pub fn period(mu: f64, radius: f64) -> f64 {
+    third_law(radius, mu)
+}
+
+/// A zero-eccentricity orbit around `primary_id`, inclined `inclination` degrees,
+/// starting on its ascending node at J2000.
+pub fn circular_orbit(primary_id: String, radius: f64, inclination: f64) -> KeplerMotive {
+    KeplerMotive {
+        primary_id,
+        shape: KeplerShape::EccentricitySMA(EccentricitySMA {
+            eccentricity: 0.0,
+            semi_major_axis: radius,
+        }),
+        rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+            inclination,
+            longitude_of_ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+        }),
+        epoch: KeplerEpoch::J2000(MeanAnomalyAtJ2000 { mean_anomaly: 0.0 }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foundations::time::Instant;
+
+    const EARTH_MU: f64 = 3.986e14;
+
+    #[test]
+    fn test_circular_velocity() {
+        let radius = 6.771e6;
+        assert_eq!(velocity(EARTH_MU, radius), (EARTH_MU / radius).sqrt());
+        // Low Earth orbit goes round in about an hour and a half
+        assert!((period(EARTH_MU, radius) / 60.0 - 92.4).abs() < 0.5, "{}", period(EARTH_MU, radius));
+        // Going once round at that speed takes one period
+        let circumference = std::f64::consts::TAU * radius;
+        assert!((circumference / velocity(EARTH_MU, radius) - period(EARTH_MU, radius)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_circular_orbit() {
+        let radius = 4.2164e7;
+        let orbit = circular_orbit(String::from("earth"), radius, 28.5);
+        assert_eq!(orbit.eccentricity(), 0.0);
+        assert_eq!(orbit.semi_major_axis(), radius);
+        assert_eq!(orbit.inclination(), 28.5);
+
+        let time = Instant::from_seconds_since_j2000(3600.0);
+        let displacement = orbit.displacement(time, EARTH_MU).unwrap();
+        assert!((displacement.length() - radius).abs() < 1e-3);
+        assert!((orbit.velocity(time, EARTH_MU).length() - velocity(EARTH_MU, radius)).abs() < 1e-6);
+    }
+}
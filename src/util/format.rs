@@ -1,3 +1,4 @@
+use std::time::Duration;
 use lazy_static::lazy_static;
 use num_traits::Pow;
 use regex::Regex;
@@ -30,6 +31,87 @@ pub fn seconds_to_naive_date(total_seconds: i64) -> String {
     format!("{}{}y {}d {}h {}m {}s", sign, remaining_years, days, hours, mins, secs)
 }
 
+/// A time since the Unix epoch as "2025-10-14T13-24-05.123", for naming files.
+pub fn file_timestamp(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs();
+    let millis = since_epoch.subsec_millis();
+    // Break epoch seconds into date/time components
+    let days = secs / 86400;
+    let day_secs = secs % 86400;
+    let hours = day_secs / 3600;
+    let mins = (day_secs % 3600) / 60;
+    let s = day_secs % 60;
+    let (y, m, d) = epoch_days_to_ymd(days as i64);
+    format!("{y:04}-{m:02}-{d:02}T{hours:02}-{mins:02}-{s:02}.{millis:03}")
+}
+
+/// Convert days since Unix epoch to (year, month, day).
+pub(crate) fn epoch_days_to_ymd(mut days: i64) -> (i64, u32, u32) {
+    // Shift to March-based year to simplify leap year handling
+    days += 719468; // days from 0000-03-01 to 1970-01-01
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097) as u32;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
+}
+
+/// The Julian Day of the Unix epoch, 1970-01-01 00:00:00.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+/// A Julian Day as "2000-01-01 12:00:00 UTC (JD 2451545.0)", on the proleptic Gregorian
+/// calendar. Too far out to hold in whole seconds, it's just the Julian Day.
+pub fn calendar_date(julian_day: f64) -> String {
+    let seconds = ((julian_day - UNIX_EPOCH_JULIAN_DAY) * 86400.0).round();
+    if !(seconds.abs() < 1e17) {
+        return format!("JD {:.1}", julian_day);
+    }
+    let seconds = seconds as i64;
+    let day_secs = seconds.rem_euclid(86400);
+    let (y, m, d) = epoch_days_to_ymd(seconds.div_euclid(86400));
+    let (hours, mins, s) = (day_secs / 3600, (day_secs % 3600) / 60, day_secs % 60);
+    format!("{y:04}-{m:02}-{d:02} {hours:02}:{mins:02}:{s:02} UTC (JD {julian_day:.1})")
+}
+
+/// Units `elapsed` breaks a duration into, largest first, with their length in seconds.
+/// Years are Julian years.
+const ELAPSED_UNITS: [(&str, f64); 5] = [("y", 31_557_600.0), ("d", 86400.0), ("h", 3600.0), ("m", 60.0), ("s", 1.0)];
+
+/// A span of seconds as "1 y 34 d 5 h": the largest unit in it and the two after, leaving out
+/// any that are zero. Spans under a minute are given in seconds to two places, and spans of a
+/// million years or more in years alone.
+pub fn elapsed(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return format!("{} s", seconds);
+    }
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    if seconds.abs() < 60.0 {
+        return format!("{}{:.2} s", sign, seconds.abs());
+    }
+    let mut remaining = seconds.abs().round();
+    let (year, year_length) = ELAPSED_UNITS[0];
+    if remaining >= 1e6 * year_length {
+        return format!("{}{:.3e} {}", sign, remaining / year_length, year);
+    }
+    let Some(largest) = ELAPSED_UNITS.iter().position(|(_, length)| remaining >= *length) else {
+        return "0 s".to_string();
+    };
+    let mut parts = Vec::new();
+    for (unit, length) in &ELAPSED_UNITS[largest..(largest + 3).min(ELAPSED_UNITS.len())] {
+        let count = (remaining / length).floor();
+        remaining -= count * length;
+        if count > 0.0 {
+            parts.push(format!("{} {}", count, unit));
+        }
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
 pub fn sci_not(n: f64) -> String {
     if n.is_nan() {
         return "[NaN]".to_string();
@@ -45,21 +127,134 @@ pub fn sci_not(n: f64) -> String {
     format!("{:.3} x 10 ^ {}", mantissa, exponent)
 }
 
+const SI_PREFIXES: [&str; 17] = ["y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y"];
+
+/// "1.234 Gm" for 1.234e9 and "m". Past yotta or yocto it falls back to `sci_not`.
+pub fn si_prefixed(n: f64, unit: &str) -> String {
+    if n == 0.0 || !n.is_finite() {
+        return format!("{:.3} {}", n, unit);
+    }
+    let power = (n.abs().log10() / 3.0).floor() as i64;
+    if power.abs() > 8 {
+        return format!("{} {}", sci_not(n), unit);
+    }
+    let mantissa = n / 1000f64.powi(power as i32);
+    format!("{:.3} {}{}", mantissa, SI_PREFIXES[(power + 8) as usize], unit)
+}
+
 lazy_static! {
-    static ref SCI_RE: Regex = Regex::new(r"\d?\.\d+\s?x\s?10\s?\^\s?\d+").unwrap();
+    /// "1.5 x 10 ^ 8", as written by `sci_not`. Also takes `×` or `*`, any spacing, and signs on both parts.
+    static ref SCI_RE: Regex = Regex::new(r"^\s*([+-]?(?:\d+\.?\d*|\.\d+))\s*[xX×*]\s*10\s*\^\s*([+-]?\d+)\s*$").unwrap();
 }
 
+/// Parses "1.5 x 10 ^ 8" style numbers, plus anything Rust's float parser takes ("1.5e8", "-2").
 pub fn sci_not_parser(s: &str) -> Option<f64> {
-    if !SCI_RE.is_match(s) {
-        return None;
+    if let Ok(result) = s.trim().parse::<f64>() {
+        return result.is_finite().then_some(result);
     }
-    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
-    let a = s.split("x").collect::<Vec<&str>>();
-    let mantissa = a[0].parse::<f64>().ok()?;
-    let b = a[1].split("^").collect::<Vec<&str>>();
-    let exponent = b[1].parse::<i64>().ok()?;
+
+    let captures = SCI_RE.captures(s)?;
+    let mantissa = captures[1].parse::<f64>().ok()?;
+    let exponent = captures[2].parse::<i64>().ok()?;
 
     let result = mantissa * (10.0f64.pow(exponent as f64));
 
-    Some(result)
-}
\ No newline at end of file
+    result.is_finite().then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_parses(s: &str, expected: f64) {
+        let parsed = sci_not_parser(s).unwrap_or_else(|| panic!("{:?} didn't parse", s));
+        assert!((parsed - expected).abs() <= expected.abs() * 1e-12, "{:?} parsed as {}, expected {}", s, parsed, expected);
+    }
+
+    #[test]
+    fn test_sci_not_parser_whitespace() {
+        assert_parses("1.5 x 10 ^ 8", 1.5e8);
+        assert_parses("1.5x10^8", 1.5e8);
+        assert_parses("  1.5   x  10^ 8 ", 1.5e8);
+        assert_parses("1.5\tx\t10\t^\t8", 1.5e8);
+        assert_parses("1.5 X 10 ^ 8", 1.5e8);
+        assert_parses("1.5 × 10 ^ 8", 1.5e8);
+        assert_parses(".5 x 10 ^ 2", 50.0);
+        assert_parses("15 x 10 ^ 7", 1.5e8);
+    }
+
+    #[test]
+    fn test_sci_not_parser_negative() {
+        assert_parses("1.5 x 10 ^ -8", 1.5e-8);
+        assert_parses("-1.5 x 10 ^ 8", -1.5e8);
+        assert_parses("-1.5 x 10 ^ -8", -1.5e-8);
+        assert_parses("1.5 x 10 ^ +8", 1.5e8);
+    }
+
+    #[test]
+    fn test_sci_not_parser_e_notation() {
+        assert_parses("1.5e8", 1.5e8);
+        assert_parses("1.5E-8", 1.5e-8);
+        assert_parses(" -2 ", -2.0);
+    }
+
+    #[test]
+    fn test_sci_not_parser_rejects() {
+        assert_eq!(sci_not_parser(""), None);
+        assert_eq!(sci_not_parser("x 10 ^ 8"), None);
+        assert_eq!(sci_not_parser("1.5 x 10 ^"), None);
+        assert_eq!(sci_not_parser("1.5 x 11 ^ 8"), None);
+        assert_eq!(sci_not_parser("NaN"), None);
+        assert_eq!(sci_not_parser("1.5 x 10 ^ 400"), None);
+    }
+
+    #[test]
+    fn test_file_timestamp() {
+        assert_eq!(file_timestamp(Duration::from_millis(1_760_448_245_123)), "2025-10-14T13-24-05.123");
+        assert_eq!(file_timestamp(Duration::ZERO), "1970-01-01T00-00-00.000");
+        // The day after a leap day
+        assert_eq!(file_timestamp(Duration::from_secs(951_868_800)), "2000-03-01T00-00-00.000");
+    }
+
+    #[test]
+    fn test_calendar_date() {
+        assert_eq!(calendar_date(2451545.0), "2000-01-01 12:00:00 UTC (JD 2451545.0)");
+        assert_eq!(calendar_date(2440587.5), "1970-01-01 00:00:00 UTC (JD 2440587.5)");
+        assert_eq!(calendar_date(2460000.5), "2023-02-25 00:00:00 UTC (JD 2460000.5)");
+        // Before the Unix epoch, and before year zero
+        assert_eq!(calendar_date(2415020.5), "1900-01-01 00:00:00 UTC (JD 2415020.5)");
+        assert_eq!(calendar_date(0.0), "-4713-11-24 12:00:00 UTC (JD 0.0)");
+        assert_eq!(calendar_date(1e20), "JD 100000000000000000000.0");
+    }
+
+    #[test]
+    fn test_elapsed() {
+        assert_eq!(elapsed(31_557_600.0 + 34.0 * 86400.0 + 5.0 * 3600.0 + 59.0), "1 y 34 d 5 h");
+        assert_eq!(elapsed(90061.0), "1 d 1 h 1 m");
+        assert_eq!(elapsed(31_557_600.0 + 30.0), "1 y");
+        assert_eq!(elapsed(-3600.0), "-1 h");
+        assert_eq!(elapsed(59.6), "59.60 s");
+        assert_eq!(elapsed(0.3), "0.30 s");
+        assert_eq!(elapsed(0.5), "0.50 s");
+        assert_eq!(elapsed(-0.5), "-0.50 s");
+        assert_eq!(elapsed(0.0), "0.00 s");
+        assert_eq!(elapsed(1e20), "3.169e12 y");
+        assert_eq!(elapsed(-1e20), "-3.169e12 y");
+    }
+
+    #[test]
+    fn test_si_prefixed() {
+        assert_eq!(si_prefixed(2.25e11, "m"), "225.000 Gm");
+        assert_eq!(si_prefixed(1000.0, "m"), "1.000 km");
+        assert_eq!(si_prefixed(-4.5e-4, "s"), "-450.000 µs");
+        assert_eq!(si_prefixed(12.0, "m/s"), "12.000 m/s");
+        assert_eq!(si_prefixed(0.0, "m"), "0.000 m");
+    }
+
+    #[test]
+    fn test_sci_not_round_trip() {
+        for n in [1.5e8, -2.25e-3, 6.417e23, 0.0] {
+            assert_parses(&sci_not(n), n);
+        }
+    }
+}
@@ -1,9 +1,14 @@
 
 pub mod common;
 pub mod patched_conics;
+pub mod overlapping_chunks;
 pub mod bitfutz;
 pub mod time_map;
 pub mod format;
 pub mod mappings;
 pub mod bevystuff;
 pub mod ease;
+pub mod units;
+pub mod circular;
+pub mod rng;
+pub mod belt;
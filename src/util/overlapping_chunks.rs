@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use bevy::math::DVec3;
+
+type ChunkKey = (i64, i64, i64);
+
+/// Spatial hash over positions in cubic chunks.
+///
+/// Each entry is also filed under every neighboring chunk within `overlap` of it,
+/// so any radius query up to `overlap` only has to look in the chunk holding its center.
+pub struct ChunkGrid<T> {
+    chunk_size: f64,
+    overlap: f64,
+    entries: Vec<(DVec3, T)>,
+    chunks: HashMap<ChunkKey, Vec<usize>>,
+}
+
+impl<T> ChunkGrid<T> {
+    pub fn new(chunk_size: f64, overlap: f64) -> Self {
+        assert!(chunk_size > 0.0, "Chunk size must be positive, got {}", chunk_size);
+        Self {
+            chunk_size,
+            overlap: overlap.max(0.0),
+            entries: Vec::new(),
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.chunks.clear();
+    }
+
+    pub fn insert(&mut self, position: DVec3, value: T) {
+        let index = self.entries.len();
+        self.entries.push((position, value));
+        for key in self.keys_around(position, self.overlap) {
+            self.chunks.entry(key).or_default().push(index);
+        }
+    }
+
+    /// Everything within `radius` of `center`, each entry once, in insertion order.
+    pub fn query_radius(&self, center: DVec3, radius: f64) -> impl Iterator<Item = &T> {
+        // Overlap already covers this much of the search
+        let reach = (radius - self.overlap).max(0.0);
+        let mut found: Vec<usize> = self.keys_around(center, reach)
+            .filter_map(|key| self.chunks.get(&key))
+            .flatten()
+            .copied()
+            .filter(|&index| self.entries[index].0.distance_squared(center) <= radius * radius)
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found.into_iter().map(|index| &self.entries[index].1)
+    }
+
+    fn key(&self, position: DVec3) -> ChunkKey {
+        let cell = (position / self.chunk_size).floor();
+        (cell.x as i64, cell.y as i64, cell.z as i64)
+    }
+
+    /// Keys of all chunks touching the cube of half-width `margin` around `position`.
+    fn keys_around(&self, position: DVec3, margin: f64) -> impl Iterator<Item = ChunkKey> + use<T> {
+        let (lx, ly, lz) = self.key(position - DVec3::splat(margin));
+        let (hx, hy, hz) = self.key(position + DVec3::splat(margin));
+        (lx..=hx).flat_map(move |x| (ly..=hy).flat_map(move |y| (lz..=hz).map(move |z| (x, y, z))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(grid: &ChunkGrid<&'static str>, center: DVec3, radius: f64) -> Vec<&'static str> {
+        grid.query_radius(center, radius).copied().collect()
+    }
+
+    #[test]
+    fn test_neighbors_across_chunk_boundary() {
+        let mut grid = ChunkGrid::new(100.0, 10.0);
+        grid.insert(DVec3::new(99.0, 50.0, 50.0), "west of boundary");
+        grid.insert(DVec3::new(101.0, 50.0, 50.0), "east of boundary");
+        grid.insert(DVec3::new(150.0, 50.0, 50.0), "far east");
+
+        assert_eq!(found(&grid, DVec3::new(99.5, 50.0, 50.0), 5.0), vec!["west of boundary", "east of boundary"]);
+        assert_eq!(found(&grid, DVec3::new(101.0, 50.0, 50.0), 2.0), vec!["west of boundary", "east of boundary"]);
+        assert_eq!(found(&grid, DVec3::new(101.0, 50.0, 50.0), 1.0), vec!["east of boundary"]);
+
+        // Corners straddle eight chunks
+        let mut grid = ChunkGrid::new(100.0, 10.0);
+        grid.insert(DVec3::new(-1.0, -1.0, -1.0), "corner");
+        assert_eq!(found(&grid, DVec3::new(1.0, 1.0, 1.0), 4.0), vec!["corner"]);
+    }
+
+    #[test]
+    fn test_query_wider_than_overlap() {
+        let mut grid = ChunkGrid::new(100.0, 10.0);
+        grid.insert(DVec3::new(-250.0, 0.0, 0.0), "far");
+        grid.insert(DVec3::new(20.0, 0.0, 0.0), "near");
+
+        assert_eq!(found(&grid, DVec3::ZERO, 255.0), vec!["far", "near"]);
+        assert_eq!(found(&grid, DVec3::ZERO, 245.0), vec!["near"]);
+        assert!(found(&grid, DVec3::new(1.0e6, 0.0, 0.0), 1000.0).is_empty());
+        assert_eq!(grid.len(), 2);
+    }
+}
@@ -1,3 +1,9 @@
+use std::collections::HashMap;
+use bevy::math::DVec3;
+use itertools::Itertools;
+use crate::body::motive::kepler_motive::KeplerMotive;
+use crate::body::motive::{Motive, MotiveSelection, TransitionEvent};
+use crate::foundations::time::Instant;
 
 /// "Least accurate"
 pub fn laplace(semi_major_axis: f64, body_mass: f64, primary_mass: f64) -> f64 {
@@ -28,3 +34,111 @@ pub fn hill(instantaneous_r_to_primary: f64, eccentricity: f64, body_mass: f64,
 pub fn black_hole(gravitational_constant: f64, mass: f64, velocity_dispersion: f64) -> f64 {
     (gravitational_constant * mass) / (velocity_dispersion * velocity_dispersion)
 }
+
+/// Keplerian segments keyed by the J2000 second each one takes over, as in `PatchedConicsEntry::route`.
+/// This is a legacy format, so keys are the raw bits of the time rather than bitfutz keys.
+pub type Route = HashMap<u64, KeplerMotive>;
+
+/// Segments with their start times, earliest first.
+fn segments(route: &Route) -> Vec<(f64, &KeplerMotive)> {
+    route.iter()
+        .map(|(key, segment)| (f64::from_bits(*key), segment))
+        .sorted_by(|a, b| a.0.total_cmp(&b.0))
+        .collect()
+}
+
+/// The segment in effect at `time`. The first segment also covers everything before it starts.
+pub fn segment_at(route: &Route, time: Instant) -> Option<&KeplerMotive> {
+    let time = time.to_j2000_seconds();
+    let segments = segments(route);
+    segments.iter()
+        .rev()
+        .find(|(start, _)| *start <= time)
+        .or(segments.first())
+        .map(|(_, segment)| *segment)
+}
+
+/// Displacement from the active segment's primary at `time`, along with that primary's id.
+/// `gravitational_parameter` looks up a primary's mu by id.
+pub fn displacement<'a>(route: &'a Route, time: Instant, gravitational_parameter: impl Fn(&str) -> Option<f64>) -> Option<(&'a str, DVec3)> {
+    let segment = segment_at(route, time)?;
+    let mu = gravitational_parameter(&segment.primary_id)?;
+    Some((&segment.primary_id, segment.displacement(time, mu)?))
+}
+
+/// The route as a compound motive, with an SOI change at each handoff.
+/// The first segment starts no later than J2000, matching other single-motive bodies.
+pub fn to_motive(route: &Route) -> Option<Motive> {
+    let mut motive = Motive::empty();
+    for (i, (start, segment)) in segments(route).into_iter().enumerate() {
+        let (start, event) = if i == 0 {
+            (start.min(0.0), TransitionEvent::Epoch)
+        } else {
+            (start, TransitionEvent::SOIChange)
+        };
+        motive.insert_event(Instant::from_seconds_since_j2000(start), event, MotiveSelection::Keplerian(segment.clone()));
+    }
+    if motive.is_empty() { None } else { Some(motive) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::motive::kepler_motive::{EccentricitySMA, KeplerEpoch, KeplerEulerAngles, KeplerRotation, KeplerShape, MeanAnomalyAtEpoch};
+
+    const SUN_MU: f64 = 1.327e20;
+    const EARTH_MU: f64 = 3.986e14;
+
+    fn circular(primary_id: &str, semi_major_axis: f64, inclination: f64) -> KeplerMotive {
+        KeplerMotive {
+            primary_id: String::from(primary_id),
+            shape: KeplerShape::EccentricitySMA(EccentricitySMA { eccentricity: 0.01, semi_major_axis }),
+            rotation: KeplerRotation::EulerAngles(KeplerEulerAngles {
+                inclination,
+                longitude_of_ascending_node: 30.0,
+                argument_of_periapsis: 60.0,
+            }),
+            epoch: KeplerEpoch::MeanAnomaly(MeanAnomalyAtEpoch { epoch: Instant::from_seconds_since_j2000(0.0), mean_anomaly: 1.0 }),
+        }
+    }
+
+    #[test]
+    fn test_route_is_continuous_at_handoff() {
+        let earth = circular("sun", 1.496e11, 0.0);
+        let handoff = Instant::from_seconds_since_j2000(86_400.0 * 40.0);
+
+        // Captured into a low orbit; work backwards to the heliocentric leg that arrives there
+        let parking = circular("earth", 7.0e6, 28.5);
+        let ship_position = earth.displacement(handoff, SUN_MU).unwrap() + parking.displacement(handoff, EARTH_MU).unwrap();
+        let ship_velocity = earth.velocity(handoff, SUN_MU) + parking.velocity(handoff, EARTH_MU);
+        let cruise = KeplerMotive::from_state_vectors(String::from("sun"), ship_position, ship_velocity, SUN_MU, handoff).unwrap();
+
+        let route: Route = HashMap::from([
+            (0f64.to_bits(), cruise),
+            (handoff.to_j2000_seconds().to_bits(), parking),
+        ]);
+        let mu = |id: &str| match id {
+            "sun" => Some(SUN_MU),
+            "earth" => Some(EARTH_MU),
+            _ => None,
+        };
+        let absolute = |time: Instant| {
+            let (primary, local) = displacement(&route, time, mu).unwrap();
+            let primary_position = if primary == "earth" { earth.displacement(time, SUN_MU).unwrap() } else { DVec3::ZERO };
+            (primary.to_string(), primary_position + local)
+        };
+
+        let just_before = Instant::from_seconds_since_j2000(handoff.to_j2000_seconds() - 1e-3);
+        let (before_primary, before) = absolute(just_before);
+        let (after_primary, after) = absolute(handoff);
+        assert_eq!(before_primary, "sun");
+        assert_eq!(after_primary, "earth");
+        // Moving ~30 km/s, so a millisecond apart is tens of meters
+        assert!((after - before).length() < 100.0, "{}", (after - before).length());
+
+        let motive = to_motive(&route).unwrap();
+        assert_eq!(motive.motive_at(just_before).1.primary_id(), Some("sun"));
+        assert_eq!(motive.motive_at(handoff).1.primary_id(), Some("earth"));
+        assert_eq!(segment_at(&route, Instant::from_seconds_since_j2000(-1.0)).unwrap().primary_id, "sun");
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::slice::Iter;
 use bevy::math::DVec3;
 use bevy::prelude::*;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use crate::foundations::time::{Instant, TimeLength};
 use crate::util::bitfutz;
@@ -114,7 +115,20 @@ impl<V: Clone + Lerpable> TimeMap<V>
         self.time_keys.as_vec()
     }
 
-    pub fn range(&self, start_time: f64, end_time: f64) -> TimeMap<V> {
+    /// Entries with `start <= time <= end`, in time order.
+    pub fn range(&self, start: f64, end: f64) -> impl Iterator<Item = (f64, &V)> {
+        self.time_keys.slice(start, end)
+            .iter()
+            .filter_map(move |time| self.map.get(&bitfutz::f64::to_u64(*time)).map(|v| (*time, v)))
+    }
+
+    /// Consecutive pairs of entries, in time order.
+    pub fn segments(&self) -> impl Iterator<Item = (&V, &V)> {
+        self.iter().map(|(_, v)| v).tuple_windows()
+    }
+
+    /// A copy of the map restricted to `start <= time <= end`.
+    pub fn sub_map(&self, start_time: f64, end_time: f64) -> TimeMap<V> {
         let restricted_times = self.time_keys.range(start_time, end_time);
         let mut map = HashMap::new();
 
@@ -152,11 +166,20 @@ impl<V: Clone + Lerpable> TimeMap<V>
         match &self.periodicity {
             None => None,
             Some(periodicity) => {
-                Some(self.range(periodicity.interval_start, periodicity.interval_start + periodicity.interval_size))
+                Some(self.sub_map(periodicity.interval_start, periodicity.interval_start + periodicity.interval_size))
             }
         }
     }
 
+    /// The same times and periodicity, with `f` applied to every value.
+    pub fn map_values(&self, f: impl Fn(&V) -> V) -> TimeMap<V> {
+        Self {
+            map: self.map.iter().map(|(key, value)| (*key, f(value))).collect(),
+            time_keys: self.time_keys.clone(),
+            periodicity: self.periodicity.clone(),
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (f64, &V)> {
         self.time_keys.in_order
             .iter()
@@ -165,6 +188,62 @@ impl<V: Clone + Lerpable> TimeMap<V>
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Catmull-Rom spline through the samples, with tangents scaled for uneven spacing.
+    CatmullRom,
+}
+
+impl TimeMap<DVec3> {
+    /// Value at `time`, linearly interpolated between the two samples around it.
+    /// None outside the sampled range.
+    pub fn interpolate(&self, time: f64) -> Option<DVec3> {
+        self.interpolate_with(time, Interpolation::Linear)
+    }
+
+    pub fn interpolate_with(&self, time: f64, interpolation: Interpolation) -> Option<DVec3> {
+        let times = &self.time_keys.in_order;
+        if let Some(value) = self.get(time) {
+            return Some(*value);
+        }
+        let (a, b) = self.time_keys.get_pair_that_surrounds(time)?;
+        let value_a = *self.get(a)?;
+        let value_b = *self.get(b)?;
+        let span = b - a;
+        let s = (time - a) / span;
+
+        match interpolation {
+            Interpolation::Linear => Some(value_a.lerp(value_b, s)),
+            Interpolation::CatmullRom => {
+                // Index of b; a is the one before it.
+                let index_b = times.partition_point(|&x| x < b);
+                let index_a = index_b - 1;
+
+                // Tangents per second, one-sided at the ends of the samples.
+                let tangent = |index: usize| -> Option<DVec3> {
+                    let before = index.checked_sub(1).unwrap_or(index);
+                    let after = (index + 1).min(times.len() - 1);
+                    let (t0, t1) = (times[before], times[after]);
+                    Some((*self.get(t1)? - *self.get(t0)?) / (t1 - t0))
+                };
+                let tangent_a = tangent(index_a)? * span;
+                let tangent_b = tangent(index_b)? * span;
+
+                // Cubic Hermite basis
+                let s2 = s * s;
+                let s3 = s2 * s;
+                let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+                let h10 = s3 - 2.0 * s2 + s;
+                let h01 = -2.0 * s3 + 3.0 * s2;
+                let h11 = s3 - s2;
+                Some(value_a * h00 + tangent_a * h10 + value_b * h01 + tangent_b * h11)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortedTimes {
     in_order: Vec<f64>,
@@ -300,6 +379,13 @@ impl SortedTimes {
         self.in_order.drain(index..).collect::<Vec<f64>>()
     }
 
+    /// The times with `start <= time <= end`.
+    pub fn slice(&self, start: f64, end: f64) -> &[f64] {
+        let low = self.in_order.partition_point(|&x| x < start);
+        let high = self.in_order.partition_point(|&x| x <= end);
+        &self.in_order[low..high.max(low)]
+    }
+
     pub fn range(&self, start: f64, end: f64) -> SortedTimes {
         let values = self.in_order.iter()
             .filter(|&&x| x >= start && x <= end)
@@ -314,3 +400,90 @@ impl SortedTimes {
         self.in_order.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A line sampled unevenly, and a parabola sampled evenly.
+    fn line() -> TimeMap<DVec3> {
+        let mut map = TimeMap::new();
+        for time in [0.0, 1.0, 3.0, 4.0, 10.0] {
+            map.insert(time, DVec3::new(time, 2.0 * time, -time));
+        }
+        map
+    }
+
+    fn parabola() -> TimeMap<DVec3> {
+        let mut map = TimeMap::new();
+        for i in 0..=10 {
+            let time = i as f64;
+            map.insert(time, DVec3::new(time, time * time, 0.0));
+        }
+        map
+    }
+
+    #[test]
+    fn test_interpolate_exact_hit() {
+        let map = parabola();
+        for interpolation in [Interpolation::Linear, Interpolation::CatmullRom] {
+            assert_eq!(map.interpolate_with(4.0, interpolation), Some(DVec3::new(4.0, 16.0, 0.0)));
+            assert_eq!(map.interpolate_with(0.0, interpolation), Some(DVec3::ZERO));
+            assert_eq!(map.interpolate_with(10.0, interpolation), Some(DVec3::new(10.0, 100.0, 0.0)));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_between_samples() {
+        let map = line();
+        for interpolation in [Interpolation::Linear, Interpolation::CatmullRom] {
+            let value = map.interpolate_with(2.0, interpolation).unwrap();
+            assert!((value - DVec3::new(2.0, 4.0, -2.0)).length() < 1e-12, "{:?}: {:?}", interpolation, value);
+            let value = map.interpolate_with(7.5, interpolation).unwrap();
+            assert!((value - DVec3::new(7.5, 15.0, -7.5)).length() < 1e-12, "{:?}: {:?}", interpolation, value);
+        }
+
+        // Linear cuts the corner of the parabola, Catmull-Rom follows it
+        let map = parabola();
+        assert_eq!(map.interpolate(4.5), Some(DVec3::new(4.5, 20.5, 0.0)));
+        let smooth = map.interpolate_with(4.5, Interpolation::CatmullRom).unwrap();
+        assert!((smooth.y - 20.25).abs() < 1e-12, "{:?}", smooth);
+    }
+
+    #[test]
+    fn test_interpolate_out_of_range() {
+        let map = line();
+        assert_eq!(map.interpolate(-0.5), None);
+        assert_eq!(map.interpolate(10.5), None);
+        assert_eq!(map.interpolate_with(10.5, Interpolation::CatmullRom), None);
+        assert_eq!(TimeMap::<DVec3>::new().interpolate(0.0), None);
+    }
+
+    #[test]
+    fn test_range_and_segments() {
+        let empty = TimeMap::<DVec3>::new();
+        assert_eq!(empty.range(f64::MIN, f64::MAX).count(), 0);
+        assert_eq!(empty.segments().count(), 0);
+
+        let mut single = TimeMap::new();
+        single.insert(3.0, DVec3::X);
+        assert_eq!(single.range(0.0, 5.0).collect::<Vec<_>>(), vec![(3.0, &DVec3::X)]);
+        assert_eq!(single.segments().count(), 0);
+
+        // Inserted out of order, still comes back sorted
+        let mut map = TimeMap::new();
+        for t in [4.0, 0.0, 2.0, 1.0, 3.0] {
+            map.insert(t, DVec3::splat(t));
+        }
+        let times: Vec<f64> = map.range(1.5, 10.0).map(|(t, _)| t).collect();
+        assert_eq!(times, vec![2.0, 3.0, 4.0]);
+        let times: Vec<f64> = map.range(-10.0, 1.0).map(|(t, _)| t).collect();
+        assert_eq!(times, vec![0.0, 1.0]);
+        assert_eq!(map.range(5.0, 1.0).count(), 0);
+
+        let segments: Vec<(DVec3, DVec3)> = map.segments().map(|(a, b)| (*a, *b)).collect();
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0], (DVec3::splat(0.0), DVec3::splat(1.0)));
+        assert_eq!(segments[3], (DVec3::splat(3.0), DVec3::splat(4.0)));
+    }
+}
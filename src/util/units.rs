@@ -0,0 +1,140 @@
+//! Display units for editing. Everything is stored in SI; these only exist at the edges.
+
+use crate::foundations::time::JD_SECONDS_PER_JULIAN_DAY;
+
+pub const METERS_PER_KILOMETER: f64 = 1000.0;
+pub const METERS_PER_AU: f64 = 1.495978707e11;
+pub const KILOGRAMS_PER_SOLAR_MASS: f64 = 1.98892e30;
+pub const KILOGRAMS_PER_EARTH_MASS: f64 = 5.9722e24;
+pub const SECONDS_PER_JULIAN_YEAR: f64 = 365.25 * JD_SECONDS_PER_JULIAN_DAY;
+/// One AU³/(M☉·year²), in m³ kg⁻¹ s⁻². G is about 4π² of these.
+pub const ASTRONOMICAL_GRAVITATIONAL_UNIT: f64 = METERS_PER_AU * METERS_PER_AU * METERS_PER_AU
+    / (KILOGRAMS_PER_SOLAR_MASS * SECONDS_PER_JULIAN_YEAR * SECONDS_PER_JULIAN_YEAR);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Distance,
+    Mass,
+    GravitationalConstant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Meter,
+    Kilometer,
+    AstronomicalUnit,
+    Kilogram,
+    SolarMass,
+    EarthMass,
+    /// m³ kg⁻¹ s⁻²
+    GravitySi,
+    /// AU³/(M☉·year²)
+    GravityAstronomical,
+}
+
+impl Unit {
+    const DISTANCES: [Unit; 3] = [Unit::Meter, Unit::Kilometer, Unit::AstronomicalUnit];
+    const MASSES: [Unit; 3] = [Unit::Kilogram, Unit::SolarMass, Unit::EarthMass];
+    const GRAVITATIONAL_CONSTANTS: [Unit; 2] = [Unit::GravitySi, Unit::GravityAstronomical];
+
+    pub fn of_quantity(quantity: Quantity) -> &'static [Unit] {
+        match quantity {
+            Quantity::Distance => &Self::DISTANCES,
+            Quantity::Mass => &Self::MASSES,
+            Quantity::GravitationalConstant => &Self::GRAVITATIONAL_CONSTANTS,
+        }
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            Unit::Meter | Unit::Kilometer | Unit::AstronomicalUnit => Quantity::Distance,
+            Unit::Kilogram | Unit::SolarMass | Unit::EarthMass => Quantity::Mass,
+            Unit::GravitySi | Unit::GravityAstronomical => Quantity::GravitationalConstant,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Meter => "m",
+            Unit::Kilometer => "km",
+            Unit::AstronomicalUnit => "AU",
+            Unit::Kilogram => "kg",
+            Unit::SolarMass => "M☉",
+            Unit::EarthMass => "M⊕",
+            Unit::GravitySi => "m³/(kg·s²)",
+            Unit::GravityAstronomical => "AU³/(M☉·yr²)",
+        }
+    }
+
+    /// SI units in one of this unit.
+    pub fn factor(&self) -> f64 {
+        match self {
+            Unit::Meter => 1.0,
+            Unit::Kilometer => METERS_PER_KILOMETER,
+            Unit::AstronomicalUnit => METERS_PER_AU,
+            Unit::Kilogram => 1.0,
+            Unit::SolarMass => KILOGRAMS_PER_SOLAR_MASS,
+            Unit::EarthMass => KILOGRAMS_PER_EARTH_MASS,
+            Unit::GravitySi => 1.0,
+            Unit::GravityAstronomical => ASTRONOMICAL_GRAVITATIONAL_UNIT,
+        }
+    }
+
+    pub fn to_si(&self, value: f64) -> f64 {
+        value * self.factor()
+    }
+
+    pub fn from_si(&self, value: f64) -> f64 {
+        value / self.factor()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversion_constants() {
+        assert_eq!(Unit::AstronomicalUnit.to_si(1.0), 1.495978707e11);
+        assert_eq!(Unit::Kilometer.to_si(1.0), 1000.0);
+        assert_eq!(Unit::SolarMass.to_si(1.0), 1.98892e30);
+        assert_eq!(Unit::EarthMass.to_si(1.0), 5.9722e24);
+        assert_eq!(Unit::Meter.to_si(1.0), 1.0);
+        assert_eq!(Unit::Kilogram.to_si(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let all = [Quantity::Distance, Quantity::Mass, Quantity::GravitationalConstant];
+        for unit in all.iter().flat_map(|quantity| Unit::of_quantity(*quantity)) {
+            for value in [0.0, 1.0, -3.5, 1.49598023e11, 6.4171e23, 1e-9] {
+                let round_tripped = unit.to_si(unit.from_si(value));
+                assert!((round_tripped - value).abs() <= value.abs() * 1e-15, "{:?}: {} became {}", unit, value, round_tripped);
+            }
+        }
+    }
+
+    #[test]
+    fn test_quantities() {
+        for unit in Unit::of_quantity(Quantity::Distance) {
+            assert_eq!(unit.quantity(), Quantity::Distance);
+        }
+        for unit in Unit::of_quantity(Quantity::Mass) {
+            assert_eq!(unit.quantity(), Quantity::Mass);
+        }
+        for unit in Unit::of_quantity(Quantity::GravitationalConstant) {
+            assert_eq!(unit.quantity(), Quantity::GravitationalConstant);
+        }
+    }
+
+    #[test]
+    fn test_gravitational_constant_in_astronomical_units() {
+        // Gaussian gravitational constant: k² is G in AU³/(M☉·day²)
+        const GAUSSIAN_K: f64 = 0.01720209895;
+        let expected = (GAUSSIAN_K * 365.25).powi(2);
+        let g = Unit::GravityAstronomical.from_si(crate::foundations::gravity::G);
+        // The solar mass constant here predates the IAU's, so only agree to a few parts in 10⁴
+        assert!((g - expected).abs() / expected < 1e-3, "{} vs {}", g, expected);
+        assert!((g - 4.0 * std::f64::consts::PI * std::f64::consts::PI).abs() < 0.1);
+    }
+}